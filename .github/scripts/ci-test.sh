@@ -40,6 +40,12 @@ for fn in $(ls src/tests/*.rs); do
     # Run the test with each plan it needs.
     for MMTK_PLAN in $PLANS; do
         env MMTK_PLAN=$MMTK_PLAN cargo test --features "$FEATURES" -- $t;
+        # MarkCompact can store forwarding pointers in the header or in side metadata. Test both.
+        if [[ $MMTK_PLAN == 'MarkCompact' ]]; then
+            env MMTK_PLAN=$MMTK_PLAN MMTK_MARK_COMPACT_SIDE_FORWARDING=true cargo test --features "$FEATURES" -- $t;
+        fi
+        # GC can run on GC threads or synchronously on the mutator that requests it. Test both.
+        env MMTK_PLAN=$MMTK_PLAN MMTK_SYNCHRONOUS_GC=true cargo test --features "$FEATURES" -- $t;
     done
 done
 
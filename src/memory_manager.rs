@@ -14,10 +14,12 @@
 use crate::mmtk::MMTKBuilder;
 use crate::mmtk::MMTK;
 use crate::plan::AllocationSemantics;
+use crate::plan::{AllocationSiteId, AllocationSiteStats};
 use crate::plan::{Mutator, MutatorContext};
 use crate::scheduler::WorkBucketStage;
-use crate::scheduler::{GCController, GCWork, GCWorker};
+use crate::scheduler::{GCController, GCWork, GCWorker, ZeroingThread};
 use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::card_table::CardTable;
 use crate::util::constants::{LOG_BYTES_IN_PAGE, MIN_OBJECT_SIZE};
 use crate::util::heap::layout::vm_layout_constants::HEAP_END;
 use crate::util::heap::layout::vm_layout_constants::HEAP_START;
@@ -36,8 +38,8 @@ use std::sync::atomic::Ordering;
 /// 1. Create an [`crate::MMTKBuilder`] instance.
 /// 2. Set command line options for MMTKBuilder by [`crate::memory_manager::process`] or [`crate::memory_manager::process_bulk`].
 /// 3. Initialize MMTk by calling this function, `mmtk_init()`, and pass the builder earlier. This call will return an MMTK instance.
-///    Usually a binding store the MMTK instance statically as a singleton. We plan to allow multiple instances, but this is not yet fully
-///    supported. Currently we assume a binding will only need one MMTk instance.
+///    Usually a binding store the MMTK instance statically as a singleton. A binding may create multiple instances, each of which
+///    gives a separate heap. See [`crate::MMTK`] for the states that are shared by the instances.
 /// 4. Enable garbage collection in MMTk by [`crate::memory_manager::enable_collection`]. A binding should only call this once its
 ///    thread system is ready. MMTk will not trigger garbage collection before this call.
 ///
@@ -93,6 +95,151 @@ pub fn lazy_init_vm_space<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, start: Add
     mmtk.plan.base_mut().vm_space.lazy_initialize(start, size);
 }
 
+/// Add a memory region to the VM space, such as a pre-built image loaded by the VM. The region is
+/// outside the MMTk heap, and the VM is responsible for mapping and populating the memory. The
+/// objects in the region are traced, but never moved or reclaimed. The region must not overlap
+/// with other VM space regions after the regions are aligned to chunks.
+///
+/// After adding a region, the binding should call [`initialize_vm_space_object`] for each object
+/// in the region to set its metadata, such as the VO bit.
+///
+/// The binding must not call this function during a GC. Other threads may run mutators and call
+/// this function at the same time.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `start`: The start address of the region.
+/// * `size`: The size of the region in bytes.
+#[cfg(feature = "vm_space")]
+pub fn add_vm_space_region<VM: VMBinding>(mmtk: &MMTK<VM>, start: Address, size: usize) {
+    mmtk.plan.base().vm_space.add_region(start, size);
+}
+
+/// Remove the VM space region that contains the address `start`, e.g. when an image is unloaded.
+/// After this call, the objects in the region are no longer in MMTk spaces. The VM should make
+/// sure that no object refers to the objects in the region, and it may unmap the memory afterwards.
+///
+/// The binding must not call this function during a GC. Other threads may run mutators and call
+/// this function at the same time.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `start`: An address in the region.
+#[cfg(feature = "vm_space")]
+pub fn remove_vm_space_region<VM: VMBinding>(mmtk: &MMTK<VM>, start: Address) {
+    mmtk.plan.base().vm_space.remove_region(start);
+}
+
+/// Initialize the metadata of an object in the VM space, such as the VO bit, the mark bit and the
+/// log bit. The binding should call this for each object in a VM space region after adding the region.
+/// With the log bit set, the object barrier of generational plans remembers the VM space objects
+/// that are mutated, so nursery GCs do not need to scan the whole VM space. If the option
+/// `vm_space_remembered_set` is enabled, full heap GCs do not trace the VM space either, and only
+/// scan the mutated objects. In that case, the objects in a region must only refer to objects in
+/// the VM space when they are initialized.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `object`: The object in the VM space.
+#[cfg(feature = "vm_space")]
+pub fn initialize_vm_space_object<VM: VMBinding>(mmtk: &MMTK<VM>, object: ObjectReference) {
+    use crate::policy::sft::SFT;
+    debug_assert!(mmtk.plan.base().vm_space.is_in_space(object));
+    mmtk.plan
+        .base()
+        .vm_space
+        .initialize_object_metadata(object, false);
+}
+
+/// Save the objects in the immortal space and the non-moving space to a heap snapshot file, so
+/// that they can be loaded into the VM space later with [`load_heap_snapshot`]. See
+/// [`crate::util::heap_snapshot`] for the format of the file.
+///
+/// The binding should call this right after a full heap GC, such as one triggered by
+/// [`handle_user_compaction_request`], when no mutator is running. Otherwise, dead objects in the
+/// non-moving space may be saved. All the objects in the two spaces are saved, and each reference in the saved objects must refer to an object in
+/// the two spaces. Otherwise, an error is returned. This is not supported for plans without the
+/// immortal and the non-moving spaces, such as NoGC.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that calls this function. It is passed to [`crate::vm::Scanning::scan_object`] to
+///   find the references in the saved objects.
+/// * `path`: The path of the snapshot file.
+#[cfg(feature = "heap_snapshot")]
+pub fn save_heap_snapshot<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMWorkerThread,
+    path: &std::path::Path,
+) -> std::io::Result<()> {
+    crate::util::heap_snapshot::save_heap_snapshot(mmtk, tls, path)
+}
+
+/// Load a heap snapshot file saved by [`save_heap_snapshot`] into the VM space. The image is mapped
+/// at `start`, and the references in the image are relocated. The image is added as a VM space
+/// region in the same way as [`add_vm_space_region`], and the metadata of the objects in the image
+/// is initialized. Return the loaded snapshot, which the binding can use to translate the
+/// references to the saved objects.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `path`: The path of the snapshot file.
+/// * `start`: The address to load the image. It must be chunk-aligned, and outside the heap range.
+#[cfg(feature = "heap_snapshot")]
+pub fn load_heap_snapshot<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    path: &std::path::Path,
+    start: Address,
+) -> std::io::Result<crate::util::heap_snapshot::HeapSnapshot> {
+    crate::util::heap_snapshot::load_heap_snapshot(mmtk, path, start)
+}
+
+/// Make a range in the code spaces readable and executable. The range is no longer writable. The
+/// permission is managed at page granularity, so it also applies to other objects in the same pages.
+/// The binding must call `make_code_writable` before it modifies code or allocates more code
+/// objects in the pages. Memory in other spaces is never executable.
+///
+/// GC may write to code objects, e.g. to mark them. MMTk makes the executable ranges writable while
+/// the mutators are stopped for GC, and makes them executable again before mutators resume. Code
+/// spaces are non-moving, so code objects are never copied or compacted.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `start`: The start of the range. It must be in a code space.
+/// * `bytes`: The size of the range in bytes.
+#[cfg(feature = "code_space")]
+pub fn make_code_executable<VM: VMBinding>(mmtk: &MMTK<VM>, start: Address, bytes: usize) {
+    mmtk.plan.base().make_code_executable(start, bytes);
+}
+
+/// Make a range in the code spaces readable and writable. The range is no longer executable.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `start`: The start of the range. It must be in a code space.
+/// * `bytes`: The size of the range in bytes.
+#[cfg(feature = "code_space")]
+pub fn make_code_writable<VM: VMBinding>(mmtk: &MMTK<VM>, start: Address, bytes: usize) {
+    mmtk.plan.base().make_code_writable(start, bytes);
+}
+
+/// Seal the read-only space. The pages allocated in the read-only space so far are protected as
+/// read-only, and later allocations with `AllocationSemantics::ReadOnly` go to fresh pages. GC does
+/// not write to objects in the read-only space, as their mark bits are on the side. The space can
+/// be sealed multiple times.
+///
+/// This resets the read-only allocators of all the mutators returned by `ActivePlan::mutators()`.
+/// The binding must make sure that no mutator is allocating in the read-only space when this is
+/// called. If the log bit is in the header, the binding must also make sure that sealed objects
+/// have not been written with the object barrier since the last GC, as GC resets their log bits.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+#[cfg(feature = "ro_space")]
+pub fn seal_read_only_space<VM: VMBinding>(mmtk: &MMTK<VM>) {
+    mmtk.plan.base().ro_space.seal();
+}
+
 /// Request MMTk to create a mutator for the given thread. The ownership
 /// of returned boxed mutator is transferred to the binding, and the binding needs to take care of its
 /// lifetime. For performance reasons, A VM should store the returned mutator in a thread local storage
@@ -188,6 +335,93 @@ pub fn post_alloc<VM: VMBinding>(
     mutator.post_alloc(refer, bytes, semantics);
 }
 
+/// Allocate memory for an object, and tell MMTk the allocation site of the object. This is the same as
+/// [`alloc`], except that generational plans (GenCopy and GenImmix) with the `pretenuring` option enabled
+/// track the survival rate for each site, and may allocate objects from a site with a high survival rate
+/// directly into the mature space. Other plans ignore the site. An object allocated with this function
+/// must be initialized with [`post_alloc_with_site`] with the same site.
+///
+/// Arguments:
+/// * `mutator`: The mutator to perform this allocation request.
+/// * `size`: The number of bytes required for the object.
+/// * `align`: Required alignment for the object.
+/// * `offset`: Offset associated with the alignment.
+/// * `semantics`: The allocation semantic required for the allocation. Only `AllocationSemantics::Default` may be pretenured.
+/// * `site`: The allocation site of the object. `UNKNOWN_ALLOCATION_SITE` is never tracked or pretenured.
+pub fn alloc_with_site<VM: VMBinding>(
+    mutator: &mut Mutator<VM>,
+    size: usize,
+    align: usize,
+    offset: usize,
+    semantics: AllocationSemantics,
+    site: AllocationSiteId,
+) -> Address {
+    // See the comments in `alloc()`.
+    debug_assert!(size >= MIN_OBJECT_SIZE);
+    debug_assert!(align >= VM::MIN_ALIGNMENT);
+    debug_assert!(align <= VM::MAX_ALIGNMENT);
+    debug_assert!(VM::USE_ALLOCATION_OFFSET || offset == 0);
+
+    mutator.alloc_with_site(size, align, offset, semantics, site)
+}
+
+/// Perform post-allocation actions for an object allocated with [`alloc_with_site`]. This records
+/// the allocation site for the object if the object is allocated into the nursery.
+///
+/// Arguments:
+/// * `mutator`: The mutator to perform post-alloc actions.
+/// * `refer`: The newly allocated object.
+/// * `bytes`: The size of the space allocated for the object (in bytes).
+/// * `semantics`: The allocation semantics used for the allocation.
+/// * `site`: The allocation site used for the allocation.
+pub fn post_alloc_with_site<VM: VMBinding>(
+    mutator: &mut Mutator<VM>,
+    refer: ObjectReference,
+    bytes: usize,
+    semantics: AllocationSemantics,
+    site: AllocationSiteId,
+) {
+    mutator.post_alloc_with_site(refer, bytes, semantics, site);
+}
+
+/// Get the pretenuring statistics and decision for an allocation site. The statistics are from the last nursery GC.
+/// This returns `None` if the plan does not support pretenuring or the `pretenuring` option is disabled.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `site`: The allocation site to query.
+pub fn get_allocation_site_stats<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    site: AllocationSiteId,
+) -> Option<AllocationSiteStats> {
+    mmtk.plan
+        .generational()
+        .and_then(|gen| gen.allocation_site_tracker())
+        .map(|tracker| tracker.get_stats(site))
+}
+
+/// Get all the allocation sites that are currently pretenured. This returns an empty vector if
+/// the plan does not support pretenuring or the `pretenuring` option is disabled.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn get_pretenured_allocation_sites<VM: VMBinding>(mmtk: &MMTK<VM>) -> Vec<AllocationSiteId> {
+    mmtk.plan
+        .generational()
+        .and_then(|gen| gen.allocation_site_tracker())
+        .map_or(vec![], |tracker| tracker.get_pretenured_sites())
+}
+
+/// Get the card table if the plan uses the card-marking barrier (see the `generational_barrier` option).
+/// A binding may use it to implement the fast path of the barrier, or to query the state of a card.
+/// This returns `None` if the plan does not use the card-marking barrier.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn get_card_table<VM: VMBinding>(mmtk: &MMTK<VM>) -> Option<&CardTable> {
+    mmtk.plan.generational().and_then(|gen| gen.card_table())
+}
+
 /// The *subsuming* write barrier by MMTk. For performance reasons, a VM should implement the write barrier
 /// fast-path on their side rather than just calling this function.
 ///
@@ -428,7 +662,31 @@ pub fn gc_poll<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMMutatorThread) {
     }
 }
 
-/// Run the main loop for the GC controller thread. This method does not return.
+/// Perform the pending GC on the current mutator thread if the option `synchronous_gc` is set. In this
+/// mode, MMTk does not spawn any GC thread, and the binding should call this function in
+/// `Collection::block_for_gc`. The current thread then acts as the GC controller and executes all
+/// the work packets, and MMTk calls `Collection::stop_all_mutators` and `Collection::resume_mutators`
+/// from the current thread. The binding should treat the current thread as stopped, and other threads
+/// that are blocked in this function as stopped as well.
+///
+/// This function returns after the GC is finished. It returns immediately if there is no pending
+/// GC, e.g. the GC has been performed by another mutator thread. If a work packet panics, the panic
+/// is resumed on the current thread after `Collection::gc_panicked` is called, and any later call of
+/// this function panics.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The mutator thread that requests GC.
+pub fn collect_now<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: VMMutatorThread) {
+    assert!(
+        mmtk.plan.is_initialized(),
+        "MMTk collection has not been initialized (was initialize_collection() called before?)"
+    );
+    mmtk.scheduler.run_synchronous_gc(tls);
+}
+
+/// Run the main loop for the GC controller thread. This method does not return unless GC threads
+/// are stopped by [`prepare_to_fork`]. The thread should exit once this method returns.
 ///
 /// Arguments:
 /// * `tls`: The thread that will be used as the GC controller.
@@ -443,7 +701,8 @@ pub fn start_control_collector<VM: VMBinding>(
     gc_controller.run(tls);
 }
 
-/// Run the main loop of a GC worker. This method does not return.
+/// Run the main loop of a GC worker. This method does not return unless GC threads are stopped by
+/// [`prepare_to_fork`]. The thread should exit once this method returns.
 ///
 /// Arguments:
 /// * `tls`: The thread that will be used as the GC worker.
@@ -458,6 +717,22 @@ pub fn start_worker<VM: VMBinding>(
     worker.run(tls, mmtk);
 }
 
+/// Run the main loop of a concurrent zeroing thread. This method does not return unless GC threads
+/// are stopped by [`prepare_to_fork`]. The thread should exit once this method returns.
+///
+/// Arguments:
+/// * `tls`: The thread that will be used as the zeroing thread.
+/// * `zeroing_thread`: The execution context of the zeroing thread.
+///   It is the `ZeroingThread` passed to `Collection::spawn_gc_thread`.
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn start_zeroing_thread<VM: VMBinding>(
+    _mmtk: &'static MMTK<VM>,
+    tls: VMWorkerThread,
+    zeroing_thread: &mut ZeroingThread<VM>,
+) {
+    zeroing_thread.run(tls);
+}
+
 /// Initialize the scheduler and GC workers that are required for doing garbage collections.
 /// This is a mandatory call for a VM during its boot process once its thread system
 /// is ready. This should only be called once. This call will invoke Collection::spawn_gc_thread()
@@ -476,6 +751,73 @@ pub fn initialize_collection<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: VMThre
     mmtk.plan.base().initialized.store(true, Ordering::SeqCst);
 }
 
+/// Stop all the GC threads so that the VM can call `fork()`. GC threads do not survive `fork()`,
+/// and MMTk cannot do GC in the child process unless they are spawned again by [`after_fork`].
+/// This function asks the GC controller and the GC workers to return from
+/// [`start_control_collector`] and [`start_worker`], and blocks until all of them have returned.
+/// The binding should then let those threads exit, and join them if the OS thread must be gone
+/// before forking.
+///
+/// This function must not be called when GC is in progress, and no GC can be triggered until
+/// [`after_fork`] is called.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn prepare_to_fork<VM: VMBinding>(mmtk: &'static MMTK<VM>) {
+    assert!(
+        mmtk.plan.is_initialized(),
+        "MMTk collection has not been initialized (was initialize_collection() called before?)"
+    );
+    assert!(
+        !mmtk.plan.base().gc_in_progress(),
+        "prepare_to_fork() cannot be called when GC is in progress"
+    );
+    mmtk.scheduler.stop_gc_threads(mmtk);
+}
+
+/// Spawn the GC threads again after they are stopped by [`prepare_to_fork`]. This should be called
+/// in both the parent and the child process after `fork()`. Like [`initialize_collection`], this
+/// call will invoke `Collection::spawn_gc_thread()` to create GC threads.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that calls `fork()`. This value will be passed back to the VM in
+///   `Collection::spawn_gc_thread()` so that the VM knows the context.
+pub fn after_fork<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: VMThread) {
+    assert!(
+        mmtk.plan.is_initialized(),
+        "MMTk collection has not been initialized (was initialize_collection() called before?)"
+    );
+    mmtk.scheduler.spawn_gc_threads(mmtk, tls);
+}
+
+/// Shut down an MMTk instance so that a new instance can be created in the same process. This
+/// function
+/// 1. asks the GC threads to return from [`start_control_collector`], [`start_worker`] and
+///    [`start_zeroing_thread`], and blocks until all of them have returned. The binding should
+///    let those threads exit, and join them.
+/// 2. calls `Collection::run_pending_finalizers` with the objects that are ready for finalization
+///    but have not been fetched by [`get_finalized_object`].
+/// 3. unmaps the memory of the spaces, discards their side metadata, and releases their address
+///    ranges so that other MMTk instances can use them. The memory of the VM space is not
+///    unmapped, as it is mapped by the VM. When the last live instance is shut down, the global
+///    states, such as the SFT map, are reset and all the side metadata is unmapped.
+///
+/// Other MMTk instances are not affected, and they can keep running during this call.
+///
+/// This function must not be called when GC is in progress. It can be called after a GC is aborted
+/// because a work packet panicked (see `Collection::gc_panicked`). The binding must not use the
+/// instance, its mutators or any object in its heap after this call. It is fine to drop the
+/// instance afterwards if the binding owns it.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that shuts down MMTk. This value will be passed back to the VM in
+///   `Collection::run_pending_finalizers`.
+pub fn mmtk_shutdown<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: VMThread) {
+    mmtk.shutdown(tls);
+}
+
 /// Allow MMTk to trigger garbage collection when heap is full. This should only be used in pair with disable_collection().
 /// See the comments on disable_collection(). If disable_collection() is not used, there is no need to call this function at all.
 /// Note this call is not thread safe, only one VM thread should call this.
@@ -578,6 +920,22 @@ pub fn handle_user_collection_request<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMMut
     mmtk.plan.handle_user_collection_request(tls, false, false);
 }
 
+/// Trigger a garbage collection that compacts the heap so that free memory can be returned to the
+/// OS, as requested by the user. This request cannot be ignored, and the GC is a full heap GC.
+/// For Immix-based plans, the GC evacuates the least occupied chunks, and releases the chunks
+/// that become empty. Other plans simply do a full heap GC.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `tls`: The thread that triggers this collection request.
+pub fn handle_user_compaction_request<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMMutatorThread) {
+    mmtk.plan
+        .base()
+        .user_triggered_compaction
+        .store(true, Ordering::Relaxed);
+    mmtk.plan.handle_user_collection_request(tls, true, true);
+}
+
 /// Is the object alive?
 ///
 /// Arguments:
@@ -633,6 +991,18 @@ pub fn is_mmtk_object(addr: Address) -> bool {
     SFT_MAP.get_checked(addr).is_mmtk_object(addr)
 }
 
+/// Check if `addr` is the address of an object reference to an MMTk object allocated by the MMTk
+/// instance `mmtk`. This is the same as [`is_mmtk_object`], except that it returns false for the
+/// objects of other MMTk instances.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `addr`: An arbitrary address.
+#[cfg(feature = "is_mmtk_object")]
+pub fn is_mmtk_object_of<VM: VMBinding>(mmtk: &MMTK<VM>, addr: Address) -> bool {
+    is_mmtk_object(addr) && mmtk.owns_address(addr)
+}
+
 /// Return true if the `object` lies in a region of memory where
 /// -   only MMTk can allocate into, or
 /// -   only MMTk's delegated memory allocator (such as a malloc implementation) can allocate into
@@ -671,6 +1041,16 @@ pub fn is_in_mmtk_spaces<VM: VMBinding>(object: ObjectReference) -> bool {
         .is_in_space(object)
 }
 
+/// Return true if the `object` is in the spaces of the MMTk instance `mmtk`. This is the same as
+/// [`is_in_mmtk_spaces`], except that it returns false for the objects of other MMTk instances.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `object`: The object reference to query.
+pub fn is_in_mmtk_spaces_of<VM: VMBinding>(mmtk: &MMTK<VM>, object: ObjectReference) -> bool {
+    is_in_mmtk_spaces::<VM>(object) && mmtk.owns_address(object.to_address::<VM>())
+}
+
 /// Is the address in the mapped memory? The runtime can use this function to check
 /// if an address is mapped by MMTk. Note that this is different than is_in_mmtk_spaces().
 /// For malloc spaces, MMTk does not map those addresses (malloc does the mmap), so
@@ -765,7 +1145,8 @@ pub fn add_finalizer<VM: VMBinding>(
 }
 
 /// Pin an object. MMTk will make sure that the object does not move
-/// during GC. Note that action cannot happen in some plans, eg, semispace.
+/// during GC. In copying spaces, the memory of a pinned object is retained
+/// by the GC. In mark-compact spaces, objects are compacted around it.
 /// It returns true if the pinning operation has been performed, i.e.,
 /// the object status changed from non-pinned to pinned
 ///
@@ -867,8 +1248,8 @@ pub fn get_finalizers_for<VM: VMBinding>(
 }
 
 /// Get the number of workers. MMTk spawns worker threads for the 'threads' defined in the options.
-/// So the number of workers is derived from the threads option. Note the feature single_worker overwrites
-/// the threads option, and force one worker thread.
+/// So the number of workers is derived from the threads option, unless it is changed by [`set_gc_threads`].
+/// Note the feature single_worker overwrites the threads option, and force one worker thread.
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
@@ -876,6 +1257,37 @@ pub fn num_of_workers<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> usize {
     mmtk.scheduler.num_workers()
 }
 
+/// Get the number of workers that execute work packets in the current GC, or in the last GC if no
+/// GC is in progress. This is the same as [`num_of_workers`] unless the option `adaptive_threads`
+/// is enabled.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn num_of_active_workers<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> usize {
+    mmtk.scheduler.active_workers()
+}
+
+/// Get the maximum number of workers that [`set_gc_threads`] accepts. This is the larger one of the
+/// 'threads' option and the number of CPUs.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn max_num_of_workers<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> usize {
+    mmtk.scheduler.max_workers()
+}
+
+/// Change the number of GC workers. The change takes effect at the start of the next GC, when MMTk
+/// spawns more workers with `Collection::spawn_gc_thread()`, or asks some workers to return from
+/// [`start_worker`]. The binding should let those threads exit. This can be called at any time,
+/// including when GC is in progress.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `num_workers`: The number of workers. It must be between 1 and [`max_num_of_workers`].
+pub fn set_gc_threads<VM: VMBinding>(mmtk: &'static MMTK<VM>, num_workers: usize) {
+    mmtk.scheduler.request_num_workers(num_workers);
+}
+
 /// Add a work packet to the given work bucket. Note that this simply adds the work packet to the given
 /// work bucket, and the scheduler will decide when to execute the work packet.
 ///
@@ -1,5 +1,6 @@
 ///! MMTk instance.
 use crate::plan::Plan;
+use crate::policy::sft::SFT;
 use crate::policy::sft_map::{create_sft_map, SFTMap};
 use crate::scheduler::GCWorkScheduler;
 
@@ -7,34 +8,36 @@ use crate::scheduler::GCWorkScheduler;
 use crate::util::edge_logger::EdgeLogger;
 use crate::util::finalizable_processor::FinalizableProcessor;
 use crate::util::heap::layout::{self, Mmapper, VMMap};
+use crate::util::heap::space_descriptor::SpaceDescriptor;
 use crate::util::opaque_pointer::*;
 use crate::util::options::Options;
 use crate::util::reference_processor::ReferenceProcessors;
+#[cfg(feature = "verify_remset")]
+use crate::util::sanity::remset_verifier::RemsetVerifier;
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::SanityChecker;
+use crate::util::Address;
+use crate::vm::Collection;
 use crate::vm::ReferenceGlue;
 use crate::vm::VMBinding;
 use std::default::Default;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 
 lazy_static! {
-    // I am not sure if we should include these mmappers as part of MMTk struct.
-    // The considerations are:
-    // 1. We need VMMap and Mmapper to create spaces. It is natural that the mappers are not
-    //    part of MMTK, as creating MMTK requires these mappers. We could use Rc/Arc for these mappers though.
-    // 2. These mmappers are possibly global across multiple MMTk instances, as they manage the
-    //    entire address space.
-    // TODO: We should refactor this when we know more about how multiple MMTK instances work.
-
-    /// A global VMMap that manages the mapping of spaces to virtual memory ranges.
-    pub static ref VM_MAP: Box<dyn VMMap> = layout::create_vm_map();
-
-    /// A global Mmapper for mmaping and protection of virtual memory.
+    /// A global Mmapper for mmaping and protection of virtual memory. It is shared by all the MMTk
+    /// instances, as it manages the entire address space of the process.
     pub static ref MMAPPER: Box<dyn Mmapper> = layout::create_mmapper();
+
+    /// Creating and shutting down MMTk instances are serialized, as the spaces of an instance
+    /// update the global SFT map and reserve their address ranges when they are created.
+    static ref CREATION_LOCK: Mutex<()> = Mutex::new(());
 }
 
+/// The number of live MMTk instances, i.e. the instances that are created and not shut down yet.
+static LIVE_INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
 use crate::util::rust_util::InitializeOnce;
 
 // A global space function table that allows efficient dispatch space specific code for addresses in our heap.
@@ -78,16 +81,48 @@ impl Default for MMTKBuilder {
 }
 
 /// An MMTk instance. MMTk allows multiple instances to run independently, and each instance gives users a separate heap.
-/// *Note that multi-instances is not fully supported yet*
+///
+/// Each instance has its own options, plan, spaces, GC workers and VM map. Its spaces reserve
+/// address ranges that are disjoint from the spaces of other instances, and GCs of different
+/// instances can run at the same time. Use [`crate::memory_manager::is_in_mmtk_spaces_of`] to find
+/// out whether an object belongs to a given instance.
+///
+/// Some states are global, and they are shared by all the instances:
+/// * The SFT map, the mmapper and the side metadata. They are indexed by address, so the entries
+///   of an instance are those for the address ranges of its spaces. They are cleared or discarded
+///   for the ranges of an instance in [`crate::memory_manager::mmtk_shutdown`], and the memory
+///   for them is only unmapped when the last live instance is shut down.
+/// * The block size and the line size of Immix spaces. See the option `immix_block_size`.
+///
+/// The VM callbacks, such as [`crate::vm::ActivePlan::mutators`] and
+/// [`crate::vm::Collection::stop_all_mutators`], do not tell which instance they are called for.
+/// The binding should find out the instance from the thread that calls them, e.g. by remembering
+/// the instance for each GC thread it spawns in [`crate::vm::Collection::spawn_gc_thread`].
+///
+/// There are a few limitations:
+/// * On 64-bit targets, each space takes a fixed-size slot in the heap range, and the number of slots
+///   limits the number of instances that can be alive at a time.
+/// * Only one instance can be alive at a time on 32-bit targets, as the discontiguous spaces share the
+///   discontiguous range. The range is only released when the instance is shut down. The same applies
+///   to the `malloc_mark_sweep` and the `nogc_lock_free` features, as their spaces do not reserve their
+///   address ranges. This is also the only case where the chunk-based SFT map is used on 64-bit
+///   targets, and its limit of 255 spaces applies to the spaces of that instance.
+/// * The VM space regions of different instances must be disjoint. The regions are outside the heap
+///   range, and on 64-bit targets they share the SFT entries unless the SFT map is chunk-based.
 pub struct MMTK<VM: VMBinding> {
     pub(crate) options: Arc<Options>,
     pub(crate) plan: Box<dyn Plan<VM = VM>>,
+    /// The VM map for the spaces of this instance. The spaces in the plan refer to it, so it is
+    /// dropped after the plan.
+    vm_map: Box<dyn VMMap>,
     pub(crate) reference_processors: ReferenceProcessors,
     pub(crate) finalizable_processor:
         Mutex<FinalizableProcessor<<VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType>>,
     pub(crate) scheduler: Arc<GCWorkScheduler<VM>>,
     #[cfg(feature = "sanity")]
     pub(crate) sanity_checker: Mutex<SanityChecker<VM::VMEdge>>,
+    #[cfg(feature = "verify_remset")]
+    pub(crate) remset_verifier: RemsetVerifier,
     #[cfg(feature = "extreme_assertions")]
     pub(crate) edge_logger: EdgeLogger<VM::VMEdge>,
     inside_harness: AtomicBool,
@@ -99,33 +134,69 @@ impl<VM: VMBinding> MMTK<VM> {
         // The first call will initialize SFT map. Other calls will be blocked until SFT map is initialized.
         SFT_MAP.initialize_once(&create_sft_map);
 
-        let num_workers = if cfg!(feature = "single_worker") {
+        let num_workers = if *options.synchronous_gc {
+            // The mutator that requests GC performs GC without any worker.
+            0
+        } else if cfg!(feature = "single_worker") {
             1
         } else {
             *options.threads
         };
 
-        let scheduler = GCWorkScheduler::new(num_workers, (*options.thread_affinity).clone());
+        // The binding may increase the number of workers up to the number of CPUs later.
+        let max_workers = if *options.synchronous_gc || cfg!(feature = "single_worker") {
+            1
+        } else {
+            num_workers.max(num_cpus::get())
+        };
+
+        let scheduler = GCWorkScheduler::new(
+            num_workers,
+            max_workers,
+            *options.adaptive_threads,
+            *options.synchronous_gc,
+            (*options.thread_affinity).clone(),
+            *options.work_queue_order,
+            *options.work_stealing,
+        );
+
+        let creation_guard = CREATION_LOCK.lock().unwrap();
+        let live_instances = LIVE_INSTANCES.fetch_add(1, Ordering::SeqCst);
+        if cfg!(any(
+            target_pointer_width = "32",
+            feature = "malloc_mark_sweep",
+            feature = "nogc_lock_free"
+        )) {
+            assert_eq!(
+                live_instances, 0,
+                "Only one MMTk instance can be alive at a time with this build"
+            );
+        }
+
+        let vm_map = layout::create_vm_map();
+        // The plan is dropped before the VM map, so the spaces never see a dangling VM map.
+        let vm_map_ref: &'static dyn VMMap = unsafe { &*(vm_map.as_ref() as *const dyn VMMap) };
 
         let plan = crate::plan::create_plan(
             *options.plan,
-            VM_MAP.as_ref(),
+            vm_map_ref,
             MMAPPER.as_ref(),
             options.clone(),
             scheduler.clone(),
         );
 
-        // TODO: This probably does not work if we have multiple MMTk instances.
-        VM_MAP.boot();
+        vm_map.boot();
         // This needs to be called after we create Plan. It needs to use HeapMeta, which is gradually built when we create spaces.
-        VM_MAP.finalize_static_space_map(
+        vm_map.finalize_static_space_map(
             plan.base().heap.get_discontig_start(),
             plan.base().heap.get_discontig_end(),
         );
+        drop(creation_guard);
 
         MMTK {
             options,
             plan,
+            vm_map,
             reference_processors: ReferenceProcessors::new(),
             finalizable_processor: Mutex::new(FinalizableProcessor::<
                 <VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType,
@@ -133,6 +204,8 @@ impl<VM: VMBinding> MMTK<VM> {
             scheduler,
             #[cfg(feature = "sanity")]
             sanity_checker: Mutex::new(SanityChecker::new()),
+            #[cfg(feature = "verify_remset")]
+            remset_verifier: RemsetVerifier::new(),
             inside_harness: AtomicBool::new(false),
             #[cfg(feature = "extreme_assertions")]
             edge_logger: EdgeLogger::new(),
@@ -151,6 +224,64 @@ impl<VM: VMBinding> MMTK<VM> {
         self.inside_harness.store(false, Ordering::SeqCst);
     }
 
+    /// Shut down this MMTk instance, and release its address ranges so that new instances can use
+    /// them. See [`crate::memory_manager::mmtk_shutdown`].
+    pub(crate) fn shutdown(&'static self, tls: VMThread) {
+        assert!(
+            !self.plan.base().gc_in_progress(),
+            "mmtk_shutdown() cannot be called when GC is in progress"
+        );
+
+        if self.plan.is_initialized() {
+            // In synchronous mode, there is no GC thread to stop.
+            if !self.scheduler.is_synchronous() {
+                self.scheduler.stop_gc_threads(self);
+            }
+            self.plan.base().initialized.store(false, Ordering::SeqCst);
+        }
+
+        // Let the binding run the finalizers before the objects are gone.
+        let pending: Vec<_> = {
+            let mut finalizable_processor = self.finalizable_processor.lock().unwrap();
+            std::iter::from_fn(|| finalizable_processor.get_ready_object()).collect()
+        };
+        if !pending.is_empty() {
+            debug!("Run {} pending finalizers before shutdown", pending.len());
+            <VM as VMBinding>::VMCollection::run_pending_finalizers(tls, pending);
+        }
+
+        // Nothing should use the spaces of this instance from now on.
+        let _creation_guard = CREATION_LOCK.lock().unwrap();
+        for space in self.plan.get_spaces() {
+            space.release_on_shutdown();
+        }
+        unsafe { self.vm_map.reset() };
+        self.plan.base().heap.release();
+
+        // Reset the global states when the last instance is gone. This also releases the memory
+        // that is shared by the instances, such as the discontiguous range and the side metadata.
+        if LIVE_INSTANCES.fetch_sub(1, Ordering::SeqCst) == 1 {
+            unsafe { SFT_MAP.reset() };
+            MMAPPER
+                .unmap_all()
+                .unwrap_or_else(|e| panic!("Failed to unmap the heap: {}", e));
+            SpaceDescriptor::reset_discontiguous_space_index();
+            crate::policy::immix::reset_block_and_line_sizes();
+            #[cfg(feature = "extreme_assertions")]
+            crate::util::metadata::side_metadata::reset_content_sanity_map();
+        }
+    }
+
+    /// Is the address in a space of this MMTk instance? This looks up the space for the address
+    /// in the SFT map, and compares it with the spaces of this instance.
+    pub fn owns_address(&self, addr: Address) -> bool {
+        let sft = SFT_MAP.get_checked(addr) as *const dyn SFT as *const ();
+        self.plan
+            .get_spaces()
+            .iter()
+            .any(|space| space.as_sft() as *const dyn SFT as *const () == sft)
+    }
+
     pub fn get_plan(&self) -> &dyn Plan<VM = VM> {
         self.plan.as_ref()
     }
@@ -1,5 +1,7 @@
 //! Read/Write barrier implementations.
 
+use crate::util::card_table::CardTable;
+use crate::util::metadata::field_log_bit;
 use crate::vm::edge_shape::{Edge, MemorySlice};
 use crate::vm::ObjectModel;
 use crate::{
@@ -8,6 +10,8 @@ use crate::{
 };
 use atomic::Ordering;
 use downcast_rs::Downcast;
+use std::marker::PhantomData;
+use strum_macros::EnumString;
 
 /// BarrierSelector describes which barrier to use.
 ///
@@ -15,10 +19,14 @@ use downcast_rs::Downcast;
 /// For example, immix can use this selector to enable different barriers for analysis.
 ///
 /// VM bindings may also use this to enable the correct fast-path, if the fast-path is implemented in the binding.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, EnumString)]
 pub enum BarrierSelector {
     NoBarrier,
     ObjectBarrier,
+    /// Card-marking barrier. It dirties the card that contains the modified slot.
+    CardBarrier,
+    /// Field barrier. It logs each modified slot with the per-field log bits.
+    FieldBarrier,
 }
 
 impl BarrierSelector {
@@ -247,3 +255,124 @@ impl<S: BarrierSemantics> Barrier<S::VM> for ObjectBarrier<S> {
         }
     }
 }
+
+/// Field-logging barrier with a type argument defining its slow-path behaviour.
+///
+/// Unlike the object barrier, which logs a whole object on its first modification, this barrier logs
+/// each reference field separately, using one log bit per word (see [`crate::util::metadata::field_log_bit`]).
+/// A plan marks all the fields of an object as unlogged when the object becomes mature. The first write to an
+/// unlogged field atomically logs the field and passes the slot to the slow-path semantics (e.g. the generational
+/// semantics records the slot in its field modbuf). Later writes to the same field take the fast path until the
+/// plan marks the field as unlogged again, usually after the slot is processed in the next GC.
+///
+/// If [`Edge::slot_address`] returns `None` for a slot, the field cannot be logged, and the slow-path semantics
+/// is called for every write to the slot. [`Barrier::object_probable_write`] cannot tell which fields will be
+/// modified, so it always calls the slow-path semantics for the whole object.
+pub struct FieldBarrier<S: BarrierSemantics> {
+    semantics: S,
+}
+
+impl<S: BarrierSemantics> FieldBarrier<S> {
+    pub fn new(semantics: S) -> Self {
+        Self { semantics }
+    }
+}
+
+impl<S: BarrierSemantics> Barrier<S::VM> for FieldBarrier<S> {
+    fn flush(&mut self) {
+        self.semantics.flush();
+    }
+
+    fn object_reference_write_post(
+        &mut self,
+        src: ObjectReference,
+        slot: <S::VM as VMBinding>::VMEdge,
+        target: ObjectReference,
+    ) {
+        if slot
+            .slot_address()
+            .map_or(true, field_log_bit::is_field_unlogged)
+        {
+            self.object_reference_write_slow(src, slot, target);
+        }
+    }
+
+    fn object_reference_write_slow(
+        &mut self,
+        src: ObjectReference,
+        slot: <S::VM as VMBinding>::VMEdge,
+        target: ObjectReference,
+    ) {
+        if slot.slot_address().map_or(true, field_log_bit::log_field) {
+            self.semantics
+                .object_reference_write_slow(src, slot, target);
+        }
+    }
+
+    fn memory_region_copy_post(
+        &mut self,
+        src: <S::VM as VMBinding>::VMMemorySlice,
+        dst: <S::VM as VMBinding>::VMMemorySlice,
+    ) {
+        self.semantics.memory_region_copy_slow(src, dst);
+    }
+
+    fn object_probable_write(&mut self, obj: ObjectReference) {
+        self.semantics.object_probable_write_slow(obj);
+    }
+}
+
+/// Card-marking barrier. The barrier dirties the cards that contain the modified slots in a card
+/// table, and the plan scans the objects in dirty cards in the next GC. Unlike the object barrier,
+/// this barrier has no slow path and no buffers to flush.
+///
+/// If [`Edge::slot_address`] returns `None` for a slot, we dirty the card that contains the source object instead.
+pub struct CardBarrier<VM: VMBinding> {
+    card_table: &'static CardTable,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> CardBarrier<VM> {
+    pub fn new(card_table: &'static CardTable) -> Self {
+        Self {
+            card_table,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<VM: VMBinding> Barrier<VM> for CardBarrier<VM> {
+    fn object_reference_write_post(
+        &mut self,
+        src: ObjectReference,
+        slot: VM::VMEdge,
+        _target: ObjectReference,
+    ) {
+        let addr = slot
+            .slot_address()
+            .unwrap_or_else(|| src.to_address::<VM>());
+        self.card_table.mark_card(addr);
+    }
+
+    fn object_reference_write_slow(
+        &mut self,
+        src: ObjectReference,
+        slot: VM::VMEdge,
+        target: ObjectReference,
+    ) {
+        self.object_reference_write_post(src, slot, target);
+    }
+
+    fn memory_region_copy_post(&mut self, _src: VM::VMMemorySlice, dst: VM::VMMemorySlice) {
+        // Only dirty the cards that overlap with the destination slice
+        self.card_table.mark_range(dst.start(), dst.bytes());
+    }
+
+    fn object_probable_write(&mut self, obj: ObjectReference) {
+        // Any field of the object may be modified. Dirty all the cards of the object.
+        self.card_table.mark_range(
+            VM::VMObjectModel::ref_to_object_start(obj),
+            VM::VMObjectModel::get_current_size(obj),
+        );
+    }
+}
@@ -6,6 +6,8 @@ use std::sync::{Condvar, Mutex};
 struct RequestSync {
     request_count: isize,
     last_request_count: isize,
+    /// True if the GC controller is asked to exit.
+    exit_requested: bool,
 }
 
 /// GC requester.  This object allows other threads to request (trigger) GC,
@@ -30,6 +32,7 @@ impl<VM: VMBinding> GCRequester<VM> {
             request_sync: Mutex::new(RequestSync {
                 request_count: 0,
                 last_request_count: -1,
+                exit_requested: false,
             }),
             request_condvar: Condvar::new(),
             request_flag: AtomicBool::new(false),
@@ -56,11 +59,43 @@ impl<VM: VMBinding> GCRequester<VM> {
         drop(guard);
     }
 
-    pub fn wait_for_request(&self) {
+    /// Wait until a GC is requested. Return false if the controller is asked to exit instead.
+    pub fn wait_for_request(&self) -> bool {
         let mut guard = self.request_sync.lock().unwrap();
         guard.last_request_count += 1;
         while guard.last_request_count == guard.request_count {
+            if guard.exit_requested {
+                // No request is consumed. A controller spawned later will wait for the same request.
+                guard.last_request_count -= 1;
+                return false;
+            }
             guard = self.request_condvar.wait(guard).unwrap();
         }
+        true
+    }
+
+    /// Consume a pending GC request without waiting. Return false if there is no pending request.
+    /// This is used if GC is synchronous, when there is no GC controller thread waiting for requests.
+    pub fn take_request(&self) -> bool {
+        let mut guard = self.request_sync.lock().unwrap();
+        if guard.request_count > guard.last_request_count + 1 {
+            guard.last_request_count += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Ask the GC controller to exit when it is waiting for requests.
+    pub fn request_exit(&self) {
+        let mut guard = self.request_sync.lock().unwrap();
+        guard.exit_requested = true;
+        self.request_condvar.notify_all();
+    }
+
+    /// Clear the exit request so that a newly spawned GC controller can wait for requests.
+    pub fn clear_exit_request(&self) {
+        let mut guard = self.request_sync.lock().unwrap();
+        guard.exit_requested = false;
     }
 }
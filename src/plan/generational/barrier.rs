@@ -6,11 +6,12 @@ use crate::plan::VectorQueue;
 use crate::scheduler::WorkBucketStage;
 use crate::util::constants::BYTES_IN_ADDRESS;
 use crate::util::*;
-use crate::vm::edge_shape::MemorySlice;
+use crate::vm::edge_shape::{Edge, MemorySlice};
 use crate::vm::VMBinding;
 use crate::MMTK;
 
 use super::gc_work::GenNurseryProcessEdges;
+use super::gc_work::ProcessFieldModBuf;
 use super::gc_work::ProcessModBuf;
 use super::gc_work::ProcessRegionModBuf;
 use super::global::GenerationalPlanExt;
@@ -44,6 +45,8 @@ impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>>
     fn flush_modbuf(&mut self) {
         let buf = self.modbuf.take();
         if !buf.is_empty() {
+            #[cfg(feature = "vm_space")]
+            self.plan.base().vm_space.remember_objects(&buf);
             self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure]
                 .add(ProcessModBuf::<GenNurseryProcessEdges<VM, P>>::new(buf));
         }
@@ -53,6 +56,8 @@ impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>>
         let buf = self.region_modbuf.take();
         if !buf.is_empty() {
             debug_assert!(!buf.is_empty());
+            #[cfg(feature = "vm_space")]
+            self.plan.base().vm_space.remember_slices(&buf);
             self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure].add(ProcessRegionModBuf::<
                 GenNurseryProcessEdges<VM, P>,
             >::new(buf));
@@ -95,6 +100,10 @@ impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>> BarrierSem
                 0,
                 "bytes should be a multiple of words"
             );
+            #[cfg(feature = "verify_remset")]
+            self.mmtk
+                .remset_verifier
+                .record_region(dst.start(), dst.bytes());
             self.region_modbuf.push(dst);
             self.region_modbuf
                 .is_full()
@@ -108,3 +117,138 @@ impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>> BarrierSem
         self.modbuf.is_full().then(|| self.flush_modbuf());
     }
 }
+
+/// The slow-path semantics of the field barrier for generational plans.
+pub struct GenFieldBarrierSemantics<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>>
+{
+    /// MMTk instance
+    mmtk: &'static MMTK<VM>,
+    /// Generational plan
+    plan: &'static P,
+    /// Field modbuf. Contains a list of slots in mature objects that may point to the nursery space.
+    modbuf: VectorQueue<VM::VMEdge>,
+    /// Object modbuf. Contains a list of mature objects whose modified slots cannot be logged individually.
+    object_modbuf: VectorQueue<ObjectReference>,
+    /// Array-copy modbuf. Contains a list of sub-arrays or array slices that may contain pointers to the nursery space.
+    region_modbuf: VectorQueue<VM::VMMemorySlice>,
+}
+
+impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>>
+    GenFieldBarrierSemantics<VM, P>
+{
+    pub fn new(mmtk: &'static MMTK<VM>, plan: &'static P) -> Self {
+        Self {
+            mmtk,
+            plan,
+            modbuf: VectorQueue::new(),
+            object_modbuf: VectorQueue::new(),
+            region_modbuf: VectorQueue::new(),
+        }
+    }
+
+    fn flush_modbuf(&mut self) {
+        let buf = self.modbuf.take();
+        if !buf.is_empty() {
+            #[cfg(feature = "vm_space")]
+            self.plan.base().vm_space.remember_edges(&buf);
+            self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure].add(ProcessFieldModBuf::<
+                GenNurseryProcessEdges<VM, P>,
+            >::new(
+                buf, vec![]
+            ));
+        }
+    }
+
+    fn flush_object_modbuf(&mut self) {
+        let buf = self.object_modbuf.take();
+        if !buf.is_empty() {
+            #[cfg(feature = "vm_space")]
+            self.plan.base().vm_space.remember_objects(&buf);
+            self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure].add(ProcessFieldModBuf::<
+                GenNurseryProcessEdges<VM, P>,
+            >::new(
+                vec![], buf
+            ));
+        }
+    }
+
+    fn flush_region_modbuf(&mut self) {
+        let buf = self.region_modbuf.take();
+        if !buf.is_empty() {
+            #[cfg(feature = "vm_space")]
+            self.plan.base().vm_space.remember_slices(&buf);
+            self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure].add(ProcessRegionModBuf::<
+                GenNurseryProcessEdges<VM, P>,
+            >::new(buf));
+        }
+    }
+
+    fn enqueue_object(&mut self, obj: ObjectReference) {
+        // Only mature objects may point to the nursery space.
+        if !self.plan.is_object_in_nursery(obj) {
+            #[cfg(feature = "verify_remset")]
+            self.mmtk.remset_verifier.record_object(obj);
+            self.object_modbuf.push(obj);
+            self.object_modbuf
+                .is_full()
+                .then(|| self.flush_object_modbuf());
+        }
+    }
+}
+
+impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>> BarrierSemantics
+    for GenFieldBarrierSemantics<VM, P>
+{
+    type VM = VM;
+
+    fn flush(&mut self) {
+        self.flush_modbuf();
+        self.flush_object_modbuf();
+        self.flush_region_modbuf();
+    }
+
+    fn object_reference_write_slow(
+        &mut self,
+        src: ObjectReference,
+        slot: VM::VMEdge,
+        _target: ObjectReference,
+    ) {
+        if slot.slot_address().is_some() {
+            // The field has been logged. Only mature objects have unlogged fields.
+            self.modbuf.push(slot);
+            self.modbuf.is_full().then(|| self.flush_modbuf());
+        } else {
+            // We cannot log the field. Remember the object instead.
+            self.enqueue_object(src);
+        }
+    }
+
+    fn memory_region_copy_slow(&mut self, _src: VM::VMMemorySlice, dst: VM::VMMemorySlice) {
+        // Check if the destination object/slice is in nursery space.
+        let dst_in_nursery = match dst.object() {
+            Some(obj) => self.plan.is_object_in_nursery(obj),
+            None => self.plan.is_address_in_nursery(dst.start()),
+        };
+        // Only enqueue array slices in mature spaces
+        if !dst_in_nursery {
+            debug_assert_eq!(
+                dst.bytes() & (BYTES_IN_ADDRESS - 1),
+                0,
+                "bytes should be a multiple of words"
+            );
+            #[cfg(feature = "verify_remset")]
+            self.mmtk
+                .remset_verifier
+                .record_region(dst.start(), dst.bytes());
+            self.region_modbuf.push(dst);
+            self.region_modbuf
+                .is_full()
+                .then(|| self.flush_region_modbuf());
+        }
+    }
+
+    fn object_probable_write_slow(&mut self, obj: ObjectReference) {
+        // Any field of the object may be modified without a barrier. Remember the object.
+        self.enqueue_object(obj);
+    }
+}
@@ -1,24 +1,30 @@
 use super::gc_work::GenCopyGCWorkContext;
 use super::gc_work::GenCopyNurseryGCWorkContext;
 use super::mutator::ALLOCATOR_MAPPING;
+use crate::plan::generational::gc_work::GenNurseryProcessEdges;
+use crate::plan::generational::gc_work::ScanDirtyCards;
 use crate::plan::generational::global::CommonGenPlan;
 use crate::plan::generational::global::GenerationalPlan;
 use crate::plan::generational::global::GenerationalPlanExt;
+use crate::plan::generational::pretenure::AllocationSiteTracker;
 use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::CreateGeneralPlanArgs;
 use crate::plan::global::CreateSpecificPlanArgs;
 use crate::plan::global::GcStatus;
 use crate::plan::AllocationSemantics;
+use crate::plan::BarrierSelector;
 use crate::plan::Plan;
 use crate::plan::PlanConstraints;
 use crate::policy::copyspace::CopySpace;
 use crate::policy::space::Space;
 use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::card_table::CardTable;
 use crate::util::copy::*;
 use crate::util::heap::VMRequest;
 use crate::util::metadata::side_metadata::SideMetadataSanity;
+use crate::util::options::Options;
 use crate::util::Address;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
@@ -41,12 +47,18 @@ pub struct GenCopy<VM: VMBinding> {
 }
 
 pub const GENCOPY_CONSTRAINTS: PlanConstraints = crate::plan::generational::GEN_CONSTRAINTS;
+/// GenCopy constraints when the card-marking barrier is used.
+pub const GENCOPY_CARD_BARRIER_CONSTRAINTS: PlanConstraints =
+    crate::plan::generational::GEN_CARD_BARRIER_CONSTRAINTS;
+/// GenCopy constraints when the field barrier is used.
+pub const GENCOPY_FIELD_BARRIER_CONSTRAINTS: PlanConstraints =
+    crate::plan::generational::GEN_FIELD_BARRIER_CONSTRAINTS;
 
 impl<VM: VMBinding> Plan for GenCopy<VM> {
     type VM = VM;
 
     fn constraints(&self) -> &'static PlanConstraints {
-        &GENCOPY_CONSTRAINTS
+        Self::get_constraints(self.options())
     }
 
     fn create_copy_config(&'static self) -> CopyConfig<Self::VM> {
@@ -61,7 +73,7 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
                 // The tospace argument doesn't matter, we will rebind before a GC anyway.
                 (CopySelector::CopySpace(0), self.tospace()),
             ],
-            constraints: &GENCOPY_CONSTRAINTS,
+            constraints: self.constraints(),
         }
     }
 
@@ -87,6 +99,15 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
             scheduler.schedule_common_work::<GenCopyGCWorkContext<VM>>(self);
         } else {
             scheduler.schedule_common_work::<GenCopyNurseryGCWorkContext<VM>>(self);
+            if self.gen.card_table.is_some() {
+                scheduler.work_buckets[WorkBucketStage::Closure]
+                    .add(ScanDirtyCards::<GenNurseryProcessEdges<VM, Self>>::new());
+            }
+            #[cfg(feature = "object_pinning")]
+            self.gen
+                .schedule_scan_retained_nursery_objects::<GenNurseryProcessEdges<VM, Self>>(
+                    scheduler,
+                );
         }
     }
 
@@ -107,6 +128,9 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
 
         self.fromspace_mut()
             .set_copy_for_sft_trace(Some(CopySemantics::Mature));
+        // The from-space is not traced or released in a nursery GC. Keep the objects retained in it.
+        #[cfg(feature = "object_pinning")]
+        self.fromspace().set_keep_retained_objects(!full_heap);
         self.tospace_mut().set_copy_for_sft_trace(None);
     }
 
@@ -174,6 +198,11 @@ impl<VM: VMBinding> GenerationalPlan for GenCopy<VM> {
         self.gen.nursery.address_in_space(addr)
     }
 
+    #[cfg(feature = "object_pinning")]
+    fn is_object_retained_in_nursery(&self, object: ObjectReference) -> bool {
+        self.gen.nursery.is_in_retained_region(object)
+    }
+
     fn get_mature_physical_pages_available(&self) -> usize {
         self.tospace().available_physical_pages()
     }
@@ -189,6 +218,14 @@ impl<VM: VMBinding> GenerationalPlan for GenCopy<VM> {
     fn last_collection_full_heap(&self) -> bool {
         self.gen.last_collection_full_heap()
     }
+
+    fn allocation_site_tracker(&self) -> Option<&AllocationSiteTracker> {
+        self.gen.alloc_sites.as_ref()
+    }
+
+    fn card_table(&self) -> Option<&CardTable> {
+        self.gen.card_table.as_ref()
+    }
 }
 
 impl<VM: VMBinding> GenerationalPlanExt<VM> for GenCopy<VM> {
@@ -204,11 +241,12 @@ impl<VM: VMBinding> GenerationalPlanExt<VM> for GenCopy<VM> {
 
 impl<VM: VMBinding> GenCopy<VM> {
     pub fn new(args: CreateGeneralPlanArgs<VM>) -> Self {
+        let constraints = Self::get_constraints(&args.options);
         let mut plan_args = CreateSpecificPlanArgs {
             global_args: args,
-            constraints: &GENCOPY_CONSTRAINTS,
+            constraints,
             global_side_metadata_specs:
-                crate::plan::generational::new_generational_global_metadata_specs::<VM>(),
+                crate::plan::generational::new_generational_global_metadata_specs::<VM>(constraints),
         };
 
         let copyspace0 = CopySpace::new(
@@ -221,7 +259,10 @@ impl<VM: VMBinding> GenCopy<VM> {
         );
 
         let res = GenCopy {
-            gen: CommonGenPlan::new(plan_args),
+            gen: CommonGenPlan::new(
+                plan_args,
+                crate::plan::generational::GENCOPY_MATURE_ALLOCATOR,
+            ),
             hi: AtomicBool::new(false),
             copyspace0,
             copyspace1,
@@ -242,6 +283,14 @@ impl<VM: VMBinding> GenCopy<VM> {
         res
     }
 
+    fn get_constraints(options: &Options) -> &'static PlanConstraints {
+        match *options.generational_barrier {
+            BarrierSelector::CardBarrier => &GENCOPY_CARD_BARRIER_CONSTRAINTS,
+            BarrierSelector::FieldBarrier => &GENCOPY_FIELD_BARRIER_CONSTRAINTS,
+            _ => &GENCOPY_CONSTRAINTS,
+        }
+    }
+
     fn requires_full_heap_collection(&self) -> bool {
         self.gen.requires_full_heap_collection(self)
     }
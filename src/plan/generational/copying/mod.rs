@@ -6,4 +6,6 @@ pub(in crate::plan) mod mutator;
 
 pub use self::global::GenCopy;
 
+pub use self::global::GENCOPY_CARD_BARRIER_CONSTRAINTS;
 pub use self::global::GENCOPY_CONSTRAINTS;
+pub use self::global::GENCOPY_FIELD_BARRIER_CONSTRAINTS;
@@ -1,8 +1,8 @@
 pub(super) use super::super::ALLOCATOR_MAPPING;
 use super::GenCopy;
-use crate::plan::barriers::ObjectBarrier;
-use crate::plan::generational::barrier::GenObjectBarrierSemantics;
+use crate::plan::generational::create_gen_barrier;
 use crate::plan::generational::create_gen_space_mapping;
+use crate::plan::generational::GENCOPY_MATURE_ALLOCATOR;
 use crate::plan::mutator_context::Mutator;
 use crate::plan::mutator_context::MutatorConfig;
 use crate::plan::AllocationSemantics;
@@ -26,6 +26,19 @@ pub fn gencopy_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: V
     .downcast_mut::<BumpAllocator<VM>>()
     .unwrap();
     bump_allocator.reset();
+
+    // rebind the allocator for pretenured objects to the current mature tospace
+    let gencopy = mutator.plan.downcast_ref::<GenCopy<VM>>().unwrap();
+    if gencopy.gen.alloc_sites.is_some() {
+        let mature_allocator = unsafe {
+            mutator
+                .allocators
+                .get_allocator_mut(GENCOPY_MATURE_ALLOCATOR)
+        }
+        .downcast_mut::<BumpAllocator<VM>>()
+        .unwrap();
+        mature_allocator.rebind(gencopy.tospace());
+    }
 }
 
 pub fn create_gencopy_mutator<VM: VMBinding>(
@@ -35,16 +48,20 @@ pub fn create_gencopy_mutator<VM: VMBinding>(
     let gencopy = mmtk.plan.downcast_ref::<GenCopy<VM>>().unwrap();
     let config = MutatorConfig {
         allocator_mapping: &ALLOCATOR_MAPPING,
-        space_mapping: Box::new(create_gen_space_mapping(&*mmtk.plan, &gencopy.gen.nursery)),
+        space_mapping: Box::new({
+            let mut vec = create_gen_space_mapping(&*mmtk.plan, &gencopy.gen.nursery);
+            if gencopy.gen.alloc_sites.is_some() {
+                vec.push((GENCOPY_MATURE_ALLOCATOR, gencopy.tospace()));
+            }
+            vec
+        }),
         prepare_func: &gencopy_mutator_prepare,
         release_func: &gencopy_mutator_release,
     };
 
     Mutator {
         allocators: Allocators::<VM>::new(mutator_tls, &*mmtk.plan, &config.space_mapping),
-        barrier: Box::new(ObjectBarrier::new(GenObjectBarrierSemantics::new(
-            mmtk, gencopy,
-        ))),
+        barrier: create_gen_barrier(mmtk, gencopy),
         mutator_tls,
         config,
         plan: gencopy,
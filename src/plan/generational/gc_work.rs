@@ -1,8 +1,14 @@
 use atomic::Ordering;
 
+use crate::mmtk::SFT_MAP;
+use crate::plan::ObjectsClosure;
 use crate::plan::PlanTraceObject;
-use crate::scheduler::{gc_work::*, GCWork, GCWorker};
-use crate::util::ObjectReference;
+use crate::scheduler::{gc_work::*, GCWork, GCWorker, WorkBucketStage};
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::linear_scan::{DefaultObjectSize, ObjectIterator};
+use crate::util::metadata::field_log_bit;
+use crate::util::metadata::vo_bit;
+use crate::util::{Address, ObjectReference};
 use crate::vm::edge_shape::{Edge, MemorySlice};
 use crate::vm::*;
 use crate::MMTK;
@@ -42,7 +48,11 @@ impl<VM: VMBinding, P: GenerationalPlanExt<VM> + PlanTraceObject<VM>> ProcessEdg
     fn process_edge(&mut self, slot: EdgeOf<Self>) {
         let object = slot.load();
         let new_object = self.trace_object(object);
-        debug_assert!(!self.plan.is_object_in_nursery(new_object));
+        // A nursery object that is not moved is pinned, and it stays in the nursery.
+        debug_assert!(
+            !self.plan.is_object_in_nursery(new_object)
+                || (cfg!(feature = "object_pinning") && new_object == object)
+        );
         slot.store(new_object);
     }
 
@@ -114,6 +124,52 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessModBuf<E> {
     }
 }
 
+/// The field modbuf contains a list of slots in mature objects that may point to the nursery space,
+/// and a list of mature objects whose slots could not be logged individually.
+/// This work packet forwards the recorded slots and scans the recorded objects.
+pub struct ProcessFieldModBuf<E: ProcessEdgesWork> {
+    modbuf: Vec<EdgeOf<E>>,
+    object_modbuf: Vec<ObjectReference>,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ProcessFieldModBuf<E> {
+    pub fn new(modbuf: Vec<EdgeOf<E>>, object_modbuf: Vec<ObjectReference>) -> Self {
+        debug_assert!(!modbuf.is_empty() || !object_modbuf.is_empty());
+        Self {
+            modbuf,
+            object_modbuf,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessFieldModBuf<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        // Flip the per-field log bits to "unlogged" state.
+        for edge in &self.modbuf {
+            field_log_bit::mark_field_as_unlogged(edge.slot_address().unwrap());
+        }
+        // Process the modbuf only if the current GC is a nursery GC
+        if mmtk.plan.generational().unwrap().is_current_gc_nursery() {
+            // Forward the recorded slots
+            let modbuf = std::mem::take(&mut self.modbuf);
+            if !modbuf.is_empty() {
+                GCWork::do_work(&mut E::new(modbuf, false, mmtk), worker, mmtk)
+            }
+            // Scan the recorded objects and forward pointers
+            let object_modbuf = std::mem::take(&mut self.object_modbuf);
+            if !object_modbuf.is_empty() {
+                GCWork::do_work(
+                    &mut ScanObjects::<E>::new(object_modbuf, false, false),
+                    worker,
+                    mmtk,
+                )
+            }
+        }
+    }
+}
+
 /// The array-copy modbuf contains a list of array slices in mature space(s) that
 /// may contain pointers to the nursery space.
 /// This work packet forwards and updates each entry in the recorded slices.
@@ -136,15 +192,178 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessRegionModBuf<E> {
     fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
         // Scan modbuf only if the current GC is a nursery GC
         if mmtk.plan.generational().unwrap().is_current_gc_nursery() {
-            // Collect all the entries in all the slices
-            let mut edges = vec![];
+            // Collect all the entries in all the slices, and forward them in work packets of at
+            // most `E::CAPACITY` edges each, so that large slices are processed in parallel.
+            let mut closure = ObjectsClosure::<E>::new(worker);
             for slice in &self.modbuf {
                 for edge in slice.iter_edges() {
-                    edges.push(edge);
+                    closure.visit_edge(edge);
                 }
             }
-            // Forward entries
-            GCWork::do_work(&mut E::new(edges, false, mmtk), worker, mmtk)
+        }
+    }
+}
+
+/// The maximum number of dirty cards in a [`ScanCards`] work packet.
+const CARDS_PER_PACKET: usize = 64;
+
+/// Find the dirty cards in the card table, and split them into [`ScanCards`] work packets.
+/// This is used in nursery GCs by plans that use the card-marking barrier.
+pub struct ScanDirtyCards<E: ProcessEdgesWork> {
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ScanDirtyCards<E> {
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> Default for ScanDirtyCards<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanDirtyCards<E> {
+    fn do_work(&mut self, _worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        let plan = mmtk.plan.generational().unwrap();
+        let card_table = plan.card_table().unwrap();
+        let mut packets: Vec<Box<dyn GCWork<E::VM>>> = vec![];
+        for chunk in card_table.take_dirty_chunks() {
+            let cards = card_table.take_dirty_cards(chunk);
+            // Nursery objects are traced anyway. No need to scan their cards.
+            if plan.is_address_in_nursery(chunk) {
+                continue;
+            }
+            for cards in cards.chunks(CARDS_PER_PACKET) {
+                packets.push(Box::new(ScanCards::<E>::new(cards.to_vec())));
+            }
+        }
+        debug!("Generated {} ScanCards packets", packets.len());
+        mmtk.scheduler.work_buckets[WorkBucketStage::Closure].bulk_add(packets);
+    }
+}
+
+/// Scan the mature objects that overlap a list of dirty cards, and process the slots in those cards.
+pub struct ScanCards<E: ProcessEdgesWork> {
+    /// The start addresses of the dirty cards, in increasing order.
+    cards: Vec<Address>,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ScanCards<E> {
+    pub fn new(cards: Vec<Address>) -> Self {
+        debug_assert!(!cards.is_empty());
+        debug_assert!(cards.windows(2).all(|w| w[0] < w[1]));
+        Self {
+            cards,
+            phantom: PhantomData,
+        }
+    }
+
+    fn object_end(object: ObjectReference) -> Address {
+        <E::VM as VMBinding>::VMObjectModel::ref_to_object_start(object)
+            + <E::VM as VMBinding>::VMObjectModel::get_current_size(object)
+    }
+
+    /// Find the last object that starts before the card. The object may or may not overlap with the card.
+    /// The search does not go beyond the space that contains the card.
+    fn find_object_before_card(card: Address) -> Option<ObjectReference> {
+        let space_name = SFT_MAP.get_checked(card).name();
+        let mut end = card;
+        while !end.is_zero() {
+            let chunk_start = (end - 1usize).align_down(BYTES_IN_CHUNK);
+            // Do not search other spaces. Their VO bits may not be mapped.
+            if SFT_MAP.get_checked(chunk_start).name() != space_name {
+                return None;
+            }
+            if let Some(object) = vo_bit::find_last_object_in_range::<E::VM>(chunk_start, end) {
+                return Some(object);
+            }
+            end = chunk_start;
+        }
+        None
+    }
+
+    /// Find the objects that overlap the dirty cards.
+    fn find_objects(&self, card_bytes: usize) -> Vec<ObjectReference> {
+        let mut objects = vec![];
+        // The end of the last object we found.
+        let mut found_end = Address::ZERO;
+        // The end of the last card we looked at.
+        let mut last_card_end = Address::ZERO;
+        for card in self.cards.iter().copied() {
+            let card_end = card + card_bytes;
+            // If the last card is adjacent, we have already found the object that overlaps with this card from before.
+            if last_card_end != card && found_end <= card {
+                if let Some(object) = Self::find_object_before_card(card) {
+                    let end = Self::object_end(object);
+                    if end > card {
+                        objects.push(object);
+                        found_end = end;
+                    }
+                }
+            }
+            let from = if found_end > card { found_end } else { card };
+            if from < card_end {
+                for object in
+                    ObjectIterator::<E::VM, DefaultObjectSize<E::VM>, true>::new(from, card_end)
+                {
+                    objects.push(object);
+                    found_end = Self::object_end(object);
+                }
+            }
+            last_card_end = card_end;
+        }
+        objects
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanCards<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        let plan = mmtk.plan.generational().unwrap();
+        let card_bytes = plan.card_table().unwrap().card_bytes();
+        let tls = worker.tls;
+        let cards = &self.cards;
+        let in_dirty_card =
+            |addr: Address| cards.binary_search(&addr.align_down(card_bytes)).is_ok();
+
+        let mut scan_later = vec![];
+        {
+            let mut closure = ObjectsClosure::<E>::new(worker);
+            for object in self.find_objects(card_bytes) {
+                // Only mature objects may point to nursery objects.
+                if plan.is_object_in_nursery(object) {
+                    continue;
+                }
+                if !<E::VM as VMBinding>::VMScanning::support_edge_enqueuing(tls, object) {
+                    scan_later.push(object);
+                    continue;
+                }
+                // Only process the slots in dirty cards. A slot without an address may be in any card.
+                <E::VM as VMBinding>::VMScanning::scan_object(
+                    tls,
+                    object,
+                    &mut |edge: EdgeOf<E>| {
+                        if edge.slot_address().map_or(true, in_dirty_card) {
+                            closure.visit_edge(edge);
+                        }
+                    },
+                );
+            }
+        }
+
+        // We do not know where the slots are for objects that do not support edge-enqueuing.
+        // Scan those objects as a whole.
+        if !scan_later.is_empty() {
+            GCWork::do_work(
+                &mut ScanObjects::<E>::new(scan_later, false, false),
+                worker,
+                mmtk,
+            )
         }
     }
 }
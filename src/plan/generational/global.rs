@@ -1,3 +1,4 @@
+use crate::plan::generational::pretenure::{AllocationSiteTracker, ALLOCATION_SITE_SPEC};
 use crate::plan::global::CommonPlan;
 use crate::plan::global::CreateSpecificPlanArgs;
 use crate::plan::ObjectQueue;
@@ -5,6 +6,8 @@ use crate::plan::Plan;
 use crate::policy::copyspace::CopySpace;
 use crate::policy::space::Space;
 use crate::scheduler::*;
+use crate::util::alloc::AllocatorSelector;
+use crate::util::card_table::CardTable;
 use crate::util::copy::CopySemantics;
 use crate::util::heap::VMRequest;
 use crate::util::metadata::side_metadata::SideMetadataSanity;
@@ -34,18 +37,47 @@ pub struct CommonGenPlan<VM: VMBinding> {
     /// Is next GC full heap?
     pub next_gc_full_heap: AtomicBool,
     pub full_heap_gc_count: Arc<Mutex<EventCounter>>,
+    /// Allocation site tracking for pretenuring. This is `None` if pretenuring is disabled.
+    pub alloc_sites: Option<AllocationSiteTracker>,
+    /// The card table for the card-marking barrier. This is `None` if the plan uses the object barrier.
+    pub card_table: Option<CardTable>,
 }
 
 impl<VM: VMBinding> CommonGenPlan<VM> {
-    pub fn new(mut args: CreateSpecificPlanArgs<VM>) -> Self {
-        let nursery = CopySpace::new(
+    /// Create the common generational plan.
+    ///
+    /// Arguments:
+    /// * `args`: the arguments to create the plan.
+    /// * `mature_allocator`: the mutator allocator that allocates into the mature space. This is used for pretenured allocation.
+    pub fn new(mut args: CreateSpecificPlanArgs<VM>, mature_allocator: AllocatorSelector) -> Self {
+        let pretenuring = *args.global_args.options.pretenuring;
+        let nursery = CopySpace::new_with_local_specs(
             args.get_space_args(
                 "nursery",
                 true,
                 VMRequest::fixed_extent(args.global_args.options.get_max_nursery_bytes(), false),
             ),
             true,
+            if pretenuring {
+                vec![ALLOCATION_SITE_SPEC]
+            } else {
+                vec![]
+            },
         );
+        let zeroing = *args.global_args.options.nursery_zeroing;
+        nursery
+            .get_page_resource()
+            .update_zeroing_approach(zeroing.is_nontemporal(), zeroing.is_concurrent());
+        let alloc_sites = if pretenuring {
+            Some(AllocationSiteTracker::new(
+                &args.global_args.options,
+                mature_allocator,
+            ))
+        } else {
+            None
+        };
+        let card_table = crate::plan::generational::uses_card_barrier(&args.global_args.options)
+            .then(|| CardTable::new(*args.global_args.options.card_size));
         let common = CommonPlan::new(args);
 
         let full_heap_gc_count = common.base.stats.new_event_counter("majorGC", true, true);
@@ -56,6 +88,8 @@ impl<VM: VMBinding> CommonGenPlan<VM> {
             gc_full_heap: AtomicBool::default(),
             next_gc_full_heap: AtomicBool::new(false),
             full_heap_gc_count,
+            alloc_sites,
+            card_table,
         }
     }
 
@@ -82,12 +116,41 @@ impl<VM: VMBinding> CommonGenPlan<VM> {
         self.nursery.prepare(true);
         self.nursery
             .set_copy_for_sft_trace(Some(CopySemantics::PromoteToMature));
+        #[cfg(feature = "object_pinning")]
+        self.nursery.set_keep_retained_objects(!full_heap);
+    }
+
+    /// Schedule a work packet to scan the objects kept in place in the nursery as roots, if the current
+    /// GC is a nursery GC. Those objects stay in the nursery, so the edges from mature objects to them are
+    /// not remembered. A generational plan should call this when scheduling a nursery GC.
+    #[cfg(feature = "object_pinning")]
+    pub fn schedule_scan_retained_nursery_objects<E: ProcessEdgesWork<VM = VM>>(
+        &self,
+        scheduler: &GCWorkScheduler<VM>,
+    ) {
+        let objects = self.nursery.retained_objects();
+        if !objects.is_empty() {
+            scheduler.work_buckets[WorkBucketStage::Closure].add(
+                crate::scheduler::gc_work::ScanObjects::<E>::new(objects, false, true),
+            );
+        }
     }
 
     /// Release Gen. This should be called by a single thread in GC release work.
     pub fn release(&mut self, tls: VMWorkerThread) {
         let full_heap = !self.is_current_gc_nursery();
         self.common.release(tls, full_heap);
+        if let Some(alloc_sites) = self.alloc_sites.as_ref() {
+            alloc_sites.update_decisions(!full_heap);
+            // All the nursery objects are either dead or promoted. Clear their allocation sites.
+            self.nursery
+                .bzero_allocated_side_metadata(&ALLOCATION_SITE_SPEC);
+        }
+        if let Some(card_table) = self.card_table.as_ref() {
+            // In a nursery GC, the dirty cards have been scanned and cleared.
+            // In a full heap GC, there is no mature-to-nursery pointer after the GC.
+            card_table.clear();
+        }
         self.nursery.release();
     }
 
@@ -243,6 +306,17 @@ impl<VM: VMBinding> CommonGenPlan<VM> {
     ) -> ObjectReference {
         // Evacuate nursery objects
         if self.nursery.in_space(object) {
+            if let Some(alloc_sites) = self.alloc_sites.as_ref() {
+                return self.nursery.trace_object_with_copy_hook::<Q, _>(
+                    queue,
+                    object,
+                    Some(CopySemantics::PromoteToMature),
+                    worker,
+                    |object, new_object| {
+                        alloc_sites.on_nursery_object_promoted::<VM>(object, new_object)
+                    },
+                );
+            }
             return self.nursery.trace_object::<Q>(
                 queue,
                 object,
@@ -317,6 +391,13 @@ pub trait GenerationalPlan: Plan {
     /// conservative result.
     fn is_address_in_nursery(&self, addr: Address) -> bool;
 
+    /// Is the object kept in place in the nursery because it was pinned during a GC? Such objects are
+    /// scanned as roots in nursery GCs, and edges from mature objects to them do not need to be remembered.
+    #[cfg(feature = "object_pinning")]
+    fn is_object_retained_in_nursery(&self, _object: ObjectReference) -> bool {
+        false
+    }
+
     /// Return the number of pages available for allocation into the mature space.
     fn get_mature_physical_pages_available(&self) -> usize;
 
@@ -328,6 +409,16 @@ pub trait GenerationalPlan: Plan {
 
     /// Force the next collection to be full heap.
     fn force_full_heap_collection(&self);
+
+    /// Return the allocation site tracker if the plan supports pretenuring and pretenuring is enabled.
+    fn allocation_site_tracker(&self) -> Option<&AllocationSiteTracker> {
+        None
+    }
+
+    /// Return the card table if the plan uses the card-marking barrier.
+    fn card_table(&self) -> Option<&CardTable> {
+        None
+    }
 }
 
 /// This trait is the extension trait for [`GenerationalPlan`] (see Rust's extension trait pattern).
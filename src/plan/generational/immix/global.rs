@@ -1,13 +1,17 @@
 use super::gc_work::GenImmixMatureGCWorkContext;
 use super::gc_work::GenImmixNurseryGCWorkContext;
+use crate::plan::generational::gc_work::GenNurseryProcessEdges;
+use crate::plan::generational::gc_work::ScanDirtyCards;
 use crate::plan::generational::global::CommonGenPlan;
 use crate::plan::generational::global::GenerationalPlan;
+use crate::plan::generational::pretenure::AllocationSiteTracker;
 use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::CreateGeneralPlanArgs;
 use crate::plan::global::CreateSpecificPlanArgs;
 use crate::plan::global::GcStatus;
 use crate::plan::AllocationSemantics;
+use crate::plan::BarrierSelector;
 use crate::plan::Plan;
 use crate::plan::PlanConstraints;
 use crate::policy::immix::ImmixSpace;
@@ -16,9 +20,12 @@ use crate::policy::immix::{TRACE_KIND_DEFRAG, TRACE_KIND_FAST};
 use crate::policy::space::Space;
 use crate::scheduler::GCWorkScheduler;
 use crate::scheduler::GCWorker;
+use crate::scheduler::WorkBucketStage;
 use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::card_table::CardTable;
 use crate::util::copy::*;
 use crate::util::heap::VMRequest;
+use crate::util::options::Options;
 use crate::util::Address;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
@@ -48,6 +55,8 @@ pub struct GenImmix<VM: VMBinding> {
     pub last_gc_was_defrag: AtomicBool,
     /// Whether the last GC was a full heap GC
     pub last_gc_was_full_heap: AtomicBool,
+    /// The constraints of the plan. They depend on the block size of the immix space.
+    constraints: PlanConstraints,
 }
 
 pub const GENIMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
@@ -63,12 +72,22 @@ pub const GENIMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
     ),
     ..crate::plan::generational::GEN_CONSTRAINTS
 };
+/// GenImmix constraints when the card-marking barrier is used.
+pub const GENIMMIX_CARD_BARRIER_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    max_non_los_default_alloc_bytes: GENIMMIX_CONSTRAINTS.max_non_los_default_alloc_bytes,
+    ..crate::plan::generational::GEN_CARD_BARRIER_CONSTRAINTS
+};
+/// GenImmix constraints when the field barrier is used.
+pub const GENIMMIX_FIELD_BARRIER_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    max_non_los_default_alloc_bytes: GENIMMIX_CONSTRAINTS.max_non_los_default_alloc_bytes,
+    ..crate::plan::generational::GEN_FIELD_BARRIER_CONSTRAINTS
+};
 
 impl<VM: VMBinding> Plan for GenImmix<VM> {
     type VM = VM;
 
-    fn constraints(&self) -> &'static PlanConstraints {
-        &GENIMMIX_CONSTRAINTS
+    fn constraints(&self) -> &PlanConstraints {
+        &self.constraints
     }
 
     fn create_copy_config(&'static self) -> CopyConfig<Self::VM> {
@@ -80,15 +99,15 @@ impl<VM: VMBinding> Plan for GenImmix<VM> {
                 _ => CopySelector::Unused,
             },
             space_mapping: vec![(CopySelector::ImmixHybrid(0), &self.immix_space)],
-            constraints: &GENIMMIX_CONSTRAINTS,
+            constraints: self.constraints(),
         }
     }
 
     fn last_collection_was_exhaustive(&self) -> bool {
         self.last_gc_was_full_heap.load(Ordering::Relaxed)
-            && ImmixSpace::<VM>::is_last_gc_exhaustive(
-                self.last_gc_was_defrag.load(Ordering::Relaxed),
-            )
+            && self
+                .immix_space
+                .is_last_gc_exhaustive(self.last_gc_was_defrag.load(Ordering::Relaxed))
     }
 
     fn collection_required(&self, space_full: bool, space: Option<&dyn Space<Self::VM>>) -> bool
@@ -118,6 +137,15 @@ impl<VM: VMBinding> Plan for GenImmix<VM> {
         if !is_full_heap {
             debug!("Nursery GC");
             scheduler.schedule_common_work::<GenImmixNurseryGCWorkContext<VM>>(self);
+            if self.gen.card_table.is_some() {
+                scheduler.work_buckets[WorkBucketStage::Closure]
+                    .add(ScanDirtyCards::<GenNurseryProcessEdges<VM, Self>>::new());
+            }
+            #[cfg(feature = "object_pinning")]
+            self.gen
+                .schedule_scan_retained_nursery_objects::<GenNurseryProcessEdges<VM, Self>>(
+                    scheduler,
+                );
         } else {
             crate::plan::immix::Immix::schedule_immix_full_heap_collection::<
                 GenImmix<VM>,
@@ -204,6 +232,11 @@ impl<VM: VMBinding> GenerationalPlan for GenImmix<VM> {
         self.gen.nursery.address_in_space(addr)
     }
 
+    #[cfg(feature = "object_pinning")]
+    fn is_object_retained_in_nursery(&self, object: ObjectReference) -> bool {
+        self.gen.nursery.is_in_retained_region(object)
+    }
+
     fn get_mature_physical_pages_available(&self) -> usize {
         self.immix_space.available_physical_pages()
     }
@@ -219,6 +252,14 @@ impl<VM: VMBinding> GenerationalPlan for GenImmix<VM> {
     fn last_collection_full_heap(&self) -> bool {
         self.gen.last_collection_full_heap()
     }
+
+    fn allocation_site_tracker(&self) -> Option<&AllocationSiteTracker> {
+        self.gen.alloc_sites.as_ref()
+    }
+
+    fn card_table(&self) -> Option<&CardTable> {
+        self.gen.card_table.as_ref()
+    }
 }
 
 impl<VM: VMBinding> crate::plan::generational::global::GenerationalPlanExt<VM> for GenImmix<VM> {
@@ -234,11 +275,23 @@ impl<VM: VMBinding> crate::plan::generational::global::GenerationalPlanExt<VM> f
 
 impl<VM: VMBinding> GenImmix<VM> {
     pub fn new(args: CreateGeneralPlanArgs<VM>) -> Self {
+        let space_args = ImmixSpaceArgs::from_options(&args.options);
+        let base_constraints = Self::get_constraints(&args.options);
+        // Like GENIMMIX_CONSTRAINTS, limit the objects allocated without LOS by the max immix object size.
+        let constraints = PlanConstraints {
+            max_non_los_default_alloc_bytes: usize::min(
+                space_args.max_object_size(),
+                crate::plan::generational::GEN_CONSTRAINTS.max_non_los_default_alloc_bytes,
+            ),
+            ..*base_constraints
+        };
         let mut plan_args = CreateSpecificPlanArgs {
             global_args: args,
-            constraints: &GENIMMIX_CONSTRAINTS,
+            constraints: base_constraints,
             global_side_metadata_specs:
-                crate::plan::generational::new_generational_global_metadata_specs::<VM>(),
+                crate::plan::generational::new_generational_global_metadata_specs::<VM>(
+                    base_constraints,
+                ),
         };
         let immix_space = ImmixSpace::new(
             plan_args.get_space_args("immix_mature", true, VMRequest::discontiguous()),
@@ -249,14 +302,19 @@ impl<VM: VMBinding> GenImmix<VM> {
                 unlog_object_when_traced: false,
                 // In GenImmix, young objects are not allocated in ImmixSpace directly.
                 mixed_age: false,
+                ..space_args
             },
         );
 
         let genimmix = GenImmix {
-            gen: CommonGenPlan::new(plan_args),
+            gen: CommonGenPlan::new(
+                plan_args,
+                crate::plan::generational::GENIMMIX_MATURE_ALLOCATOR,
+            ),
             immix_space,
             last_gc_was_defrag: AtomicBool::new(false),
             last_gc_was_full_heap: AtomicBool::new(false),
+            constraints,
         };
 
         // Use SideMetadataSanity to check if each spec is valid. This is also needed for check
@@ -275,6 +333,14 @@ impl<VM: VMBinding> GenImmix<VM> {
         genimmix
     }
 
+    fn get_constraints(options: &Options) -> &'static PlanConstraints {
+        match *options.generational_barrier {
+            BarrierSelector::CardBarrier => &GENIMMIX_CARD_BARRIER_CONSTRAINTS,
+            BarrierSelector::FieldBarrier => &GENIMMIX_FIELD_BARRIER_CONSTRAINTS,
+            _ => &GENIMMIX_CONSTRAINTS,
+        }
+    }
+
     fn requires_full_heap_collection(&self) -> bool {
         self.gen.requires_full_heap_collection(self)
     }
@@ -6,4 +6,6 @@ pub(in crate::plan) mod mutator;
 
 pub use self::global::GenImmix;
 
+pub use self::global::GENIMMIX_CARD_BARRIER_CONSTRAINTS;
 pub use self::global::GENIMMIX_CONSTRAINTS;
+pub use self::global::GENIMMIX_FIELD_BARRIER_CONSTRAINTS;
@@ -1,13 +1,14 @@
 pub(super) use super::super::ALLOCATOR_MAPPING;
-use crate::plan::barriers::ObjectBarrier;
-use crate::plan::generational::barrier::GenObjectBarrierSemantics;
+use crate::plan::generational::create_gen_barrier;
 use crate::plan::generational::create_gen_space_mapping;
 use crate::plan::generational::immix::GenImmix;
+use crate::plan::generational::GENIMMIX_MATURE_ALLOCATOR;
 use crate::plan::mutator_context::Mutator;
 use crate::plan::mutator_context::MutatorConfig;
 use crate::plan::AllocationSemantics;
 use crate::util::alloc::allocators::Allocators;
 use crate::util::alloc::BumpAllocator;
+use crate::util::alloc::ImmixAllocator;
 use crate::util::{VMMutatorThread, VMWorkerThread};
 use crate::vm::VMBinding;
 use crate::MMTK;
@@ -24,6 +25,24 @@ pub fn genimmix_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls:
     .downcast_mut::<BumpAllocator<VM>>()
     .unwrap();
     bump_allocator.reset();
+
+    // reset the allocator for pretenured objects
+    if mutator
+        .plan
+        .generational()
+        .unwrap()
+        .allocation_site_tracker()
+        .is_some()
+    {
+        let immix_allocator = unsafe {
+            mutator
+                .allocators
+                .get_allocator_mut(GENIMMIX_MATURE_ALLOCATOR)
+        }
+        .downcast_mut::<ImmixAllocator<VM>>()
+        .unwrap();
+        immix_allocator.reset();
+    }
 }
 
 pub fn create_genimmix_mutator<VM: VMBinding>(
@@ -33,16 +52,20 @@ pub fn create_genimmix_mutator<VM: VMBinding>(
     let genimmix = mmtk.plan.downcast_ref::<GenImmix<VM>>().unwrap();
     let config = MutatorConfig {
         allocator_mapping: &ALLOCATOR_MAPPING,
-        space_mapping: Box::new(create_gen_space_mapping(&*mmtk.plan, &genimmix.gen.nursery)),
+        space_mapping: Box::new({
+            let mut vec = create_gen_space_mapping(&*mmtk.plan, &genimmix.gen.nursery);
+            if genimmix.gen.alloc_sites.is_some() {
+                vec.push((GENIMMIX_MATURE_ALLOCATOR, &genimmix.immix_space));
+            }
+            vec
+        }),
         prepare_func: &genimmix_mutator_prepare,
         release_func: &genimmix_mutator_release,
     };
 
     Mutator {
         allocators: Allocators::<VM>::new(mutator_tls, &*mmtk.plan, &config.space_mapping),
-        barrier: Box::new(ObjectBarrier::new(GenObjectBarrierSemantics::new(
-            mmtk, genimmix,
-        ))),
+        barrier: create_gen_barrier(mmtk, genimmix),
         mutator_tls,
         config,
         plan: genimmix,
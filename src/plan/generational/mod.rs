@@ -1,18 +1,25 @@
 use enum_map::EnumMap;
 
 ///! Generational plans
-use crate::plan::barriers::BarrierSelector;
+use crate::plan::barriers::{Barrier, BarrierSelector, CardBarrier, FieldBarrier, ObjectBarrier};
+use crate::plan::generational::barrier::{GenFieldBarrierSemantics, GenObjectBarrierSemantics};
+use crate::plan::generational::global::GenerationalPlanExt;
 use crate::plan::mutator_context::create_allocator_mapping;
 use crate::plan::AllocationSemantics;
 use crate::plan::PlanConstraints;
+use crate::plan::PlanTraceObject;
 use crate::policy::copyspace::CopySpace;
 use crate::policy::space::Space;
 use crate::util::alloc::AllocatorSelector;
+use crate::util::card_table::CardTable;
+use crate::util::metadata::field_log_bit::FIELD_LOG_BIT_SIDE_METADATA_SPEC;
 use crate::util::metadata::side_metadata::SideMetadataContext;
 use crate::util::metadata::side_metadata::SideMetadataSpec;
+use crate::util::options::Options;
 use crate::vm::ObjectModel;
 use crate::vm::VMBinding;
 use crate::Plan;
+use crate::MMTK;
 
 use super::mutator_context::create_space_mapping;
 use super::mutator_context::ReservedAllocators;
@@ -24,6 +31,8 @@ pub mod barrier;
 pub mod copying;
 /// Generational immix (GenImmix)
 pub mod immix;
+/// Allocation-site based pretenuring
+pub mod pretenure;
 
 // Common generational code
 
@@ -36,6 +45,12 @@ pub(super) mod global;
 ///  - Set `ACTIVE_BARRIER` to `BarrierSelector::NoBarrier`.
 /// ## 2. Object barrier
 ///  - Set `ACTIVE_BARRIER` to `BarrierSelector::ObjectBarrier`.
+/// ## 3. Card-marking barrier
+///  - Set the `generational_barrier` option to `CardBarrier`.
+/// ## 4. Field barrier
+///  - Set the `generational_barrier` option to `FieldBarrier`.
+///
+/// `ACTIVE_BARRIER` is only used when the `generational_barrier` option is `ObjectBarrier` (the default).
 pub const ACTIVE_BARRIER: BarrierSelector = BarrierSelector::ObjectBarrier;
 /// Full heap collection as nursery GC.
 pub const FULL_NURSERY_GC: bool = false;
@@ -57,22 +72,65 @@ pub const GEN_CONSTRAINTS: PlanConstraints = PlanConstraints {
     ..PlanConstraints::default()
 };
 
+/// Constraints for generational plans that use the card-marking barrier.
+/// Each generational plan that supports the card-marking barrier should overwrite based on this constant.
+pub const GEN_CARD_BARRIER_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    needs_log_bit: false,
+    barrier: BarrierSelector::CardBarrier,
+    // An object that overlaps dirty cards scanned by different work packets is scanned more than once.
+    may_trace_duplicate_edges: true,
+    ..GEN_CONSTRAINTS
+};
+
+/// Constraints for generational plans that use the field barrier.
+/// Each generational plan that supports the field barrier should overwrite based on this constant.
+pub const GEN_FIELD_BARRIER_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    needs_log_bit: false,
+    needs_field_log_bit: true,
+    barrier: BarrierSelector::FieldBarrier,
+    // A slot may be recorded by both the field barrier and the array-copy barrier.
+    may_trace_duplicate_edges: true,
+    ..GEN_CONSTRAINTS
+};
+
+/// Does the plan use the card-marking barrier? This is selected by the `generational_barrier` option.
+pub(crate) fn uses_card_barrier(options: &Options) -> bool {
+    *options.generational_barrier == BarrierSelector::CardBarrier
+}
+
 /// Create global side metadata specs for generational plans. This will call SideMetadataContext::new_global_specs().
 /// So if a plan calls this, it should not call SideMetadataContext::new_global_specs() again.
-pub fn new_generational_global_metadata_specs<VM: VMBinding>() -> Vec<SideMetadataSpec> {
-    let specs = if ACTIVE_BARRIER == BarrierSelector::ObjectBarrier {
+pub fn new_generational_global_metadata_specs<VM: VMBinding>(
+    constraints: &PlanConstraints,
+) -> Vec<SideMetadataSpec> {
+    let barrier = constraints.barrier;
+    let mut specs = if constraints.needs_log_bit {
         crate::util::metadata::extract_side_metadata(&[*VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC])
     } else {
         vec![]
     };
+    if barrier == BarrierSelector::CardBarrier {
+        specs.extend(CardTable::side_metadata_specs());
+    }
+    if barrier == BarrierSelector::FieldBarrier {
+        specs.push(FIELD_LOG_BIT_SIDE_METADATA_SPEC);
+    }
     SideMetadataContext::new_global_specs(&specs)
 }
 
+// The first bump pointer allocator is used for the nursery. The second bump pointer allocator (GenCopy)
+// and the immix allocator (GenImmix) allocate pretenured objects into the mature space.
 const RESERVED_ALLOCATORS: ReservedAllocators = ReservedAllocators {
-    n_bump_pointer: 1,
+    n_bump_pointer: 2,
+    n_immix: 1,
     ..ReservedAllocators::DEFAULT
 };
 
+/// The mutator allocator for pretenured objects in GenCopy.
+pub(crate) const GENCOPY_MATURE_ALLOCATOR: AllocatorSelector = AllocatorSelector::BumpPointer(1);
+/// The mutator allocator for pretenured objects in GenImmix.
+pub(crate) const GENIMMIX_MATURE_ALLOCATOR: AllocatorSelector = AllocatorSelector::Immix(0);
+
 lazy_static! {
     static ref ALLOCATOR_MAPPING: EnumMap<AllocationSemantics, AllocatorSelector> = {
         let mut map = create_allocator_mapping(RESERVED_ALLOCATORS, true);
@@ -89,3 +147,22 @@ fn create_gen_space_mapping<VM: VMBinding>(
     vec.push((AllocatorSelector::BumpPointer(0), nursery));
     vec
 }
+
+/// Create the barrier for a mutator of a generational plan, as selected by the plan constraints.
+pub(crate) fn create_gen_barrier<
+    VM: VMBinding,
+    P: GenerationalPlanExt<VM> + PlanTraceObject<VM>,
+>(
+    mmtk: &'static MMTK<VM>,
+    plan: &'static P,
+) -> Box<dyn Barrier<VM>> {
+    match plan.constraints().barrier {
+        BarrierSelector::CardBarrier => Box::new(CardBarrier::new(plan.card_table().unwrap())),
+        BarrierSelector::FieldBarrier => {
+            Box::new(FieldBarrier::new(GenFieldBarrierSemantics::new(mmtk, plan)))
+        }
+        _ => Box::new(ObjectBarrier::new(GenObjectBarrierSemantics::new(
+            mmtk, plan,
+        ))),
+    }
+}
@@ -0,0 +1,332 @@
+//! Allocation-site based pretenuring for generational plans.
+//!
+//! A binding may allocate objects with an allocation site ID (see [`crate::memory_manager::alloc_with_site`]).
+//! For each object allocated into the nursery with a known site, we record the site in side metadata,
+//! and in nursery GCs we count the bytes promoted to the mature space for each site. At the end of each
+//! nursery GC, a site whose survival rate reaches the `pretenure_threshold` option is marked as pretenured,
+//! and later allocations from the site are redirected to the mature space allocator of the mutator.
+//!
+//! Pretenuring decisions are sticky: once a site is pretenured, it stays pretenured. We do not track
+//! the death of pretenured objects in the mature space.
+//!
+//! The tracker only exists if the `pretenuring` option is enabled. It allocates the counters for a group of
+//! sites when a site in the group is first used, and it only visits the sites that have allocated into the
+//! nursery since the last GC when it updates the decisions.
+
+use crate::util::alloc::AllocatorSelector;
+use crate::util::metadata::side_metadata::SideMetadataSpec;
+use crate::util::options::Options;
+use crate::util::ObjectReference;
+use crate::vm::{ObjectModel, VMBinding};
+use spin::{Mutex, Once};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// The ID of an allocation site. The meaning of a site is defined by the binding, e.g. a bytecode index.
+pub type AllocationSiteId = u16;
+
+/// An unknown allocation site. Objects allocated from this site are never tracked or pretenured.
+pub const UNKNOWN_ALLOCATION_SITE: AllocationSiteId = 0;
+
+/// The number of allocation sites we can track.
+pub const MAX_ALLOCATION_SITES: usize = 1 << (std::mem::size_of::<AllocationSiteId>() * 8);
+
+/// Side metadata that records the allocation site for objects in the nursery. We record one site per 64 bytes, so the
+/// metadata takes 1/32 of the nursery. If two small objects share the same 64 bytes, both of them will be attributed
+/// to the site of the later one. This is fine for a heuristic.
+pub(crate) const ALLOCATION_SITE_SPEC: SideMetadataSpec =
+    crate::util::metadata::side_metadata::spec_defs::GEN_ALLOC_SITE;
+
+/// Statistics for an allocation site.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllocationSiteStats {
+    /// Bytes allocated into the nursery from this site before the last nursery GC.
+    pub allocated_bytes: usize,
+    /// Bytes from this site that survived the last nursery GC.
+    pub survived_bytes: usize,
+    /// Bytes allocated into the mature space from this site since the site was pretenured.
+    pub pretenured_bytes: usize,
+    /// Is this site pretenured? If so, objects from this site are allocated into the mature space.
+    pub pretenured: bool,
+}
+
+/// The number of sites in a group. The counters of the sites in a group are allocated together.
+const SITES_IN_GROUP: usize = 256;
+
+#[derive(Default)]
+struct SiteCounters {
+    /// Has the site allocated into the nursery since the last GC? If so, the site is in `touched_sites`.
+    touched: AtomicBool,
+    /// Bytes allocated into the nursery since the last GC
+    allocated: AtomicUsize,
+    /// Bytes promoted in the current GC
+    survived: AtomicUsize,
+    /// Bytes allocated into the nursery before the last nursery GC
+    last_allocated: AtomicUsize,
+    /// Bytes promoted in the last nursery GC
+    last_survived: AtomicUsize,
+    /// Bytes allocated into the mature space
+    pretenured_bytes: AtomicUsize,
+    /// Is the site pretenured?
+    pretenured: AtomicBool,
+}
+
+/// Per-site survival tracking and pretenuring decisions for a generational plan.
+pub struct AllocationSiteTracker {
+    /// The counters of the sites, one entry for each group of `SITES_IN_GROUP` sites. The counters of a group
+    /// are allocated when a site in the group is first used.
+    groups: Box<[Once<Box<[SiteCounters]>>]>,
+    /// The sites that have allocated into the nursery since the last GC.
+    touched_sites: Mutex<Vec<AllocationSiteId>>,
+    /// A site is pretenured if its survival rate (in percentage) is at least this value.
+    threshold: usize,
+    /// A site needs to allocate at least this many bytes into the nursery between two GCs before we make any decision for it.
+    min_bytes: usize,
+    /// The mutator allocator that allocates into the mature space.
+    mature_allocator: AllocatorSelector,
+}
+
+impl AllocationSiteTracker {
+    pub(crate) fn new(options: &Options, mature_allocator: AllocatorSelector) -> Self {
+        Self {
+            groups: (0..MAX_ALLOCATION_SITES / SITES_IN_GROUP)
+                .map(|_| Once::new())
+                .collect(),
+            touched_sites: Mutex::new(vec![]),
+            threshold: *options.pretenure_threshold,
+            min_bytes: *options.pretenure_min_bytes,
+            mature_allocator,
+        }
+    }
+
+    /// The mutator allocator that allocates pretenured objects into the mature space.
+    pub(crate) fn mature_allocator(&self) -> AllocatorSelector {
+        self.mature_allocator
+    }
+
+    /// Get the counters of the site if they have been allocated.
+    fn get_counters(&self, site: AllocationSiteId) -> Option<&SiteCounters> {
+        let site = site as usize;
+        self.groups[site / SITES_IN_GROUP]
+            .get()
+            .map(|group| &group[site % SITES_IN_GROUP])
+    }
+
+    /// Get the counters of the site, and allocate the counters of its group if they have not been allocated.
+    fn get_or_create_counters(&self, site: AllocationSiteId) -> &SiteCounters {
+        let site = site as usize;
+        let group = self.groups[site / SITES_IN_GROUP].call_once(|| {
+            (0..SITES_IN_GROUP)
+                .map(|_| SiteCounters::default())
+                .collect()
+        });
+        &group[site % SITES_IN_GROUP]
+    }
+
+    /// Should objects from the site be allocated into the mature space?
+    pub fn should_pretenure(&self, site: AllocationSiteId) -> bool {
+        self.get_counters(site).map_or(false, |counters| {
+            counters.pretenured.load(Ordering::Relaxed)
+        })
+    }
+
+    /// Get the statistics of the site.
+    pub fn get_stats(&self, site: AllocationSiteId) -> AllocationSiteStats {
+        let counters = match self.get_counters(site) {
+            Some(counters) => counters,
+            None => return AllocationSiteStats::default(),
+        };
+        AllocationSiteStats {
+            allocated_bytes: counters.last_allocated.load(Ordering::Relaxed),
+            survived_bytes: counters.last_survived.load(Ordering::Relaxed),
+            pretenured_bytes: counters.pretenured_bytes.load(Ordering::Relaxed),
+            pretenured: counters.pretenured.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get all the sites that are pretenured.
+    pub fn get_pretenured_sites(&self) -> Vec<AllocationSiteId> {
+        let mut sites = vec![];
+        for (index, group) in self.groups.iter().enumerate() {
+            if let Some(group) = group.get() {
+                for (offset, counters) in group.iter().enumerate() {
+                    if counters.pretenured.load(Ordering::Relaxed) {
+                        sites.push((index * SITES_IN_GROUP + offset) as AllocationSiteId);
+                    }
+                }
+            }
+        }
+        sites
+    }
+
+    /// An object is allocated into the nursery from the site.
+    pub(crate) fn on_nursery_alloc<VM: VMBinding>(
+        &self,
+        object: ObjectReference,
+        bytes: usize,
+        site: AllocationSiteId,
+    ) {
+        if site == UNKNOWN_ALLOCATION_SITE {
+            return;
+        }
+        ALLOCATION_SITE_SPEC.store_atomic::<u16>(
+            object.to_address::<VM>(),
+            site,
+            Ordering::Relaxed,
+        );
+        let counters = self.get_or_create_counters(site);
+        counters.allocated.fetch_add(bytes, Ordering::Relaxed);
+        if !counters.touched.load(Ordering::Relaxed)
+            && !counters.touched.swap(true, Ordering::Relaxed)
+        {
+            self.touched_sites.lock().push(site);
+        }
+    }
+
+    /// An object is allocated into the mature space from the site.
+    pub(crate) fn on_pretenured_alloc(&self, bytes: usize, site: AllocationSiteId) {
+        self.get_or_create_counters(site)
+            .pretenured_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// A nursery object is promoted to the mature space in a nursery GC. This should be called once for each promoted object.
+    pub(crate) fn on_nursery_object_promoted<VM: VMBinding>(
+        &self,
+        object: ObjectReference,
+        new_object: ObjectReference,
+    ) {
+        let site =
+            ALLOCATION_SITE_SPEC.load_atomic::<u16>(object.to_address::<VM>(), Ordering::Relaxed);
+        if site == UNKNOWN_ALLOCATION_SITE {
+            return;
+        }
+        let bytes = VM::VMObjectModel::get_current_size(new_object);
+        self.get_or_create_counters(site)
+            .survived
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Update pretenuring decisions at the end of a GC. This should be called by a single thread in GC release.
+    /// We only make decisions after nursery GCs, as we only count survivors in nursery GCs.
+    pub(crate) fn update_decisions(&self, nursery_gc: bool) {
+        // Only the sites that have allocated into the nursery since the last GC may have survivors.
+        let touched_sites = std::mem::take(&mut *self.touched_sites.lock());
+        for &site in touched_sites.iter() {
+            let counters = self.get_counters(site).unwrap();
+            counters.touched.store(false, Ordering::Relaxed);
+            let allocated = counters.allocated.swap(0, Ordering::Relaxed);
+            let survived = counters.survived.swap(0, Ordering::Relaxed);
+            if !nursery_gc || allocated == 0 {
+                continue;
+            }
+            counters.last_allocated.store(allocated, Ordering::Relaxed);
+            counters.last_survived.store(survived, Ordering::Relaxed);
+            let survival_rate = survived * 100 / allocated;
+            debug!(
+                "Allocation site {}: {} of {} bytes survived ({}%)",
+                site, survived, allocated, survival_rate
+            );
+            if allocated >= self.min_bytes
+                && survival_rate >= self.threshold
+                && !counters.pretenured.swap(true, Ordering::Relaxed)
+            {
+                info!(
+                    "Pretenure allocation site {}: {} of {} bytes survived ({}%)",
+                    site, survived, allocated, survival_rate
+                );
+            }
+        }
+        if nursery_gc {
+            debug!(
+                "Tracked {} allocation sites in this GC",
+                touched_sites.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_tracker() -> AllocationSiteTracker {
+        let mut options = Options::default();
+        options.pretenure_threshold.set(50);
+        options.pretenure_min_bytes.set(1024);
+        AllocationSiteTracker::new(&options, AllocatorSelector::BumpPointer(1))
+    }
+
+    fn count(
+        tracker: &AllocationSiteTracker,
+        site: AllocationSiteId,
+        allocated: usize,
+        survived: usize,
+    ) {
+        let counters = tracker.get_or_create_counters(site);
+        counters.allocated.fetch_add(allocated, Ordering::Relaxed);
+        counters.survived.fetch_add(survived, Ordering::Relaxed);
+        if !counters.touched.swap(true, Ordering::Relaxed) {
+            tracker.touched_sites.lock().push(site);
+        }
+    }
+
+    #[test]
+    fn pretenure_long_lived_site() {
+        let tracker = new_tracker();
+        count(&tracker, 1, 4096, 4096);
+        count(&tracker, 2, 4096, 1024);
+        // Not enough allocation to make a decision
+        count(&tracker, 3, 512, 512);
+        tracker.update_decisions(true);
+
+        assert!(tracker.should_pretenure(1));
+        assert!(!tracker.should_pretenure(2));
+        assert!(!tracker.should_pretenure(3));
+        assert_eq!(tracker.get_pretenured_sites(), vec![1]);
+        assert_eq!(
+            tracker.get_stats(2),
+            AllocationSiteStats {
+                allocated_bytes: 4096,
+                survived_bytes: 1024,
+                pretenured_bytes: 0,
+                pretenured: false,
+            }
+        );
+    }
+
+    #[test]
+    fn allocate_counters_for_used_sites() {
+        let tracker = new_tracker();
+        let allocated_groups = |tracker: &AllocationSiteTracker| {
+            tracker
+                .groups
+                .iter()
+                .filter(|group| group.get().is_some())
+                .count()
+        };
+        // Querying a site does not allocate its counters.
+        assert!(!tracker.should_pretenure(1));
+        assert_eq!(tracker.get_stats(1), AllocationSiteStats::default());
+        assert_eq!(allocated_groups(&tracker), 0);
+
+        count(&tracker, 1, 4096, 4096);
+        count(&tracker, 1000, 4096, 4096);
+        assert_eq!(allocated_groups(&tracker), 2);
+        tracker.update_decisions(true);
+        assert!(tracker.touched_sites.lock().is_empty());
+        assert_eq!(tracker.get_pretenured_sites(), vec![1, 1000]);
+    }
+
+    #[test]
+    fn no_decision_in_full_heap_gc() {
+        let tracker = new_tracker();
+        count(&tracker, 1, 4096, 4096);
+        tracker.update_decisions(false);
+        assert!(!tracker.should_pretenure(1));
+        assert_eq!(tracker.get_stats(1), AllocationSiteStats::default());
+
+        // Counters are reset after each GC
+        tracker.update_decisions(true);
+        assert!(!tracker.should_pretenure(1));
+    }
+}
@@ -7,6 +7,8 @@ use crate::plan::tracing::ObjectQueue;
 use crate::plan::Mutator;
 use crate::policy::immortalspace::ImmortalSpace;
 use crate::policy::largeobjectspace::LargeObjectSpace;
+#[cfg(feature = "ro_space")]
+use crate::policy::readonlyspace::ReadOnlySpace;
 use crate::policy::space::{PlanCreateSpaceArgs, Space};
 #[cfg(feature = "vm_space")]
 use crate::policy::vmspace::VMSpace;
@@ -14,6 +16,8 @@ use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 #[cfg(feature = "analysis")]
 use crate::util::analysis::AnalysisManager;
+#[cfg(feature = "code_space")]
+use crate::util::code_protection::CodeProtection;
 use crate::util::copy::{CopyConfig, GCWorkerCopyContext};
 use crate::util::heap::gc_trigger::GCTrigger;
 use crate::util::heap::layout::Mmapper;
@@ -25,6 +29,8 @@ use crate::util::metadata::side_metadata::SideMetadataSpec;
 use crate::util::options::Options;
 use crate::util::options::PlanSelector;
 use crate::util::statistics::stats::Stats;
+#[cfg(feature = "code_space")]
+use crate::util::Address;
 use crate::util::ObjectReference;
 use crate::util::{VMMutatorThread, VMWorkerThread};
 use crate::vm::*;
@@ -168,7 +174,7 @@ pub fn create_gc_worker_context<VM: VMBinding>(
 pub trait Plan: 'static + Sync + Downcast {
     type VM: VMBinding;
 
-    fn constraints(&self) -> &'static PlanConstraints;
+    fn constraints(&self) -> &PlanConstraints;
 
     /// Create a copy config for this plan. A copying GC plan MUST override this method,
     /// and provide a valid config.
@@ -350,7 +356,7 @@ pub trait Plan: 'static + Sync + Downcast {
 
 impl_downcast!(Plan assoc VM);
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 pub enum GcStatus {
     NotInGC,
     GcPrepare,
@@ -371,6 +377,7 @@ pub struct BasePlan<VM: VMBinding> {
     pub last_stress_pages: AtomicUsize,
     pub emergency_collection: AtomicBool,
     pub user_triggered_collection: AtomicBool,
+    pub user_triggered_compaction: AtomicBool,
     pub internal_triggered_collection: AtomicBool,
     pub last_internal_triggered_collection: AtomicBool,
     // Has an allocation succeeded since the emergency collection?
@@ -408,22 +415,26 @@ pub struct BasePlan<VM: VMBinding> {
     #[cfg(feature = "code_space")]
     #[trace]
     pub code_lo_space: ImmortalSpace<VM>,
+    /// The executable ranges in the code spaces.
+    #[cfg(feature = "code_space")]
+    code_protection: CodeProtection,
     #[cfg(feature = "ro_space")]
     #[trace]
-    pub ro_space: ImmortalSpace<VM>,
+    pub ro_space: ReadOnlySpace<VM>,
 
     /// A VM space is a space allocated and populated by the VM.  Currently it is used by JikesRVM
-    /// for boot image.
+    /// for boot image.  It may consist of multiple regions, which can be added and removed at
+    /// runtime (see `memory_manager::add_vm_space_region`).
     ///
     /// If VM space is present, it has some special interaction with the
     /// `memory_manager::is_mmtk_object` and the `memory_manager::is_in_mmtk_spaces` functions.
     ///
-    /// -   The `is_mmtk_object` funciton requires the valid object (VO) bit side metadata to identify objects,
-    ///     but currently we do not require the boot image to provide it, so it will not work if the
-    ///     address argument is in the VM space.
+    /// -   The `is_mmtk_object` funciton requires the valid object (VO) bit side metadata to identify objects.
+    ///     It only works for objects in the VM space if the binding has called
+    ///     `memory_manager::initialize_vm_space_object` for the objects.
     ///
     /// -   The `is_in_mmtk_spaces` currently returns `true` if the given object reference is in
-    ///     the VM space.
+    ///     a region of the VM space.
     #[cfg(feature = "vm_space")]
     #[trace]
     pub vm_space: VMSpace<VM>,
@@ -494,8 +505,10 @@ impl<VM: VMBinding> BasePlan<VM> {
                 true,
                 VMRequest::discontiguous(),
             )),
+            #[cfg(feature = "code_space")]
+            code_protection: CodeProtection::new(),
             #[cfg(feature = "ro_space")]
-            ro_space: ImmortalSpace::new(args.get_space_args(
+            ro_space: ReadOnlySpace::new(args.get_space_args(
                 "ro_space",
                 true,
                 VMRequest::discontiguous(),
@@ -510,6 +523,7 @@ impl<VM: VMBinding> BasePlan<VM> {
             stacks_prepared: AtomicBool::new(false),
             emergency_collection: AtomicBool::new(false),
             user_triggered_collection: AtomicBool::new(false),
+            user_triggered_compaction: AtomicBool::new(false),
             internal_triggered_collection: AtomicBool::new(false),
             last_internal_triggered_collection: AtomicBool::new(false),
             allocation_success: AtomicBool::new(false),
@@ -577,6 +591,8 @@ impl<VM: VMBinding> BasePlan<VM> {
             .store(false, Ordering::SeqCst);
         self.user_triggered_collection
             .store(false, Ordering::Relaxed);
+        self.user_triggered_compaction
+            .store(false, Ordering::Relaxed);
     }
 
     // Depends on what base spaces we use, unsync may be unused.
@@ -642,6 +658,9 @@ impl<VM: VMBinding> BasePlan<VM> {
     }
 
     pub fn prepare(&mut self, _tls: VMWorkerThread, _full_heap: bool) {
+        // GC may write to code objects.
+        #[cfg(feature = "code_space")]
+        self.code_protection.prepare_for_gc();
         #[cfg(feature = "code_space")]
         self.code_space.prepare();
         #[cfg(feature = "code_space")]
@@ -657,12 +676,42 @@ impl<VM: VMBinding> BasePlan<VM> {
         self.code_space.release();
         #[cfg(feature = "code_space")]
         self.code_lo_space.release();
+        #[cfg(feature = "code_space")]
+        self.code_protection.release_after_gc();
         #[cfg(feature = "ro_space")]
         self.ro_space.release();
         #[cfg(feature = "vm_space")]
         self.vm_space.release();
     }
 
+    /// Make a range in the code spaces executable. See `memory_manager::make_code_executable`.
+    #[cfg(feature = "code_space")]
+    pub fn make_code_executable(&self, start: Address, bytes: usize) {
+        self.assert_in_code_space(start, bytes);
+        self.code_protection.make_executable(start, bytes);
+    }
+
+    /// Make a range in the code spaces writable. See `memory_manager::make_code_writable`.
+    #[cfg(feature = "code_space")]
+    pub fn make_code_writable(&self, start: Address, bytes: usize) {
+        self.assert_in_code_space(start, bytes);
+        self.code_protection.make_writable(start, bytes);
+    }
+
+    #[cfg(feature = "code_space")]
+    fn assert_in_code_space(&self, start: Address, bytes: usize) {
+        assert!(bytes > 0, "The code range is empty");
+        let last = start + bytes - 1;
+        assert!(
+            [&self.code_space, &self.code_lo_space]
+                .iter()
+                .any(|space| space.address_in_space(start) && space.address_in_space(last)),
+            "The range {} to {} is not in a code space",
+            start,
+            start + bytes
+        );
+    }
+
     pub fn set_collection_kind<P: Plan>(&self, plan: &P) {
         self.cur_collection_attempts.store(
             if self.is_user_triggered_collection() {
@@ -708,6 +757,11 @@ impl<VM: VMBinding> BasePlan<VM> {
         self.stacks_prepared.load(Ordering::SeqCst)
     }
 
+    /// Get the number of stacks that have been scanned in the current GC.
+    pub fn scanned_stacks(&self) -> usize {
+        self.scanned_stacks.load(Ordering::SeqCst)
+    }
+
     /// Prepare for stack scanning. This is usually used with `inform_stack_scanned()`.
     /// This should be called before doing stack scanning.
     pub fn prepare_for_stack_scanning(&self) {
@@ -758,6 +812,11 @@ impl<VM: VMBinding> BasePlan<VM> {
         self.user_triggered_collection.load(Ordering::Relaxed)
     }
 
+    /// Return true if this collection was triggered by application code to compact the heap.
+    pub fn is_user_triggered_compaction(&self) -> bool {
+        self.user_triggered_compaction.load(Ordering::Relaxed)
+    }
+
     /// Return true if this collection was triggered internally.
     pub fn is_internal_triggered_collection(&self) -> bool {
         let is_internal_triggered = self
@@ -1032,11 +1091,11 @@ pub enum AllocationSemantics {
     /// threshold must be allocated with the `Los` semantic.
     /// This semantic may get removed and MMTk will transparently allocate into large object space for large objects.
     Los = 2,
-    /// Code objects have execution permission.
-    /// Note that this is a place holder for now. Currently all the memory MMTk allocates has execution permission.
+    /// Code objects can be made executable with `memory_manager::make_code_executable`. Memory in
+    /// other spaces is never executable. This requires the feature `code_space`.
     Code = 3,
-    /// Read-only objects cannot be mutated once it is initialized.
-    /// Note that this is a place holder for now. It does not provide read only semantic.
+    /// Read-only objects cannot be mutated once the read-only space is sealed with
+    /// `memory_manager::seal_read_only_space`. This requires the feature `ro_space`.
     ReadOnly = 4,
     /// Los + Code.
     LargeCode = 5,
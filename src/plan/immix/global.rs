@@ -34,10 +34,12 @@ pub struct Immix<VM: VMBinding> {
     #[fallback_trace]
     pub common: CommonPlan<VM>,
     last_gc_was_defrag: AtomicBool,
+    /// The constraints of the plan. They depend on the arguments of the immix space.
+    constraints: PlanConstraints,
 }
 
 pub const IMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
-    moves_objects: crate::policy::immix::DEFRAG,
+    moves_objects: true,
     gc_header_bits: 2,
     gc_header_words: 0,
     num_specialized_scans: 1,
@@ -54,11 +56,12 @@ impl<VM: VMBinding> Plan for Immix<VM> {
     }
 
     fn last_collection_was_exhaustive(&self) -> bool {
-        ImmixSpace::<VM>::is_last_gc_exhaustive(self.last_gc_was_defrag.load(Ordering::Relaxed))
+        self.immix_space
+            .is_last_gc_exhaustive(self.last_gc_was_defrag.load(Ordering::Relaxed))
     }
 
-    fn constraints(&self) -> &'static PlanConstraints {
-        &IMMIX_CONSTRAINTS
+    fn constraints(&self) -> &PlanConstraints {
+        &self.constraints
     }
 
     fn create_copy_config(&'static self) -> CopyConfig<Self::VM> {
@@ -69,7 +72,7 @@ impl<VM: VMBinding> Plan for Immix<VM> {
                 _ => CopySelector::Unused,
             },
             space_mapping: vec![(CopySelector::Immix(0), &self.immix_space)],
-            constraints: &IMMIX_CONSTRAINTS,
+            constraints: &self.constraints,
         }
     }
 
@@ -128,30 +131,28 @@ impl<VM: VMBinding> Plan for Immix<VM> {
 
 impl<VM: VMBinding> Immix<VM> {
     pub fn new(args: CreateGeneralPlanArgs<VM>) -> Self {
+        let space_args = ImmixSpaceArgs::from_options(&args.options);
         let plan_args = CreateSpecificPlanArgs {
-            global_args: args,
             constraints: &IMMIX_CONSTRAINTS,
+            global_args: args,
             global_side_metadata_specs: SideMetadataContext::new_global_specs(&[]),
         };
-        Self::new_with_args(
-            plan_args,
-            ImmixSpaceArgs {
-                reset_log_bit_in_major_gc: false,
-                unlog_object_when_traced: false,
-                mixed_age: false,
-            },
-        )
+        Self::new_with_args(plan_args, space_args)
     }
 
+    /// Create the plan with the given arguments. The constraints in `plan_args` are for the default
+    /// arguments of the immix space, and the plan adjusts them for `space_args`.
     pub fn new_with_args(
         mut plan_args: CreateSpecificPlanArgs<VM>,
         space_args: ImmixSpaceArgs,
     ) -> Self {
+        let constraints = space_args.plan_constraints(plan_args.constraints);
         let immix = Immix {
             immix_space: ImmixSpace::new(
                 plan_args.get_space_args("immix", true, VMRequest::discontiguous()),
                 space_args,
             ),
+            constraints,
             common: CommonPlan::new(plan_args),
             last_gc_was_defrag: AtomicBool::new(false),
         };
@@ -186,6 +187,7 @@ impl<VM: VMBinding> Immix<VM> {
             plan.base().cur_collection_attempts.load(Ordering::SeqCst),
             plan.base().is_user_triggered_collection(),
             *plan.base().options.full_heap_system_gc,
+            plan.base().is_user_triggered_compaction(),
         );
 
         if in_defrag {
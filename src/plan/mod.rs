@@ -54,16 +54,25 @@ mod semispace;
 
 pub(crate) use generational::global::is_nursery_gc;
 pub(crate) use generational::global::GenerationalPlan;
+pub use generational::pretenure::{
+    AllocationSiteId, AllocationSiteStats, MAX_ALLOCATION_SITES, UNKNOWN_ALLOCATION_SITE,
+};
 
 // Expose plan constraints as public. Though a binding can get them from plan.constraints(),
 // it is possible for performance reasons that they want the constraints as constants.
 
+pub use generational::copying::GENCOPY_CARD_BARRIER_CONSTRAINTS;
 pub use generational::copying::GENCOPY_CONSTRAINTS;
+pub use generational::copying::GENCOPY_FIELD_BARRIER_CONSTRAINTS;
+pub use generational::immix::GENIMMIX_CARD_BARRIER_CONSTRAINTS;
 pub use generational::immix::GENIMMIX_CONSTRAINTS;
+pub use generational::immix::GENIMMIX_FIELD_BARRIER_CONSTRAINTS;
 pub use immix::IMMIX_CONSTRAINTS;
 pub use markcompact::MARKCOMPACT_CONSTRAINTS;
 pub use marksweep::MS_CONSTRAINTS;
 pub use nogc::NOGC_CONSTRAINTS;
 pub use pageprotect::PP_CONSTRAINTS;
 pub use semispace::SS_CONSTRAINTS;
+pub use sticky::immix::STICKY_IMMIX_CARD_BARRIER_CONSTRAINTS;
 pub use sticky::immix::STICKY_IMMIX_CONSTRAINTS;
+pub use sticky::immix::STICKY_IMMIX_FIELD_BARRIER_CONSTRAINTS;
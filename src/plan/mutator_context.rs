@@ -3,13 +3,16 @@
 use crate::plan::barriers::Barrier;
 use crate::plan::global::Plan;
 use crate::plan::AllocationSemantics;
+use crate::plan::AllocationSiteId;
 use crate::policy::space::Space;
 use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+use crate::util::metadata::field_log_bit;
 use crate::util::{Address, ObjectReference};
 use crate::util::{VMMutatorThread, VMWorkerThread};
-use crate::vm::VMBinding;
+use crate::vm::{ObjectModel, VMBinding};
 
 use enum_map::EnumMap;
+use std::sync::atomic::Ordering;
 
 pub(crate) type SpaceMapping<VM> = Vec<(AllocatorSelector, &'static dyn Space<VM>)>;
 
@@ -141,6 +144,73 @@ impl<VM: VMBinding> Mutator<VM> {
             .collect()
     }
 
+    /// Allocate memory for an object from the given allocation site. If the plan decides to pretenure objects
+    /// from the site, the object is allocated into the mature space. Otherwise, this is the same as `alloc()`.
+    pub fn alloc_with_site(
+        &mut self,
+        size: usize,
+        align: usize,
+        offset: usize,
+        allocator: AllocationSemantics,
+        site: AllocationSiteId,
+    ) -> Address {
+        if allocator == AllocationSemantics::Default {
+            if let Some(tracker) = self
+                .plan
+                .generational()
+                .and_then(|gen| gen.allocation_site_tracker())
+            {
+                if tracker.should_pretenure(site) {
+                    return unsafe {
+                        self.allocators
+                            .get_allocator_mut(tracker.mature_allocator())
+                    }
+                    .alloc(size, align, offset);
+                }
+            }
+        }
+        self.alloc(size, align, offset, allocator)
+    }
+
+    /// Perform post-allocation actions for an object allocated by `alloc_with_site()`.
+    pub fn post_alloc_with_site(
+        &mut self,
+        refer: ObjectReference,
+        bytes: usize,
+        allocator: AllocationSemantics,
+        site: AllocationSiteId,
+    ) {
+        if allocator == AllocationSemantics::Default {
+            if let Some(gen) = self.plan.generational() {
+                if let Some(tracker) = gen.allocation_site_tracker() {
+                    if gen.is_object_in_nursery(refer) {
+                        tracker.on_nursery_alloc::<VM>(refer, bytes, site);
+                    } else {
+                        // The object is pretenured.
+                        unsafe {
+                            self.allocators
+                                .get_allocator_mut(tracker.mature_allocator())
+                        }
+                        .get_space()
+                        .initialize_object_metadata(refer, true);
+                        // The object is mature. Mark it as unlogged so the barrier will remember it
+                        // when the binding writes references of nursery objects to it.
+                        if self.plan.constraints().needs_log_bit {
+                            VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
+                                .mark_as_unlogged::<VM>(refer, Ordering::SeqCst);
+                        }
+                        if self.plan.constraints().needs_field_log_bit {
+                            field_log_bit::mark_fields_as_unlogged::<VM>(refer, bytes);
+                        }
+                        tracker.on_pretenured_alloc(bytes, site);
+                        return;
+                    }
+                }
+            }
+        }
+        self.post_alloc(refer, bytes, allocator)
+    }
+
     /// Inform each allocator about destroying. Call allocator-specific on destroy methods.
     pub fn on_destroy(&mut self) {
         for selector in self.get_all_allocator_selectors() {
@@ -20,8 +20,25 @@ const MULTI_SPACE_RESERVED_ALLOCATORS: ReservedAllocators = ReservedAllocators {
 
 lazy_static! {
     /// When nogc_multi_space is disabled, force all the allocation go to the default allocator and space.
-    static ref ALLOCATOR_MAPPING_SINGLE_SPACE: EnumMap<AllocationSemantics, AllocatorSelector> = enum_map! {
-        _ => AllocatorSelector::BumpPointer(0),
+    static ref ALLOCATOR_MAPPING_SINGLE_SPACE: EnumMap<AllocationSemantics, AllocatorSelector> = {
+        #[allow(unused_mut)]
+        let mut map = enum_map! {
+            _ => AllocatorSelector::BumpPointer(0),
+        };
+        #[cfg(any(feature = "code_space", feature = "ro_space"))]
+        let multi_space = create_allocator_mapping(MULTI_SPACE_RESERVED_ALLOCATORS, false);
+        // Code objects still go to the code spaces, as only the code spaces can be made executable.
+        #[cfg(feature = "code_space")]
+        {
+            map[AllocationSemantics::Code] = multi_space[AllocationSemantics::Code];
+            map[AllocationSemantics::LargeCode] = multi_space[AllocationSemantics::LargeCode];
+        }
+        // Read-only objects still go to the read-only space, so they are protected when the space is sealed.
+        #[cfg(feature = "ro_space")]
+        {
+            map[AllocationSemantics::ReadOnly] = multi_space[AllocationSemantics::ReadOnly];
+        }
+        map
     };
     pub static ref ALLOCATOR_MAPPING: EnumMap<AllocationSemantics, AllocatorSelector> = {
         if cfg!(feature = "nogc_multi_space") {
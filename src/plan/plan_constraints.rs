@@ -21,6 +21,8 @@ pub struct PlanConstraints {
     pub max_non_los_copy_bytes: usize,
     /// Does this plan use the log bit? See vm::ObjectModel::GLOBAL_LOG_BIT_SPEC.
     pub needs_log_bit: bool,
+    /// Does this plan use the per-field log bits? See crate::util::metadata::field_log_bit.
+    pub needs_field_log_bit: bool,
     /// Some plans may allow benign race for testing mark bit, and this will lead to trace the same edges
     /// multiple times. If a plan allows tracing duplicate edges, we will not run duplicate edge check
     /// in extreme_assertions.
@@ -50,6 +52,7 @@ impl PlanConstraints {
             may_trace_duplicate_edges: false,
             needs_forward_after_liveness: false,
             needs_log_bit: false,
+            needs_field_log_bit: false,
             barrier: BarrierSelector::NoBarrier,
         }
     }
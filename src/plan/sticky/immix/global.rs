@@ -1,17 +1,23 @@
+use crate::plan::generational::gc_work::GenNurseryProcessEdges;
+use crate::plan::generational::gc_work::ScanDirtyCards;
 use crate::plan::generational::global::GenerationalPlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::CreateGeneralPlanArgs;
 use crate::plan::global::CreateSpecificPlanArgs;
 use crate::plan::immix;
+use crate::plan::BarrierSelector;
 use crate::plan::GcStatus;
 use crate::plan::PlanConstraints;
 use crate::policy::immix::ImmixSpace;
 use crate::policy::sft::SFT;
 use crate::policy::space::Space;
+use crate::scheduler::WorkBucketStage;
+use crate::util::card_table::CardTable;
 use crate::util::copy::CopyConfig;
 use crate::util::copy::CopySelector;
 use crate::util::copy::CopySemantics;
 use crate::util::metadata::side_metadata::SideMetadataContext;
+use crate::util::options::Options;
 use crate::util::statistics::counter::EventCounter;
 use crate::vm::ObjectModel;
 use crate::vm::VMBinding;
@@ -33,22 +39,36 @@ pub struct StickyImmix<VM: VMBinding> {
     gc_full_heap: AtomicBool,
     next_gc_full_heap: AtomicBool,
     full_heap_gc_count: Arc<Mutex<EventCounter>>,
+    /// The card table for the card-marking barrier. This is `None` if the plan uses the object barrier.
+    card_table: Option<CardTable>,
 }
 
 pub const STICKY_IMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
-    moves_objects: crate::policy::immix::DEFRAG || crate::policy::immix::PREFER_COPY_ON_NURSERY_GC,
+    // Immix may move objects in both defrag GCs and nursery GCs, unless the option `immix_non_moving` is set.
+    moves_objects: true,
     needs_log_bit: true,
     barrier: crate::plan::BarrierSelector::ObjectBarrier,
     // We may trace duplicate edges in sticky immix (or any plan that uses object remembering barrier). See https://github.com/mmtk/mmtk-core/issues/743.
     may_trace_duplicate_edges: true,
     ..immix::IMMIX_CONSTRAINTS
 };
-
+/// StickyImmix constraints when the card-marking barrier is used. We still need the log bit,
+/// as the immix space uses it to tell whether an object has been traced.
+pub const STICKY_IMMIX_CARD_BARRIER_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    barrier: crate::plan::BarrierSelector::CardBarrier,
+    ..STICKY_IMMIX_CONSTRAINTS
+};
+/// StickyImmix constraints when the field barrier is used. Like the card-marking barrier, we still need the log bit.
+pub const STICKY_IMMIX_FIELD_BARRIER_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    needs_field_log_bit: true,
+    barrier: crate::plan::BarrierSelector::FieldBarrier,
+    ..STICKY_IMMIX_CONSTRAINTS
+};
 impl<VM: VMBinding> Plan for StickyImmix<VM> {
     type VM = VM;
 
-    fn constraints(&self) -> &'static crate::plan::PlanConstraints {
-        &STICKY_IMMIX_CONSTRAINTS
+    fn constraints(&self) -> &crate::plan::PlanConstraints {
+        self.immix.constraints()
     }
 
     fn create_copy_config(&'static self) -> CopyConfig<Self::VM> {
@@ -59,7 +79,7 @@ impl<VM: VMBinding> Plan for StickyImmix<VM> {
                 _ => CopySelector::Unused,
             },
             space_mapping: vec![(CopySelector::Immix(0), &self.immix.immix_space)],
-            constraints: &STICKY_IMMIX_CONSTRAINTS,
+            constraints: self.constraints(),
         }
     }
 
@@ -92,6 +112,10 @@ impl<VM: VMBinding> Plan for StickyImmix<VM> {
             info!("Nursery GC");
             // nursery GC -- we schedule it
             scheduler.schedule_common_work::<StickyImmixNurseryGCWorkContext<VM>>(self);
+            if self.card_table.is_some() {
+                scheduler.work_buckets[WorkBucketStage::Closure]
+                    .add(ScanDirtyCards::<GenNurseryProcessEdges<VM, Self>>::new());
+            }
         } else {
             info!("Full heap GC");
             use crate::plan::immix::Immix;
@@ -127,6 +151,11 @@ impl<VM: VMBinding> Plan for StickyImmix<VM> {
     }
 
     fn release(&mut self, tls: crate::util::VMWorkerThread) {
+        if let Some(card_table) = self.card_table.as_ref() {
+            // In a nursery GC, the dirty cards have been scanned and cleared.
+            // In a full heap GC, there is no mature-to-nursery pointer after the GC.
+            card_table.clear();
+        }
         if self.is_current_gc_nursery() {
             let was_defrag = self.immix.immix_space.release(false);
             self.immix
@@ -230,6 +259,10 @@ impl<VM: VMBinding> GenerationalPlan for StickyImmix<VM> {
     fn last_collection_full_heap(&self) -> bool {
         self.gc_full_heap.load(Ordering::SeqCst)
     }
+
+    fn card_table(&self) -> Option<&CardTable> {
+        self.card_table.as_ref()
+    }
 }
 
 impl<VM: VMBinding> crate::plan::generational::global::GenerationalPlanExt<VM> for StickyImmix<VM> {
@@ -245,7 +278,7 @@ impl<VM: VMBinding> crate::plan::generational::global::GenerationalPlanExt<VM> f
                 trace!("Immix mature object {}, skip", object);
                 return object;
             } else {
-                let object = if crate::policy::immix::PREFER_COPY_ON_NURSERY_GC {
+                let object = if self.immix.immix_space.prefer_copy_on_nursery_gc() {
                     let ret = self.immix.immix_space.trace_object_with_opportunistic_copy(
                         queue,
                         object,
@@ -293,11 +326,17 @@ impl<VM: VMBinding> crate::plan::generational::global::GenerationalPlanExt<VM> f
 
 impl<VM: VMBinding> StickyImmix<VM> {
     pub fn new(args: CreateGeneralPlanArgs<VM>) -> Self {
+        let space_args = crate::policy::immix::ImmixSpaceArgs::from_options(&args.options);
+        let constraints = Self::get_constraints(&args.options);
+        let card_table = crate::plan::generational::uses_card_barrier(&args.options)
+            .then(|| CardTable::new(*args.options.card_size));
         let plan_args = CreateSpecificPlanArgs {
             global_args: args,
-            constraints: &STICKY_IMMIX_CONSTRAINTS,
+            constraints,
             global_side_metadata_specs: SideMetadataContext::new_global_specs(
-                &crate::plan::generational::new_generational_global_metadata_specs::<VM>(),
+                &crate::plan::generational::new_generational_global_metadata_specs::<VM>(
+                    constraints,
+                ),
             ),
         };
 
@@ -313,6 +352,7 @@ impl<VM: VMBinding> StickyImmix<VM> {
                 reset_log_bit_in_major_gc: true,
                 // In StickyImmix, both young and old objects are allocated in the ImmixSpace.
                 mixed_age: true,
+                ..space_args
             },
         );
         let full_heap_gc_count = immix.base().stats.new_event_counter("majorGC", true, true);
@@ -321,6 +361,16 @@ impl<VM: VMBinding> StickyImmix<VM> {
             gc_full_heap: AtomicBool::new(false),
             next_gc_full_heap: AtomicBool::new(false),
             full_heap_gc_count,
+            card_table,
+        }
+    }
+
+    /// Get the constraints for the default immix space arguments with the selected barrier.
+    fn get_constraints(options: &Options) -> &'static PlanConstraints {
+        match *options.generational_barrier {
+            BarrierSelector::CardBarrier => &STICKY_IMMIX_CARD_BARRIER_CONSTRAINTS,
+            BarrierSelector::FieldBarrier => &STICKY_IMMIX_FIELD_BARRIER_CONSTRAINTS,
+            _ => &STICKY_IMMIX_CONSTRAINTS,
         }
     }
 
@@ -3,4 +3,6 @@ pub(in crate::plan) mod global;
 pub(in crate::plan) mod mutator;
 
 pub use global::StickyImmix;
+pub use global::STICKY_IMMIX_CARD_BARRIER_CONSTRAINTS;
 pub use global::STICKY_IMMIX_CONSTRAINTS;
+pub use global::STICKY_IMMIX_FIELD_BARRIER_CONSTRAINTS;
@@ -1,5 +1,4 @@
-use crate::plan::barriers::ObjectBarrier;
-use crate::plan::generational::barrier::GenObjectBarrierSemantics;
+use crate::plan::generational::create_gen_barrier;
 use crate::plan::immix;
 use crate::plan::mutator_context::{create_space_mapping, MutatorConfig};
 use crate::plan::sticky::immix::global::StickyImmix;
@@ -39,10 +38,7 @@ pub fn create_stickyimmix_mutator<VM: VMBinding>(
 
     Mutator {
         allocators: Allocators::<VM>::new(mutator_tls, &*mmtk.plan, &config.space_mapping),
-        barrier: Box::new(ObjectBarrier::new(GenObjectBarrierSemantics::new(
-            mmtk,
-            stickyimmix,
-        ))),
+        barrier: create_gen_barrier(mmtk, stickyimmix),
         mutator_tls,
         config,
         plan: &*mmtk.plan,
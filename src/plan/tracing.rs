@@ -51,6 +51,11 @@ impl<T> VectorQueue<T> {
         self.buffer.len() >= Self::CAPACITY
     }
 
+    /// Remove the most recently pushed element and return it.
+    pub fn pop(&mut self) -> Option<T> {
+        self.buffer.pop()
+    }
+
     pub fn push(&mut self, v: T) {
         if self.buffer.is_empty() {
             self.buffer.reserve(Self::CAPACITY);
@@ -5,21 +5,38 @@ use crate::policy::sft::SFT;
 use crate::policy::space::{CommonSpace, Space};
 use crate::scheduler::GCWorker;
 use crate::util::copy::*;
-#[cfg(feature = "vo_bit")]
-use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
 use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::metadata::side_metadata::SideMetadataSpec;
 use crate::util::metadata::{extract_side_metadata, MetadataSpec};
 use crate::util::object_forwarding;
 use crate::util::{Address, ObjectReference};
 use crate::vm::*;
-use libc::{mprotect, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE};
+use libc::{mprotect, PROT_NONE, PROT_READ, PROT_WRITE};
+#[cfg(feature = "object_pinning")]
+use spin::RwLock;
 use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "object_pinning")]
+use std::sync::Mutex;
 
 /// This type implements a simple copying space.
 pub struct CopySpace<VM: VMBinding> {
     common: CommonSpace<VM>,
     pr: MonotonePageResource<VM>,
     from_space: AtomicBool,
+    /// Objects that are marked and kept in place in the current GC, because they are pinned, or they are in
+    /// the retained regions.
+    #[cfg(feature = "object_pinning")]
+    pinned_objects: Mutex<Vec<ObjectReference>>,
+    /// Objects kept in place by the last release of this space. Their memory is retained.
+    #[cfg(feature = "object_pinning")]
+    retained_objects: Mutex<Vec<ObjectReference>>,
+    /// The address ranges of `retained_objects`, sorted by address.
+    #[cfg(feature = "object_pinning")]
+    retained_regions: RwLock<Vec<(Address, Address)>>,
+    /// If true, objects in the retained regions are not moved or marked when this space is the from-space,
+    /// and they stay retained at the release. The plan must scan them as roots. This is used for nursery GCs.
+    #[cfg(feature = "object_pinning")]
+    keep_retained_objects: AtomicBool,
 }
 
 impl<VM: VMBinding> SFT for CopySpace<VM> {
@@ -28,22 +45,29 @@ impl<VM: VMBinding> SFT for CopySpace<VM> {
     }
 
     fn is_live(&self, object: ObjectReference) -> bool {
-        !self.is_from_space() || object_forwarding::is_forwarded::<VM>(object)
+        if !self.is_from_space() {
+            return true;
+        }
+        #[cfg(feature = "object_pinning")]
+        if self.is_kept_in_place(object) {
+            return self.keep_retained_objects.load(Ordering::SeqCst) || Self::is_marked(object);
+        }
+        object_forwarding::is_forwarded::<VM>(object)
     }
 
     #[cfg(feature = "object_pinning")]
-    fn pin_object(&self, _object: ObjectReference) -> bool {
-        panic!("Cannot pin/unpin objects of CopySpace.")
+    fn pin_object(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.pin_object::<VM>(object)
     }
 
     #[cfg(feature = "object_pinning")]
-    fn unpin_object(&self, _object: ObjectReference) -> bool {
-        panic!("Cannot pin/unpin objects of CopySpace.")
+    fn unpin_object(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.unpin_object::<VM>(object)
     }
 
     #[cfg(feature = "object_pinning")]
-    fn is_object_pinned(&self, _object: ObjectReference) -> bool {
-        false
+    fn is_object_pinned(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(object)
     }
 
     fn is_movable(&self) -> bool {
@@ -65,6 +89,11 @@ impl<VM: VMBinding> SFT for CopySpace<VM> {
             return None;
         }
 
+        #[cfg(feature = "object_pinning")]
+        if self.is_kept_in_place(object) {
+            return None;
+        }
+
         if object_forwarding::is_forwarded::<VM>(object) {
             Some(object_forwarding::read_forwarding_pointer::<VM>(object))
         } else {
@@ -136,16 +165,40 @@ impl<VM: VMBinding> crate::policy::gc_work::PolicyTraceObject<VM> for CopySpace<
 
 impl<VM: VMBinding> CopySpace<VM> {
     pub fn new(args: crate::policy::space::PlanCreateSpaceArgs<VM>, from_space: bool) -> Self {
+        Self::new_with_local_specs(args, from_space, vec![])
+    }
+
+    /// Create a copy space with additional local side metadata specs. This is used if a plan needs to
+    /// keep extra per-object metadata for this space (e.g. allocation sites for a nursery).
+    pub fn new_with_local_specs(
+        args: crate::policy::space::PlanCreateSpaceArgs<VM>,
+        from_space: bool,
+        extra_local_specs: Vec<SideMetadataSpec>,
+    ) -> Self {
         let vm_map = args.vm_map;
         let is_discontiguous = args.vmrequest.is_discontiguous();
-        let common = CommonSpace::new(args.into_policy_args(
-            true,
-            false,
-            extract_side_metadata(&[
-                *VM::VMObjectModel::LOCAL_FORWARDING_BITS_SPEC,
-                *VM::VMObjectModel::LOCAL_FORWARDING_POINTER_SPEC,
-            ]),
-        ));
+        let mut local_specs = extract_side_metadata(&[
+            *VM::VMObjectModel::LOCAL_FORWARDING_BITS_SPEC,
+            *VM::VMObjectModel::LOCAL_FORWARDING_POINTER_SPEC,
+            #[cfg(feature = "object_pinning")]
+            *VM::VMObjectModel::LOCAL_MARK_BIT_SPEC,
+            #[cfg(feature = "object_pinning")]
+            *VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC,
+        ]);
+        local_specs.extend(extra_local_specs);
+        // Objects kept in place are marked with the mark bit, while other objects are forwarded.
+        #[cfg(feature = "object_pinning")]
+        if let (MetadataSpec::InHeader(mark), MetadataSpec::InHeader(forwarding)) = (
+            *VM::VMObjectModel::LOCAL_MARK_BIT_SPEC,
+            *VM::VMObjectModel::LOCAL_FORWARDING_BITS_SPEC,
+        ) {
+            assert!(
+                mark.bit_offset + mark.num_of_bits as isize <= forwarding.bit_offset
+                    || forwarding.bit_offset + forwarding.num_of_bits as isize <= mark.bit_offset,
+                "The copy space needs a mark bit that does not overlap the forwarding bits for object pinning"
+            );
+        }
+        let common = CommonSpace::new(args.into_policy_args(true, false, local_specs));
         CopySpace {
             pr: if is_discontiguous {
                 MonotonePageResource::new_discontiguous(vm_map)
@@ -154,23 +207,87 @@ impl<VM: VMBinding> CopySpace<VM> {
             },
             common,
             from_space: AtomicBool::new(from_space),
+            #[cfg(feature = "object_pinning")]
+            pinned_objects: Mutex::new(vec![]),
+            #[cfg(feature = "object_pinning")]
+            retained_objects: Mutex::new(vec![]),
+            #[cfg(feature = "object_pinning")]
+            retained_regions: RwLock::new(vec![]),
+            #[cfg(feature = "object_pinning")]
+            keep_retained_objects: AtomicBool::new(false),
         }
     }
 
     pub fn prepare(&self, from_space: bool) {
         self.from_space.store(from_space, Ordering::SeqCst);
+        // Objects kept in place in the last GC may still be marked if the space was not released.
+        #[cfg(feature = "object_pinning")]
+        for object in self.pinned_objects.lock().unwrap().drain(..) {
+            Self::clear_mark(object);
+        }
         // Clear the metadata if we are using side forwarding status table. Otherwise
         // objects may inherit forwarding status from the previous GC.
         // TODO: Fix performance.
         if let MetadataSpec::OnSide(side_forwarding_status_table) =
             *<VM::VMObjectModel as ObjectModel<VM>>::LOCAL_FORWARDING_BITS_SPEC
         {
-            side_forwarding_status_table
-                .bzero_metadata(self.common.start, self.pr.cursor() - self.common.start);
+            side_forwarding_status_table.bzero_metadata(
+                self.common.start,
+                self.pr.allocated_end() - self.common.start,
+            );
+        }
+    }
+
+    /// Set whether objects in the retained regions should be kept in place without being traced when this
+    /// space is the from-space. This is used by generational plans for nursery GCs, in which case the plan
+    /// must scan [`CopySpace::retained_objects`] as roots, as mature objects that point to them are not
+    /// remembered. This should be called after [`CopySpace::prepare`].
+    #[cfg(feature = "object_pinning")]
+    pub fn set_keep_retained_objects(&self, keep: bool) {
+        self.keep_retained_objects.store(keep, Ordering::SeqCst);
+    }
+
+    /// Get the objects that were kept in place by the last release of this space.
+    #[cfg(feature = "object_pinning")]
+    pub fn retained_objects(&self) -> Vec<ObjectReference> {
+        self.retained_objects.lock().unwrap().clone()
+    }
+
+    /// Is the object in one of the regions retained for objects kept in place?
+    #[cfg(feature = "object_pinning")]
+    pub fn is_in_retained_region(&self, object: ObjectReference) -> bool {
+        let regions = self.retained_regions.read();
+        if regions.is_empty() {
+            return false;
         }
+        let addr = object.to_object_start::<VM>();
+        let index = regions.partition_point(|&(start, _)| start <= addr);
+        index > 0 && addr < regions[index - 1].1
+    }
+
+    /// Will the object be kept in place if it is traced when this space is the from-space?
+    #[cfg(feature = "object_pinning")]
+    fn is_kept_in_place(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(object)
+            || (self.keep_retained_objects.load(Ordering::Relaxed)
+                && self.is_in_retained_region(object))
+    }
+
+    /// Zero the given side metadata for the memory that has been allocated in this space since the last release.
+    /// This should be called before [`CopySpace::release`].
+    pub fn bzero_allocated_side_metadata(&self, spec: &SideMetadataSpec) {
+        debug_assert!(self.common.contiguous);
+        spec.bzero_metadata(
+            self.common.start,
+            self.pr.allocated_end() - self.common.start,
+        );
     }
 
     pub fn release(&self) {
+        #[cfg(feature = "object_pinning")]
+        let retained = self.retain_pinned_objects();
+        #[cfg(feature = "object_pinning")]
+        self.reset_pinning_bits();
         unsafe {
             #[cfg(feature = "vo_bit")]
             self.reset_vo_bit();
@@ -178,17 +295,119 @@ impl<VM: VMBinding> CopySpace<VM> {
         }
         self.common.metadata.reset();
         self.from_space.store(false, Ordering::SeqCst);
+        #[cfg(feature = "object_pinning")]
+        {
+            #[cfg(feature = "vo_bit")]
+            for object in retained.iter() {
+                crate::util::metadata::vo_bit::set_vo_bit::<VM>(*object);
+            }
+            *self.retained_objects.lock().unwrap() = retained;
+            self.keep_retained_objects.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Collect the objects that are kept in place in this GC, and retain their memory in the page resource.
+    /// Return the objects. Their mark bits are cleared.
+    #[cfg(feature = "object_pinning")]
+    fn retain_pinned_objects(&self) -> Vec<ObjectReference> {
+        let mut retained: Vec<ObjectReference> =
+            std::mem::take(&mut *self.pinned_objects.lock().unwrap());
+        for object in retained.iter() {
+            Self::clear_mark(*object);
+        }
+        if self.keep_retained_objects.load(Ordering::SeqCst) {
+            retained.extend(self.retained_objects.lock().unwrap().iter().copied());
+        }
+        let regions: Vec<(Address, Address)> = retained
+            .iter()
+            .map(|object| {
+                let start = object.to_object_start::<VM>();
+                (start, start + VM::VMObjectModel::get_current_size(*object))
+            })
+            .collect();
+        self.pr.set_retained_regions(regions.clone());
+        let mut sorted_regions = regions;
+        sorted_regions.sort_unstable();
+        *self.retained_regions.write() = sorted_regions;
+        retained
+    }
+
+    /// Clear the side pinning bits for the memory that has been allocated in this space, except for the
+    /// retained regions, so the objects allocated in the released memory are not pinned. This should be
+    /// called after [`CopySpace::retain_pinned_objects`] and before the page resource is reset.
+    #[cfg(feature = "object_pinning")]
+    fn reset_pinning_bits(&self) {
+        debug_assert!(self.common.contiguous);
+        let pinning_bit = VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC;
+        let mut cursor = self.common.start;
+        for &(start, end) in self.retained_regions.read().iter() {
+            if start > cursor {
+                pinning_bit.bzero_side_metadata(cursor, start - cursor);
+            }
+            cursor = cursor.max(end);
+        }
+        let allocated_end = self.pr.allocated_end();
+        if allocated_end > cursor {
+            pinning_bit.bzero_side_metadata(cursor, allocated_end - cursor);
+        }
+    }
+
+    /// Mark an object that is kept in place. Return true if this call marks the object.
+    #[cfg(feature = "object_pinning")]
+    fn attempt_mark(&self, object: ObjectReference) -> bool {
+        let marked = VM::VMObjectModel::LOCAL_MARK_BIT_SPEC
+            .compare_exchange_metadata::<VM, u8>(
+                object,
+                0,
+                1,
+                None,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok();
+        if marked {
+            // The object is not copied, so the copy context will not unlog it for us.
+            if self.common.needs_log_bit {
+                VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
+                    .mark_as_unlogged::<VM>(object, Ordering::SeqCst);
+            }
+            if self.common.needs_field_log_bit {
+                crate::util::metadata::field_log_bit::mark_fields_as_unlogged::<VM>(
+                    object,
+                    VM::VMObjectModel::get_current_size(object),
+                );
+            }
+            self.pinned_objects.lock().unwrap().push(object);
+        }
+        marked
+    }
+
+    #[cfg(feature = "object_pinning")]
+    fn is_marked(object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_MARK_BIT_SPEC.load_atomic::<VM, u8>(object, None, Ordering::SeqCst)
+            == 1
+    }
+
+    #[cfg(feature = "object_pinning")]
+    fn clear_mark(object: ObjectReference) {
+        VM::VMObjectModel::LOCAL_MARK_BIT_SPEC.store_atomic::<VM, u8>(
+            object,
+            0,
+            None,
+            Ordering::SeqCst,
+        );
     }
 
     #[cfg(feature = "vo_bit")]
     unsafe fn reset_vo_bit(&self) {
-        let current_chunk = self.pr.get_current_chunk();
         if self.common.contiguous {
-            // If we have allocated something into this space, we need to clear its VO bit.
-            if current_chunk != self.common.start {
+            // If we have allocated something into this space, we need to clear its VO bit. This includes the
+            // first chunk of the space, and the retained regions that may be beyond the cursor.
+            let allocated_end = self.pr.allocated_end();
+            if allocated_end > self.common.start {
                 crate::util::metadata::vo_bit::bzero_vo_bit(
                     self.common.start,
-                    current_chunk + BYTES_IN_CHUNK - self.common.start,
+                    allocated_end - self.common.start,
                 );
             }
         } else {
@@ -206,15 +425,53 @@ impl<VM: VMBinding> CopySpace<VM> {
         object: ObjectReference,
         semantics: Option<CopySemantics>,
         worker: &mut GCWorker<VM>,
+    ) -> ObjectReference {
+        self.trace_object_with_copy_hook(queue, object, semantics, worker, |_, _| {})
+    }
+
+    /// Trace an object in the same way as [`CopySpace::trace_object`]. If the object is copied by this call,
+    /// `on_copy` is called with the original object and the new object. As only one thread can copy an object,
+    /// `on_copy` is called at most once for each object in a GC.
+    pub fn trace_object_with_copy_hook<
+        Q: ObjectQueue,
+        F: FnOnce(ObjectReference, ObjectReference),
+    >(
+        &self,
+        queue: &mut Q,
+        object: ObjectReference,
+        semantics: Option<CopySemantics>,
+        worker: &mut GCWorker<VM>,
+        on_copy: F,
     ) -> ObjectReference {
         trace!("copyspace.trace_object(, {:?}, {:?})", object, semantics,);
 
         // If this is not from space, we do not need to trace it (the object has been copied to the tosapce)
         if !self.is_from_space() {
+            // Objects kept in place by a previous GC are not copied, and need to be scanned.
+            #[cfg(feature = "object_pinning")]
+            if self.is_in_retained_region(object) && self.attempt_mark(object) {
+                queue.enqueue(object);
+            }
             // The copy semantics for tospace should be none.
             return object;
         }
 
+        #[cfg(feature = "object_pinning")]
+        if self.keep_retained_objects.load(Ordering::Relaxed) && self.is_in_retained_region(object)
+        {
+            // The plan scans the retained objects as roots.
+            return object;
+        }
+
+        #[cfg(feature = "object_pinning")]
+        if VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(object) {
+            // Pinned objects are marked and kept in place. Their memory is retained at release.
+            if self.attempt_mark(object) {
+                queue.enqueue(object);
+            }
+            return object;
+        }
+
         // This object is in from space, we will copy. Make sure we have a valid copy semantic.
         debug_assert!(semantics.is_some());
 
@@ -246,6 +503,8 @@ impl<VM: VMBinding> CopySpace<VM> {
             #[cfg(feature = "vo_bit")]
             crate::util::metadata::vo_bit::set_vo_bit::<VM>(new_object);
 
+            on_copy(object, new_object);
+
             trace!("Forwarding pointer");
             queue.enqueue(new_object);
             trace!("Copied [{:?} -> {:?}]", object, new_object);
@@ -278,11 +537,7 @@ impl<VM: VMBinding> CopySpace<VM> {
         let start = self.common().start;
         let extent = self.common().extent;
         unsafe {
-            mprotect(
-                start.to_mut_ptr(),
-                extent,
-                PROT_READ | PROT_WRITE | PROT_EXEC,
-            );
+            mprotect(start.to_mut_ptr(), extent, PROT_READ | PROT_WRITE);
         }
         trace!("Unprotect {:x} {:x}", start, start + extent);
     }
@@ -5,12 +5,12 @@ use crate::util::constants::*;
 use crate::util::heap::blockpageresource::BlockPool;
 use crate::util::heap::chunk_map::Chunk;
 use crate::util::linear_scan::{Region, RegionIterator};
-use crate::util::metadata::side_metadata::{MetadataByteArrayRef, SideMetadataSpec};
+use crate::util::metadata::side_metadata::{address_to_meta_address, SideMetadataSpec};
 #[cfg(feature = "vo_bit")]
 use crate::util::metadata::vo_bit;
 use crate::util::Address;
 use crate::vm::*;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// The block allocation state.
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -68,14 +68,18 @@ impl BlockState {
 #[derive(Debug, Clone, Copy, PartialOrd, PartialEq)]
 pub struct Block(Address);
 
+/// The log of the block size in bytes. All the Immix spaces that are alive at the same time use the
+/// same block size, which is set from the options when the first of them is created.
+static LOG_BYTES_IN_BLOCK: AtomicUsize = AtomicUsize::new(Block::DEFAULT_LOG_BYTES);
+
 impl Region for Block {
-    #[cfg(not(feature = "immix_smaller_block"))]
-    const LOG_BYTES: usize = 15;
-    #[cfg(feature = "immix_smaller_block")]
-    const LOG_BYTES: usize = 13;
+    /// The block size is decided at run time, so `Block` does not have the constants `LOG_BYTES` and `BYTES`.
+    fn log_bytes() -> usize {
+        LOG_BYTES_IN_BLOCK.load(Ordering::Relaxed)
+    }
 
     fn from_aligned_address(address: Address) -> Self {
-        debug_assert!(address.is_aligned_to(Self::BYTES));
+        debug_assert!(address.is_aligned_to(Self::bytes()));
         Self(address)
     }
 
@@ -85,14 +89,43 @@ impl Region for Block {
 }
 
 impl Block {
+    /// The smallest block size that can be set with the option `immix_block_size`.
+    pub const MIN_LOG_BYTES: usize = 13;
+    /// The default block size. The default can be changed to the smallest block size with the
+    /// `immix_smaller_block` feature.
+    pub const DEFAULT_LOG_BYTES: usize = if cfg!(feature = "immix_smaller_block") {
+        Self::MIN_LOG_BYTES
+    } else {
+        15
+    };
+    /// The largest number of lines in a block. The number of unavailable lines in a reusable block
+    /// is stored in a byte, and must not be confused with the other block states.
+    pub const MAX_LOG_LINES: usize = 7;
+
+    pub(super) fn set_log_bytes(log_bytes: usize) {
+        LOG_BYTES_IN_BLOCK.store(log_bytes, Ordering::Relaxed);
+    }
+
     /// Log pages in block
-    pub const LOG_PAGES: usize = Self::LOG_BYTES - LOG_BYTES_IN_PAGE as usize;
+    pub fn log_pages() -> usize {
+        Self::log_bytes() - LOG_BYTES_IN_PAGE as usize
+    }
     /// Pages in block
-    pub const PAGES: usize = 1 << Self::LOG_PAGES;
+    pub fn pages() -> usize {
+        1 << Self::log_pages()
+    }
     /// Log lines in block
-    pub const LOG_LINES: usize = Self::LOG_BYTES - Line::LOG_BYTES;
+    pub fn log_lines() -> usize {
+        Self::log_bytes() - Line::log_bytes()
+    }
     /// Lines in block
-    pub const LINES: usize = 1 << Self::LOG_LINES;
+    pub fn num_lines() -> usize {
+        1 << Self::log_lines()
+    }
+    /// Blocks in chunk
+    pub fn blocks_in_chunk() -> usize {
+        1 << (Chunk::LOG_BYTES - Self::log_bytes())
+    }
 
     /// Block defrag state table (side)
     pub const DEFRAG_STATE_TABLE: SideMetadataSpec =
@@ -107,23 +140,35 @@ impl Block {
         Chunk::from_unaligned_address(self.0)
     }
 
-    /// Get the address range of the block's line mark table.
+    /// The address of the block in the side metadata for blocks. The side metadata has a byte for
+    /// each block of the smallest size. The n-th block in a chunk uses the byte of the n-th smallest
+    /// block in the chunk, so the bytes used by the blocks of a chunk are contiguous regardless of
+    /// the block size.
+    fn metadata_address(&self) -> Address {
+        let chunk = Chunk::align(self.start());
+        chunk + ((self.start() - chunk) >> (Self::log_bytes() - Self::MIN_LOG_BYTES))
+    }
+
+    /// Get the line mark table of the block, which has a byte for each line of the block.
     #[allow(clippy::assertions_on_constants)]
-    pub fn line_mark_table(&self) -> MetadataByteArrayRef<{ Block::LINES }> {
+    pub fn line_mark_table(&self) -> &'static [u8] {
         debug_assert!(!super::BLOCK_ONLY);
-        MetadataByteArrayRef::<{ Block::LINES }>::new(&Line::MARK_TABLE, self.start(), Self::BYTES)
+        let start =
+            address_to_meta_address(&Line::MARK_TABLE, self.start_line().metadata_address());
+        // Safety: the metadata memory is assumed to be mapped when accessing.
+        unsafe { std::slice::from_raw_parts(start.to_ptr::<u8>(), Self::num_lines()) }
     }
 
     /// Get block mark state.
     pub fn get_state(&self) -> BlockState {
-        let byte = Self::MARK_TABLE.load_atomic::<u8>(self.start(), Ordering::SeqCst);
+        let byte = Self::MARK_TABLE.load_atomic::<u8>(self.metadata_address(), Ordering::SeqCst);
         byte.into()
     }
 
     /// Set block mark state.
     pub fn set_state(&self, state: BlockState) {
         let state = u8::from(state);
-        Self::MARK_TABLE.store_atomic::<u8>(self.start(), state, Ordering::SeqCst);
+        Self::MARK_TABLE.store_atomic::<u8>(self.metadata_address(), state, Ordering::SeqCst);
     }
 
     // Defrag byte
@@ -132,7 +177,8 @@ impl Block {
 
     /// Test if the block is marked for defragmentation.
     pub fn is_defrag_source(&self) -> bool {
-        let byte = Self::DEFRAG_STATE_TABLE.load_atomic::<u8>(self.start(), Ordering::SeqCst);
+        let byte =
+            Self::DEFRAG_STATE_TABLE.load_atomic::<u8>(self.metadata_address(), Ordering::SeqCst);
         // The byte should be 0 (not defrag source) or 255 (defrag source) if this is a major defrag GC, as we set the values in PrepareBlockState.
         // But it could be any value in a nursery GC.
         byte == Self::DEFRAG_SOURCE_STATE
@@ -141,17 +187,26 @@ impl Block {
     /// Mark the block for defragmentation.
     pub fn set_as_defrag_source(&self, defrag: bool) {
         let byte = if defrag { Self::DEFRAG_SOURCE_STATE } else { 0 };
-        Self::DEFRAG_STATE_TABLE.store_atomic::<u8>(self.start(), byte, Ordering::SeqCst);
+        Self::DEFRAG_STATE_TABLE.store_atomic::<u8>(
+            self.metadata_address(),
+            byte,
+            Ordering::SeqCst,
+        );
     }
 
     /// Record the number of holes in the block.
     pub fn set_holes(&self, holes: usize) {
-        Self::DEFRAG_STATE_TABLE.store_atomic::<u8>(self.start(), holes as u8, Ordering::SeqCst);
+        Self::DEFRAG_STATE_TABLE.store_atomic::<u8>(
+            self.metadata_address(),
+            holes as u8,
+            Ordering::SeqCst,
+        );
     }
 
     /// Get the number of holes.
     pub fn get_holes(&self) -> usize {
-        let byte = Self::DEFRAG_STATE_TABLE.load_atomic::<u8>(self.start(), Ordering::SeqCst);
+        let byte =
+            Self::DEFRAG_STATE_TABLE.load_atomic::<u8>(self.metadata_address(), Ordering::SeqCst);
         debug_assert_ne!(byte, Self::DEFRAG_SOURCE_STATE);
         byte as usize
     }
@@ -163,7 +218,7 @@ impl Block {
         } else {
             BlockState::Unmarked
         });
-        Self::DEFRAG_STATE_TABLE.store_atomic::<u8>(self.start(), 0, Ordering::SeqCst);
+        Self::DEFRAG_STATE_TABLE.store_atomic::<u8>(self.metadata_address(), 0, Ordering::SeqCst);
     }
 
     /// Deinitalize a block before releasing.
@@ -201,6 +256,10 @@ impl Block {
                     #[cfg(feature = "vo_bit")]
                     vo_bit::helper::on_region_swept::<VM, _>(self, false);
 
+                    #[cfg(feature = "object_pinning")]
+                    VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC
+                        .bzero_side_metadata(self.start(), Self::bytes());
+
                     // Release the block if it is allocated but not marked by the current GC.
                     space.release_block(*self);
                     true
@@ -230,8 +289,14 @@ impl Block {
                         holes += 1;
                     }
 
-                    #[cfg(feature = "immix_zero_on_release")]
-                    crate::util::memory::zero(line.start(), Line::BYTES);
+                    if space.space_args.zero_on_release {
+                        crate::util::memory::zero(line.start(), Line::bytes());
+                    }
+
+                    // The objects allocated in the line later should not be pinned.
+                    #[cfg(feature = "object_pinning")]
+                    VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC
+                        .bzero_side_metadata(line.start(), Line::bytes());
 
                     prev_line_is_marked = false;
                 }
@@ -246,7 +311,7 @@ impl Block {
                 true
             } else {
                 // There are some marked lines. Keep the block live.
-                if marked_lines != Block::LINES {
+                if marked_lines != Block::num_lines() {
                     // There are holes. Mark the block as reusable.
                     self.set_state(BlockState::Reusable {
                         unavailable_lines: marked_lines as _,
@@ -280,7 +345,7 @@ impl Block {
                 match self.get_state() {
                     BlockState::Unmarked => {
                         // It may contain young objects.  Clear it.
-                        vo_bit::bzero_vo_bit(self.start(), Self::BYTES);
+                        vo_bit::bzero_vo_bit(self.start(), Self::bytes());
                     }
                     BlockState::Marked => {
                         // It contains old objects.  Skip it.
@@ -293,7 +358,7 @@ impl Block {
                 for line in self.lines() {
                     if !line.is_marked(state) {
                         // It may contain young objects.  Clear it.
-                        vo_bit::bzero_vo_bit(line.start(), Line::BYTES);
+                        vo_bit::bzero_vo_bit(line.start(), Line::bytes());
                     }
                 }
             }
@@ -4,9 +4,11 @@ use super::{
     ImmixSpace,
 };
 use crate::policy::space::Space;
+use crate::util::heap::chunk_map::{Chunk, ChunkState};
 use crate::util::linear_scan::Region;
 use crate::{util::constants::LOG_BYTES_IN_PAGE, vm::*};
 use spin::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 pub type Histogram = [usize; Defrag::NUM_BINS];
@@ -23,13 +25,32 @@ pub struct Defrag {
     pub defrag_spill_threshold: AtomicUsize,
     /// The number of remaining clean pages in defrag space.
     available_clean_pages_for_defrag: AtomicUsize,
+    /// Is current GC a chunk evacuation GC? A chunk evacuation GC is a defrag GC that evacuates
+    /// whole chunks, and releases the empty chunks at the end of the GC.
+    in_chunk_evacuation: AtomicBool,
+    /// The number of live lines in each chunk, reported by the sweeping of the last GC.
+    chunk_live_lines: Mutex<Vec<(Chunk, usize)>>,
+    /// The chunks selected as evacuation sources in the current chunk evacuation GC.
+    evacuation_chunks: Mutex<HashSet<Chunk>>,
+    /// The free blocks in the evacuation chunks. They are taken out of the page resource during
+    /// a chunk evacuation GC so that we do not copy objects into them.
+    withdrawn_blocks: Mutex<Vec<Block>>,
 }
 
 impl Defrag {
-    const NUM_BINS: usize = (Block::LINES >> 1) + 1;
+    /// The histograms are sized for the largest number of lines in a block.
+    const NUM_BINS: usize = ((1 << Block::MAX_LOG_LINES) >> 1) + 1;
     const DEFRAG_LINE_REUSE_RATIO: f32 = 0.99;
     const MIN_SPILL_THRESHOLD: usize = 2;
-    const DEFRAG_HEADROOM_PERCENT: usize = 2;
+
+    /// The number of bins used with the current number of lines in a block.
+    fn num_bins() -> usize {
+        (Block::num_lines() >> 1) + 1
+    }
+
+    fn lines_in_chunk() -> usize {
+        1 << (Chunk::LOG_BYTES - Line::log_bytes())
+    }
 
     /// Allocate a new local histogram.
     pub const fn new_histogram(&self) -> Histogram {
@@ -46,30 +67,169 @@ impl Defrag {
         self.in_defrag_collection.load(Ordering::Acquire)
     }
 
+    /// Check if the current GC is a chunk evacuation GC.
+    pub fn in_chunk_evacuation(&self) -> bool {
+        self.in_chunk_evacuation.load(Ordering::Acquire)
+    }
+
+    /// Check if the chunk is selected as an evacuation source in the current GC.
+    pub fn is_evacuation_chunk(&self, chunk: Chunk) -> bool {
+        self.evacuation_chunks.lock().contains(&chunk)
+    }
+
     /// Determine whether the current GC should do defragmentation.
-    pub fn decide_whether_to_defrag(
+    #[allow(clippy::too_many_arguments)]
+    pub fn decide_whether_to_defrag<VM: VMBinding>(
         &self,
+        space: &ImmixSpace<VM>,
         emergency_collection: bool,
         collect_whole_heap: bool,
         collection_attempts: usize,
         user_triggered: bool,
-        exhausted_reusable_space: bool,
         full_heap_system_gc: bool,
+        chunk_evacuation_requested: bool,
     ) {
-        let in_defrag = super::DEFRAG
+        let in_chunk_evacuation = space.space_args.defrag
+            && collect_whole_heap
+            && (chunk_evacuation_requested || self.is_heap_spread_over_chunks(space));
+        let exhausted_reusable_space = space.reusable_blocks.len() == 0;
+        let in_defrag = space.space_args.defrag
             && (emergency_collection
                 || (collection_attempts > 1)
                 || !exhausted_reusable_space
-                || super::STRESS_DEFRAG
-                || (collect_whole_heap && user_triggered && full_heap_system_gc));
+                || space.space_args.stress_defrag
+                || (collect_whole_heap && user_triggered && full_heap_system_gc)
+                || in_chunk_evacuation);
         // println!("Defrag: {}", in_defrag);
         self.in_defrag_collection
-            .store(in_defrag, Ordering::Release)
+            .store(in_defrag, Ordering::Release);
+        self.in_chunk_evacuation
+            .store(in_chunk_evacuation, Ordering::Release);
+    }
+
+    /// Check if the memory of the chunks used by the space greatly exceeds the live bytes after
+    /// the last GC, so that we should do a chunk evacuation GC.
+    fn is_heap_spread_over_chunks<VM: VMBinding>(&self, space: &ImmixSpace<VM>) -> bool {
+        let trigger_percent = space.space_args.chunk_evacuation_trigger_percent;
+        if trigger_percent == 0 {
+            return false;
+        }
+        let chunk_live_lines = self.chunk_live_lines.lock();
+        let chunks = chunk_live_lines.len();
+        let live_lines = chunk_live_lines
+            .iter()
+            .map(|(_, live_lines)| *live_lines)
+            .sum::<usize>();
+        chunks > 1
+            && (chunks * Self::lines_in_chunk()) as f64
+                > live_lines as f64 * trigger_percent as f64 / 100f64
+    }
+
+    /// Report the number of live lines in a chunk after it is swept.
+    pub fn add_chunk_live_lines(&self, chunk: Chunk, live_lines: usize) {
+        self.chunk_live_lines.lock().push((chunk, live_lines))
+    }
+
+    /// Clear the chunk occupancy reported by the last GC.
+    pub fn clear_chunk_live_lines(&self) {
+        self.chunk_live_lines.lock().clear()
+    }
+
+    /// Forget the occupancy of the chunks that are no longer used by the space.
+    pub fn forget_chunks(&self, chunks: &HashSet<Chunk>) {
+        self.chunk_live_lines
+            .lock()
+            .retain(|(chunk, _)| !chunks.contains(chunk))
+    }
+
+    /// Select the least occupied chunks as evacuation sources, as long as their live lines fit
+    /// into the free lines of the other chunks. The occupancy is from the last GC. If the objects
+    /// allocated since then do not fit, we stop evacuating when the defrag space is exhausted.
+    fn select_evacuation_chunks<VM: VMBinding>(&self, space: &ImmixSpace<VM>) {
+        let chunks = self
+            .chunk_live_lines
+            .lock()
+            .iter()
+            .filter(|(chunk, _)| space.chunk_map.get(*chunk) == ChunkState::Allocated)
+            .copied()
+            .collect::<Vec<_>>();
+        let (selected, required_lines) = Self::select_chunks(chunks, Self::lines_in_chunk());
+        debug!(
+            "Chunk evacuation: {} chunks selected, {} live lines to evacuate",
+            selected.len(),
+            required_lines
+        );
+        *self.evacuation_chunks.lock() = selected;
+    }
+
+    /// Select the chunks to evacuate from the live lines of each chunk, starting from the least
+    /// occupied one. Return the selected chunks and the number of live lines in them.
+    fn select_chunks(
+        mut chunks: Vec<(Chunk, usize)>,
+        lines_in_chunk: usize,
+    ) -> (HashSet<Chunk>, usize) {
+        chunks.sort_by_key(|(_, live_lines)| *live_lines);
+        let mut available_lines = chunks
+            .iter()
+            .map(|(_, live_lines)| lines_in_chunk - live_lines)
+            .sum::<usize>();
+        let mut required_lines = 0;
+        let mut selected = HashSet::new();
+        for (chunk, live_lines) in chunks {
+            // The free lines in an evacuation chunk cannot be used as to-space.
+            let free_lines = lines_in_chunk - live_lines;
+            if ((required_lines + live_lines) as f32 / Self::DEFRAG_LINE_REUSE_RATIO)
+                > (available_lines - free_lines) as f32
+            {
+                break;
+            }
+            available_lines -= free_lines;
+            required_lines += live_lines;
+            selected.insert(chunk);
+        }
+        (selected, required_lines)
+    }
+
+    /// Group free blocks by their chunks. Return the chunks whose blocks are all free, and the
+    /// free blocks in the other chunks.
+    pub fn find_free_chunks(blocks: Vec<Block>) -> (Vec<Chunk>, Vec<Block>) {
+        let mut free_blocks_in_chunks: HashMap<Chunk, Vec<Block>> = HashMap::new();
+        for block in blocks {
+            free_blocks_in_chunks
+                .entry(block.chunk())
+                .or_default()
+                .push(block);
+        }
+        let mut free_chunks = vec![];
+        let mut remaining_blocks = vec![];
+        for (chunk, blocks) in free_blocks_in_chunks {
+            if blocks.len() == Block::blocks_in_chunk() {
+                free_chunks.push(chunk);
+            } else {
+                remaining_blocks.extend(blocks);
+            }
+        }
+        (free_chunks, remaining_blocks)
+    }
+
+    /// Remember the free blocks taken out of the page resource during a chunk evacuation GC.
+    pub fn add_withdrawn_blocks(&self, blocks: Vec<Block>) {
+        self.withdrawn_blocks.lock().extend(blocks)
+    }
+
+    /// Finish a chunk evacuation GC. Return the free blocks taken out of the page resource if the
+    /// current GC is a chunk evacuation GC.
+    pub fn finish_chunk_evacuation(&self) -> Option<Vec<Block>> {
+        if !self.in_chunk_evacuation.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        self.evacuation_chunks.lock().clear();
+        Some(std::mem::take(&mut *self.withdrawn_blocks.lock()))
     }
 
     /// Get the number of defrag headroom pages.
     pub fn defrag_headroom_pages<VM: VMBinding>(&self, space: &ImmixSpace<VM>) -> usize {
-        space.get_page_resource().reserved_pages() * Self::DEFRAG_HEADROOM_PERCENT / 100
+        space.get_page_resource().reserved_pages() * space.space_args.defrag_headroom_percent / 100
     }
 
     /// Check if the defrag space is exhausted.
@@ -85,30 +245,29 @@ impl Defrag {
                     Ordering::SeqCst,
                     Ordering::SeqCst,
                     |available_clean_pages_for_defrag| {
-                        if available_clean_pages_for_defrag <= Block::PAGES {
+                        if available_clean_pages_for_defrag <= Block::pages() {
                             Some(0)
                         } else {
-                            Some(available_clean_pages_for_defrag - Block::PAGES)
+                            Some(available_clean_pages_for_defrag - Block::pages())
                         }
                     },
                 );
-            if available_clean_pages_for_defrag.unwrap() <= Block::PAGES {
+            if available_clean_pages_for_defrag.unwrap() <= Block::pages() {
                 self.defrag_space_exhausted.store(true, Ordering::SeqCst);
             }
         }
     }
 
     /// Prepare work. Should be called in ImmixSpace::prepare.
-    #[allow(clippy::assertions_on_constants)]
     pub fn prepare<VM: VMBinding>(&self, space: &ImmixSpace<VM>) {
-        debug_assert!(super::DEFRAG);
+        debug_assert!(space.space_args.defrag);
         self.defrag_space_exhausted.store(false, Ordering::Release);
 
         // Calculate available free space for defragmentation.
 
-        let mut available_clean_pages_for_defrag = VM::VMActivePlan::global().get_total_pages()
-            as isize
-            - VM::VMActivePlan::global().get_reserved_pages() as isize
+        let plan = space.get_gc_trigger().plan();
+        let mut available_clean_pages_for_defrag = plan.get_total_pages() as isize
+            - plan.get_reserved_pages() as isize
             + self.defrag_headroom_pages(space) as isize;
         if available_clean_pages_for_defrag < 0 {
             available_clean_pages_for_defrag = 0
@@ -117,13 +276,14 @@ impl Defrag {
         self.available_clean_pages_for_defrag
             .store(available_clean_pages_for_defrag as usize, Ordering::Release);
 
-        if self.in_defrag() {
+        if self.in_chunk_evacuation() {
+            self.select_evacuation_chunks(space)
+        } else if self.in_defrag() {
             self.establish_defrag_spill_threshold(space)
         }
 
         self.available_clean_pages_for_defrag.store(
-            available_clean_pages_for_defrag as usize
-                + VM::VMActivePlan::global().get_collection_reserved_pages(),
+            available_clean_pages_for_defrag as usize + plan.get_collection_reserved_pages(),
             Ordering::Release,
         );
     }
@@ -141,7 +301,7 @@ impl Defrag {
                 BlockState::Reusable { unavailable_lines } => unavailable_lines as usize,
                 s => unreachable!("{:?} {:?}", block, s),
             };
-            let available_lines = Block::LINES - unavailable_lines;
+            let available_lines = Block::num_lines() - unavailable_lines;
             spill_avail_histograms[bucket] += available_lines;
             total_available_lines += available_lines;
         });
@@ -156,19 +316,20 @@ impl Defrag {
             + (self
                 .available_clean_pages_for_defrag
                 .load(Ordering::Acquire)
-                << (LOG_BYTES_IN_PAGE as usize - Line::LOG_BYTES));
+                << LOG_BYTES_IN_PAGE
+                >> Line::log_bytes());
 
         // Number of lines we will evacuate.
         let mut required_lines = 0isize;
         // Number of to-space free lines we can use for defragmentation.
         let mut limit = (available_lines as f32 / Self::DEFRAG_LINE_REUSE_RATIO) as isize;
-        let mut threshold = Block::LINES >> 1;
+        let mut threshold = Block::num_lines() >> 1;
         let mark_histograms = self.mark_histograms.lock();
         // Blocks are grouped by buckets, indexed by the number of holes in the block.
         // `mark_histograms` remembers the number of live lines for each bucket.
         // Here, reversely iterate all the bucket to find a threshold that all buckets above this
         // threshold can be evacuated, without causing to-space overflow.
-        for index in (Self::MIN_SPILL_THRESHOLD..Self::NUM_BINS).rev() {
+        for index in (Self::MIN_SPILL_THRESHOLD..Self::num_bins()).rev() {
             threshold = index;
             // Calculate total number of live lines in this bucket.
             let this_bucket_mark = mark_histograms
@@ -192,9 +353,70 @@ impl Defrag {
     }
 
     /// Release work. Should be called in ImmixSpace::release.
-    #[allow(clippy::assertions_on_constants)]
-    pub fn release<VM: VMBinding>(&self, _space: &ImmixSpace<VM>) {
-        debug_assert!(super::DEFRAG);
+    pub fn release<VM: VMBinding>(&self, space: &ImmixSpace<VM>) {
+        debug_assert!(space.space_args.defrag);
         self.in_defrag_collection.store(false, Ordering::Release);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::heap::layout::vm_layout_constants::HEAP_START;
+
+    fn chunk(index: usize) -> Chunk {
+        Chunk::from_aligned_address(HEAP_START + (index << Chunk::LOG_BYTES))
+    }
+
+    #[test]
+    fn select_least_occupied_chunks() {
+        let lines = Defrag::lines_in_chunk();
+        let chunks = vec![
+            (chunk(1), lines / 2),
+            (chunk(2), lines / 10),
+            (chunk(3), lines / 4),
+            (chunk(4), lines * 9 / 10),
+        ];
+        // The live lines of chunk 1 would not fit into the free lines of chunk 4.
+        let (selected, required_lines) = Defrag::select_chunks(chunks, lines);
+        assert_eq!(selected, HashSet::from([chunk(2), chunk(3)]));
+        assert_eq!(required_lines, lines / 10 + lines / 4);
+    }
+
+    #[test]
+    fn select_no_chunks_from_full_heap() {
+        let lines = Defrag::lines_in_chunk();
+        let chunks = vec![(chunk(1), lines), (chunk(2), lines - 1)];
+        let (selected, required_lines) = Defrag::select_chunks(chunks, lines);
+        assert!(selected.is_empty());
+        assert_eq!(required_lines, 0);
+    }
+
+    #[test]
+    fn select_empty_chunks() {
+        let lines = Defrag::lines_in_chunk();
+        let chunks = vec![(chunk(1), lines), (chunk(2), 0), (chunk(3), 0)];
+        let (selected, required_lines) = Defrag::select_chunks(chunks, lines);
+        assert_eq!(selected, HashSet::from([chunk(2), chunk(3)]));
+        assert_eq!(required_lines, 0);
+    }
+
+    #[test]
+    fn find_chunks_with_all_blocks_free() {
+        let blocks_in_chunk = Block::blocks_in_chunk();
+        let blocks = |chunk: Chunk, n: usize| {
+            (0..n)
+                .map(move |i| Block::from_aligned_address(chunk.start() + i * Block::bytes()))
+                .collect::<Vec<_>>()
+        };
+        let mut free_blocks = blocks(chunk(1), 2);
+        free_blocks.extend(blocks(chunk(2), blocks_in_chunk));
+        free_blocks.extend(blocks(chunk(3), blocks_in_chunk - 1));
+        let (free_chunks, remaining_blocks) = Defrag::find_free_chunks(free_blocks);
+        assert_eq!(free_chunks, vec![chunk(2)]);
+        assert_eq!(remaining_blocks.len(), 2 + blocks_in_chunk - 1);
+        assert!(remaining_blocks
+            .iter()
+            .all(|block| block.chunk() == chunk(1) || block.chunk() == chunk(3)));
+    }
+}
@@ -1,5 +1,7 @@
 use super::line::*;
 use super::{block::*, defrag::Defrag};
+use crate::mmtk::SFT_MAP;
+use crate::plan::PlanConstraints;
 use crate::plan::VectorObjectQueue;
 use crate::policy::gc_work::TraceKind;
 use crate::policy::sft::GCWorkerMutRef;
@@ -11,11 +13,13 @@ use crate::util::heap::chunk_map::*;
 use crate::util::heap::BlockPageResource;
 use crate::util::heap::PageResource;
 use crate::util::linear_scan::{Region, RegionIterator};
+use crate::util::metadata::field_log_bit;
 use crate::util::metadata::side_metadata::SideMetadataSpec;
 #[cfg(feature = "vo_bit")]
 use crate::util::metadata::vo_bit;
 use crate::util::metadata::{self, MetadataSpec};
 use crate::util::object_forwarding as ForwardingWord;
+use crate::util::options::Options;
 use crate::util::{Address, ObjectReference};
 use crate::vm::*;
 use crate::{
@@ -25,6 +29,7 @@ use crate::{
     MMTK,
 };
 use atomic::Ordering;
+use std::collections::HashSet;
 use std::sync::{atomic::AtomicU8, atomic::AtomicUsize, Arc};
 
 pub(crate) const TRACE_KIND_FAST: TraceKind = 0;
@@ -50,7 +55,7 @@ pub struct ImmixSpace<VM: VMBinding> {
     /// Work packet scheduler
     scheduler: Arc<GCWorkScheduler<VM>>,
     /// Some settings for this space
-    space_args: ImmixSpaceArgs,
+    pub(super) space_args: ImmixSpaceArgs,
 }
 
 /// Some arguments for Immix Space.
@@ -75,6 +80,78 @@ pub struct ImmixSpaceArgs {
     /// only StickyImmix is affected.  GenImmix allocates young objects in a separete CopySpace
     /// nursery and its VO bits can be cleared in bulk.
     pub mixed_age: bool,
+    /// Do we allow Immix to do defragmentation?
+    pub defrag: bool,
+    /// Make every full heap GC a defrag GC. (for debugging)
+    pub stress_defrag: bool,
+    /// Mark every allocated block as defragmentation source in a defrag GC. (for debugging)
+    /// Set both this and `stress_defrag` to true to make Immix move as many objects as possible.
+    pub defrag_every_block: bool,
+    /// The percentage of the heap reserved for defragmentation.
+    pub defrag_headroom_percent: usize,
+    /// Do a chunk evacuation GC if the memory of the chunks used by the space exceeds this
+    /// percentage of the live bytes after the last GC. 0 means never doing it automatically.
+    pub chunk_evacuation_trigger_percent: usize,
+    /// If Immix is used as a nursery space, do we prefer copy?
+    pub prefer_copy_on_nursery_gc: bool,
+    /// Mark lines when scanning objects.
+    /// Otherwise, do it at mark time.
+    pub mark_line_at_scan_time: bool,
+    /// Zero the unmarked lines after a GC cycle. This helps debug untraced objects.
+    pub zero_on_release: bool,
+    /// The log of the block size in bytes.
+    pub block_log_bytes: usize,
+    /// The log of the line size in bytes.
+    pub line_log_bytes: usize,
+}
+
+impl ImmixSpaceArgs {
+    /// Create the arguments from the options. The plan-specific arguments are set to false, and
+    /// the plan should override them if needed.
+    pub fn from_options(options: &Options) -> Self {
+        let moving = !*options.immix_non_moving;
+        ImmixSpaceArgs {
+            unlog_object_when_traced: false,
+            reset_log_bit_in_major_gc: false,
+            mixed_age: false,
+            defrag: moving,
+            // Stress defrag is meaningless if we do not move objects.
+            stress_defrag: moving && *options.immix_stress_defrag,
+            defrag_every_block: moving && *options.immix_defrag_every_block,
+            defrag_headroom_percent: *options.immix_defrag_headroom_percent,
+            chunk_evacuation_trigger_percent: *options.immix_chunk_evacuation_trigger_percent,
+            prefer_copy_on_nursery_gc: moving,
+            mark_line_at_scan_time: *options.immix_mark_line_at_scan_time,
+            zero_on_release: *options.immix_zero_on_release,
+            block_log_bytes: options.immix_block_size.block_log_bytes,
+            line_log_bytes: options.immix_block_size.line_log_bytes,
+        }
+    }
+
+    /// The max object size that can be allocated into the space: half of a block.
+    pub fn max_object_size(&self) -> usize {
+        (1 << self.block_log_bytes) >> 1
+    }
+
+    /// Create the constraints for a plan whose default allocator allocates into an Immix space
+    /// with these arguments. `base` is the constraints for the default arguments. The plan may
+    /// not move objects, and the objects that can be allocated without LOS are limited by the
+    /// block size.
+    pub fn plan_constraints(&self, base: &PlanConstraints) -> PlanConstraints {
+        PlanConstraints {
+            moves_objects: base.moves_objects && !self.never_move_objects(),
+            max_non_los_default_alloc_bytes: self.max_object_size(),
+            ..*base
+        }
+    }
+
+    /// In some cases/settings, Immix may never move objects.
+    /// Currently we only have two cases where we move objects: 1. defrag, 2. nursery copy.
+    /// If we do neither, we will not move objects.
+    /// If we have other reasons to move objects, we need to add them here.
+    pub fn never_move_objects(&self) -> bool {
+        !self.defrag && !self.prefer_copy_on_nursery_gc
+    }
 }
 
 unsafe impl<VM: VMBinding> Sync for ImmixSpace<VM> {}
@@ -103,7 +180,7 @@ impl<VM: VMBinding> SFT for ImmixSpace<VM> {
         }
 
         // If we never move objects, look no further.
-        if super::NEVER_MOVE_OBJECTS {
+        if self.space_args.never_move_objects() {
             return false;
         }
 
@@ -134,7 +211,7 @@ impl<VM: VMBinding> SFT for ImmixSpace<VM> {
         VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(object)
     }
     fn is_movable(&self) -> bool {
-        !super::NEVER_MOVE_OBJECTS
+        !self.space_args.never_move_objects()
     }
 
     #[cfg(feature = "sanity")]
@@ -217,7 +294,7 @@ impl<VM: VMBinding> crate::policy::gc_work::PolicyTraceObject<VM> for ImmixSpace
     }
 
     fn post_scan_object(&self, object: ObjectReference) {
-        if super::MARK_LINE_AT_SCAN_TIME && !super::BLOCK_ONLY {
+        if self.space_args.mark_line_at_scan_time && !super::BLOCK_ONLY {
             debug_assert!(self.in_space(object));
             self.mark_lines(object);
         }
@@ -271,12 +348,12 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         args: crate::policy::space::PlanCreateSpaceArgs<VM>,
         space_args: ImmixSpaceArgs,
     ) -> Self {
-        #[cfg(feature = "immix_non_moving")]
-        info!(
-            "Creating non-moving ImmixSpace: {}. Block size: 2^{}",
-            args.name,
-            Block::LOG_BYTES
-        );
+        if space_args.never_move_objects() {
+            info!(
+                "Creating non-moving ImmixSpace: {}. Block size: 2^{}",
+                args.name, space_args.block_log_bytes
+            );
+        }
 
         if space_args.unlog_object_when_traced || space_args.reset_log_bit_in_major_gc {
             assert!(
@@ -285,7 +362,8 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             );
         }
 
-        super::validate_features();
+        super::validate_space_args(&space_args);
+        super::set_block_and_line_sizes(&space_args);
         #[cfg(feature = "vo_bit")]
         vo_bit::helper::validate_config::<VM>();
         let vm_map = args.vm_map;
@@ -295,17 +373,17 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         ImmixSpace {
             pr: if common.vmrequest.is_discontiguous() {
                 BlockPageResource::new_discontiguous(
-                    Block::LOG_PAGES,
+                    Block::log_pages(),
                     vm_map,
-                    scheduler.num_workers(),
+                    scheduler.max_workers(),
                 )
             } else {
                 BlockPageResource::new_contiguous(
-                    Block::LOG_PAGES,
+                    Block::log_pages(),
                     common.start,
                     common.extent,
                     vm_map,
-                    scheduler.num_workers(),
+                    scheduler.max_workers(),
                 )
             },
             common,
@@ -313,7 +391,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             line_mark_state: AtomicU8::new(Line::RESET_MARK_STATE),
             line_unavail_state: AtomicU8::new(Line::RESET_MARK_STATE),
             lines_consumed: AtomicUsize::new(0),
-            reusable_blocks: ReusableBlockPool::new(scheduler.num_workers()),
+            reusable_blocks: ReusableBlockPool::new(scheduler.max_workers()),
             defrag: Defrag::default(),
             // Set to the correct mark state when inititialized. We cannot rely on prepare to set it (prepare may get skipped in nursery GCs).
             mark_state: Self::MARKED_STATE,
@@ -334,12 +412,19 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         self.defrag.defrag_headroom_pages(self)
     }
 
+    /// Do we prefer copying nursery objects when Immix is used as a nursery space?
+    pub fn prefer_copy_on_nursery_gc(&self) -> bool {
+        self.space_args.prefer_copy_on_nursery_gc
+    }
+
     /// Check if current GC is a defrag GC.
     pub fn in_defrag(&self) -> bool {
         self.defrag.in_defrag()
     }
 
     /// check if the current GC should do defragmentation.
+    /// If `chunk_evacuation_requested` is true, a full heap GC will evacuate the least occupied
+    /// chunks, and release the empty chunks at the end of the GC.
     pub fn decide_whether_to_defrag(
         &self,
         emergency_collection: bool,
@@ -347,14 +432,16 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         collection_attempts: usize,
         user_triggered_collection: bool,
         full_heap_system_gc: bool,
+        chunk_evacuation_requested: bool,
     ) -> bool {
         self.defrag.decide_whether_to_defrag(
+            self,
             emergency_collection,
             collect_whole_heap,
             collection_attempts,
             user_triggered_collection,
-            self.reusable_blocks.len() == 0,
             full_heap_system_gc,
+            chunk_evacuation_requested,
         );
         self.defrag.in_defrag()
     }
@@ -375,10 +462,18 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             }
 
             // Prepare defrag info
-            if super::DEFRAG {
+            if self.space_args.defrag {
                 self.defrag.prepare(self);
             }
 
+            // Do not copy objects into the free blocks of the evacuation chunks.
+            if self.defrag.in_chunk_evacuation() {
+                let blocks = self
+                    .pr
+                    .take_free_blocks(|block| self.defrag.is_evacuation_chunk(block.chunk()));
+                self.defrag.add_withdrawn_blocks(blocks);
+            }
+
             // Prepare each block for GC
             let threshold = self.defrag.defrag_spill_threshold.load(Ordering::Acquire);
             // # Safety: ImmixSpace reference is always valid within this collection cycle.
@@ -387,11 +482,12 @@ impl<VM: VMBinding> ImmixSpace<VM> {
                 Box::new(PrepareBlockState {
                     space,
                     chunk,
-                    defrag_threshold: if space.in_defrag() {
+                    defrag_threshold: if space.in_defrag() && !space.defrag.in_chunk_evacuation() {
                         Some(threshold)
                     } else {
                         None
                     },
+                    evacuate_chunk: space.defrag.is_evacuation_chunk(chunk),
                 })
             });
             self.scheduler().work_buckets[WorkBucketStage::Prepare].bulk_add(work_packets);
@@ -462,7 +558,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         // Sweep chunks and blocks
         let work_packets = self.generate_sweep_tasks();
         self.scheduler().work_buckets[WorkBucketStage::Release].bulk_add(work_packets);
-        if super::DEFRAG {
+        if self.space_args.defrag {
             self.defrag.release(self);
         }
 
@@ -474,6 +570,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
     /// Generate chunk sweep tasks
     fn generate_sweep_tasks(&self) -> Vec<Box<dyn GCWork<VM>>> {
         self.defrag.mark_histograms.lock().clear();
+        self.defrag.clear_chunk_live_lines();
         // # Safety: ImmixSpace reference is always valid within this collection cycle.
         let space = unsafe { &*(self as *const Self) };
         let epilogue = Arc::new(FlushPageResource {
@@ -491,6 +588,33 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         tasks
     }
 
+    /// Release the chunks that are entirely free at the end of a chunk evacuation GC, so that the
+    /// memory can be returned to the OS. This must be called after the page resource is flushed.
+    fn release_free_chunks(&self) {
+        let mut free_blocks = match self.defrag.finish_chunk_evacuation() {
+            Some(blocks) => blocks,
+            None => return,
+        };
+        free_blocks.extend(self.pr.take_free_blocks(|_| true));
+        let (free_chunks, remaining_blocks) = Defrag::find_free_chunks(free_blocks);
+        let mut released_chunks = HashSet::new();
+        for chunk in free_chunks {
+            debug_assert_eq!(self.chunk_map.get(chunk), ChunkState::Free);
+            self.pr.release_free_chunk(chunk.start());
+            // The chunk no longer belongs to this space. It gets a new SFT entry when it is allocated again.
+            unsafe { SFT_MAP.clear_chunk(chunk.start()) };
+            self.chunk_map.set(chunk, ChunkState::Free);
+            released_chunks.insert(chunk);
+        }
+        self.pr.return_free_blocks(remaining_blocks);
+        self.defrag.forget_chunks(&released_chunks);
+        info!(
+            "Chunk evacuation: released {} chunks from {}",
+            released_chunks.len(),
+            self.get_name()
+        );
+    }
+
     /// Release a block.
     pub fn release_block(&self, block: Block) {
         block.deinit();
@@ -499,7 +623,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
 
     /// Allocate a clean block.
     pub fn get_clean_block(&self, tls: VMThread, copy: bool) -> Option<Block> {
-        let block_address = self.acquire(tls, Block::PAGES);
+        let block_address = self.acquire(tls, Block::pages());
         if block_address.is_zero() {
             return None;
         }
@@ -508,7 +632,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         block.init(copy);
         self.chunk_map.set(block.chunk(), ChunkState::Allocated);
         self.lines_consumed
-            .fetch_add(Block::LINES, Ordering::SeqCst);
+            .fetch_add(Block::num_lines(), Ordering::SeqCst);
         Some(block)
     }
 
@@ -527,9 +651,9 @@ impl<VM: VMBinding> ImmixSpace<VM> {
                 // Get available lines. Do this before block.init which will reset block state.
                 let lines_delta = match block.get_state() {
                     BlockState::Reusable { unavailable_lines } => {
-                        Block::LINES - unavailable_lines as usize
+                        Block::num_lines() - unavailable_lines as usize
                     }
-                    BlockState::Unmarked => Block::LINES,
+                    BlockState::Unmarked => Block::num_lines(),
                     _ => unreachable!("{:?} {:?}", block, block.get_state()),
                 };
                 self.lines_consumed.fetch_add(lines_delta, Ordering::SeqCst);
@@ -554,7 +678,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         if self.attempt_mark(object, self.mark_state) {
             // Mark block and lines
             if !super::BLOCK_ONLY {
-                if !super::MARK_LINE_AT_SCAN_TIME {
+                if !self.space_args.mark_line_at_scan_time {
                     self.mark_lines(object);
                 }
             } else {
@@ -670,7 +794,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             // Make sure the side metadata for the line can fit into one byte. For smaller line size, we should
             // use `mark_as_unlogged` instead to mark the bit.
             const_assert!(
-                Line::BYTES
+                (1 << Line::MIN_LOG_BYTES)
                     >= (1
                         << (crate::util::constants::LOG_BITS_IN_BYTE
                             + crate::util::constants::LOG_MIN_OBJECT_SIZE))
@@ -680,11 +804,17 @@ impl<VM: VMBinding> ImmixSpace<VM> {
                 0
             ); // We should put this to the addition, but type casting is not allowed in constant assertions.
 
-            // Every immix line is 256 bytes, which is mapped to 4 bytes in the side metadata.
+            // Every immix line is at least 256 bytes, which is mapped to at least 4 bytes in the side metadata.
             // If we have one object in the line that is mature, we can assume all the objects in the line are mature objects.
             // So we can just mark the byte.
             VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
                 .mark_byte_as_unlogged::<VM>(object, Ordering::Relaxed);
+            if self.common.needs_field_log_bit {
+                field_log_bit::mark_fields_as_unlogged::<VM>(
+                    object,
+                    VM::VMObjectModel::get_current_size(object),
+                );
+            }
         }
     }
 
@@ -765,7 +895,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         let mut cursor = start_cursor;
         // Find start
         while cursor < mark_data.len() {
-            let mark = mark_data.get(cursor);
+            let mark = mark_data[cursor];
             if mark != unavail_state && mark != current_state {
                 break;
             }
@@ -777,7 +907,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         let start = search_start.next_nth(cursor - start_cursor);
         // Find limit
         while cursor < mark_data.len() {
-            let mark = mark_data.get(cursor);
+            let mark = mark_data[cursor];
             if mark == unavail_state || mark == current_state {
                 break;
             }
@@ -789,8 +919,8 @@ impl<VM: VMBinding> ImmixSpace<VM> {
         Some((start, end))
     }
 
-    pub fn is_last_gc_exhaustive(did_defrag_for_last_gc: bool) -> bool {
-        if super::DEFRAG {
+    pub fn is_last_gc_exhaustive(&self, did_defrag_for_last_gc: bool) -> bool {
+        if self.space_args.defrag {
             did_defrag_for_last_gc
         } else {
             // If defrag is disabled, every GC is exhaustive.
@@ -799,7 +929,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
     }
 
     pub(crate) fn get_pages_allocated(&self) -> usize {
-        self.lines_consumed.load(Ordering::SeqCst) >> (LOG_BYTES_IN_PAGE - Line::LOG_BYTES as u8)
+        (self.lines_consumed.load(Ordering::SeqCst) << Line::log_bytes()) >> LOG_BYTES_IN_PAGE
     }
 
     /// Post copy routine for Immix copy contexts
@@ -812,7 +942,7 @@ impl<VM: VMBinding> ImmixSpace<VM> {
             Ordering::SeqCst,
         );
         // Mark the line
-        if !super::MARK_LINE_AT_SCAN_TIME {
+        if !self.space_args.mark_line_at_scan_time {
             self.mark_lines(object);
         }
     }
@@ -824,6 +954,8 @@ pub struct PrepareBlockState<VM: VMBinding> {
     pub space: &'static ImmixSpace<VM>,
     pub chunk: Chunk,
     pub defrag_threshold: Option<usize>,
+    /// Set every block in this chunk as defrag source, because we want to empty the chunk.
+    pub evacuate_chunk: bool,
 }
 
 impl<VM: VMBinding> PrepareBlockState<VM> {
@@ -844,6 +976,9 @@ impl<VM: VMBinding> PrepareBlockState<VM> {
                 // objects. In either cases, we do not need to set log bit at tracing.
                 unimplemented!("We cannot bulk zero unlogged bit.")
             }
+            if self.space.common.needs_field_log_bit {
+                field_log_bit::bzero_field_log_bits(self.chunk.start(), Chunk::BYTES);
+            }
         }
     }
 }
@@ -860,12 +995,15 @@ impl<VM: VMBinding> GCWork<VM> for PrepareBlockState<VM> {
                 continue;
             }
             // Check if this block needs to be defragmented.
-            let is_defrag_source = if !super::DEFRAG {
+            let is_defrag_source = if !self.space.space_args.defrag {
                 // Do not set any block as defrag source if defrag is disabled.
                 false
-            } else if super::DEFRAG_EVERY_BLOCK {
+            } else if self.space.space_args.defrag_every_block {
                 // Set every block as defrag source if so desired.
                 true
+            } else if self.evacuate_chunk {
+                // This chunk is selected for evacuation in a chunk evacuation GC.
+                true
             } else if let Some(defrag_threshold) = self.defrag_threshold {
                 // This GC is a defrag GC.
                 block.get_holes() > defrag_threshold
@@ -885,7 +1023,7 @@ impl<VM: VMBinding> GCWork<VM> for PrepareBlockState<VM> {
                 // make sure `ImmixSpace::is_live` is fixed, too.
                 if let MetadataSpec::OnSide(side) = *VM::VMObjectModel::LOCAL_FORWARDING_BITS_SPEC {
                     // Clear on-the-side forwarding bits.
-                    side.bzero_metadata(block.start(), Block::BYTES);
+                    side.bzero_metadata(block.start(), Block::bytes());
                 }
             }
             // NOTE: We don't need to reset the forwarding pointer metadata because it is meaningless
@@ -914,6 +1052,8 @@ impl<VM: VMBinding> GCWork<VM> for SweepChunk<VM> {
             };
             // number of allocated blocks.
             let mut allocated_blocks = 0;
+            // number of live lines in the allocated blocks.
+            let mut live_lines = 0;
             // Iterate over all allocated blocks in this chunk.
             for block in self
                 .chunk
@@ -923,11 +1063,19 @@ impl<VM: VMBinding> GCWork<VM> for SweepChunk<VM> {
                 if !block.sweep(self.space, &mut histogram, line_mark_state) {
                     // Block is live. Increment the allocated block count.
                     allocated_blocks += 1;
+                    live_lines += match block.get_state() {
+                        BlockState::Reusable { unavailable_lines } => unavailable_lines as usize,
+                        _ => Block::num_lines(),
+                    };
                 }
             }
             // Set this chunk as free if there is not live blocks.
             if allocated_blocks == 0 {
                 self.space.chunk_map.set(self.chunk, ChunkState::Free)
+            } else {
+                self.space
+                    .defrag
+                    .add_chunk_live_lines(self.chunk, live_lines);
             }
         }
         self.space.defrag.add_completed_mark_histogram(histogram);
@@ -947,7 +1095,8 @@ impl<VM: VMBinding> FlushPageResource<VM> {
         if 1 == self.counter.fetch_sub(1, Ordering::SeqCst) {
             // We've finished releasing all the dead blocks to the BlockPageResource's thread-local queues.
             // Now flush the BlockPageResource.
-            self.space.flush_page_resource()
+            self.space.flush_page_resource();
+            self.space.release_free_chunks();
         }
     }
 }
@@ -1,23 +1,32 @@
 use super::block::Block;
+use crate::util::heap::chunk_map::Chunk;
 use crate::util::linear_scan::{Region, RegionIterator};
 use crate::util::metadata::side_metadata::SideMetadataSpec;
 use crate::{
     util::{Address, ObjectReference},
     vm::*,
 };
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Data structure to reference a line within an immix block.
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq)]
 pub struct Line(Address);
 
+/// The log of the line size in bytes. All the Immix spaces that are alive at the same time use the
+/// same line size, which is set from the options when the first of them is created.
+static LOG_BYTES_IN_LINE: AtomicUsize = AtomicUsize::new(Line::DEFAULT_LOG_BYTES);
+
 impl Region for Line {
-    const LOG_BYTES: usize = 8;
+    /// The line size is decided at run time, so `Line` does not have the constants `LOG_BYTES` and `BYTES`.
+    fn log_bytes() -> usize {
+        LOG_BYTES_IN_LINE.load(Ordering::Relaxed)
+    }
 
     #[allow(clippy::assertions_on_constants)] // make sure line is not used when BLOCK_ONLY is turned on.
     fn from_aligned_address(address: Address) -> Self {
         debug_assert!(!super::BLOCK_ONLY);
-        debug_assert!(address.is_aligned_to(Self::BYTES));
+        debug_assert!(address.is_aligned_to(Self::bytes()));
         Self(address)
     }
 
@@ -31,10 +40,27 @@ impl Line {
     pub const RESET_MARK_STATE: u8 = 1;
     pub const MAX_MARK_STATE: u8 = 127;
 
+    /// The smallest line size that can be set with the option `immix_block_size`.
+    pub const MIN_LOG_BYTES: usize = 8;
+    /// The default line size.
+    pub const DEFAULT_LOG_BYTES: usize = Self::MIN_LOG_BYTES;
+
+    pub(super) fn set_log_bytes(log_bytes: usize) {
+        LOG_BYTES_IN_LINE.store(log_bytes, Ordering::Relaxed);
+    }
+
     /// Line mark table (side)
     pub const MARK_TABLE: SideMetadataSpec =
         crate::util::metadata::side_metadata::spec_defs::IX_LINE_MARK;
 
+    /// The address of the line in the line mark table. Like [`Block`], the table has a byte for each
+    /// line of the smallest size, and the n-th line in a chunk uses the byte of the n-th smallest
+    /// line in the chunk.
+    pub(super) fn metadata_address(&self) -> Address {
+        let chunk = Chunk::align(self.start());
+        chunk + ((self.start() - chunk) >> (Self::log_bytes() - Self::MIN_LOG_BYTES))
+    }
+
     /// Get the block containing the line.
     pub fn block(&self) -> Block {
         debug_assert!(!super::BLOCK_ONLY);
@@ -44,21 +70,21 @@ impl Line {
     /// Get line index within its containing block.
     pub fn get_index_within_block(&self) -> usize {
         let addr = self.start();
-        addr.get_extent(Block::align(addr)) >> Line::LOG_BYTES
+        addr.get_extent(Block::align(addr)) >> Line::log_bytes()
     }
 
     /// Mark the line. This will update the side line mark table.
     pub fn mark(&self, state: u8) {
         debug_assert!(!super::BLOCK_ONLY);
         unsafe {
-            Self::MARK_TABLE.store::<u8>(self.start(), state);
+            Self::MARK_TABLE.store::<u8>(self.metadata_address(), state);
         }
     }
 
     /// Test line mark state.
     pub fn is_marked(&self, state: u8) -> bool {
         debug_assert!(!super::BLOCK_ONLY);
-        unsafe { Self::MARK_TABLE.load::<u8>(self.start()) == state }
+        unsafe { Self::MARK_TABLE.load::<u8>(self.metadata_address()) == state }
     }
 
     /// Mark all lines the object is spanned to.
@@ -6,45 +6,72 @@ pub mod line;
 pub use immixspace::*;
 
 use crate::policy::immix::block::Block;
+use crate::policy::immix::line::Line;
+use crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK;
 use crate::util::linear_scan::Region;
 
-/// The max object size for immix: half of a block
-pub const MAX_IMMIX_OBJECT_SIZE: usize = Block::BYTES >> 1;
+/// The max object size for immix with the default block size: half of a block.
+/// The block size can be changed with the option `immix_block_size`. Use [`max_immix_object_size`]
+/// or the plan constraints to get the max object size for the current block size.
+pub const MAX_IMMIX_OBJECT_SIZE: usize = (1 << Block::DEFAULT_LOG_BYTES) >> 1;
+
+/// The max object size for immix with the current block size: half of a block.
+pub fn max_immix_object_size() -> usize {
+    Block::bytes() >> 1
+}
 
 /// Mark/sweep memory for block-level only
 pub const BLOCK_ONLY: bool = false;
 
-/// Do we allow Immix to do defragmentation?
-pub const DEFRAG: bool = !cfg!(feature = "immix_non_moving"); // defrag if we are allowed to move.
-
-/// Make every GC a defragment GC. (for debugging)
-pub const STRESS_DEFRAG: bool = false;
-
-/// Mark every allocated block as defragmentation source before GC. (for debugging)
-/// Set both this and `STRESS_DEFRAG` to true to make Immix move as many objects as possible.
-pub const DEFRAG_EVERY_BLOCK: bool = false;
-
-/// If Immix is used as a nursery space, do we prefer copy?
-pub const PREFER_COPY_ON_NURSERY_GC: bool = !cfg!(feature = "immix_non_moving"); // copy nursery objects if we are allowed to move.
-
-/// In some cases/settings, Immix may never move objects.
-/// Currently we only have two cases where we move objects: 1. defrag, 2. nursery copy.
-/// If we do neither, we will not move objects.
-/// If we have other reasons to move objects, we need to add them here.
-pub const NEVER_MOVE_OBJECTS: bool = !DEFRAG && !PREFER_COPY_ON_NURSERY_GC;
-
-/// Mark lines when scanning objects.
-/// Otherwise, do it at mark time.
-pub const MARK_LINE_AT_SCAN_TIME: bool = true;
-
 macro_rules! validate {
     ($x: expr) => { assert!($x, stringify!($x)) };
     ($x: expr => $y: expr) => { if $x { assert!($y, stringify!($x implies $y)) } };
 }
 
-fn validate_features() {
+fn validate_space_args(args: &ImmixSpaceArgs) {
     // Block-only immix cannot do defragmentation
-    validate!(DEFRAG => !BLOCK_ONLY);
+    validate!(args.defrag => !BLOCK_ONLY);
+    // Stress defrag only makes sense if we can do defragmentation
+    validate!(args.stress_defrag => args.defrag);
+    validate!(args.defrag_every_block => args.defrag);
+    validate!(args.defrag_headroom_percent <= 100);
+    validate!(
+        args.chunk_evacuation_trigger_percent == 0 || args.chunk_evacuation_trigger_percent > 100
+    );
+    validate!(args.block_log_bytes >= Block::MIN_LOG_BYTES);
+    validate!(args.block_log_bytes < LOG_BYTES_IN_CHUNK);
+    validate!(args.line_log_bytes >= Line::MIN_LOG_BYTES);
+    validate!(args.line_log_bytes < args.block_log_bytes);
     // Number of lines in a block should not exceed BlockState::MARK_MARKED
-    assert!(Block::LINES / 2 <= u8::MAX as usize - 2);
+    validate!(args.block_log_bytes - args.line_log_bytes <= Block::MAX_LOG_LINES);
+}
+
+/// Whether the block size and the line size have been set by an Immix space.
+static BLOCK_AND_LINE_SIZES_SET: spin::Mutex<bool> = spin::Mutex::new(false);
+
+/// Set the block size and the line size. The sizes are global, because the code that works with
+/// blocks and lines does not know which space they belong to. All the Immix spaces that are alive
+/// at the same time, including the ones in other MMTk instances, must use the same sizes.
+fn set_block_and_line_sizes(args: &ImmixSpaceArgs) {
+    let mut set = BLOCK_AND_LINE_SIZES_SET.lock();
+    if *set {
+        assert!(
+            Block::log_bytes() == args.block_log_bytes && Line::log_bytes() == args.line_log_bytes,
+            "All the Immix spaces that are alive at the same time must use the same block size and line size. Current: 2^{} and 2^{}, requested: 2^{} and 2^{}",
+            Block::log_bytes(),
+            Line::log_bytes(),
+            args.block_log_bytes,
+            args.line_log_bytes
+        );
+    } else {
+        Block::set_log_bytes(args.block_log_bytes);
+        Line::set_log_bytes(args.line_log_bytes);
+        *set = true;
+    }
+}
+
+/// Allow the next Immix space to set the block size and the line size. This is called when the last
+/// MMTk instance is shut down, so a new instance can use different sizes.
+pub(crate) fn reset_block_and_line_sizes() {
+    *BLOCK_AND_LINE_SIZES_SET.lock() = false;
 }
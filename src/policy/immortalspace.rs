@@ -4,6 +4,7 @@ use crate::policy::sft::SFT;
 use crate::policy::space::{CommonSpace, Space};
 use crate::util::address::Address;
 use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::metadata::field_log_bit;
 use crate::util::metadata::mark_bit::MarkState;
 
 use crate::util::{metadata, ObjectReference};
@@ -60,6 +61,14 @@ impl<VM: VMBinding> SFT for ImmortalSpace<VM> {
         if self.common.needs_log_bit {
             VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.mark_as_unlogged::<VM>(object, Ordering::SeqCst);
         }
+        if self.common.needs_field_log_bit {
+            // Objects in the immortal space are mature when they are allocated. This requires
+            // the object size to be available in `post_alloc`.
+            field_log_bit::mark_fields_as_unlogged::<VM>(
+                object,
+                VM::VMObjectModel::get_current_size(object),
+            );
+        }
         #[cfg(feature = "vo_bit")]
         crate::util::metadata::vo_bit::set_vo_bit::<VM>(object);
     }
@@ -182,6 +191,12 @@ impl<VM: VMBinding> ImmortalSpace<VM> {
         self.mark_state.on_global_release::<VM>();
     }
 
+    /// Call `f` with the start and the size of each memory region that may contain objects.
+    #[cfg(feature = "heap_snapshot")]
+    pub(crate) fn for_allocated_regions<F: FnMut(Address, usize)>(&self, f: F) {
+        self.pr.for_allocated_regions(f)
+    }
+
     pub fn trace_object<Q: ObjectQueue>(
         &self,
         queue: &mut Q,
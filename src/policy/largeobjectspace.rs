@@ -8,6 +8,7 @@ use crate::policy::space::{CommonSpace, Space};
 use crate::util::constants::BYTES_IN_PAGE;
 use crate::util::heap::{FreeListPageResource, PageResource};
 use crate::util::metadata;
+use crate::util::metadata::field_log_bit;
 use crate::util::opaque_pointer::*;
 use crate::util::treadmill::TreadMill;
 use crate::util::{Address, ObjectReference};
@@ -212,6 +213,12 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
                     VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
                         .mark_as_unlogged::<VM>(object, Ordering::SeqCst);
                 }
+                if nursery_object && self.common.needs_field_log_bit {
+                    field_log_bit::mark_fields_as_unlogged::<VM>(
+                        object,
+                        VM::VMObjectModel::get_current_size(object),
+                    );
+                }
                 queue.enqueue(object);
             } else {
                 trace!(
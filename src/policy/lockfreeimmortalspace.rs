@@ -10,7 +10,9 @@ use crate::policy::space::{CommonSpace, Space};
 use crate::util::address::Address;
 
 use crate::util::conversions;
-use crate::util::heap::layout::vm_layout_constants::{AVAILABLE_BYTES, AVAILABLE_START};
+use crate::util::heap::layout::vm_layout_constants::{
+    AVAILABLE_BYTES, AVAILABLE_START, BYTES_IN_CHUNK,
+};
 use crate::util::heap::PageResource;
 use crate::util::metadata::side_metadata::SideMetadataContext;
 use crate::util::metadata::side_metadata::SideMetadataSanity;
@@ -107,6 +109,18 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
         unsafe { SFT_MAP.update(self.as_sft(), self.start, self.extent) };
     }
 
+    fn release_on_shutdown(&self) {
+        let mut chunk = self.start;
+        while chunk < self.start + self.extent {
+            unsafe { SFT_MAP.clear(chunk) };
+            chunk += BYTES_IN_CHUNK;
+        }
+        // The memory is mapped by this space directly, not by the mmapper.
+        crate::util::memory::munmap(self.start, self.extent).unwrap();
+        self.metadata
+            .discard_metadata_space(self.start, self.extent);
+    }
+
     fn reserved_pages(&self) -> usize {
         let cursor = self.cursor.load(Ordering::Relaxed);
         let data_pages = conversions::bytes_to_pages_up(self.limit - cursor);
@@ -114,6 +128,11 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
         data_pages + meta_pages
     }
 
+    fn zeroes_concurrently(&self) -> bool {
+        // The space has no page resource, and it never releases memory.
+        false
+    }
+
     fn acquire(&self, _tls: VMThread, pages: usize) -> Address {
         let bytes = conversions::pages_to_bytes(pages);
         let start = self
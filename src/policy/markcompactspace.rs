@@ -5,13 +5,15 @@ use crate::policy::gc_work::TraceKind;
 use crate::policy::sft::GCWorkerMutRef;
 use crate::scheduler::GCWorker;
 use crate::util::alloc::allocator::align_allocation_no_fill;
-use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::constants::{LOG_BYTES_IN_WORD, MIN_OBJECT_SIZE};
 use crate::util::copy::CopySemantics;
 use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::metadata::side_metadata::{spec_defs, SideMetadataSpec};
 use crate::util::metadata::{extract_side_metadata, vo_bit};
 use crate::util::{Address, ObjectReference};
 use crate::{vm::*, ObjectQueue};
 use atomic::Ordering;
+use std::sync::atomic::AtomicBool;
 
 pub(crate) const TRACE_KIND_MARK: TraceKind = 0;
 pub(crate) const TRACE_KIND_FORWARD: TraceKind = 1;
@@ -19,6 +21,11 @@ pub(crate) const TRACE_KIND_FORWARD: TraceKind = 1;
 pub struct MarkCompactSpace<VM: VMBinding> {
     common: CommonSpace<VM>,
     pr: MonotonePageResource<VM>,
+    /// Store forwarding addresses in side metadata rather than in the header. See the option `mark_compact_side_forwarding`.
+    side_forwarding: bool,
+    /// Whether the forwarding addresses in the side metadata are valid, i.e. between
+    /// [`MarkCompactSpace::calculate_forwarding_pointer`] and [`MarkCompactSpace::compact`].
+    side_forwarding_calculated: AtomicBool,
 }
 
 const GC_MARK_BIT_MASK: u8 = 1;
@@ -29,12 +36,37 @@ const GC_MARK_BIT_MASK: u8 = 1;
 pub const GC_EXTRA_HEADER_WORD: usize = 1;
 const GC_EXTRA_HEADER_BYTES: usize = GC_EXTRA_HEADER_WORD << LOG_BYTES_IN_WORD;
 
+// With side forwarding, the live memory is marked in a bitmap with one bit per granule ([`MIN_OBJECT_SIZE`]), and we
+// record the forwarding address for the start of each region. The forwarding address of an object is then the
+// forwarding address of its region, plus the size of the live memory in the region before the object.
+
+/// Log bytes in a region for side forwarding.
+pub(crate) const LOG_BYTES_IN_FORWARDING_REGION: usize = 9;
+const BYTES_IN_FORWARDING_REGION: usize = 1 << LOG_BYTES_IN_FORWARDING_REGION;
+/// The live bitmap for side forwarding.
+const LIVE_BITMAP: SideMetadataSpec = spec_defs::MC_LIVE_BITMAP;
+/// The forwarding address of each region for side forwarding.
+const REGION_FORWARDING: SideMetadataSpec = spec_defs::MC_REGION_FORWARDING;
+/// A region whose objects are not moved is tagged with this bit in [`REGION_FORWARDING`]. This happens if it contains a
+/// pinned object, or an object that needs a larger alignment than [`MIN_OBJECT_SIZE`] when copied. During marking, the value of such a region is set to exactly this tag.
+const IN_PLACE_REGION_TAG: usize = 1;
+
 impl<VM: VMBinding> SFT for MarkCompactSpace<VM> {
     fn name(&self) -> &str {
         self.get_name()
     }
 
     fn get_forwarded_object(&self, object: ObjectReference) -> Option<ObjectReference> {
+        if self.side_forwarding {
+            // Before the forwarding addresses are calculated, the region forwarding table is stale.
+            return if self.side_forwarding_calculated.load(Ordering::SeqCst)
+                && Self::is_live_memory(object.to_object_start::<VM>())
+            {
+                Some(Self::get_side_forwarding_pointer(object))
+            } else {
+                None
+            };
+        }
         let forwarding_pointer = Self::get_header_forwarding_pointer(object);
         if forwarding_pointer.is_null() {
             None
@@ -50,18 +82,18 @@ impl<VM: VMBinding> SFT for MarkCompactSpace<VM> {
     }
 
     #[cfg(feature = "object_pinning")]
-    fn pin_object(&self, _object: ObjectReference) -> bool {
-        panic!("Cannot pin/unpin objects of MarkCompactSpace.")
+    fn pin_object(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.pin_object::<VM>(object)
     }
 
     #[cfg(feature = "object_pinning")]
-    fn unpin_object(&self, _object: ObjectReference) -> bool {
-        panic!("Cannot pin/unpin objects of MarkCompactSpace.")
+    fn unpin_object(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.unpin_object::<VM>(object)
     }
 
     #[cfg(feature = "object_pinning")]
-    fn is_object_pinned(&self, _object: ObjectReference) -> bool {
-        false
+    fn is_object_pinned(&self, object: ObjectReference) -> bool {
+        VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(object)
     }
 
     fn is_movable(&self) -> bool {
@@ -196,7 +228,15 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
     pub fn new(args: crate::policy::space::PlanCreateSpaceArgs<VM>) -> Self {
         let vm_map = args.vm_map;
         let is_discontiguous = args.vmrequest.is_discontiguous();
-        let local_specs = extract_side_metadata(&[*VM::VMObjectModel::LOCAL_MARK_BIT_SPEC]);
+        let side_forwarding = *args.options.mark_compact_side_forwarding;
+        let mut local_specs = extract_side_metadata(&[
+            *VM::VMObjectModel::LOCAL_MARK_BIT_SPEC,
+            #[cfg(feature = "object_pinning")]
+            *VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC,
+        ]);
+        if side_forwarding {
+            local_specs.extend([LIVE_BITMAP, REGION_FORWARDING]);
+        }
         let common = CommonSpace::new(args.into_policy_args(true, false, local_specs));
         MarkCompactSpace {
             pr: if is_discontiguous {
@@ -205,10 +245,16 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
                 MonotonePageResource::new_contiguous(common.start, common.extent, vm_map)
             },
             common,
+            side_forwarding,
+            side_forwarding_calculated: AtomicBool::new(false),
         }
     }
 
-    pub fn prepare(&self) {}
+    pub fn prepare(&self) {
+        if self.side_forwarding {
+            LIVE_BITMAP.bzero_metadata(self.common.start, self.pr.cursor() - self.common.start);
+        }
+    }
 
     pub fn release(&self) {}
 
@@ -223,6 +269,9 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
             object
         );
         if MarkCompactSpace::<VM>::test_and_mark(object) {
+            if self.side_forwarding {
+                Self::mark_live_memory(object);
+            }
             queue.enqueue(object);
         }
         object
@@ -244,7 +293,72 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
             queue.enqueue(object);
         }
 
-        Self::get_header_forwarding_pointer(object)
+        if self.side_forwarding {
+            Self::get_side_forwarding_pointer(object)
+        } else {
+            Self::get_header_forwarding_pointer(object)
+        }
+    }
+
+    /// Mark the memory of a live object in the live bitmap. If the object is pinned, or it needs a larger alignment
+    /// than [`MIN_OBJECT_SIZE`], its region will not be compacted. Sliding the object would lose its alignment, as the
+    /// padding before it is not recorded in the live bitmap.
+    fn mark_live_memory(object: ObjectReference) {
+        let start = object.to_object_start::<VM>();
+        let size = VM::VMObjectModel::get_current_size(object);
+        debug_assert!(start.is_aligned_to(MIN_OBJECT_SIZE));
+        debug_assert_eq!(
+            size,
+            VM::VMObjectModel::get_size_when_copied(object),
+            "{}: Objects cannot change their sizes when copied with side forwarding",
+            object
+        );
+        let end = (start + size).align_up(MIN_OBJECT_SIZE);
+        let mut granule = start;
+        while granule < end {
+            LIVE_BITMAP.fetch_or_atomic::<u8>(granule, 1, Ordering::SeqCst);
+            granule += MIN_OBJECT_SIZE;
+        }
+        if Self::is_pinned(object)
+            || VM::VMObjectModel::get_align_when_copied(object) > MIN_OBJECT_SIZE
+        {
+            REGION_FORWARDING.store_atomic::<usize>(
+                start.align_down(BYTES_IN_FORWARDING_REGION),
+                IN_PLACE_REGION_TAG,
+                Ordering::SeqCst,
+            );
+        }
+    }
+
+    fn is_live_memory(granule: Address) -> bool {
+        LIVE_BITMAP.load_atomic::<u8>(granule, Ordering::SeqCst) == 1
+    }
+
+    /// Get the number of live bytes in the range. Both ends should be aligned to [`MIN_OBJECT_SIZE`].
+    fn live_bytes_in_range(start: Address, end: Address) -> usize {
+        let mut live_bytes = 0;
+        let mut granule = start;
+        while granule < end {
+            if Self::is_live_memory(granule) {
+                live_bytes += MIN_OBJECT_SIZE;
+            }
+            granule += MIN_OBJECT_SIZE;
+        }
+        live_bytes
+    }
+
+    /// Get the forwarding pointer of a live object from the side metadata. This is only valid after
+    /// [`MarkCompactSpace::calculate_forwarding_pointer`].
+    fn get_side_forwarding_pointer(object: ObjectReference) -> ObjectReference {
+        let start = object.to_object_start::<VM>();
+        let region = start.align_down(BYTES_IN_FORWARDING_REGION);
+        let region_forwarding = REGION_FORWARDING.load_atomic::<usize>(region, Ordering::SeqCst);
+        if region_forwarding & IN_PLACE_REGION_TAG != 0 {
+            return object;
+        }
+        let new_start = unsafe { Address::from_usize(region_forwarding) }
+            + Self::live_bytes_in_range(region, start);
+        VM::VMObjectModel::get_reference_when_copied_to(object, new_start)
     }
 
     pub fn test_and_mark(object: ObjectReference) -> bool {
@@ -318,7 +432,24 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
         Self::is_marked(object)
     }
 
+    /// Is the object pinned? A pinned object is never moved by compaction. Objects before it slide
+    /// down as usual, and objects after it slide down to the end of it, leaving a gap in front of it.
+    fn is_pinned(_object: ObjectReference) -> bool {
+        #[cfg(feature = "object_pinning")]
+        {
+            VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.is_object_pinned::<VM>(_object)
+        }
+        #[cfg(not(feature = "object_pinning"))]
+        {
+            false
+        }
+    }
+
     pub fn calculate_forwarding_pointer(&self) {
+        if self.side_forwarding {
+            self.calculate_side_forwarding_pointer();
+            return;
+        }
         let start = self.common.start;
         let end = self.pr.cursor();
         let mut to = start;
@@ -328,6 +459,17 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
                 start, end,
             );
         for obj in linear_scan.filter(|obj| Self::to_be_compacted(*obj)) {
+            if Self::is_pinned(obj) {
+                // A pinned object stays where it is, and acts as a barrier for the sliding cursor.
+                // As objects are only ever slid down, `to` cannot have passed the start of it.
+                let obj_start = obj.to_object_start::<VM>();
+                debug_assert!(to <= obj_start - Self::HEADER_RESERVED_IN_BYTES);
+                Self::store_header_forwarding_pointer(obj, obj);
+                trace!("Calculate forward: {} is pinned", obj);
+                to = obj_start + VM::VMObjectModel::get_current_size(obj);
+                continue;
+            }
+
             let copied_size =
                 VM::VMObjectModel::get_size_when_copied(obj) + Self::HEADER_RESERVED_IN_BYTES;
             let align = VM::VMObjectModel::get_align_when_copied(obj);
@@ -353,7 +495,50 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
         debug!("Calculate forward end: to = {}", to);
     }
 
+    /// Calculate the forwarding address of each region for side forwarding.
+    fn calculate_side_forwarding_pointer(&self) {
+        let start = self.common.start;
+        let end = self.pr.cursor();
+        let mut to = start;
+
+        let mut region = start;
+        while region < end {
+            let region_end = region + BYTES_IN_FORWARDING_REGION;
+            if REGION_FORWARDING.load_atomic::<usize>(region, Ordering::SeqCst)
+                == IN_PLACE_REGION_TAG
+            {
+                // The region has a pinned object. Keep all its objects in place. As objects are only ever slid
+                // down, `to` cannot have passed the first live object in the region.
+                let mut granule = region;
+                while granule < region_end {
+                    if Self::is_live_memory(granule) {
+                        debug_assert!(to <= granule);
+                        to = granule + MIN_OBJECT_SIZE;
+                    }
+                    granule += MIN_OBJECT_SIZE;
+                }
+                trace!("Calculate forward: region {} is in place", region);
+                REGION_FORWARDING.store_atomic::<usize>(
+                    region,
+                    region.as_usize() | IN_PLACE_REGION_TAG,
+                    Ordering::SeqCst,
+                );
+            } else {
+                REGION_FORWARDING.store_atomic::<usize>(region, to.as_usize(), Ordering::SeqCst);
+                to += Self::live_bytes_in_range(region, region_end);
+            }
+            region = region_end;
+        }
+        debug!("Calculate side forward end: to = {}", to);
+        self.side_forwarding_calculated
+            .store(true, Ordering::SeqCst);
+    }
+
     pub fn compact(&self) {
+        if self.side_forwarding {
+            self.compact_with_side_forwarding();
+            return;
+        }
         let start = self.common.start;
         let end = self.pr.cursor();
         let mut to = end;
@@ -369,7 +554,12 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
             let forwarding_pointer = Self::get_header_forwarding_pointer(obj);
 
             trace!("Compact {} to {}", obj, forwarding_pointer);
-            if !forwarding_pointer.is_null() {
+            if forwarding_pointer == obj {
+                // The object is pinned. Keep it in place.
+                Self::clear_header_forwarding_pointer(obj);
+                vo_bit::set_vo_bit::<VM>(obj);
+                to = obj.to_object_start::<VM>() + VM::VMObjectModel::get_current_size(obj);
+            } else if !forwarding_pointer.is_null() {
                 let copied_size = VM::VMObjectModel::get_size_when_copied(obj);
                 let new_object = forwarding_pointer;
                 Self::clear_header_forwarding_pointer(new_object);
@@ -381,10 +571,52 @@ impl<VM: VMBinding> MarkCompactSpace<VM> {
                 vo_bit::set_vo_bit::<VM>(new_object);
                 to = new_object.to_object_start::<VM>() + copied_size;
                 debug_assert_eq!(end_of_new_object, to);
+            } else {
+                // The object is dead. Clear its pin bit in case it was pinned.
+                #[cfg(feature = "object_pinning")]
+                VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.unpin_object::<VM>(obj);
+            }
+        }
+
+        debug!("Compact end: to = {}", to);
+
+        // reset the bump pointer
+        self.pr.reset_cursor(to);
+    }
+
+    /// Compact live objects with the forwarding addresses in the side metadata.
+    fn compact_with_side_forwarding(&self) {
+        let start = self.common.start;
+        let end = self.pr.cursor();
+        let mut to = start;
+
+        let linear_scan =
+            crate::util::linear_scan::ObjectIterator::<VM, MarkCompactObjectSize<VM>, true>::new(
+                start, end,
+            );
+        for obj in linear_scan {
+            // clear the VO bit
+            vo_bit::unset_vo_bit::<VM>(obj);
+
+            if Self::is_live_memory(obj.to_object_start::<VM>()) {
+                let size = VM::VMObjectModel::get_current_size(obj);
+                let new_object = Self::get_side_forwarding_pointer(obj);
+                trace!("Compact {} to {}", obj, new_object);
+                if new_object != obj {
+                    VM::VMObjectModel::copy_to(obj, new_object, Address::ZERO);
+                }
+                vo_bit::set_vo_bit::<VM>(new_object);
+                to = new_object.to_object_start::<VM>() + size;
+            } else {
+                // The object is dead. Clear its pin bit in case it was pinned.
+                #[cfg(feature = "object_pinning")]
+                VM::VMObjectModel::LOCAL_PINNING_BIT_SPEC.unpin_object::<VM>(obj);
             }
         }
 
         debug!("Compact end: to = {}", to);
+        self.side_forwarding_calculated
+            .store(false, Ordering::SeqCst);
 
         // reset the bump pointer
         self.pr.reset_cursor(to);
@@ -149,6 +149,11 @@ impl<VM: VMBinding> Space<VM> for MallocSpace<VM> {
         // Do nothing - we will set sft when we get new results from malloc
     }
 
+    fn release_on_shutdown(&self) {
+        // Do nothing - the memory is from malloc. Only one MMTk instance can use malloc mark sweep
+        // at a time, and the SFT map and the side metadata are reset when it is shut down.
+    }
+
     fn release_multiple_pages(&mut self, _start: Address) {
         unreachable!()
     }
@@ -209,6 +214,11 @@ impl<VM: VMBinding> Space<VM> for MallocSpace<VM> {
         data_pages + meta_pages
     }
 
+    fn zeroes_concurrently(&self) -> bool {
+        // The space has no page resource. The memory is from malloc.
+        false
+    }
+
     fn verify_side_metadata_sanity(&self, side_metadata_sanity_checker: &mut SideMetadataSanity) {
         side_metadata_sanity_checker
             .verify_metadata_context(std::any::type_name::<Self>(), &self.metadata)
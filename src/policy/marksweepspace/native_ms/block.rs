@@ -34,7 +34,9 @@ impl std::fmt::Debug for Block {
 }
 
 impl Region for Block {
-    const LOG_BYTES: usize = 16;
+    fn log_bytes() -> usize {
+        Self::LOG_BYTES
+    }
 
     fn from_aligned_address(address: Address) -> Self {
         debug_assert!(address.is_aligned_to(Self::BYTES));
@@ -48,6 +50,11 @@ impl Region for Block {
 }
 
 impl Block {
+    /// Log bytes in block
+    pub const LOG_BYTES: usize = 16;
+    /// Bytes in block
+    pub const BYTES: usize = 1 << Self::LOG_BYTES;
+
     pub const METADATA_SPECS: [SideMetadataSpec; 7] = [
         Self::MARK_TABLE,
         Self::NEXT_BLOCK_TABLE,
@@ -27,5 +27,7 @@ pub mod largeobjectspace;
 pub mod lockfreeimmortalspace;
 pub mod markcompactspace;
 pub mod marksweepspace;
+#[cfg(feature = "ro_space")]
+pub mod readonlyspace;
 #[cfg(feature = "vm_space")]
 pub mod vmspace;
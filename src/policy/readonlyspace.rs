@@ -0,0 +1,238 @@
+use atomic::Ordering;
+
+use crate::plan::{ObjectQueue, VectorObjectQueue};
+use crate::policy::sft::GCWorkerMutRef;
+use crate::policy::sft::SFT;
+use crate::policy::space::{CommonSpace, Space};
+use crate::util::address::Address;
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::memory;
+use crate::util::metadata::field_log_bit;
+use crate::util::metadata::side_metadata::{spec_defs, SideMetadataSpec};
+use crate::util::ObjectReference;
+use crate::vm::{ActivePlan, ObjectModel, VMBinding};
+use crate::AllocationSemantics;
+use std::sync::Mutex;
+
+/// The mark bit of the read-only space. It is always on the side so that GC does not write to sealed objects.
+const MARK_BIT: SideMetadataSpec = spec_defs::RO_MARK_BIT;
+
+/// This type implements a non-moving and non-collecting space for read-only objects. It behaves like
+/// an [`ImmortalSpace`](crate::policy::immortalspace::ImmortalSpace) until it is sealed. Sealing the
+/// space protects the pages allocated so far as read-only, and later allocations go to fresh pages.
+/// GC never writes to the objects in this space, as the mark bit is on the side.
+pub struct ReadOnlySpace<VM: VMBinding> {
+    common: CommonSpace<VM>,
+    pr: MonotonePageResource<VM>,
+    /// Sealed memory ranges as `(start, end)`. A range may grow if it is sealed again.
+    sealed: Mutex<Vec<(Address, Address)>>,
+}
+
+impl<VM: VMBinding> SFT for ReadOnlySpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, _object: ObjectReference) -> bool {
+        true
+    }
+    fn is_reachable(&self, object: ObjectReference) -> bool {
+        MARK_BIT.load_atomic::<u8>(object.to_address::<VM>(), Ordering::SeqCst) == 1
+    }
+    #[cfg(feature = "object_pinning")]
+    fn pin_object(&self, _object: ObjectReference) -> bool {
+        false
+    }
+    #[cfg(feature = "object_pinning")]
+    fn unpin_object(&self, _object: ObjectReference) -> bool {
+        false
+    }
+    #[cfg(feature = "object_pinning")]
+    fn is_object_pinned(&self, _object: ObjectReference) -> bool {
+        true
+    }
+    fn is_movable(&self) -> bool {
+        false
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_object_metadata(&self, object: ObjectReference, _alloc: bool) {
+        // The object is not sealed yet. We can still write to its header.
+        if self.common.needs_log_bit {
+            VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.mark_as_unlogged::<VM>(object, Ordering::SeqCst);
+        }
+        if self.common.needs_field_log_bit {
+            field_log_bit::mark_fields_as_unlogged::<VM>(
+                object,
+                VM::VMObjectModel::get_current_size(object),
+            );
+        }
+        #[cfg(feature = "vo_bit")]
+        crate::util::metadata::vo_bit::set_vo_bit::<VM>(object);
+    }
+    #[cfg(feature = "is_mmtk_object")]
+    fn is_mmtk_object(&self, addr: Address) -> bool {
+        crate::util::metadata::vo_bit::is_vo_bit_set_for_addr::<VM>(addr).is_some()
+    }
+    fn sft_trace_object(
+        &self,
+        queue: &mut VectorObjectQueue,
+        object: ObjectReference,
+        _worker: GCWorkerMutRef,
+    ) -> ObjectReference {
+        self.trace_object(queue, object)
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for ReadOnlySpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        &self.pr
+    }
+    fn common(&self) -> &CommonSpace<VM> {
+        &self.common
+    }
+
+    fn initialize_sft(&self) {
+        self.common().initialize_sft(self.as_sft())
+    }
+
+    fn release_multiple_pages(&mut self, _start: Address) {
+        panic!("readonlyspace only releases pages enmasse")
+    }
+}
+
+use crate::scheduler::GCWorker;
+use crate::util::copy::CopySemantics;
+
+impl<VM: VMBinding> crate::policy::gc_work::PolicyTraceObject<VM> for ReadOnlySpace<VM> {
+    fn trace_object<Q: ObjectQueue, const KIND: crate::policy::gc_work::TraceKind>(
+        &self,
+        queue: &mut Q,
+        object: ObjectReference,
+        _copy: Option<CopySemantics>,
+        _worker: &mut GCWorker<VM>,
+    ) -> ObjectReference {
+        self.trace_object(queue, object)
+    }
+    fn may_move_objects<const KIND: crate::policy::gc_work::TraceKind>() -> bool {
+        false
+    }
+}
+
+impl<VM: VMBinding> ReadOnlySpace<VM> {
+    pub fn new(args: crate::policy::space::PlanCreateSpaceArgs<VM>) -> Self {
+        let vm_map = args.vm_map;
+        let is_discontiguous = args.vmrequest.is_discontiguous();
+        let common = CommonSpace::new(args.into_policy_args(false, true, vec![MARK_BIT]));
+        ReadOnlySpace {
+            pr: if is_discontiguous {
+                MonotonePageResource::new_discontiguous(vm_map)
+            } else {
+                MonotonePageResource::new_contiguous(common.start, common.extent, vm_map)
+            },
+            common,
+            sealed: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn prepare(&mut self) {
+        self.for_allocated_regions(|addr, size| MARK_BIT.bzero_metadata(addr, size));
+    }
+
+    pub fn release(&mut self) {}
+
+    /// Call `f` with the start and the size of each memory region that contains allocated pages.
+    fn for_allocated_regions<F: FnMut(Address, usize)>(&self, mut f: F) {
+        if self.common.contiguous {
+            // The page resource reports the whole chunk for a contiguous space. Use the cursor instead.
+            let cursor = self.pr.cursor();
+            if cursor > self.common.start {
+                f(self.common.start, cursor - self.common.start);
+            }
+        } else {
+            self.pr.for_allocated_regions(|addr, size| {
+                if size > 0 {
+                    f(addr, size)
+                }
+            });
+        }
+    }
+
+    /// Is the object in a sealed range?
+    pub fn is_sealed(&self, object: ObjectReference) -> bool {
+        let addr = object.to_address::<VM>();
+        self.sealed
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|&(start, end)| addr >= start && addr < end)
+    }
+
+    /// Protect all the pages allocated so far as read-only, and reset the read-only allocators of
+    /// all the mutators so later allocations go to fresh pages.
+    pub fn seal(&self) {
+        let mut sealed = self.sealed.lock().unwrap();
+        self.for_allocated_regions(|start, size| {
+            debug_assert!(start.is_aligned_to(BYTES_IN_PAGE));
+            let end = (start + size).align_up(BYTES_IN_PAGE);
+            let range = match sealed.iter_mut().find(|(s, _)| *s == start) {
+                Some(range) => range,
+                None => {
+                    sealed.push((start, start));
+                    sealed.last_mut().unwrap()
+                }
+            };
+            if range.1 < end {
+                debug!("{}: seal {} to {}", self.get_name(), range.1, end);
+                memory::mprotect_read_only(range.1, end - range.1).unwrap_or_else(|e| {
+                    panic!(
+                        "Failed to seal {} to {} in {}: {}",
+                        range.1,
+                        end,
+                        self.get_name(),
+                        e
+                    )
+                });
+                range.1 = end;
+            }
+        });
+        drop(sealed);
+
+        // The thread-local buffers of the mutators are in the sealed pages.
+        for mutator in VM::VMActivePlan::mutators() {
+            let selector = mutator.config.allocator_mapping[AllocationSemantics::ReadOnly];
+            let allocator = unsafe { mutator.allocators.get_allocator_mut(selector) };
+            if allocator.get_space().common().descriptor == self.common.descriptor {
+                allocator
+                    .downcast_mut::<crate::util::alloc::BumpAllocator<VM>>()
+                    .unwrap()
+                    .reset();
+            }
+        }
+    }
+
+    pub fn trace_object<Q: ObjectQueue>(
+        &self,
+        queue: &mut Q,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        #[cfg(feature = "vo_bit")]
+        debug_assert!(
+            crate::util::metadata::vo_bit::is_vo_bit_set::<VM>(object),
+            "{:x}: VO bit not set",
+            object
+        );
+        if MARK_BIT.fetch_or_atomic::<u8>(object.to_address::<VM>(), 1, Ordering::SeqCst) == 0 {
+            queue.enqueue(object);
+        }
+        object
+    }
+}
@@ -58,6 +58,22 @@ pub trait SFTMap {
     /// The address must have a valid SFT entry in the map. Usually we know this if the address is from an object reference, or from our space address range.
     /// Otherwise, the caller should check with `has_sft_entry()` before calling this method.
     unsafe fn clear(&self, address: Address);
+
+    /// Clear the SFT entry for a chunk that a space no longer uses, so the chunk is not considered
+    /// to be in the space until a space allocates it again. A map with one entry for each space keeps
+    /// the entry, as the other chunks of the space still use it.
+    ///
+    /// # Safety
+    /// The chunk must have a valid SFT entry in the map.
+    unsafe fn clear_chunk(&self, chunk_start: Address) {
+        self.clear(chunk_start)
+    }
+
+    /// Clear all the SFT entries so that spaces can be created again.
+    ///
+    /// # Safety
+    /// The caller must make sure that no space uses the SFT map any more.
+    unsafe fn reset(&self);
 }
 
 pub(crate) fn create_sft_map() -> Box<dyn SFTMap> {
@@ -136,6 +152,14 @@ mod space_map {
             let index = Self::addr_to_index(addr);
             *mut_self.sft.get_unchecked_mut(index) = &EMPTY_SPACE_SFT;
         }
+
+        unsafe fn clear_chunk(&self, _chunk_start: Address) {
+            // The entry is for the whole space. The space still uses its other chunks.
+        }
+
+        unsafe fn reset(&self) {
+            self.mut_self().sft.fill(&EMPTY_SPACE_SFT);
+        }
     }
 
     impl<'a> SFTSpaceMap<'a> {
@@ -249,9 +273,10 @@ mod dense_chunk_map {
         /// The dense table, one entry per space. We use side metadata to store the space index for each chunk.
         /// 0 is EMPTY_SPACE_SFT.
         sft: Vec<&'a (dyn SFT + Sync + 'static)>,
-        /// A map from space name (assuming they are unique) to their index. We use this to know whether we have
-        /// pushed &dyn SFT for a space, and to know its index.
-        index_map: HashMap<String, usize>,
+        /// A map from the address of each space to its index. We use this to know whether we have
+        /// pushed &dyn SFT for a space, and to know its index. We do not use space names, as
+        /// spaces in different MMTk instances may have the same name.
+        index_map: HashMap<usize, usize>,
     }
 
     unsafe impl<'a> Sync for SFTDenseChunkMap<'a> {}
@@ -313,9 +338,10 @@ mod dense_chunk_map {
             // If not, push the space pointer to the table and add an entry to the hahs map.
             let index: u8 = *mut_self
                 .index_map
-                .entry(space.name().to_string())
+                .entry(space as *const _ as *const () as usize)
                 .or_insert_with(|| {
                     let count = mut_self.sft.len();
+                    assert!(count <= u8::MAX as usize, "Too many spaces in the SFT map");
                     mut_self.sft.push(space);
                     count
                 }) as u8;
@@ -346,6 +372,14 @@ mod dense_chunk_map {
                 Ordering::SeqCst,
             );
         }
+
+        unsafe fn reset(&self) {
+            // The side metadata for the chunks is not cleared here. It is unmapped with other side
+            // metadata, and it reads as the empty SFT index once it is mapped again.
+            let mut_self = self.mut_self();
+            mut_self.sft.truncate(Self::EMPTY_SFT_INDEX as usize + 1);
+            mut_self.index_map.clear();
+        }
     }
 
     impl<'a> SFTDenseChunkMap<'a> {
@@ -442,6 +476,10 @@ mod sparse_chunk_map {
             let chunk_idx = chunk_start.chunk_index();
             self.set(chunk_idx, &EMPTY_SPACE_SFT);
         }
+
+        unsafe fn reset(&self) {
+            self.mut_self().sft.fill(&EMPTY_SPACE_SFT);
+        }
     }
 
     impl<'a> SFTSparseChunkMap<'a> {
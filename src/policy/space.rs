@@ -13,7 +13,7 @@ use crate::util::heap::{PageResource, VMRequest};
 use crate::util::options::Options;
 use crate::vm::{ActivePlan, Collection};
 
-use crate::util::constants::LOG_BYTES_IN_MBYTE;
+use crate::util::constants::{LOG_BYTES_IN_MBYTE, LOG_BYTES_IN_PAGE};
 use crate::util::conversions;
 use crate::util::opaque_pointer::*;
 
@@ -31,6 +31,7 @@ use crate::util::heap::HeapMeta;
 use crate::util::memory;
 use crate::vm::VMBinding;
 use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -51,11 +52,12 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
         // Should we poll to attempt to GC?
         // - If tls is collector, we cannot attempt a GC.
         // - If gc is disabled, we cannot attempt a GC.
-        let should_poll = VM::VMActivePlan::is_mutator(tls)
-            && VM::VMActivePlan::global().should_trigger_gc_when_heap_is_full();
+        let plan = self.get_gc_trigger().plan();
+        let should_poll =
+            VM::VMActivePlan::is_mutator(tls) && plan.should_trigger_gc_when_heap_is_full();
         // Is a GC allowed here? If we should poll but are not allowed to poll, we will panic.
         // initialize_collection() has to be called so we know GC is initialized.
-        let allow_gc = should_poll && VM::VMActivePlan::global().is_initialized();
+        let allow_gc = should_poll && plan.is_initialized();
 
         trace!("Reserving pages");
         let pr = self.get_page_resource();
@@ -134,9 +136,16 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
                         map_sidemetadata();
                     }
 
-                    // TODO: Concurrent zeroing
                     if self.common().zeroed {
-                        memory::zero(res.start, bytes);
+                        let pr = self.get_page_resource().common();
+                        // With concurrent zeroing, the page resource only returns zeroed pages.
+                        if !pr.zero_concurrent.load(Ordering::Relaxed) {
+                            if pr.zero_nontemporal.load(Ordering::Relaxed) {
+                                memory::zero_nontemporal(res.start, bytes);
+                            } else {
+                                memory::zero(res.start, bytes);
+                            }
+                        }
                     }
 
                     // Some assertions
@@ -274,6 +283,37 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
         data_pages + meta_pages
     }
 
+    /// Does this space zero its released pages in a concurrent zeroing thread?
+    fn zeroes_concurrently(&self) -> bool {
+        self.get_page_resource()
+            .common()
+            .zero_concurrent
+            .load(Ordering::SeqCst)
+    }
+
+    /// Release the memory of this space when the MMTk instance is shut down, so that another
+    /// MMTk instance can use the address range. The SFT entries are cleared, the memory is
+    /// unmapped, and the side metadata is discarded. The space must not be used afterwards.
+    fn release_on_shutdown(&self) {
+        let common = self.common();
+        if !common.contiguous {
+            // Discontiguous spaces take chunks from the discontiguous range shared by all the
+            // spaces. The range is released when the last MMTk instance is shut down.
+            return;
+        }
+        let (start, extent) = (common.start, common.extent);
+        let mut chunk = start;
+        while chunk < start + extent {
+            unsafe { SFT_MAP.clear(chunk) };
+            chunk += BYTES_IN_CHUNK;
+        }
+        common
+            .mmapper
+            .unmap(start, extent >> LOG_BYTES_IN_PAGE)
+            .unwrap_or_else(|e| panic!("Failed to unmap {}: {}", self.get_name(), e));
+        common.metadata.discard_metadata_space(start, extent);
+    }
+
     /// Return the number of physical pages available.
     fn available_physical_pages(&self) -> usize {
         self.get_page_resource().get_available_physical_pages()
@@ -399,6 +439,8 @@ pub struct CommonSpace<VM: VMBinding> {
     /// This field equals to needs_log_bit in the plan constraints.
     // TODO: This should be a constant for performance.
     pub needs_log_bit: bool,
+    /// This field equals to needs_field_log_bit in the plan constraints.
+    pub needs_field_log_bit: bool,
 
     /// A lock used during acquire() to make sure only one thread can allocate.
     pub acquire_lock: Mutex<()>,
@@ -465,6 +507,7 @@ impl<VM: VMBinding> CommonSpace<VM> {
             vm_map: args.plan_args.vm_map,
             mmapper: args.plan_args.mmapper,
             needs_log_bit: args.plan_args.constraints.needs_log_bit,
+            needs_field_log_bit: args.plan_args.constraints.needs_field_log_bit,
             gc_trigger: args.plan_args.gc_trigger,
             metadata: SideMetadataContext {
                 global: args.plan_args.global_side_metadata_specs,
@@ -1,3 +1,4 @@
+use crate::plan::BarrierSelector;
 use crate::plan::{CreateGeneralPlanArgs, CreateSpecificPlanArgs};
 use crate::plan::{ObjectQueue, VectorObjectQueue};
 use crate::policy::immortalspace::ImmortalSpace;
@@ -11,42 +12,94 @@ use crate::util::heap::VMRequest;
 use crate::util::metadata::side_metadata::SideMetadataContext;
 use crate::util::metadata::side_metadata::SideMetadataSanity;
 use crate::util::ObjectReference;
+use crate::vm::edge_shape::{Edge, MemorySlice};
 use crate::vm::VMBinding;
+use std::collections::HashSet;
+use std::sync::{Mutex, RwLock};
 
-use delegate::delegate;
-
+/// The VM space consists of memory regions that are allocated and populated by the VM, such as
+/// boot images. Each region is an immortal space. The regions are outside our heap range, and they
+/// can be added and removed at runtime, while mutators and other threads may look up the regions.
 pub struct VMSpace<VM: VMBinding> {
-    inner: Option<ImmortalSpace<VM>>,
+    /// The regions, sorted by their start addresses. Each region is boxed, so its address does
+    /// not change when regions are added or removed.
+    #[allow(clippy::vec_box)]
+    inner: RwLock<Vec<Box<ImmortalSpace<VM>>>>,
+    /// The regions that have been removed. We keep them until the VM space is dropped, so the
+    /// references to a region that we hand out stay valid after the lock is released.
+    #[allow(clippy::vec_box)]
+    removed: Mutex<Vec<Box<ImmortalSpace<VM>>>>,
     // Save it
-    args: CreateSpecificPlanArgs<VM>,
+    args: Mutex<CreateSpecificPlanArgs<VM>>,
+    /// Do we use a remembered set instead of tracing the VM space in full heap GCs? See the option
+    /// `vm_space_remembered_set`.
+    use_remembered_set: bool,
+    /// The objects and the slots in the VM space that have been recorded by the barrier. They may
+    /// refer to objects outside the VM space.
+    remembered_set: Mutex<RememberedSet<VM>>,
+}
+
+struct RememberedSet<VM: VMBinding> {
+    objects: HashSet<ObjectReference>,
+    edges: HashSet<VM::VMEdge>,
 }
 
+const VM_SPACE_NAME: &str = "vm_space";
+
 impl<VM: VMBinding> SFT for VMSpace<VM> {
-    delegate! {
-        // Delegate every call to the inner space. Given that we have acquired SFT, we can assume there are objects in the space and the space is initialized.
-        to self.space() {
-            fn name(&self) -> &str;
-            fn is_live(&self, object: ObjectReference) -> bool;
-            fn is_reachable(&self, object: ObjectReference) -> bool;
-            #[cfg(feature = "object_pinning")]
-            fn pin_object(&self, object: ObjectReference) -> bool;
-            #[cfg(feature = "object_pinning")]
-            fn unpin_object(&self, object: ObjectReference) -> bool;
-            #[cfg(feature = "object_pinning")]
-            fn is_object_pinned(&self, object: ObjectReference) -> bool;
-            fn is_movable(&self) -> bool;
-            #[cfg(feature = "sanity")]
-            fn is_sane(&self) -> bool;
-            fn initialize_object_metadata(&self, object: ObjectReference, alloc: bool);
-            #[cfg(feature = "is_mmtk_object")]
-            fn is_mmtk_object(&self, addr: Address) -> bool;
-            fn sft_trace_object(
-                &self,
-                queue: &mut VectorObjectQueue,
-                object: ObjectReference,
-                worker: GCWorkerMutRef,
-            ) -> ObjectReference;
-        }
+    // The SFT entries of all the regions point to the VM space. Delegate every call to the region
+    // that contains the object. Given that we have acquired SFT, we can assume the object is in
+    // one of the regions, except for `is_in_space` and `is_mmtk_object`. The SFT map may map an
+    // address that is not in any region to the VM space if the SFT map is not chunk-based.
+    fn name(&self) -> &str {
+        VM_SPACE_NAME
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        self.space_for_object(object).is_live(object)
+    }
+    fn is_reachable(&self, object: ObjectReference) -> bool {
+        // Objects are not marked if we do not trace the VM space.
+        self.use_remembered_set || self.space_for_object(object).is_reachable(object)
+    }
+    #[cfg(feature = "object_pinning")]
+    fn pin_object(&self, object: ObjectReference) -> bool {
+        self.space_for_object(object).pin_object(object)
+    }
+    #[cfg(feature = "object_pinning")]
+    fn unpin_object(&self, object: ObjectReference) -> bool {
+        self.space_for_object(object).unpin_object(object)
+    }
+    #[cfg(feature = "object_pinning")]
+    fn is_object_pinned(&self, object: ObjectReference) -> bool {
+        self.space_for_object(object).is_object_pinned(object)
+    }
+    fn is_movable(&self) -> bool {
+        false
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn is_in_space(&self, object: ObjectReference) -> bool {
+        self.space_for_address(object.to_address::<VM>()).is_some()
+    }
+    fn initialize_object_metadata(&self, object: ObjectReference, alloc: bool) {
+        self.space_for_object(object)
+            .initialize_object_metadata(object, alloc)
+    }
+    #[cfg(feature = "is_mmtk_object")]
+    fn is_mmtk_object(&self, addr: Address) -> bool {
+        self.space_for_address(addr)
+            .map_or(false, |space| space.is_mmtk_object(addr))
+    }
+    fn sft_trace_object(
+        &self,
+        queue: &mut VectorObjectQueue,
+        object: ObjectReference,
+        worker: GCWorkerMutRef,
+    ) -> ObjectReference {
+        self.space_for_object(object)
+            .sft_trace_object(queue, object, worker)
     }
 }
 
@@ -57,6 +110,7 @@ impl<VM: VMBinding> Space<VM> for VMSpace<VM> {
     fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
         self
     }
+    // The page resource and the common space of the first region represent the VM space.
     fn get_page_resource(&self) -> &dyn PageResource<VM> {
         self.space().get_page_resource()
     }
@@ -65,9 +119,7 @@ impl<VM: VMBinding> Space<VM> for VMSpace<VM> {
     }
 
     fn initialize_sft(&self) {
-        if self.inner.is_some() {
-            self.common().initialize_sft(self.as_sft())
-        }
+        self.initialize_sft_for(&self.inner.read().unwrap())
     }
 
     fn release_multiple_pages(&mut self, _start: Address) {
@@ -78,17 +130,41 @@ impl<VM: VMBinding> Space<VM> for VMSpace<VM> {
         side_metadata_sanity_checker.verify_metadata_context(
             std::any::type_name::<Self>(),
             &SideMetadataContext {
-                global: self.args.global_side_metadata_specs.clone(),
+                global: self.args.lock().unwrap().global_side_metadata_specs.clone(),
                 local: vec![],
             },
         )
     }
 
     fn address_in_space(&self, start: Address) -> bool {
-        if let Some(space) = self.space_maybe() {
-            space.address_in_space(start)
-        } else {
-            false
+        self.space_for_address(start).is_some()
+    }
+
+    fn reserved_pages(&self) -> usize {
+        self.inner
+            .read()
+            .unwrap()
+            .iter()
+            .map(|space| space.reserved_pages())
+            .sum()
+    }
+
+    fn zeroes_concurrently(&self) -> bool {
+        // The VM space never releases pages. It may not have any region yet.
+        false
+    }
+
+    fn release_on_shutdown(&self) {
+        // The memory of the regions is mapped by the VM, and the VM should unmap it. We only
+        // forget the regions.
+        for (start, extent) in self.regions() {
+            let mut chunk = start;
+            while chunk < start + extent {
+                unsafe { crate::mmtk::SFT_MAP.clear(chunk) };
+                chunk += crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+            }
+            self.common().metadata.discard_metadata_space(start, extent);
+            self.common().mmapper.mark_as_unmapped(start, extent);
         }
     }
 }
@@ -125,20 +201,182 @@ impl<VM: VMBinding> VMSpace<VM> {
             constraints: args.constraints,
             global_side_metadata_specs: args.global_side_metadata_specs.clone(),
         };
-        // Create the space if the VM space start/size is set. Otherwise, use None.
-        let inner = (!args.global_args.options.vm_space_start.is_zero())
-            .then(|| Self::create_space(args, None));
+        // Create a region if the VM space start/size is set. Otherwise, start with no region.
+        let inner = if args.global_args.options.vm_space_start.is_zero() {
+            vec![]
+        } else {
+            vec![Box::new(Self::create_space(args, None))]
+        };
+        let use_remembered_set = *args.global_args.options.vm_space_remembered_set
+            && matches!(
+                args.constraints.barrier,
+                BarrierSelector::ObjectBarrier | BarrierSelector::FieldBarrier
+            );
+        if *args.global_args.options.vm_space_remembered_set && !use_remembered_set {
+            warn!(
+                "vm_space_remembered_set is ignored for the barrier {:?}. The VM space will be traced.",
+                args.constraints.barrier
+            );
+        }
         Self {
-            inner,
-            args: args_clone,
+            inner: RwLock::new(inner),
+            removed: Mutex::new(vec![]),
+            args: Mutex::new(args_clone),
+            use_remembered_set,
+            remembered_set: Mutex::new(RememberedSet {
+                objects: HashSet::new(),
+                edges: HashSet::new(),
+            }),
+        }
+    }
+
+    pub fn lazy_initialize(&self, start: Address, size: usize) {
+        assert!(
+            self.inner.read().unwrap().is_empty(),
+            "VM space has been initialized"
+        );
+        self.add_region(start, size);
+    }
+
+    /// Add a region to the VM space. The region must not overlap with the existing regions after
+    /// they are aligned to chunks.
+    pub fn add_region(&self, start: Address, size: usize) {
+        let space = Self::create_space(&mut self.args.lock().unwrap(), Some((start, size)));
+        let (new_start, new_end) = (
+            space.common().start,
+            space.common().start + space.common().extent,
+        );
+        let mut inner = self.inner.write().unwrap();
+        assert!(
+            inner.iter().all(|s| Address::range_intersection(
+                &(s.common().start..s.common().start + s.common().extent),
+                &(new_start..new_end)
+            )
+            .is_empty()),
+            "VM space region ({}, {}) overlaps with an existing region",
+            new_start,
+            new_end
+        );
+        let index = inner.partition_point(|s| s.common().start < new_start);
+        inner.insert(index, Box::new(space));
+
+        inner[index].common().initialize_sft(self.as_sft());
+    }
+
+    /// Remove the region that contains the address `start` from the VM space. The VO bits and the
+    /// SFT entries for the region are cleared. The memory is not unmapped, and the VM should unmap
+    /// it if needed.
+    pub fn remove_region(&self, start: Address) {
+        let mut inner = self.inner.write().unwrap();
+        let index = inner
+            .iter()
+            .position(|s| s.address_in_space(start))
+            .unwrap_or_else(|| panic!("{} is not in any VM space region", start));
+        let space = inner.remove(index);
+        let (region_start, region_extent) = (space.common().start, space.common().extent);
+        debug!(
+            "Remove VM space region ({}, {})",
+            region_start,
+            region_start + region_extent
+        );
+
+        #[cfg(feature = "vo_bit")]
+        crate::util::metadata::vo_bit::bzero_vo_bit(region_start, region_extent);
+
+        use crate::mmtk::SFT_MAP;
+        use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+        let mut chunk = region_start;
+        while chunk < region_start + region_extent {
+            unsafe { SFT_MAP.clear(chunk) };
+            chunk += BYTES_IN_CHUNK;
+        }
+        // A non chunk-based SFT map may share one entry among regions, and the entry has just
+        // been cleared. Set the entries for the remaining regions again.
+        self.initialize_sft_for(&inner);
+        drop(inner);
+        self.removed.lock().unwrap().push(space);
+
+        let in_region = |addr: Address| addr >= region_start && addr < region_start + region_extent;
+        let mut remembered_set = self.remembered_set.lock().unwrap();
+        remembered_set
+            .objects
+            .retain(|o| !in_region(o.to_address::<VM>()));
+        remembered_set
+            .edges
+            .retain(|e| !in_region(e.slot_address().unwrap()));
+    }
+
+    /// Do we use a remembered set instead of tracing the VM space in full heap GCs?
+    pub fn uses_remembered_set(&self) -> bool {
+        self.use_remembered_set
+    }
+
+    /// Is the object in the remembered set of the VM space? An object stays in the remembered
+    /// set as long as it refers to objects outside the VM space.
+    pub fn is_object_remembered(&self, object: ObjectReference) -> bool {
+        self.remembered_set
+            .lock()
+            .unwrap()
+            .objects
+            .contains(&object)
+    }
+
+    /// Record the objects in the VM space that are logged by the object barrier. They will be
+    /// scanned in every full heap GC.
+    pub(crate) fn remember_objects(&self, objects: &[ObjectReference]) {
+        if !self.use_remembered_set {
+            return;
+        }
+        let mut objects_in_space = objects.iter().filter(|o| self.in_space(**o)).peekable();
+        if objects_in_space.peek().is_some() {
+            self.remembered_set
+                .lock()
+                .unwrap()
+                .objects
+                .extend(objects_in_space);
+        }
+    }
+
+    /// Record the slots in the VM space that are logged by the field barrier. They will be
+    /// processed in every full heap GC.
+    pub(crate) fn remember_edges(&self, edges: &[VM::VMEdge]) {
+        if !self.use_remembered_set {
+            return;
+        }
+        let mut edges_in_space = edges
+            .iter()
+            .filter(|e| {
+                e.slot_address()
+                    .map_or(false, |slot| self.address_in_space(slot))
+            })
+            .peekable();
+        if edges_in_space.peek().is_some() {
+            self.remembered_set
+                .lock()
+                .unwrap()
+                .edges
+                .extend(edges_in_space);
         }
     }
 
-    pub fn lazy_initialize(&mut self, start: Address, size: usize) {
-        assert!(self.inner.is_none(), "VM space has been initialized");
-        self.inner = Some(Self::create_space(&mut self.args, Some((start, size))));
+    /// Record the slots in the memory slices that are in the VM space.
+    pub(crate) fn remember_slices(&self, slices: &[VM::VMMemorySlice]) {
+        if !self.use_remembered_set {
+            return;
+        }
+        for slice in slices.iter().filter(|s| self.address_in_space(s.start())) {
+            self.remember_edges(&slice.iter_edges().collect::<Vec<_>>());
+        }
+    }
 
-        self.common().initialize_sft(self.as_sft());
+    /// Get the start and the size of each region, after they are aligned to chunks.
+    pub fn regions(&self) -> Vec<(Address, usize)> {
+        self.inner
+            .read()
+            .unwrap()
+            .iter()
+            .map(|s| (s.common().start, s.common().extent))
+            .collect()
     }
 
     fn create_space(
@@ -178,7 +416,7 @@ impl<VM: VMBinding> VMSpace<VM> {
         );
 
         let space_args = args.get_space_args(
-            "vm_space",
+            VM_SPACE_NAME,
             false,
             VMRequest::fixed(vm_space_start_aligned, vm_space_bytes_aligned),
         );
@@ -191,26 +429,50 @@ impl<VM: VMBinding> VMSpace<VM> {
         space
     }
 
-    fn space_maybe(&self) -> Option<&ImmortalSpace<VM>> {
-        self.inner.as_ref()
+    fn initialize_sft_for(&self, regions: &[Box<ImmortalSpace<VM>>]) {
+        for space in regions.iter() {
+            space.common().initialize_sft(self.as_sft())
+        }
+    }
+
+    /// Extend the lifetime of a reference to a region from the lock guard to the VM space. This
+    /// is safe because the regions are boxed, and they are not dropped before the VM space.
+    fn region_ref(&self, space: &ImmortalSpace<VM>) -> &ImmortalSpace<VM> {
+        unsafe { &*(space as *const ImmortalSpace<VM>) }
     }
 
-    fn space(&self) -> &ImmortalSpace<VM> {
-        self.inner.as_ref().unwrap()
+    fn space_for_address(&self, addr: Address) -> Option<&ImmortalSpace<VM>> {
+        self.inner
+            .read()
+            .unwrap()
+            .iter()
+            .find(|s| s.address_in_space(addr))
+            .map(|s| self.region_ref(s))
     }
 
-    // fn space_mut(&mut self) -> &mut ImmortalSpace<VM> {
-    //     self.inner.as_mut().unwrap()
-    // }
+    fn space_for_object(&self, object: ObjectReference) -> &ImmortalSpace<VM> {
+        self.space_for_address(object.to_address::<VM>())
+            .unwrap_or_else(|| panic!("{} is not in any VM space region", object))
+    }
+
+    fn space(&self) -> &ImmortalSpace<VM> {
+        self.region_ref(
+            self.inner
+                .read()
+                .unwrap()
+                .first()
+                .expect("VM space has no region"),
+        )
+    }
 
     pub fn prepare(&mut self) {
-        if let Some(ref mut space) = &mut self.inner {
+        for space in self.inner.get_mut().unwrap().iter_mut() {
             space.prepare()
         }
     }
 
     pub fn release(&mut self) {
-        if let Some(ref mut space) = &mut self.inner {
+        for space in self.inner.get_mut().unwrap().iter_mut() {
             space.release()
         }
     }
@@ -220,10 +482,109 @@ impl<VM: VMBinding> VMSpace<VM> {
         queue: &mut Q,
         object: ObjectReference,
     ) -> ObjectReference {
-        if let Some(ref space) = &self.inner {
+        if self.use_remembered_set {
+            // We do not trace through the VM space. The references from the VM space to other
+            // spaces are in the remembered set.
+            debug_assert!(self.in_space(object));
+            object
+        } else if let Some(space) = self.space_for_address(object.to_address::<VM>()) {
             space.trace_object(queue, object)
         } else {
-            panic!("We haven't initialized vm space, but we tried to trace the object {} and thought it was in vm space?", object)
+            panic!(
+                "The object {} is not in any VM space region, but we thought it was in vm space?",
+                object
+            )
+        }
+    }
+}
+
+use crate::scheduler::gc_work::EdgeOf;
+use crate::scheduler::{GCWork, ProcessEdgesWork, WorkBucketStage};
+use crate::vm::Scanning;
+use crate::MMTK;
+use std::marker::PhantomData;
+
+/// Scan the objects and process the slots in the remembered set of the VM space. This is used in
+/// full heap GCs if the VM space uses a remembered set. The set is rebuilt in every full heap GC:
+/// only the objects and the slots that still refer to objects outside the VM space stay in the
+/// set. The slots are processed in separate work packets of at most `E::CAPACITY` slots each.
+pub(crate) struct ScanVMSpaceRememberedSet<E: ProcessEdgesWork> {
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ScanVMSpaceRememberedSet<E> {
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanVMSpaceRememberedSet<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        let vm_space = &mmtk.plan.base().vm_space;
+        // The barriers record the objects and the slots when they flush their buffers, which
+        // happens before the closure stage.
+        let (objects, edges) = {
+            let mut remembered_set = vm_space.remembered_set.lock().unwrap();
+            (
+                std::mem::take(&mut remembered_set.objects),
+                std::mem::take(&mut remembered_set.edges),
+            )
+        };
+        debug!(
+            "Scan VM space remembered set: {} objects, {} slots",
+            objects.len(),
+            edges.len()
+        );
+
+        let refers_outside = |edge: &EdgeOf<E>| {
+            let target = edge.load();
+            !target.is_null() && !vm_space.in_space(target)
+        };
+        let tls = worker.tls;
+        let mut kept_objects = vec![];
+        let mut objects_to_scan = vec![];
+        let mut edges_to_process = vec![];
+        for object in objects {
+            if <E::VM as VMBinding>::VMScanning::support_edge_enqueuing(tls, object) {
+                let len = edges_to_process.len();
+                <E::VM as VMBinding>::VMScanning::scan_object(
+                    tls,
+                    object,
+                    &mut |edge: EdgeOf<E>| {
+                        if refers_outside(&edge) {
+                            edges_to_process.push(edge);
+                        }
+                    },
+                );
+                if edges_to_process.len() > len {
+                    kept_objects.push(object);
+                }
+            } else {
+                // We cannot tell where the object refers to. Keep it, and let the binding scan it.
+                kept_objects.push(object);
+                objects_to_scan.push(object);
+            }
+        }
+        let kept_edges: Vec<EdgeOf<E>> = edges.into_iter().filter(refers_outside).collect();
+        edges_to_process.extend(kept_edges.iter().copied());
+        {
+            let mut remembered_set = vm_space.remembered_set.lock().unwrap();
+            remembered_set.objects.extend(kept_objects);
+            remembered_set.edges.extend(kept_edges);
+        }
+
+        let bucket = &mmtk.scheduler.work_buckets[WorkBucketStage::Closure];
+        for chunk in edges_to_process.chunks(E::CAPACITY) {
+            bucket.add(E::new(chunk.to_vec(), true, mmtk));
+        }
+        for chunk in objects_to_scan.chunks(E::CAPACITY) {
+            bucket.add(crate::scheduler::gc_work::ScanObjects::<E>::new(
+                chunk.to_vec(),
+                false,
+                true,
+            ));
         }
     }
 }
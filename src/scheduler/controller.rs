@@ -2,14 +2,19 @@
 //!
 //! MMTk has many GC threads.  There are many GC worker threads and one GC controller thread.
 //! The GC controller thread responds to GC requests and coordinates the workers to perform GC.
+//!
+//! If GC is synchronous, there is no GC thread.  The mutator that requests GC runs the controller,
+//! and executes all the work packets itself.
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::plan::gc_requester::GCRequester;
+use crate::plan::GcStatus;
 use crate::scheduler::gc_work::{EndOfGC, ScheduleCollection};
 use crate::scheduler::{GCWork, WorkBucketStage};
 use crate::util::VMWorkerThread;
-use crate::vm::VMBinding;
+use crate::vm::{ActivePlan, Collection, VMBinding};
 use crate::MMTK;
 
 use super::{GCWorkScheduler, GCWorker};
@@ -24,6 +29,9 @@ pub struct GCController<VM: VMBinding> {
     scheduler: Arc<GCWorkScheduler<VM>>,
     /// The `GCWorker` is used to execute packets. The controller is also a `GCWorker`.
     coordinator_worker: GCWorker<VM>,
+    /// If the current GC is still in progress at this time, the watchdog dumps the GC state. This
+    /// is `None` if the watchdog is disabled or has already fired in the current GC.
+    watchdog_deadline: Option<Instant>,
 }
 
 impl<VM: VMBinding> GCController<VM> {
@@ -38,6 +46,7 @@ impl<VM: VMBinding> GCController<VM> {
             requester,
             scheduler,
             coordinator_worker,
+            watchdog_deadline: None,
         })
     }
 
@@ -48,12 +57,102 @@ impl<VM: VMBinding> GCController<VM> {
 
         loop {
             debug!("[STWController: Waiting for request...]");
-            self.requester.wait_for_request();
+            if !self.requester.wait_for_request() {
+                debug!("[STWController: Exiting...]");
+                break;
+            }
             debug!("[STWController: Request recieved.]");
 
             self.do_gc_until_completion();
             debug!("[STWController: Worker threads complete!]");
         }
+
+        self.scheduler.on_gc_thread_exit();
+    }
+
+    /// Perform the pending GC on the current thread if GC is synchronous. Return false if there is
+    /// no pending GC.
+    pub(crate) fn run_pending_gc(&mut self, tls: VMWorkerThread) -> bool {
+        debug_assert!(self.scheduler.is_synchronous());
+        if !self.requester.take_request() {
+            return false;
+        }
+        debug!("[SynchronousController: Request recieved.]");
+        self.coordinator_worker.enter(tls, self.mmtk);
+        self.do_gc_until_completion();
+        self.coordinator_worker.leave();
+        debug!("[SynchronousController: GC complete.]");
+        true
+    }
+
+    /// Let the workers execute work packets, and wait until they all park. If GC is synchronous,
+    /// execute the work packets on the current thread instead.
+    fn resume_workers_and_wait(&mut self, all: bool) {
+        if self.scheduler.is_synchronous() {
+            while let Some(work) = self.coordinator_worker.poll_without_parking() {
+                self.coordinator_worker.execute(work, self.mmtk);
+            }
+        } else {
+            let mmtk = self.mmtk;
+            let tls = self.coordinator_worker.tls;
+            let mut timed_out = false;
+            self.scheduler
+                .worker_monitor
+                .resume_and_wait(all, self.watchdog_deadline, || {
+                    timed_out = true;
+                    Self::on_watchdog_timeout(mmtk, tls);
+                });
+            if timed_out {
+                // The watchdog only fires once in each GC.
+                self.watchdog_deadline = None;
+            }
+        }
+    }
+
+    /// Abort the current GC because a work packet has panicked. All the workers have parked, and
+    /// the remaining work packets are discarded. The GC is no longer in progress, and the workers
+    /// are asked to exit, so that the binding can still shut down MMTk. Report the panic to the
+    /// binding, and resume the panic on the current thread if the binding returns. The panic ends
+    /// the controller thread, so it is no longer counted as a live GC thread.
+    fn abort_gc(&mut self) -> ! {
+        let (message, payload) = self.scheduler.take_gc_panic().unwrap();
+        self.scheduler.deactivate_all();
+        self.mmtk.plan.base().set_gc_status(GcStatus::NotInGC);
+        error!("GC aborted. {}", message);
+        if self.scheduler.is_synchronous() {
+            self.coordinator_worker.leave();
+        } else {
+            self.scheduler.request_workers_exit(self.mmtk);
+            // The panic unwinds out of `run`, which would otherwise call this before returning.
+            self.scheduler.on_gc_thread_exit();
+        }
+        VM::VMCollection::gc_panicked(self.coordinator_worker.tls, &message);
+        std::panic::resume_unwind(payload)
+    }
+
+    /// Report the state of a GC that has not finished within the `gc_watchdog_timeout` option to the
+    /// binding, and abort if the `gc_watchdog_abort` option is set.
+    fn on_watchdog_timeout(mmtk: &'static MMTK<VM>, tls: VMWorkerThread) {
+        let base = mmtk.plan.base();
+        let abort = *mmtk.options.gc_watchdog_abort;
+        let report = format!(
+            "GC has not finished in {} ms. GC status: {:?}. Scanned stacks: {} of {} mutators (stacks prepared: {}).\n{}{}",
+            *mmtk.options.gc_watchdog_timeout,
+            *base.gc_status.lock().unwrap(),
+            base.scanned_stacks(),
+            <VM as VMBinding>::VMActivePlan::number_of_mutators(),
+            base.stacks_prepared(),
+            mmtk.scheduler.debug_dump().trim_end(),
+            if abort {
+                "\nAborting because of the GC watchdog."
+            } else {
+                ""
+            }
+        );
+        VM::VMCollection::gc_watchdog_timeout(tls, &report);
+        if abort {
+            std::process::abort();
+        }
     }
 
     /// Find more work for workers to do.  Return true if more work is available.
@@ -79,21 +178,38 @@ impl<VM: VMBinding> GCController<VM> {
     /// Coordinate workers to perform GC in response to a GC request.
     pub fn do_gc_until_completion(&mut self) {
         let gc_start = std::time::Instant::now();
+        let watchdog_timeout = *self.mmtk.options.gc_watchdog_timeout;
+        self.watchdog_deadline = if watchdog_timeout > 0 && !self.scheduler.is_synchronous() {
+            Some(gc_start + Duration::from_millis(watchdog_timeout as u64))
+        } else {
+            None
+        };
 
         debug_assert!(
             self.scheduler.worker_monitor.debug_is_sleeping(),
             "Workers are still doing work when GC started."
         );
 
+        // Spawn or retire workers if requested. All the workers are parked now.
+        if !self.scheduler.is_synchronous() {
+            self.scheduler
+                .update_workers(self.mmtk, self.coordinator_worker.tls.0);
+        }
+
         // Add a ScheduleCollection work packet.  It is the seed of other work packets.
         self.scheduler.work_buckets[WorkBucketStage::Unconstrained].add(ScheduleCollection);
 
         // Notify only one worker at this time because there is only one work packet,
         // namely `ScheduleCollection`.
-        self.scheduler.worker_monitor.resume_and_wait(false);
+        self.resume_workers_and_wait(false);
 
         // Gradually open more buckets as workers stop each time they drain all open bucket.
         loop {
+            // Do not schedule more work if any work packet has panicked.
+            if self.scheduler.has_gc_panicked() {
+                self.abort_gc();
+            }
+
             // Workers should only transition to the `Sleeping` state when all open buckets have
             // been drained.
             self.scheduler.assert_all_activated_buckets_are_empty();
@@ -107,7 +223,7 @@ impl<VM: VMBinding> GCController<VM> {
 
             // Notify all workers because there should be many work packets available in the newly
             // opened bucket(s).
-            self.scheduler.worker_monitor.resume_and_wait(true);
+            self.resume_workers_and_wait(true);
         }
 
         // All GC workers must have parked by now.
@@ -123,6 +239,10 @@ impl<VM: VMBinding> GCController<VM> {
 
         // Tell GC trigger that GC ended - this happens before EndOfGC where we resume mutators.
         self.mmtk.plan.base().gc_trigger.policy.on_gc_end(self.mmtk);
+        self.scheduler
+            .on_gc_finished(crate::util::conversions::pages_to_bytes(
+                self.mmtk.plan.get_used_pages(),
+            ));
 
         // Finalization: Resume mutators, reset gc states
         // Note: Resume-mutators must happen after all work buckets are closed.
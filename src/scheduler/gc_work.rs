@@ -3,8 +3,9 @@ use super::*;
 use crate::plan::GcStatus;
 use crate::plan::ObjectsClosure;
 use crate::plan::VectorObjectQueue;
+use crate::util::options::TraceOrder;
 use crate::util::*;
-use crate::vm::edge_shape::Edge;
+use crate::vm::edge_shape::{Edge, MemorySlice};
 use crate::vm::*;
 use crate::*;
 use std::marker::PhantomData;
@@ -45,6 +46,9 @@ impl<C: GCWorkContext> Prepare<C> {
 impl<C: GCWorkContext + 'static> GCWork<C::VM> for Prepare<C> {
     fn do_work(&mut self, worker: &mut GCWorker<C::VM>, mmtk: &'static MMTK<C::VM>) {
         trace!("Prepare Global");
+        // Verify the remembered set before any GC state is changed by the plan.
+        #[cfg(feature = "verify_remset")]
+        mmtk.remset_verifier.verify(worker.tls, mmtk);
         // We assume this is the only running work packet that accesses plan at the point of execution
         #[allow(clippy::cast_ref_to_mut)]
         let plan_mut: &mut C::PlanType = unsafe { &mut *(self.plan as *const _ as *mut _) };
@@ -54,10 +58,8 @@ impl<C: GCWorkContext + 'static> GCWork<C::VM> for Prepare<C> {
             mmtk.scheduler.work_buckets[WorkBucketStage::Prepare]
                 .add(PrepareMutator::<C::VM>::new(mutator));
         }
-        for w in &mmtk.scheduler.worker_group.workers_shared {
-            let result = w.designated_work.push(Box::new(PrepareCollector));
-            debug_assert!(result.is_ok());
-        }
+        mmtk.scheduler
+            .add_designated_work_to_active_workers(|| PrepareCollector);
     }
 }
 
@@ -125,10 +127,8 @@ impl<C: GCWorkContext + 'static> GCWork<C::VM> for Release<C> {
             mmtk.scheduler.work_buckets[WorkBucketStage::Release]
                 .add(ReleaseMutator::<C::VM>::new(mutator));
         }
-        for w in &mmtk.scheduler.worker_group.workers_shared {
-            let result = w.designated_work.push(Box::new(ReleaseCollector));
-            debug_assert!(result.is_ok());
-        }
+        mmtk.scheduler
+            .add_designated_work_to_active_workers(|| ReleaseCollector);
     }
 }
 
@@ -590,7 +590,19 @@ pub trait ProcessEdgesWork:
 
     /// Start the a scan work packet. If SCAN_OBJECTS_IMMEDIATELY, the work packet will be executed immediately, in this method.
     /// Otherwise, the work packet will be added the Closure work bucket and will be dispatched later by the scheduler.
+    /// It is added to the prioritized queue of the bucket if the `prioritize_scan_objects` option is set.
     fn start_or_dispatch_scan_work(&mut self, work_packet: impl GCWork<Self::VM>) {
+        let prioritized = *self.mmtk.options.prioritize_scan_objects;
+        self.start_or_dispatch_scan_work_with_priority(work_packet, prioritized);
+    }
+
+    /// Start a scan work packet like `start_or_dispatch_scan_work`. If the work packet is not executed
+    /// immediately, it is added to the prioritized queue of the Closure bucket if `prioritized` is true.
+    fn start_or_dispatch_scan_work_with_priority(
+        &mut self,
+        work_packet: impl GCWork<Self::VM>,
+        prioritized: bool,
+    ) {
         if Self::SCAN_OBJECTS_IMMEDIATELY {
             // We execute this `scan_objects_work` immediately.
             // This is expected to be a useful optimization because,
@@ -598,6 +610,9 @@ pub trait ProcessEdgesWork:
             // being dispatched (similar amount to `ProcessEdgesWork`).
             // Executing these work packets now can remarkably reduce the global synchronization time.
             self.worker().do_work(work_packet);
+        } else if prioritized {
+            self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure]
+                .add_prioritized(Box::new(work_packet));
         } else {
             self.mmtk.scheduler.work_buckets[WorkBucketStage::Closure].add(work_packet);
         }
@@ -614,9 +629,24 @@ pub trait ProcessEdgesWork:
     ) -> Self::ScanObjectsWorkType;
 
     /// Flush the nodes in ProcessEdgesBase, and create a ScanObjects work packet for it. If the node set is empty,
-    /// this method will simply return with no work packet created.
+    /// this method will simply return with no work packet created. If the `prioritize_large_objects` option is set,
+    /// the large objects are scanned first, in a separate prioritized work packet.
     fn flush(&mut self) {
-        let nodes = self.pop_nodes();
+        let mut nodes = self.pop_nodes();
+        if *self.mmtk.options.prioritize_large_objects && !nodes.is_empty() {
+            let max_small_object_bytes =
+                self.mmtk.plan.constraints().max_non_los_default_alloc_bytes;
+            let (large_objects, small_objects): (Vec<_>, Vec<_>) =
+                nodes.into_iter().partition(|object| {
+                    <Self::VM as VMBinding>::VMObjectModel::get_current_size(*object)
+                        > max_small_object_bytes
+                });
+            if !large_objects.is_empty() {
+                let work_packet = self.create_scan_work(large_objects, false);
+                self.start_or_dispatch_scan_work_with_priority(work_packet, true);
+            }
+            nodes = small_objects;
+        }
         if !nodes.is_empty() {
             self.start_or_dispatch_scan_work(self.create_scan_work(nodes, false));
         }
@@ -631,10 +661,119 @@ pub trait ProcessEdgesWork:
     }
 
     fn process_edges(&mut self) {
+        if *self.mmtk.options.trace_order == TraceOrder::DepthFirst {
+            self.process_edges_depth_first();
+            return;
+        }
+        let prefetch_distance = *self.mmtk.options.tracing_prefetch_distance;
+        if prefetch_distance > 0 {
+            self.process_edges_with_prefetch(prefetch_distance);
+            return;
+        }
         for i in 0..self.edges.len() {
             self.process_edge(self.edges[i])
         }
     }
+
+    /// Process the edges, and prefetch the objects pointed by the edges `distance` edges ahead of
+    /// the current edge.  The edges between the current edge and the prefetched edge form a FIFO of
+    /// objects that are being fetched into the cache while we trace the objects before them.  We
+    /// also prefetch the edges `2 * distance` edges ahead so that loading the object references
+    /// does not stall, either.
+    fn process_edges_with_prefetch(&mut self, distance: usize) {
+        let num_edges = self.edges.len();
+        let mut num_prefetched = 0;
+        for i in 0..num_edges.min(distance) {
+            num_prefetched += prefetch_object::<Self::VM>(self.edges[i].load()) as usize;
+        }
+        for i in 0..num_edges {
+            if let Some(edge) = self.edges.get(i + 2 * distance) {
+                edge.prefetch_load();
+            }
+            if let Some(edge) = self.edges.get(i + distance) {
+                num_prefetched += prefetch_object::<Self::VM>(edge.load()) as usize;
+            }
+            self.process_edge(self.edges[i]);
+        }
+        #[cfg(feature = "work_packet_stats")]
+        self.worker()
+            .shared
+            .borrow_stat_mut()
+            .on_prefetch(num_prefetched);
+        #[cfg(not(feature = "work_packet_stats"))]
+        let _ = num_prefetched;
+    }
+
+    /// Process the edges depth-first.  When an object is enqueued after processing an edge (e.g.
+    /// it has just been copied), we scan it immediately and process its edges, starting from its
+    /// first edge, before the remaining edges in this packet.  Copying plans thus copy the first
+    /// child of an object right after the object itself.
+    ///
+    /// To bound the work done by one packet, we scan at most `Self::CAPACITY` objects locally, and
+    /// keep at most `Self::CAPACITY` pending edges.  If scanning an object (e.g. a large array)
+    /// leaves more pending edges than that, the edges that would be processed last are moved to
+    /// new `ProcessEdgesWork` packets.  Other enqueued objects, including those which cannot be
+    /// scanned with `Scanning::scan_object`, are left in the node queue, and are scanned by
+    /// object-scanning work packets as usual.
+    fn process_edges_depth_first(&mut self) {
+        let tls = self.worker().tls;
+        // This work packet is only used for calling `post_scan_object`.
+        let scan_work = self.create_scan_work(vec![], false);
+        let mut stack: Vec<EdgeOf<Self>> = self.edges.iter().rev().copied().collect();
+        let mut deferred = vec![];
+        let mut num_scanned = 0;
+        while let Some(edge) = stack.pop() {
+            self.process_edge(edge);
+            while let Some(object) = self.nodes.pop() {
+                if num_scanned < Self::CAPACITY
+                    && !<Self::VM as VMBinding>::VMScanning::support_slice_enqueuing(tls, object)
+                    && <Self::VM as VMBinding>::VMScanning::support_edge_enqueuing(tls, object)
+                {
+                    let first = stack.len();
+                    <Self::VM as VMBinding>::VMScanning::scan_object(tls, object, &mut |edge| {
+                        stack.push(edge)
+                    });
+                    scan_work.post_scan_object(object);
+                    num_scanned += 1;
+                    // Pop the edges of this object in the order they are visited.
+                    stack[first..].reverse();
+                    if stack.len() > Self::CAPACITY {
+                        let excess = stack.len() - Self::CAPACITY;
+                        let spilled: Vec<EdgeOf<Self>> = stack.drain(..excess).rev().collect();
+                        for edges in spilled.chunks(Self::CAPACITY) {
+                            let work_packet = Self::new(edges.to_vec(), false, self.mmtk);
+                            self.worker()
+                                .add_work(WorkBucketStage::Closure, work_packet);
+                        }
+                    }
+                } else {
+                    deferred.push(object);
+                }
+            }
+        }
+        for object in deferred {
+            self.nodes.push(object);
+        }
+    }
+}
+
+/// Prefetch the header, the mark bit and the forwarding bits of an object that will be traced soon.
+/// Return false if the object is null and nothing is prefetched.
+#[inline(always)]
+fn prefetch_object<VM: VMBinding>(object: ObjectReference) -> bool {
+    if object.is_null() {
+        return false;
+    }
+    VM::VMObjectModel::ref_to_header(object).prefetch_load();
+    for spec in [
+        *VM::VMObjectModel::LOCAL_MARK_BIT_SPEC,
+        *VM::VMObjectModel::LOCAL_FORWARDING_BITS_SPEC,
+    ] {
+        if spec.is_on_side() {
+            spec.prefetch::<VM>(object);
+        }
+    }
+    true
 }
 
 impl<E: ProcessEdgesWork> GCWork<E::VM> for E {
@@ -704,6 +843,7 @@ impl<E: ProcessEdgesWork> Clone for ProcessEdgesWorkRootsWorkFactory<E> {
 
 impl<E: ProcessEdgesWork> RootsWorkFactory<EdgeOf<E>> for ProcessEdgesWorkRootsWorkFactory<E> {
     fn create_process_edge_roots_work(&mut self, edges: Vec<EdgeOf<E>>) {
+        self.mmtk.scheduler.on_roots_reported(edges.len());
         crate::memory_manager::add_work_packet(
             self.mmtk,
             WorkBucketStage::Closure,
@@ -712,6 +852,7 @@ impl<E: ProcessEdgesWork> RootsWorkFactory<EdgeOf<E>> for ProcessEdgesWorkRootsW
     }
 
     fn create_process_node_roots_work(&mut self, nodes: Vec<ObjectReference>) {
+        self.mmtk.scheduler.on_roots_reported(nodes.len());
         // We want to use E::create_scan_work.
         let process_edges_work = E::new(vec![], true, self.mmtk);
         let work = process_edges_work.create_scan_work(nodes, true);
@@ -807,10 +948,21 @@ pub trait ScanObjectsWork<VM: VMBinding>: GCWork<VM> + Sized {
 
         // Then scan those objects for edges.
         let mut scan_later = vec![];
+        let mut slices = vec![];
         {
             let mut closure = ObjectsClosure::<Self::E>::new(worker);
             for object in objects_to_scan.iter().copied() {
-                if <VM as VMBinding>::VMScanning::support_edge_enqueuing(tls, object) {
+                if <VM as VMBinding>::VMScanning::support_slice_enqueuing(tls, object) {
+                    trace!("Scan object (slice) {}", object);
+                    // If an object is scanned as slices (e.g. a large array), we collect the
+                    // slices and let other work packets enumerate their edges in parallel.
+                    <VM as VMBinding>::VMScanning::scan_object_as_slices(
+                        tls,
+                        object,
+                        &mut |slice| slices.push(slice),
+                    );
+                    self.post_scan_object(object);
+                } else if <VM as VMBinding>::VMScanning::support_edge_enqueuing(tls, object) {
                     trace!("Scan object (edge) {}", object);
                     // If an object supports edge-enqueuing, we enqueue its edges.
                     <VM as VMBinding>::VMScanning::scan_object(tls, object, &mut closure);
@@ -827,6 +979,18 @@ pub trait ScanObjectsWork<VM: VMBinding>: GCWork<VM> + Sized {
             }
         }
 
+        // Create one work packet for each slice so that different workers can scan different
+        // parts of large objects.
+        if !slices.is_empty() {
+            let packets = slices
+                .into_iter()
+                .map(|slice| {
+                    Box::new(ScanMemorySlice::<Self::E>::new(slice)) as Box<dyn GCWork<VM>>
+                })
+                .collect();
+            mmtk.scheduler.work_buckets[WorkBucketStage::Closure].bulk_add(packets);
+        }
+
         // If any object does not support edge-enqueuing, we process them now.
         if !scan_later.is_empty() {
             let object_tracer_context = ProcessEdgesWorkTracerContext::<Self::E> {
@@ -900,6 +1064,36 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanObjects<E> {
     }
 }
 
+/// Enumerate the edges in a memory slice, and create `ProcessEdgesWork` packets for them.
+///
+/// This is used for objects scanned with `Scanning::scan_object_as_slices`.  Each packet created by
+/// this work packet contains at most `E::CAPACITY` edges, so the edges of a large slice can be
+/// processed by multiple workers.
+pub struct ScanMemorySlice<E: ProcessEdgesWork> {
+    slice: <E::VM as VMBinding>::VMMemorySlice,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ScanMemorySlice<E> {
+    pub fn new(slice: <E::VM as VMBinding>::VMMemorySlice) -> Self {
+        Self {
+            slice,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanMemorySlice<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, _mmtk: &'static MMTK<E::VM>) {
+        trace!("ScanMemorySlice {:?}", self.slice);
+        let mut closure = ObjectsClosure::<E>::new(worker);
+        for edge in self.slice.iter_edges() {
+            closure.visit_edge(edge);
+        }
+        trace!("ScanMemorySlice End");
+    }
+}
+
 use crate::mmtk::MMTK;
 use crate::plan::Plan;
 use crate::plan::PlanTraceObject;
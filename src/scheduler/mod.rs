@@ -23,6 +23,9 @@ pub use worker::GCWorker;
 mod controller;
 pub use controller::GCController;
 
+mod zeroing;
+pub use zeroing::ZeroingThread;
+
 pub(crate) mod gc_work;
 pub use gc_work::ProcessEdgesWork;
 // TODO: We shouldn't need to expose ScanStackRoot. However, OpenJDK uses it.
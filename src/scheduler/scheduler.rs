@@ -1,17 +1,23 @@
 use super::stat::SchedulerStat;
 use super::work_bucket::*;
-use super::worker::{GCWorker, GCWorkerShared, ThreadId, WorkerGroup, WorkerMonitor};
+use super::worker::{
+    new_local_work_queue, GCWorker, GCWorkerShared, ThreadId, WorkerGroup, WorkerMonitor,
+};
 use super::*;
 use crate::mmtk::MMTK;
+use crate::util::constants::LOG_BYTES_IN_MBYTE;
 use crate::util::opaque_pointer::*;
-use crate::util::options::AffinityKind;
+use crate::util::options::{AffinityKind, StealPolicy, WorkQueueOrder};
 use crate::util::rust_util::array_from_fn;
 use crate::vm::Collection;
 use crate::vm::{GCThreadContext, VMBinding};
-use crossbeam::deque::{self, Steal};
+use crossbeam::deque::Steal;
 use enum_map::{Enum, EnumMap};
+use std::any::Any;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 
 pub struct GCWorkScheduler<VM: VMBinding> {
     /// Work buckets
@@ -24,6 +30,38 @@ pub struct GCWorkScheduler<VM: VMBinding> {
     pub(crate) worker_monitor: Arc<WorkerMonitor>,
     /// How to assign the affinity of each GC thread. Specified by the user.
     affinity: AffinityKind,
+    /// The order in which GC workers execute the packets in their local work queues.
+    work_queue_order: WorkQueueOrder,
+    /// How GC workers steal packets from other workers.
+    steal_policy: StealPolicy,
+    /// The number of GC threads (including the controller) that are spawned and have not exited.
+    live_gc_threads: Mutex<usize>,
+    /// Notified when a GC thread has exited.
+    gc_thread_exited: Condvar,
+    /// The number of workers requested by the binding. The coordinator spawns or retires workers to
+    /// match it at the start of the next GC.
+    requested_workers: AtomicUsize,
+    /// If true, the coordinator decides how many workers execute work packets in each GC.
+    adaptive_workers: bool,
+    /// The used memory in bytes at the end of the last GC. It estimates the amount of work in the next GC.
+    /// It is `usize::MAX` before the first GC.
+    used_bytes_after_last_gc: AtomicUsize,
+    /// The number of root slots and root objects reported so far in the current GC.
+    roots_in_current_gc: AtomicUsize,
+    /// The number of root slots and root objects reported in the last GC. It estimates the amount of
+    /// root processing in the next GC.
+    roots_in_last_gc: AtomicUsize,
+    /// If true, there are no GC threads. GC is performed by the mutator that requests it.
+    synchronous: bool,
+    /// The GC controller that runs on the mutator that requests GC. It is only used if GC is synchronous.
+    synchronous_controller: Mutex<Option<Box<GCController<VM>>>>,
+    /// True if a work packet has panicked in the current GC. Workers discard the remaining work
+    /// packets instead of executing them.
+    gc_panicked: AtomicBool,
+    /// The message and the payload of the first panic in the current GC.
+    gc_panic: Mutex<Option<(String, Box<dyn Any + Send>)>>,
+    /// True if a GC has been aborted because a work packet panicked.
+    gc_aborted: AtomicBool,
 }
 
 // FIXME: GCWorkScheduler should be naturally Sync, but we cannot remove this `impl` yet.
@@ -32,9 +70,25 @@ pub struct GCWorkScheduler<VM: VMBinding> {
 unsafe impl<VM: VMBinding> Sync for GCWorkScheduler<VM> {}
 
 impl<VM: VMBinding> GCWorkScheduler<VM> {
-    pub fn new(num_workers: usize, affinity: AffinityKind) -> Arc<Self> {
+    /// The amount of live memory in bytes that one worker is expected to process in a GC if the number of
+    /// workers is adaptive.
+    const ADAPTIVE_BYTES_PER_WORKER: usize = 8 << LOG_BYTES_IN_MBYTE;
+    /// The number of roots that one worker is expected to process in a GC if the number of workers is adaptive.
+    const ADAPTIVE_ROOTS_PER_WORKER: usize = 4096;
+
+    pub fn new(
+        num_workers: usize,
+        max_workers: usize,
+        adaptive_workers: bool,
+        synchronous: bool,
+        affinity: AffinityKind,
+        work_queue_order: WorkQueueOrder,
+        steal_policy: StealPolicy,
+    ) -> Arc<Self> {
+        debug_assert!(num_workers <= max_workers);
+        debug_assert!(!synchronous || num_workers == 0);
         let worker_monitor: Arc<WorkerMonitor> = Arc::new(WorkerMonitor::new(num_workers));
-        let worker_group = WorkerGroup::new(num_workers);
+        let worker_group = WorkerGroup::new(max_workers, work_queue_order);
 
         // Create work buckets for workers.
         // TODO: Replace `array_from_fn` with `std::array::from_fn` after bumping MSRV.
@@ -73,15 +127,163 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             coordinator_worker_shared,
             worker_monitor,
             affinity,
+            work_queue_order,
+            steal_policy,
+            live_gc_threads: Mutex::new(0),
+            gc_thread_exited: Condvar::new(),
+            requested_workers: AtomicUsize::new(num_workers),
+            adaptive_workers,
+            used_bytes_after_last_gc: AtomicUsize::new(usize::MAX),
+            roots_in_current_gc: AtomicUsize::new(0),
+            roots_in_last_gc: AtomicUsize::new(0),
+            synchronous,
+            synchronous_controller: Mutex::new(None),
+            gc_panicked: AtomicBool::new(false),
+            gc_panic: Mutex::new(None),
+            gc_aborted: AtomicBool::new(false),
         })
     }
 
+    /// Is GC performed by the mutator that requests it, without any GC thread?
+    pub fn is_synchronous(&self) -> bool {
+        self.synchronous
+    }
+
+    /// Get the current number of workers.
     pub fn num_workers(&self) -> usize {
-        self.worker_group.as_ref().worker_count()
+        self.worker_monitor.worker_count()
+    }
+
+    /// Get the number of workers that execute work packets in the current GC.
+    pub fn active_workers(&self) -> usize {
+        self.worker_monitor.active_workers()
+    }
+
+    /// Get the maximum number of workers. Worker ordinals are always less than this.
+    pub fn max_workers(&self) -> usize {
+        self.worker_group.max_workers()
+    }
+
+    /// Request the number of workers to be changed. The coordinator spawns or retires workers at the
+    /// start of the next GC.
+    pub(crate) fn request_num_workers(&self, num_workers: usize) {
+        assert!(
+            !self.synchronous,
+            "The number of GC workers cannot be changed if GC is synchronous"
+        );
+        assert!(
+            num_workers > 0 && num_workers <= self.max_workers(),
+            "The number of GC workers must be between 1 and {}",
+            self.max_workers()
+        );
+        self.requested_workers.store(num_workers, Ordering::SeqCst);
+    }
+
+    /// Spawn or retire workers as requested, and decide how many workers execute work packets in the
+    /// coming GC. This is called by the coordinator at the start of each GC, when all the workers are parked.
+    pub(crate) fn update_workers(self: &Arc<Self>, mmtk: &'static MMTK<VM>, tls: VMThread) {
+        let old_workers = self.num_workers();
+        let new_workers = self.requested_workers.load(Ordering::SeqCst);
+        let active_workers = if self.adaptive_workers {
+            self.adaptive_active_workers(new_workers)
+        } else {
+            new_workers
+        };
+
+        if new_workers > old_workers {
+            debug!("Spawn workers {} to {}", old_workers, new_workers - 1);
+            *self.live_gc_threads.lock().unwrap() += new_workers - old_workers;
+            self.worker_monitor
+                .set_worker_count(new_workers, active_workers);
+            self.worker_group.spawn(mmtk, tls, old_workers..new_workers);
+        } else if new_workers < old_workers {
+            debug!("Retire workers {} to {}", new_workers, old_workers - 1);
+            let live_gc_threads = self.live_gc_threads.lock().unwrap();
+            let remaining = *live_gc_threads - (old_workers - new_workers);
+            self.worker_monitor
+                .set_worker_count(new_workers, active_workers);
+            // Wait for the retired workers to exit so that their local work queues are returned.
+            let _live_gc_threads = self
+                .gc_thread_exited
+                .wait_while(live_gc_threads, |n| *n > remaining)
+                .unwrap();
+        } else {
+            self.worker_monitor
+                .set_worker_count(new_workers, active_workers);
+        }
+        trace!(
+            "GC workers: {} spawned, {} active",
+            new_workers,
+            active_workers
+        );
+    }
+
+    /// Decide the number of active workers from the used memory at the end of the last GC, and the
+    /// number of roots in the last GC.
+    fn adaptive_active_workers(&self, num_workers: usize) -> usize {
+        let used_bytes = self.used_bytes_after_last_gc.load(Ordering::Relaxed);
+        if used_bytes == usize::MAX {
+            // This is the first GC. We have no idea how much work there is.
+            return num_workers;
+        }
+        let roots = self.roots_in_last_gc.load(Ordering::Relaxed);
+        (used_bytes / Self::ADAPTIVE_BYTES_PER_WORKER + roots / Self::ADAPTIVE_ROOTS_PER_WORKER + 1)
+            .min(num_workers)
+    }
+
+    /// Count the roots reported by the binding in the current GC.
+    pub(crate) fn on_roots_reported(&self, num_roots: usize) {
+        self.roots_in_current_gc
+            .fetch_add(num_roots, Ordering::Relaxed);
+    }
+
+    /// Record the used memory and the number of roots at the end of a GC.
+    pub(crate) fn on_gc_finished(&self, used_bytes: usize) {
+        self.used_bytes_after_last_gc
+            .store(used_bytes, Ordering::Relaxed);
+        self.roots_in_last_gc.store(
+            self.roots_in_current_gc.swap(0, Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
     }
 
-    /// Create GC threads, including the controller thread and all workers.
+    /// Create GC threads, including the controller thread and all workers. If GC is synchronous, no
+    /// thread is created, and the controller is kept for the mutators to perform GC.
     pub fn spawn_gc_threads(self: &Arc<Self>, mmtk: &'static MMTK<VM>, tls: VMThread) {
+        if self.synchronous {
+            // The embedded worker of the controller uses the slot of the first worker.
+            let coordinator_worker = GCWorker::new(
+                mmtk,
+                0,
+                self.clone(),
+                true,
+                self.coordinator_worker_shared.clone(),
+                new_local_work_queue(self.work_queue_order),
+            );
+            let gc_controller = GCController::new(
+                mmtk,
+                mmtk.plan.base().gc_requester.clone(),
+                self.clone(),
+                coordinator_worker,
+            );
+            *self.synchronous_controller.lock().unwrap() = Some(gc_controller);
+            return;
+        }
+
+        // The spaces that zero their released pages concurrently. Each of them needs a zeroing thread.
+        let zeroing_spaces: Vec<_> = mmtk
+            .plan
+            .get_spaces()
+            .into_iter()
+            .filter(|space| space.zeroes_concurrently())
+            .collect();
+
+        {
+            let mut live_gc_threads = self.live_gc_threads.lock().unwrap();
+            assert_eq!(*live_gc_threads, 0, "GC threads are already running.");
+            *live_gc_threads = 1 + self.num_workers() + zeroing_spaces.len();
+        }
+
         // Spawn the controller thread.
         let coordinator_worker = GCWorker::new(
             mmtk,
@@ -89,7 +291,7 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             self.clone(),
             true,
             self.coordinator_worker_shared.clone(),
-            deque::Worker::new_fifo(),
+            new_local_work_queue(self.work_queue_order),
         );
         let gc_controller = GCController::new(
             mmtk,
@@ -99,7 +301,159 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         );
         VM::VMCollection::spawn_gc_thread(tls, GCThreadContext::<VM>::Controller(gc_controller));
 
-        self.worker_group.spawn(mmtk, tls)
+        // Spawn the zeroing threads.
+        for space in zeroing_spaces {
+            let zeroing_thread = ZeroingThread::new(space, self.clone());
+            VM::VMCollection::spawn_gc_thread(tls, GCThreadContext::<VM>::Zeroing(zeroing_thread));
+        }
+
+        self.worker_group.spawn(mmtk, tls, 0..self.num_workers())
+    }
+
+    /// Ask all GC threads to exit, and wait until they have exited.  This must not be called
+    /// when GC is in progress.
+    pub(crate) fn stop_gc_threads(&self, mmtk: &'static MMTK<VM>) {
+        let gc_requester = &mmtk.plan.base().gc_requester;
+        gc_requester.request_exit();
+        self.request_workers_exit(mmtk);
+
+        {
+            let live_gc_threads = self.live_gc_threads.lock().unwrap();
+            let _live_gc_threads = self
+                .gc_thread_exited
+                .wait_while(live_gc_threads, |n| *n > 0)
+                .unwrap();
+        }
+
+        // All GC threads have exited.  Allow GC threads spawned later to run.
+        gc_requester.clear_exit_request();
+        self.worker_monitor.clear_exit_request();
+        for space in mmtk.plan.get_spaces() {
+            if space.zeroes_concurrently() {
+                space.get_page_resource().request_zeroing_exit(false);
+            }
+        }
+    }
+
+    /// Ask the GC workers and the zeroing threads to exit. They exit once they are parked.
+    pub(crate) fn request_workers_exit(&self, mmtk: &'static MMTK<VM>) {
+        self.worker_monitor.request_exit();
+        for space in mmtk.plan.get_spaces() {
+            if space.zeroes_concurrently() {
+                space.get_page_resource().request_zeroing_exit(true);
+            }
+        }
+    }
+
+    /// Perform the pending GC on the current mutator thread if GC is synchronous. Return false if
+    /// there is no pending GC, e.g. it has been performed by another mutator.
+    pub(crate) fn run_synchronous_gc(&self, tls: VMMutatorThread) -> bool {
+        assert!(self.synchronous, "GC is not synchronous");
+        assert!(
+            !self.has_gc_aborted(),
+            "No GC can be performed after a GC is aborted because a work packet panicked"
+        );
+        let result = {
+            let mut controller = self.synchronous_controller.lock().unwrap();
+            let controller = controller.as_mut().expect(
+                "MMTk collection has not been initialized (was initialize_collection() called before?)",
+            );
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                controller.run_pending_gc(VMWorkerThread(tls.0))
+            }))
+        };
+        // Resume the panic of an aborted GC after the controller is unlocked, so that the lock is
+        // not poisoned.
+        result.unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    }
+
+    /// Add a designated work packet to each worker that executes work packets in the current GC.
+    /// If GC is synchronous, the packet is added to the controller instead.
+    pub(crate) fn add_designated_work_to_active_workers<W: GCWork<VM>>(
+        &self,
+        create: impl Fn() -> W,
+    ) {
+        let coordinator = [self.coordinator_worker_shared.clone()];
+        let workers = if self.synchronous {
+            &coordinator[..]
+        } else {
+            &self.worker_group.workers_shared[..self.active_workers()]
+        };
+        for w in workers {
+            let result = w.designated_work.push(Box::new(create()));
+            debug_assert!(result.is_ok());
+        }
+    }
+
+    /// Has any work packet panicked in the current GC?
+    pub(crate) fn has_gc_panicked(&self) -> bool {
+        self.gc_panicked.load(Ordering::SeqCst)
+    }
+
+    /// Has a GC been aborted because a work packet panicked? No more GC can be performed.
+    pub(crate) fn has_gc_aborted(&self) -> bool {
+        self.gc_aborted.load(Ordering::SeqCst)
+    }
+
+    /// Called by a GC worker when a work packet panics. Only the first panic in a GC is recorded.
+    pub(crate) fn on_work_panicked(
+        &self,
+        worker: &GCWorker<VM>,
+        packet: &str,
+        payload: Box<dyn Any + Send>,
+    ) {
+        let mut gc_panic = self.gc_panic.lock().unwrap();
+        if gc_panic.is_none() {
+            let reason = if let Some(s) = payload.downcast_ref::<&str>() {
+                s
+            } else if let Some(s) = payload.downcast_ref::<String>() {
+                s.as_str()
+            } else {
+                "Box<dyn Any>"
+            };
+            let message = if worker.is_coordinator() {
+                format!(
+                    "Work packet {} panicked on the GC controller: {}",
+                    packet, reason
+                )
+            } else {
+                format!(
+                    "Work packet {} panicked on GC worker {}: {}",
+                    packet, worker.ordinal, reason
+                )
+            };
+            *gc_panic = Some((message, payload));
+        }
+        self.gc_panicked.store(true, Ordering::SeqCst);
+    }
+
+    /// Take the message and the payload of the panic recorded in the current GC, and drop all the
+    /// remaining work packets. This must be called by the coordinator when all the workers have
+    /// parked.
+    pub(crate) fn take_gc_panic(&self) -> Option<(String, Box<dyn Any + Send>)> {
+        for bucket in self.work_buckets.values() {
+            bucket.discard_all();
+        }
+        for worker in self.worker_group.workers_shared.iter() {
+            while worker.designated_work.pop().is_some() {}
+        }
+        while self
+            .coordinator_worker_shared
+            .designated_work
+            .pop()
+            .is_some()
+        {}
+        self.gc_panicked.store(false, Ordering::SeqCst);
+        self.gc_aborted.store(true, Ordering::SeqCst);
+        self.gc_panic.lock().unwrap().take()
+    }
+
+    /// Called by a GC thread when it is about to exit.
+    pub(crate) fn on_gc_thread_exit(&self) {
+        let mut live_gc_threads = self.live_gc_threads.lock().unwrap();
+        debug_assert!(*live_gc_threads > 0);
+        *live_gc_threads -= 1;
+        self.gc_thread_exited.notify_all();
     }
 
     /// Resolve the affinity of a thread.
@@ -124,6 +478,18 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         // Release global/collectors/mutators
         self.work_buckets[WorkBucketStage::Release].add(Release::<C>::new(plan));
 
+        // Scan the remembered set of the VM space if we do not trace the VM space in this GC
+        #[cfg(feature = "vm_space")]
+        if plan.base().vm_space.uses_remembered_set()
+            && plan
+                .generational()
+                .map_or(false, |gen| !gen.is_current_gc_nursery())
+        {
+            use crate::policy::vmspace::ScanVMSpaceRememberedSet;
+            self.work_buckets[WorkBucketStage::Closure]
+                .add(ScanVMSpaceRememberedSet::<C::ProcessEdgesWorkType>::new());
+        }
+
         // Analysis GC work
         #[cfg(feature = "analysis")]
         {
@@ -289,6 +655,43 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         }
     }
 
+    /// Describe the state of the work buckets and the GC workers. This is only used for debugging.
+    pub(crate) fn debug_dump(&self) -> String {
+        let mut dump = String::new();
+        for (id, bucket) in self.work_buckets.iter() {
+            writeln!(dump, "  Bucket {:?}: {}", id, bucket.debug_dump()).unwrap();
+        }
+        writeln!(
+            dump,
+            "  Parked workers: {} of {} ({} active)",
+            self.worker_monitor.debug_parked_workers(),
+            self.num_workers(),
+            self.active_workers()
+        )
+        .unwrap();
+        for (ordinal, shared) in self
+            .worker_group
+            .workers_shared
+            .iter()
+            .enumerate()
+            .take(self.num_workers())
+        {
+            let state = match shared.current_work() {
+                Some(name) => format!("running {}", name),
+                None => "parked or polling".to_string(),
+            };
+            writeln!(
+                dump,
+                "  Worker {}: {}, designated packets: {}",
+                ordinal,
+                state,
+                shared.designated_work.len()
+            )
+            .unwrap();
+        }
+        dump
+    }
+
     /// Check if all the work buckets are empty
     pub(crate) fn assert_all_activated_buckets_are_empty(&self) {
         let mut error_example = None;
@@ -323,16 +726,34 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
             }
         }
         // Try steal some packets from any worker
-        for (id, worker_shared) in self.worker_group.workers_shared.iter().enumerate() {
+        let workers_shared = &self.worker_group.workers_shared;
+        let num_victims = workers_shared.len();
+        let first_victim = match self.steal_policy {
+            StealPolicy::Sequential => 0,
+            StealPolicy::Neighbor => worker.ordinal.wrapping_add(1),
+            StealPolicy::Random => worker.next_steal_random(),
+            StealPolicy::Disabled => return Self::empty_or_retry(should_retry),
+        };
+        for i in 0..num_victims {
+            let id = first_victim.wrapping_add(i) % num_victims;
             if id == worker.ordinal {
                 continue;
             }
-            match worker_shared.stealer.as_ref().unwrap().steal() {
-                Steal::Success(w) => return Steal::Success(w),
+            match workers_shared[id].stealer.as_ref().unwrap().steal() {
+                Steal::Success(w) => {
+                    #[cfg(feature = "work_packet_stats")]
+                    worker.shared.borrow_stat_mut().on_steal();
+                    return Steal::Success(w);
+                }
                 Steal::Retry => should_retry = true,
                 _ => {}
             }
         }
+        Self::empty_or_retry(should_retry)
+    }
+
+    /// Return `Steal::Retry` if we should retry polling, or `Steal::Empty` otherwise.
+    fn empty_or_retry(should_retry: bool) -> Steal<Box<dyn GCWork<VM>>> {
         if should_retry {
             Steal::Retry
         } else {
@@ -341,7 +762,10 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
     }
 
     /// Get a schedulable work packet.
-    fn poll_schedulable_work(&self, worker: &GCWorker<VM>) -> Option<Box<dyn GCWork<VM>>> {
+    pub(crate) fn poll_schedulable_work(
+        &self,
+        worker: &GCWorker<VM>,
+    ) -> Option<Box<dyn GCWork<VM>>> {
         // Loop until we successfully get a packet.
         loop {
             match self.poll_schedulable_work_once(worker) {
@@ -361,19 +785,22 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
 
     /// Called by workers to get a schedulable work packet.
     /// Park the worker if there're no available packets.
-    pub fn poll(&self, worker: &GCWorker<VM>) -> Box<dyn GCWork<VM>> {
+    /// Return `None` if the worker is asked to exit while parked.
+    pub fn poll(&self, worker: &GCWorker<VM>) -> Option<Box<dyn GCWork<VM>>> {
         self.poll_schedulable_work(worker)
-            .unwrap_or_else(|| self.poll_slow(worker))
+            .or_else(|| self.poll_slow(worker))
     }
 
-    fn poll_slow(&self, worker: &GCWorker<VM>) -> Box<dyn GCWork<VM>> {
+    fn poll_slow(&self, worker: &GCWorker<VM>) -> Option<Box<dyn GCWork<VM>>> {
         loop {
             // Retry polling
             if let Some(work) = self.poll_schedulable_work(worker) {
-                return work;
+                return Some(work);
             }
 
-            self.worker_monitor.park_and_wait(worker);
+            if self.worker_monitor.park_and_wait(worker) {
+                return None;
+            }
         }
     }
 
@@ -394,6 +821,10 @@ impl<VM: VMBinding> GCWorkScheduler<VM> {
         }
         let coordinator_worker_stat = self.coordinator_worker_shared.borrow_stat();
         summary.merge(&coordinator_worker_stat);
+        #[cfg(feature = "work_packet_stats")]
+        for worker in self.worker_group.workers_shared[..self.num_workers()].iter() {
+            summary.merge_worker(&worker.borrow_stat());
+        }
         summary.harness_stat()
     }
 
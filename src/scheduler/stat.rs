@@ -8,6 +8,8 @@ use std::any::TypeId;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "work_packet_stats")]
+use std::time::Duration;
 
 /// Merge and print the work-packet level statistics from all worker threads
 #[derive(Default)]
@@ -28,6 +30,14 @@ pub struct SchedulerStat {
     /// We assume different threads have the same set of work counters
     /// (in the same order).
     work_counters: HashMap<TypeId, Vec<Vec<Box<dyn WorkCounter>>>>,
+    /// The number of work packets stolen by each worker, and the time each worker was parked while
+    /// other workers were executing work packets. Indexed by the worker ordinal.
+    #[cfg(feature = "work_packet_stats")]
+    worker_stats: Vec<(usize, Duration)>,
+    /// The number of objects prefetched by all workers when processing edges. This only counts the
+    /// prefetches issued. It does not measure the cache misses they avoid.
+    #[cfg(feature = "work_packet_stats")]
+    prefetches: usize,
 }
 
 impl SchedulerStat {
@@ -116,8 +126,43 @@ impl SchedulerStat {
             stat.insert(pkt, format!("{:.3}", time / 1e6));
         }
 
+        // Steals and idle time of each worker
+        #[cfg(feature = "work_packet_stats")]
+        {
+            let mut total_steals = 0;
+            let mut total_idle_time = Duration::ZERO;
+            for (ordinal, (steals, idle_time)) in self.worker_stats.iter().enumerate() {
+                total_steals += steals;
+                total_idle_time += *idle_time;
+                stat.insert(format!("worker.{}.steals", ordinal), format!("{}", steals));
+                stat.insert(
+                    format!("worker.{}.idle.time", ordinal),
+                    format!("{:.3}", idle_time.as_secs_f64() * 1e3),
+                );
+            }
+            stat.insert(
+                "total-worker.steals".to_owned(),
+                format!("{}", total_steals),
+            );
+            stat.insert(
+                "total-worker.idle.time".to_owned(),
+                format!("{:.3}", total_idle_time.as_secs_f64() * 1e3),
+            );
+            stat.insert(
+                "total-worker.prefetches".to_owned(),
+                format!("{}", self.prefetches),
+            );
+        }
+
         stat
     }
+
+    /// Merge the steals and the idle time of a worker. This should be called for the workers in
+    /// the order of their ordinals.
+    #[cfg(feature = "work_packet_stats")]
+    pub fn merge_worker<C>(&mut self, stat: &WorkerLocalStat<C>) {
+        self.worker_stats.push((stat.steals, stat.idle_time));
+    }
     /// Merge work counters from different worker threads
     pub fn merge<C>(&mut self, stat: &WorkerLocalStat<C>) {
         // Merge work packet type ID to work packet name mapping
@@ -151,6 +196,11 @@ impl SchedulerStat {
                 v.push(c.clone());
             }
         }
+        // Merge the number of prefetched objects
+        #[cfg(feature = "work_packet_stats")]
+        {
+            self.prefetches += stat.prefetches;
+        }
     }
 }
 
@@ -188,6 +238,15 @@ pub struct WorkerLocalStat<C> {
     work_id_name_map: HashMap<TypeId, &'static str>,
     work_counts: HashMap<TypeId, usize>,
     work_counters: HashMap<TypeId, Vec<Box<dyn WorkCounter>>>,
+    /// The number of work packets stolen from other workers.
+    #[cfg(feature = "work_packet_stats")]
+    steals: usize,
+    /// The time this worker was parked while other workers were executing work packets.
+    #[cfg(feature = "work_packet_stats")]
+    idle_time: Duration,
+    /// The number of objects prefetched when processing edges.
+    #[cfg(feature = "work_packet_stats")]
+    prefetches: usize,
     enabled: AtomicBool,
     _phantom: PhantomData<C>,
 }
@@ -200,19 +259,49 @@ impl<C> Default for WorkerLocalStat<C> {
             work_id_name_map: Default::default(),
             work_counts: Default::default(),
             work_counters: Default::default(),
+            #[cfg(feature = "work_packet_stats")]
+            steals: 0,
+            #[cfg(feature = "work_packet_stats")]
+            idle_time: Duration::ZERO,
+            #[cfg(feature = "work_packet_stats")]
+            prefetches: 0,
             enabled: AtomicBool::new(false),
             _phantom: Default::default(),
         }
     }
 }
 
-impl<VM: VMBinding> WorkerLocalStat<VM> {
+impl<C> WorkerLocalStat<C> {
     pub fn is_enabled(&self) -> bool {
         self.enabled.load(Ordering::SeqCst)
     }
     pub fn enable(&self) {
         self.enabled.store(true, Ordering::SeqCst);
     }
+    /// Record that the worker stole a work packet from another worker.
+    #[cfg(feature = "work_packet_stats")]
+    pub fn on_steal(&mut self) {
+        if self.is_enabled() {
+            self.steals += 1;
+        }
+    }
+    /// Record the time the worker was parked while other workers were executing work packets.
+    #[cfg(feature = "work_packet_stats")]
+    pub fn on_idle(&mut self, idle_time: Duration) {
+        if self.is_enabled() {
+            self.idle_time += idle_time;
+        }
+    }
+    /// Record the number of objects prefetched when processing edges.
+    #[cfg(feature = "work_packet_stats")]
+    pub fn on_prefetch(&mut self, prefetches: usize) {
+        if self.is_enabled() {
+            self.prefetches += prefetches;
+        }
+    }
+}
+
+impl<VM: VMBinding> WorkerLocalStat<VM> {
     /// Measure the execution of a work packet by starting all counters for that
     /// type
     pub fn measure_work(
@@ -245,3 +334,45 @@ impl<VM: VMBinding> WorkerLocalStat<VM> {
         counters
     }
 }
+
+#[cfg(all(test, feature = "work_packet_stats"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_steals_idle_time_and_prefetches() {
+        let mut workers: Vec<WorkerLocalStat<()>> = (0..2).map(|_| Default::default()).collect();
+        // Nothing is recorded before the statistics are enabled.
+        workers[0].on_steal();
+        workers[0].on_idle(Duration::from_millis(1));
+        workers[0].on_prefetch(1);
+        assert_eq!(workers[0].steals, 0);
+        assert_eq!(workers[0].idle_time, Duration::ZERO);
+        assert_eq!(workers[0].prefetches, 0);
+
+        for worker in workers.iter() {
+            worker.enable();
+        }
+        workers[0].on_steal();
+        workers[0].on_steal();
+        workers[0].on_idle(Duration::from_millis(2));
+        workers[1].on_steal();
+        workers[1].on_idle(Duration::from_millis(3));
+        workers[0].on_prefetch(4);
+        workers[1].on_prefetch(5);
+        let mut summary = SchedulerStat::default();
+        for worker in workers.iter() {
+            summary.merge(worker);
+            summary.merge_worker(worker);
+        }
+
+        let stat = summary.harness_stat();
+        assert_eq!(stat["worker.0.steals"], "2");
+        assert_eq!(stat["worker.1.steals"], "1");
+        assert_eq!(stat["total-worker.steals"], "3");
+        assert_eq!(stat["worker.0.idle.time"], "2.000");
+        assert_eq!(stat["worker.1.idle.time"], "3.000");
+        assert_eq!(stat["total-worker.idle.time"], "5.000");
+        assert_eq!(stat["total-worker.prefetches"], "9");
+    }
+}
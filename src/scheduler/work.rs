@@ -9,6 +9,12 @@ pub trait GCWork<VM: VMBinding>: 'static + Send {
     /// Usually `do_work_with_stat()` should be used.
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>);
 
+    /// Get the type name of this work packet. This is used to identify work packets that are
+    /// queued or being executed when debugging the scheduler (see the `gc_watchdog_timeout` option).
+    fn get_type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     /// Do work and collect statistics. This internally calls `do_work()`. In most cases,
     /// this should be called rather than `do_work()` so that MMTk can correctly collect
     /// statistics for the work packets.
@@ -37,6 +37,12 @@ impl<VM: VMBinding> BucketQueue<VM> {
             self.queue.push(w);
         }
     }
+
+    /// The number of queued work packets. The queue is not modified, so this can be called while
+    /// workers are polling the queue. The number may be out of date as soon as it is returned.
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
 }
 
 pub type BucketOpenCondition<VM> = Box<dyn (Fn(&GCWorkScheduler<VM>) -> bool) + Send>;
@@ -44,7 +50,8 @@ pub type BucketOpenCondition<VM> = Box<dyn (Fn(&GCWorkScheduler<VM>) -> bool) +
 pub struct WorkBucket<VM: VMBinding> {
     active: AtomicBool,
     queue: BucketQueue<VM>,
-    prioritized_queue: Option<BucketQueue<VM>>,
+    /// Work packets in this queue are polled before the packets in `queue`.
+    prioritized_queue: BucketQueue<VM>,
     monitor: Arc<WorkerMonitor>,
     can_open: Option<BucketOpenCondition<VM>>,
     /// After this bucket is activated and all pending work packets (including the packets in this
@@ -66,7 +73,7 @@ impl<VM: VMBinding> WorkBucket<VM> {
         Self {
             active: AtomicBool::new(active),
             queue: BucketQueue::new(),
-            prioritized_queue: None,
+            prioritized_queue: BucketQueue::new(),
             monitor,
             can_open: None,
             sentinel: Mutex::new(None),
@@ -102,12 +109,7 @@ impl<VM: VMBinding> WorkBucket<VM> {
 
     /// Test if the bucket is drained
     pub fn is_empty(&self) -> bool {
-        self.queue.is_empty()
-            && self
-                .prioritized_queue
-                .as_ref()
-                .map(|q| q.is_empty())
-                .unwrap_or(true)
+        self.queue.is_empty() && self.prioritized_queue.is_empty()
     }
 
     pub fn is_drained(&self) -> bool {
@@ -116,14 +118,14 @@ impl<VM: VMBinding> WorkBucket<VM> {
 
     /// Disable the bucket
     pub fn deactivate(&self) {
-        debug_assert!(self.queue.is_empty(), "Bucket not drained before close");
+        debug_assert!(self.is_empty(), "Bucket not drained before close");
         self.active.store(false, Ordering::Relaxed);
     }
 
-    /// Add a work packet to this bucket
-    /// Panic if this bucket cannot receive prioritized packets.
+    /// Add a work packet to this bucket with a higher priority. Workers poll prioritized packets
+    /// before other packets in this bucket.
     pub fn add_prioritized(&self, work: Box<dyn GCWork<VM>>) {
-        self.prioritized_queue.as_ref().unwrap().push(work);
+        self.prioritized_queue.push(work);
         self.notify_one_worker();
     }
 
@@ -140,9 +142,8 @@ impl<VM: VMBinding> WorkBucket<VM> {
     }
 
     /// Add multiple packets with a higher priority.
-    /// Panic if this bucket cannot receive prioritized packets.
     pub fn bulk_add_prioritized(&self, work_vec: Vec<Box<dyn GCWork<VM>>>) {
-        self.prioritized_queue.as_ref().unwrap().push_all(work_vec);
+        self.prioritized_queue.push_all(work_vec);
         if self.is_activated() {
             self.notify_all_workers();
         }
@@ -164,13 +165,9 @@ impl<VM: VMBinding> WorkBucket<VM> {
         if !self.is_activated() || self.is_empty() {
             return Steal::Empty;
         }
-        if let Some(prioritized_queue) = self.prioritized_queue.as_ref() {
-            prioritized_queue
-                .steal_batch_and_pop(worker)
-                .or_else(|| self.queue.steal_batch_and_pop(worker))
-        } else {
-            self.queue.steal_batch_and_pop(worker)
-        }
+        self.prioritized_queue
+            .steal_batch_and_pop(worker)
+            .or_else(|| self.queue.steal_batch_and_pop(worker))
     }
 
     pub fn set_open_condition(
@@ -190,6 +187,38 @@ impl<VM: VMBinding> WorkBucket<VM> {
         sentinel.is_some()
     }
 
+    /// Drop all the work packets in this bucket, including the sentinel. This is used when the GC is
+    /// aborted.
+    pub(crate) fn discard_all(&self) {
+        for queue in [&self.queue, &self.prioritized_queue] {
+            while !queue.queue.steal().is_empty() {}
+        }
+        self.sentinel.lock().unwrap().take();
+    }
+
+    /// Describe the state of this bucket, including the numbers of the queued work packets and the
+    /// type name of the sentinel. This is only used for debugging. It does not change the state of
+    /// the bucket, so it can be called while workers are executing packets.
+    pub(crate) fn debug_dump(&self) -> String {
+        let sentinel = self
+            .sentinel
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|w| w.get_type_name());
+        format!(
+            "{}, queued: {}, prioritized: {}, sentinel: {:?}",
+            if self.is_activated() {
+                "open"
+            } else {
+                "closed"
+            },
+            self.queue.len(),
+            self.prioritized_queue.len(),
+            sentinel
+        )
+    }
+
     pub fn update(&self, scheduler: &GCWorkScheduler<VM>) -> bool {
         if let Some(can_open) = self.can_open.as_ref() {
             if !self.is_activated() && can_open(scheduler) {
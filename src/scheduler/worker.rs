@@ -4,13 +4,20 @@ use super::*;
 use crate::mmtk::MMTK;
 use crate::util::copy::GCWorkerCopyContext;
 use crate::util::opaque_pointer::*;
+use crate::util::options::WorkQueueOrder;
 use crate::vm::{Collection, GCThreadContext, VMBinding};
 use atomic::Atomic;
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut};
 use crossbeam::deque::{self, Stealer};
 use crossbeam::queue::ArrayQueue;
+use std::cell::Cell;
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "work_packet_stats")]
+use std::time::Duration;
+use std::time::Instant;
 
 /// Represents the ID of a GC worker thread.
 pub type ThreadId = usize;
@@ -38,6 +45,9 @@ pub struct GCWorkerShared<VM: VMBinding> {
     pub designated_work: ArrayQueue<Box<dyn GCWork<VM>>>,
     /// Handle for stealing packets from the current worker
     pub stealer: Option<Stealer<Box<dyn GCWork<VM>>>>,
+    /// The type name of the work packet that the worker is executing, or `None` if the worker is
+    /// not executing any work packet. This is only used for debugging.
+    current_work: Atomic<Option<&'static str>>,
 }
 
 impl<VM: VMBinding> GCWorkerShared<VM> {
@@ -46,8 +56,14 @@ impl<VM: VMBinding> GCWorkerShared<VM> {
             stat: Default::default(),
             designated_work: ArrayQueue::new(16),
             stealer,
+            current_work: Atomic::new(None),
         }
     }
+
+    /// Get the type name of the work packet that the worker is executing, if any.
+    pub(crate) fn current_work(&self) -> Option<&'static str> {
+        self.current_work.load(Ordering::Relaxed)
+    }
 }
 
 /// Used to synchronize mutually exclusive operations between workers and controller,
@@ -88,12 +104,20 @@ enum WorkerGroupState {
 
 /// The synchronized part of `WorkerMonitor`.
 pub(crate) struct WorkerMonitorSync {
-    /// The total number of workers.
+    /// The total number of workers. Workers with ordinals greater than or equal to this exit when they park.
     worker_count: usize,
+    /// The number of workers that may execute work packets in the current GC. Other workers stay parked.
+    active_workers: usize,
     /// Number of parked workers.
     parked_workers: usize,
     /// The worker group state.
     worker_group_state: WorkerGroupState,
+    /// True if the workers are asked to exit.  Workers only exit in the `Sleeping` state.
+    exit_requested: bool,
+    /// For each worker, indexed by the ordinal, the time when it parked if the worker group has
+    /// been `Working` since then, and its idle time that has not been added to its statistics.
+    #[cfg(feature = "work_packet_stats")]
+    idle_time: Vec<(Option<Instant>, Duration)>,
 }
 
 impl WorkerMonitor {
@@ -101,8 +125,12 @@ impl WorkerMonitor {
         Self {
             sync: Mutex::new(WorkerMonitorSync {
                 worker_count,
+                active_workers: worker_count,
                 parked_workers: 0,
                 worker_group_state: WorkerGroupState::Sleeping,
+                exit_requested: false,
+                #[cfg(feature = "work_packet_stats")]
+                idle_time: vec![],
             }),
             work_available: Default::default(),
             all_workers_parked: Default::default(),
@@ -122,23 +150,37 @@ impl WorkerMonitor {
             return;
         }
 
-        if all {
-            self.work_available.notify_all();
-        } else {
-            self.work_available.notify_one();
-        }
+        sync.notify_work_available(&self.work_available, all);
     }
 
     /// Wake up workers and wait until they transition to `Sleeping` state again.
     /// This is called by the coordinator.
     /// If `all` is true, notify all workers; otherwise only notify one worker.
-    pub fn resume_and_wait(&self, all: bool) {
+    /// If `deadline` is reached before the workers transition to the `Sleeping` state, call
+    /// `on_deadline` without holding the lock, and keep waiting after it returns.
+    pub fn resume_and_wait(
+        &self,
+        all: bool,
+        deadline: Option<Instant>,
+        on_deadline: impl FnOnce(),
+    ) {
         let mut sync = self.sync.lock().unwrap();
         sync.worker_group_state = WorkerGroupState::Working;
-        if all {
-            self.work_available.notify_all();
-        } else {
-            self.work_available.notify_one();
+        sync.notify_work_available(&self.work_available, all);
+        if let Some(deadline) = deadline {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let (new_sync, result) = self
+                .all_workers_parked
+                .wait_timeout_while(sync, timeout, |sync| {
+                    sync.worker_group_state == WorkerGroupState::Working
+                })
+                .unwrap();
+            sync = new_sync;
+            if result.timed_out() {
+                drop(sync);
+                on_deadline();
+                sync = self.sync.lock().unwrap();
+            }
         }
         let _sync = self
             .all_workers_parked
@@ -154,18 +196,78 @@ impl WorkerMonitor {
         sync.worker_group_state == WorkerGroupState::Sleeping
     }
 
+    /// Ask all workers to exit.  This must be called when workers are in the `Sleeping` state,
+    /// i.e. when GC is not in progress.
+    pub fn request_exit(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        debug_assert_eq!(sync.worker_group_state, WorkerGroupState::Sleeping);
+        sync.exit_requested = true;
+        self.work_available.notify_all();
+    }
+
+    /// Clear the exit request so that newly spawned workers can park and wait for work.
+    pub fn clear_exit_request(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.exit_requested = false;
+    }
+
+    /// Get the total number of workers.
+    pub fn worker_count(&self) -> usize {
+        self.sync.lock().unwrap().worker_count
+    }
+
+    /// Get the number of workers that may execute work packets in the current GC.
+    pub fn active_workers(&self) -> usize {
+        self.sync.lock().unwrap().active_workers
+    }
+
+    /// Get the number of parked workers. This is only used for debugging.
+    pub fn debug_parked_workers(&self) -> usize {
+        self.sync.lock().unwrap().parked_workers
+    }
+
+    /// Set the total number of workers, and the number of workers that may execute work packets in
+    /// the next GC. Workers with ordinals greater than or equal to `worker_count` exit. This must be
+    /// called by the coordinator in the `Sleeping` state, i.e. when GC is not in progress.
+    pub fn set_worker_count(&self, worker_count: usize, active_workers: usize) {
+        debug_assert!(active_workers > 0 && active_workers <= worker_count);
+        let mut sync = self.sync.lock().unwrap();
+        debug_assert_eq!(sync.worker_group_state, WorkerGroupState::Sleeping);
+        let retiring = worker_count < sync.worker_count;
+        sync.worker_count = worker_count;
+        sync.active_workers = active_workers;
+        if retiring {
+            self.work_available.notify_all();
+        }
+    }
+
     /// Park until more work is available.
     /// The argument `worker` indicates this function can only be called by workers.
-    pub fn park_and_wait<VM: VMBinding>(&self, worker: &GCWorker<VM>) {
+    ///
+    /// Return true if the worker should exit instead.
+    pub fn park_and_wait<VM: VMBinding>(&self, worker: &GCWorker<VM>) -> bool {
         let mut sync = self.sync.lock().unwrap();
 
+        // The worker is retired before it ever parks.
+        if sync.is_retired(worker.ordinal) {
+            return true;
+        }
+
         // Park this worker
         let all_parked = sync.inc_parked_workers();
         trace!("Worker {} parked.", worker.ordinal);
+        #[cfg(feature = "work_packet_stats")]
+        if sync.worker_group_state == WorkerGroupState::Working
+            && worker.ordinal < sync.active_workers
+        {
+            sync.start_idle(worker.ordinal);
+        }
 
         if all_parked {
             // If all workers are parked, enter "Sleeping" state and notify controller.
             sync.worker_group_state = WorkerGroupState::Sleeping;
+            #[cfg(feature = "work_packet_stats")]
+            sync.stop_all_idle();
             debug!(
                 "Worker {} notifies the coordinator that all workerer parked.",
                 worker.ordinal
@@ -180,38 +282,102 @@ impl WorkerMonitor {
             sync = self.work_available.wait(sync).unwrap();
         }
 
-        // If we are in the `Sleeping` state, wait until leaving that state.
+        // If we are in the `Sleeping` state, wait until leaving that state, or until asked to exit.
+        // Inactive workers keep waiting until they become active in a later GC, or until retired.
         sync = self
             .work_available
             .wait_while(sync, |sync| {
-                sync.worker_group_state == WorkerGroupState::Sleeping
+                (sync.worker_group_state == WorkerGroupState::Sleeping
+                    || worker.ordinal >= sync.active_workers)
+                    && !sync.exit_requested
+                    && !sync.is_retired(worker.ordinal)
             })
             .unwrap();
 
         // Unpark this worker.
         sync.dec_parked_workers();
         trace!("Worker {} unparked.", worker.ordinal);
+        #[cfg(feature = "work_packet_stats")]
+        {
+            let idle_time = sync.take_idle_time(worker.ordinal);
+            worker.shared.borrow_stat_mut().on_idle(idle_time);
+        }
+
+        sync.is_retired(worker.ordinal)
+            || (sync.worker_group_state == WorkerGroupState::Sleeping && sync.exit_requested)
     }
 }
 
 impl WorkerMonitorSync {
+    /// Notify one or all workers that work is available. If some workers are inactive, we have to
+    /// notify all workers, as an inactive worker would go back to wait and the notification would be lost.
+    fn notify_work_available(&self, work_available: &Condvar, all: bool) {
+        if all || self.active_workers < self.worker_count {
+            work_available.notify_all();
+        } else {
+            work_available.notify_one();
+        }
+    }
+
+    /// Should the worker exit because the number of workers has decreased?
+    fn is_retired(&self, ordinal: ThreadId) -> bool {
+        ordinal >= self.worker_count
+    }
+
     /// Increase the packed-workers counter.
     /// Called before a worker is parked.
     ///
     /// Return true if all the workers are parked.
     fn inc_parked_workers(&mut self) -> bool {
         let old = self.parked_workers;
-        debug_assert!(old < self.worker_count);
+        // Retiring workers may not have left yet when the number of workers has just decreased.
+        debug_assert!(
+            old < self.worker_count || self.worker_group_state == WorkerGroupState::Sleeping
+        );
         let new = old + 1;
         self.parked_workers = new;
         new == self.worker_count
     }
 
+    /// Start measuring the idle time of a worker that has just parked while the group is `Working`.
+    #[cfg(feature = "work_packet_stats")]
+    fn start_idle(&mut self, ordinal: ThreadId) {
+        if self.idle_time.len() <= ordinal {
+            self.idle_time.resize(ordinal + 1, (None, Duration::ZERO));
+        }
+        self.idle_time[ordinal].0 = Some(Instant::now());
+    }
+
+    /// Stop measuring the idle time of all the workers because the worker group is entering the
+    /// `Sleeping` state, and the workers are no longer waiting for other workers.
+    #[cfg(feature = "work_packet_stats")]
+    fn stop_all_idle(&mut self) {
+        let now = Instant::now();
+        for (start, idle_time) in self.idle_time.iter_mut() {
+            if let Some(start) = start.take() {
+                *idle_time += now - start;
+            }
+        }
+    }
+
+    /// Stop measuring the idle time of an unparked worker, and take its accumulated idle time.
+    #[cfg(feature = "work_packet_stats")]
+    fn take_idle_time(&mut self, ordinal: ThreadId) -> Duration {
+        match self.idle_time.get_mut(ordinal) {
+            Some((start, idle_time)) => {
+                if let Some(start) = start.take() {
+                    *idle_time += start.elapsed();
+                }
+                std::mem::take(idle_time)
+            }
+            None => Duration::ZERO,
+        }
+    }
+
     /// Decrease the packed-workers counter.
     /// Called after a worker is resumed from the parked state.
     fn dec_parked_workers(&mut self) {
         let old = self.parked_workers;
-        debug_assert!(old <= self.worker_count);
         debug_assert!(old > 0);
         let new = old - 1;
         self.parked_workers = new;
@@ -239,6 +405,10 @@ pub struct GCWorker<VM: VMBinding> {
     pub shared: Arc<GCWorkerShared<VM>>,
     /// Local work packet queue.
     pub local_work_buffer: deque::Worker<Box<dyn GCWork<VM>>>,
+    /// The maximum number of work packets in the local work queue.
+    local_work_queue_size: usize,
+    /// The state of the pseudo-random number generator for choosing the workers to steal from.
+    steal_random_state: Cell<u64>,
 }
 
 unsafe impl<VM: VMBinding> Sync for GCWorkerShared<VM> {}
@@ -277,17 +447,33 @@ impl<VM: VMBinding> GCWorker<VM> {
             is_coordinator,
             shared,
             local_work_buffer,
+            local_work_queue_size: *mmtk.options.local_work_queue_size,
+            // Any non-zero seed works for xorshift. Make it different for each worker.
+            steal_random_state: Cell::new(
+                (ordinal as u64)
+                    .wrapping_add(1)
+                    .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                    | 1,
+            ),
         }
     }
 
-    const LOCALLY_CACHED_WORK_PACKETS: usize = 16;
+    /// Get a pseudo-random number (xorshift64) for choosing the workers to steal from.
+    pub(crate) fn next_steal_random(&self) -> usize {
+        let mut x = self.steal_random_state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.steal_random_state.set(x);
+        x as usize
+    }
 
     /// Add a work packet to the work queue and mark it with a higher priority.
     /// If the bucket is activated, the packet will be pushed to the local queue, otherwise it will be
     /// pushed to the global bucket with a higher priority.
     pub fn add_work_prioritized(&mut self, bucket: WorkBucketStage, work: impl GCWork<VM>) {
         if !self.scheduler().work_buckets[bucket].is_activated()
-            || self.local_work_buffer.len() >= Self::LOCALLY_CACHED_WORK_PACKETS
+            || self.local_work_buffer.len() >= self.local_work_queue_size
         {
             self.scheduler.work_buckets[bucket].add_prioritized(Box::new(work));
             return;
@@ -300,7 +486,7 @@ impl<VM: VMBinding> GCWorker<VM> {
     /// pushed to the global bucket.
     pub fn add_work(&mut self, bucket: WorkBucketStage, work: impl GCWork<VM>) {
         if !self.scheduler().work_buckets[bucket].is_activated()
-            || self.local_work_buffer.len() >= Self::LOCALLY_CACHED_WORK_PACKETS
+            || self.local_work_buffer.len() >= self.local_work_queue_size
         {
             self.scheduler.work_buckets[bucket].add(work);
             return;
@@ -330,12 +516,59 @@ impl<VM: VMBinding> GCWorker<VM> {
     /// 2. Poll from the local work queue.
     /// 3. Poll from activated global work-buckets
     /// 4. Steal from other workers
-    fn poll(&self) -> Box<dyn GCWork<VM>> {
+    ///
+    /// Return `None` if the worker should exit.
+    fn poll(&self) -> Option<Box<dyn GCWork<VM>>> {
         self.shared
             .designated_work
             .pop()
             .or_else(|| self.local_work_buffer.pop())
-            .unwrap_or_else(|| self.scheduler().poll(self))
+            .or_else(|| self.scheduler().poll(self))
+    }
+
+    /// Poll a ready-to-execute work packet like `poll`, but return `None` instead of parking if
+    /// there is no work packet available.
+    pub(crate) fn poll_without_parking(&self) -> Option<Box<dyn GCWork<VM>>> {
+        self.shared
+            .designated_work
+            .pop()
+            .or_else(|| self.local_work_buffer.pop())
+            .or_else(|| self.scheduler().poll_schedulable_work(self))
+    }
+
+    /// Prepare to execute work packets on the current thread without running the main loop of
+    /// a worker. This is used by the embedded worker of a synchronous controller, which runs on
+    /// the mutator that requests GC.
+    pub(crate) fn enter(&mut self, tls: VMWorkerThread, mmtk: &'static MMTK<VM>) {
+        WORKER_ORDINAL.with(|x| x.store(Some(self.ordinal), Ordering::SeqCst));
+        self.tls = tls;
+        self.copy = crate::plan::create_gc_worker_context(tls, mmtk);
+    }
+
+    /// The current thread stops executing work packets. This is the opposite of `enter`.
+    pub(crate) fn leave(&mut self) {
+        debug_assert!(self.local_work_buffer.is_empty());
+        WORKER_ORDINAL.with(|x| x.store(None, Ordering::SeqCst));
+    }
+
+    /// Execute a work packet polled from the scheduler. If the work packet panics, the panic is
+    /// caught and recorded in the scheduler. After that, the remaining work packets of the GC are
+    /// discarded instead of executed, and the controller reports the panic to the binding.
+    pub(crate) fn execute(&mut self, mut work: Box<dyn GCWork<VM>>, mmtk: &'static MMTK<VM>) {
+        if self.scheduler.has_gc_panicked() {
+            return;
+        }
+        self.shared
+            .current_work
+            .store(Some(work.get_type_name()), Ordering::Relaxed);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            work.do_work_with_stat(self, mmtk);
+        }));
+        self.shared.current_work.store(None, Ordering::Relaxed);
+        if let Err(payload) = result {
+            self.scheduler
+                .on_work_panicked(self, work.get_type_name(), payload);
+        }
     }
 
     pub fn do_boxed_work(&'static mut self, mut work: Box<dyn GCWork<VM>>) {
@@ -343,31 +576,55 @@ impl<VM: VMBinding> GCWorker<VM> {
     }
 
     /// Entry of the worker thread. Resolve thread affinity, if it has been specified by the user.
-    /// Each worker will keep polling and executing work packets in a loop.
+    /// Each worker will keep polling and executing work packets in a loop, until it is asked to
+    /// exit (see [`crate::memory_manager::prepare_to_fork`]).
     pub fn run(&mut self, tls: VMWorkerThread, mmtk: &'static MMTK<VM>) {
         WORKER_ORDINAL.with(|x| x.store(Some(self.ordinal), Ordering::SeqCst));
         self.scheduler.resolve_affinity(self.ordinal);
         self.tls = tls;
         self.copy = crate::plan::create_gc_worker_context(tls, mmtk);
-        loop {
-            let mut work = self.poll();
-            work.do_work_with_stat(self, mmtk);
+        while let Some(work) = self.poll() {
+            self.execute(work, mmtk);
         }
+
+        // Give back the local work queue so that the worker can be spawned again.
+        debug_assert!(self.local_work_buffer.is_empty());
+        let local_work_buffer =
+            std::mem::replace(&mut self.local_work_buffer, deque::Worker::new_fifo());
+        self.scheduler
+            .worker_group
+            .return_local_work_queue(self.ordinal, local_work_buffer);
+        WORKER_ORDINAL.with(|x| x.store(None, Ordering::SeqCst));
+        self.scheduler.on_gc_thread_exit();
+    }
+}
+
+/// The local work queue of a GC worker.
+type LocalWorkQueue<VM> = deque::Worker<Box<dyn GCWork<VM>>>;
+
+/// Create a local work queue that pops work packets in the given order.
+pub(crate) fn new_local_work_queue<VM: VMBinding>(order: WorkQueueOrder) -> LocalWorkQueue<VM> {
+    match order {
+        WorkQueueOrder::Fifo => deque::Worker::new_fifo(),
+        WorkQueueOrder::Lifo => deque::Worker::new_lifo(),
     }
 }
 
-/// A worker group to manage all the GC workers (except the coordinator worker).
+/// A worker group to manage all the GC workers (except the coordinator worker). The group has a
+/// slot for each worker that may be spawned, but only the workers with ordinals less than the current
+/// number of workers are running.
 pub(crate) struct WorkerGroup<VM: VMBinding> {
     /// Shared worker data
     pub workers_shared: Vec<Arc<GCWorkerShared<VM>>>,
-    unspawned_local_work_queues: Mutex<Vec<deque::Worker<Box<dyn GCWork<VM>>>>>,
+    /// The local work queues of workers that are not running, indexed by the worker ordinal.
+    unspawned_local_work_queues: Mutex<Vec<Option<LocalWorkQueue<VM>>>>,
 }
 
 impl<VM: VMBinding> WorkerGroup<VM> {
-    /// Create a WorkerGroup
-    pub fn new(num_workers: usize) -> Arc<Self> {
+    /// Create a WorkerGroup. The local work queues of the workers pop packets in the given order.
+    pub fn new(num_workers: usize, order: WorkQueueOrder) -> Arc<Self> {
         let unspawned_local_work_queues = (0..num_workers)
-            .map(|_| deque::Worker::new_fifo())
+            .map(|_| new_local_work_queue(order))
             .collect::<Vec<_>>();
 
         let workers_shared = (0..num_workers)
@@ -380,30 +637,39 @@ impl<VM: VMBinding> WorkerGroup<VM> {
 
         Arc::new(Self {
             workers_shared,
-            unspawned_local_work_queues: Mutex::new(unspawned_local_work_queues),
+            unspawned_local_work_queues: Mutex::new(
+                unspawned_local_work_queues.into_iter().map(Some).collect(),
+            ),
         })
     }
 
-    /// Spawn all the worker threads
-    pub fn spawn(&self, mmtk: &'static MMTK<VM>, tls: VMThread) {
+    /// Spawn the worker threads with the given ordinals
+    pub fn spawn(&self, mmtk: &'static MMTK<VM>, tls: VMThread, ordinals: Range<ThreadId>) {
         let mut unspawned_local_work_queues = self.unspawned_local_work_queues.lock().unwrap();
         // Spawn each worker thread.
-        for (ordinal, shared) in self.workers_shared.iter().enumerate() {
+        for ordinal in ordinals {
             let worker = Box::new(GCWorker::new(
                 mmtk,
                 ordinal,
                 mmtk.scheduler.clone(),
                 false,
-                shared.clone(),
-                unspawned_local_work_queues.pop().unwrap(),
+                self.workers_shared[ordinal].clone(),
+                unspawned_local_work_queues[ordinal].take().unwrap(),
             ));
             VM::VMCollection::spawn_gc_thread(tls, GCThreadContext::<VM>::Worker(worker));
         }
-        debug_assert!(unspawned_local_work_queues.is_empty());
     }
 
-    /// Get the number of workers in the group
-    pub fn worker_count(&self) -> usize {
+    /// Return the local work queue of an exiting worker so that it can be used when the worker is
+    /// spawned again.
+    fn return_local_work_queue(&self, ordinal: ThreadId, queue: LocalWorkQueue<VM>) {
+        let mut unspawned_local_work_queues = self.unspawned_local_work_queues.lock().unwrap();
+        debug_assert!(unspawned_local_work_queues[ordinal].is_none());
+        unspawned_local_work_queues[ordinal] = Some(queue);
+    }
+
+    /// Get the maximum number of workers in the group. Not all of them are spawned.
+    pub fn max_workers(&self) -> usize {
         self.workers_shared.len()
     }
 
@@ -414,3 +680,30 @@ impl<VM: VMBinding> WorkerGroup<VM> {
             .any(|w| !w.designated_work.is_empty())
     }
 }
+
+#[cfg(all(test, feature = "work_packet_stats"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worker_idle_time() {
+        let monitor = WorkerMonitor::new(2);
+        let mut sync = monitor.sync.lock().unwrap();
+        // A worker that has not parked has no idle time.
+        assert_eq!(sync.take_idle_time(1), Duration::ZERO);
+
+        sync.start_idle(1);
+        std::thread::sleep(Duration::from_millis(1));
+        // The idle time stops when all the workers park.
+        sync.stop_all_idle();
+        let idle_time = sync.take_idle_time(1);
+        assert!(idle_time >= Duration::from_millis(1));
+        // The idle time is taken only once.
+        assert_eq!(sync.take_idle_time(1), Duration::ZERO);
+        // The idle time of an unparked worker includes the time until it is unparked.
+        sync.start_idle(0);
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(sync.take_idle_time(0) >= Duration::from_millis(1));
+        assert_eq!(sync.take_idle_time(0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,37 @@
+//! The concurrent zeroing thread.
+//!
+//! A page resource may zero the pages it releases in a GC concurrently, ahead of the allocator,
+//! instead of zeroing the pages when they are allocated. MMTk spawns a zeroing thread for each
+//! space whose page resource uses concurrent zeroing.
+
+use std::sync::Arc;
+
+use crate::policy::space::Space;
+use crate::util::VMWorkerThread;
+use crate::vm::VMBinding;
+
+use super::GCWorkScheduler;
+
+/// The thread local struct for a concurrent zeroing thread.
+pub struct ZeroingThread<VM: VMBinding> {
+    /// The space whose released pages are zeroed by this thread.
+    space: &'static dyn Space<VM>,
+    /// The reference to the scheduler.
+    scheduler: Arc<GCWorkScheduler<VM>>,
+}
+
+impl<VM: VMBinding> ZeroingThread<VM> {
+    pub(crate) fn new(
+        space: &'static dyn Space<VM>,
+        scheduler: Arc<GCWorkScheduler<VM>>,
+    ) -> Box<ZeroingThread<VM>> {
+        Box::new(Self { space, scheduler })
+    }
+
+    pub fn run(&mut self, _tls: VMWorkerThread) {
+        debug!("[Zeroing thread for {}: Started]", self.space.get_name());
+        self.space.get_page_resource().concurrent_zeroing();
+        debug!("[Zeroing thread for {}: Exiting...]", self.space.get_name());
+        self.scheduler.on_gc_thread_exit();
+    }
+}
@@ -220,6 +220,18 @@ impl Address {
         *(self.0 as *mut T)
     }
 
+    /// Prefetch the cache line that contains the address for a subsequent load. This is only a hint
+    /// to the processor. It does not fault even if the address is not mapped, and it is a no-op on
+    /// architectures for which we do not have a prefetch instruction.
+    #[inline(always)]
+    pub fn prefetch_load(self) {
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            _mm_prefetch::<_MM_HINT_T0>(self.to_ptr::<i8>())
+        }
+    }
+
     /// stores a value of type T to the address
     /// # Safety
     /// This could throw a segment fault if the address is invalid
@@ -441,6 +453,14 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn prefetch_load() {
+        // Prefetching does not fault, even if the address is not mapped.
+        Address::ZERO.prefetch_load();
+        unsafe { Address::from_usize(0xdead_0000usize) }.prefetch_load();
+        Address::MAX.prefetch_load();
+    }
 }
 
 use crate::vm::VMBinding;
@@ -14,7 +14,7 @@ use crate::vm::VMBinding;
 use super::FreeListAllocator;
 use super::MarkCompactAllocator;
 
-pub(crate) const MAX_BUMP_ALLOCATORS: usize = 6;
+pub(crate) const MAX_BUMP_ALLOCATORS: usize = 7;
 pub(crate) const MAX_LARGE_OBJECT_ALLOCATORS: usize = 2;
 pub(crate) const MAX_MALLOC_ALLOCATORS: usize = 1;
 pub(crate) const MAX_IMMIX_ALLOCATORS: usize = 1;
@@ -35,6 +35,8 @@ pub struct ImmixAllocator<VM: VMBinding> {
     request_for_large: bool,
     /// Hole-searching cursor
     line: Option<Line>,
+    /// The line size. Objects larger than a line are allocated with the bump pointer for large objects.
+    line_bytes: usize,
 }
 
 impl<VM: VMBinding> ImmixAllocator<VM> {
@@ -62,15 +64,15 @@ impl<VM: VMBinding> Allocator<VM> for ImmixAllocator<VM> {
     }
 
     fn get_thread_local_buffer_granularity(&self) -> usize {
-        crate::policy::immix::block::Block::BYTES
+        crate::policy::immix::block::Block::bytes()
     }
 
     fn alloc(&mut self, size: usize, align: usize, offset: usize) -> Address {
         debug_assert!(
-            size <= crate::policy::immix::MAX_IMMIX_OBJECT_SIZE,
-            "Trying to allocate a {} bytes object, which is larger than MAX_IMMIX_OBJECT_SIZE {}",
+            size <= crate::policy::immix::max_immix_object_size(),
+            "Trying to allocate a {} bytes object, which is larger than the max immix object size {}",
             size,
-            crate::policy::immix::MAX_IMMIX_OBJECT_SIZE
+            crate::policy::immix::max_immix_object_size()
         );
         let result = align_allocation_no_fill::<VM>(self.cursor, align, offset);
         let new_cursor = result + size;
@@ -80,7 +82,7 @@ impl<VM: VMBinding> Allocator<VM> for ImmixAllocator<VM> {
                 "{:?}: Thread local buffer used up, go to alloc slow path",
                 self.tls
             );
-            if get_maximum_aligned_size::<VM>(size, align) > Line::BYTES {
+            if get_maximum_aligned_size::<VM>(size, align) > self.line_bytes {
                 // Size larger than a line: do large allocation
                 self.overflow_alloc(size, align, offset)
             } else {
@@ -187,6 +189,7 @@ impl<VM: VMBinding> ImmixAllocator<VM> {
             large_limit: Address::ZERO,
             request_for_large: false,
             line: None,
+            line_bytes: Line::bytes(),
         }
     }
 
@@ -323,7 +326,7 @@ impl<VM: VMBinding> ImmixAllocator<VM> {
         // size check and then return the conditions where `alloc_slow_inline()` would be called
         // in an `alloc()` call, namely when both `overflow_alloc()` and `alloc_slow_hot()` fail
         // to service the allocation request
-        if insufficient_space && get_maximum_aligned_size::<VM>(size, align) > Line::BYTES {
+        if insufficient_space && get_maximum_aligned_size::<VM>(size, align) > self.line_bytes {
             let start = align_allocation_no_fill::<VM>(self.large_cursor, align, offset);
             let end = start + size;
             end > self.large_limit
@@ -11,6 +11,8 @@ use crate::vm::VMBinding;
 #[repr(C)]
 pub struct MarkCompactAllocator<VM: VMBinding> {
     bump_allocator: BumpAllocator<VM>,
+    /// The bytes reserved before each object for the forwarding pointer. It is zero if the space uses side forwarding.
+    header_reserved_in_bytes: usize,
 }
 
 impl<VM: VMBinding> MarkCompactAllocator<VM> {
@@ -51,11 +53,11 @@ impl<VM: VMBinding> Allocator<VM> for MarkCompactAllocator<VM> {
     fn alloc(&mut self, size: usize, align: usize, offset: usize) -> Address {
         let rtn = self
             .bump_allocator
-            .alloc(size + Self::HEADER_RESERVED_IN_BYTES, align, offset);
+            .alloc(size + self.header_reserved_in_bytes, align, offset);
         // Check if the result is valid and return the actual object start address
         // Note that `rtn` can be null in the case of OOM
         if !rtn.is_zero() {
-            rtn + Self::HEADER_RESERVED_IN_BYTES
+            rtn + self.header_reserved_in_bytes
         } else {
             rtn
         }
@@ -96,6 +98,11 @@ impl<VM: VMBinding> MarkCompactAllocator<VM> {
     ) -> Self {
         MarkCompactAllocator {
             bump_allocator: BumpAllocator::new(tls, space, plan),
+            header_reserved_in_bytes: if *plan.options().mark_compact_side_forwarding {
+                0
+            } else {
+                Self::HEADER_RESERVED_IN_BYTES
+            },
         }
     }
 }
@@ -0,0 +1,209 @@
+//! Card table for the card-marking barrier.
+//!
+//! The heap is divided into cards of a fixed size (set by the `card_size` option). The card-marking
+//! barrier dirties the card that contains a modified slot, and a nursery GC scans the objects
+//! that overlap dirty cards to find pointers from mature objects to nursery objects.
+//!
+//! We use one byte per card in the side metadata. To avoid going through the card table for the
+//! entire heap in each GC, we also record the chunks that contain dirty cards.
+
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::metadata::side_metadata::spec_defs;
+use crate::util::metadata::side_metadata::SideMetadataSpec;
+use crate::util::Address;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+/// Log of the minimum card size in bytes. The card table has one entry for each minimum-sized
+/// card. If the card size is larger, we only use the entry for the first minimum-sized card in a card.
+pub const LOG_MIN_CARD_BYTES: usize = 7;
+/// The minimum card size in bytes.
+pub const MIN_CARD_BYTES: usize = 1 << LOG_MIN_CARD_BYTES;
+/// The maximum card size in bytes. A card never spans multiple chunks.
+pub const MAX_CARD_BYTES: usize = BYTES_IN_CHUNK;
+
+/// One byte per card. A card is either clean or dirty.
+pub(crate) const CARD_TABLE_SPEC: SideMetadataSpec = spec_defs::CARD_TABLE;
+/// One byte per chunk. It is set if the chunk has been recorded in the dirty chunk list.
+pub(crate) const CARD_DIRTY_CHUNK_SPEC: SideMetadataSpec = spec_defs::CARD_DIRTY_CHUNK;
+
+const CARD_CLEAN: u8 = 0;
+const CARD_DIRTY: u8 = 1;
+
+/// Is the card size valid? This is used to validate the `card_size` option.
+pub(crate) fn is_valid_card_size(card_bytes: usize) -> bool {
+    card_bytes.is_power_of_two() && (MIN_CARD_BYTES..=MAX_CARD_BYTES).contains(&card_bytes)
+}
+
+/// A card table. The card table itself is global side metadata. This type records the card size
+/// and the chunks that contain dirty cards.
+pub struct CardTable {
+    log_card_bytes: usize,
+    /// Chunks that contain dirty cards. Each chunk appears at most once.
+    dirty_chunks: Mutex<Vec<Address>>,
+}
+
+impl CardTable {
+    pub(crate) fn new(card_bytes: usize) -> Self {
+        assert!(
+            is_valid_card_size(card_bytes),
+            "Invalid card size: {}",
+            card_bytes
+        );
+        Self {
+            log_card_bytes: card_bytes.trailing_zeros() as usize,
+            dirty_chunks: Mutex::new(vec![]),
+        }
+    }
+
+    /// The global side metadata specs used by the card table. A plan that uses the card table
+    /// needs to include these in its global side metadata specs.
+    pub(crate) fn side_metadata_specs() -> Vec<SideMetadataSpec> {
+        vec![CARD_TABLE_SPEC, CARD_DIRTY_CHUNK_SPEC]
+    }
+
+    /// The size of a card in bytes.
+    pub fn card_bytes(&self) -> usize {
+        1 << self.log_card_bytes
+    }
+
+    /// The start address of the card that contains the address.
+    pub fn card_start(&self, addr: Address) -> Address {
+        addr.align_down(self.card_bytes())
+    }
+
+    /// Is the card that contains the address dirty?
+    pub fn is_card_dirty(&self, addr: Address) -> bool {
+        CARD_TABLE_SPEC.load_atomic::<u8>(self.card_start(addr), Ordering::Relaxed) == CARD_DIRTY
+    }
+
+    /// Dirty the card that contains the address. This is the fast path of the card-marking barrier.
+    pub fn mark_card(&self, addr: Address) {
+        let card = self.card_start(addr);
+        if CARD_TABLE_SPEC.load_atomic::<u8>(card, Ordering::Relaxed) == CARD_DIRTY {
+            return;
+        }
+        CARD_TABLE_SPEC.store_atomic::<u8>(card, CARD_DIRTY, Ordering::Relaxed);
+        self.record_dirty_chunk(card);
+    }
+
+    /// Dirty all the cards that overlap with the address range `[start, start + bytes)`.
+    pub fn mark_range(&self, start: Address, bytes: usize) {
+        let end = start + bytes;
+        let mut card = self.card_start(start);
+        while card < end {
+            self.mark_card(card);
+            card += self.card_bytes();
+        }
+    }
+
+    /// Record the chunk of the card if the chunk is not yet recorded.
+    fn record_dirty_chunk(&self, card: Address) {
+        let chunk = card.align_down(BYTES_IN_CHUNK);
+        if CARD_DIRTY_CHUNK_SPEC.load_atomic::<u8>(chunk, Ordering::Relaxed) == CARD_CLEAN
+            && CARD_DIRTY_CHUNK_SPEC
+                .compare_exchange_atomic::<u8>(
+                    chunk,
+                    CARD_CLEAN,
+                    CARD_DIRTY,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                )
+                .is_ok()
+        {
+            self.dirty_chunks.lock().unwrap().push(chunk);
+        }
+    }
+
+    /// Take the chunks that contain dirty cards. The cards in the chunks are still dirty, and the
+    /// caller is responsible for clearing them. This should only be called during a GC.
+    pub(crate) fn take_dirty_chunks(&self) -> Vec<Address> {
+        let chunks = std::mem::take(&mut *self.dirty_chunks.lock().unwrap());
+        for chunk in chunks.iter() {
+            CARD_DIRTY_CHUNK_SPEC.store_atomic::<u8>(*chunk, CARD_CLEAN, Ordering::Relaxed);
+        }
+        chunks
+    }
+
+    /// Clear the dirty cards in the chunk, and return the start addresses of those cards in
+    /// increasing order. This should only be called during a GC.
+    pub(crate) fn take_dirty_cards(&self, chunk: Address) -> Vec<Address> {
+        debug_assert!(chunk.is_aligned_to(BYTES_IN_CHUNK));
+        let mut cards = vec![];
+        let mut card = chunk;
+        while card < chunk + BYTES_IN_CHUNK {
+            if CARD_TABLE_SPEC.load_atomic::<u8>(card, Ordering::Relaxed) == CARD_DIRTY {
+                CARD_TABLE_SPEC.store_atomic::<u8>(card, CARD_CLEAN, Ordering::Relaxed);
+                cards.push(card);
+            }
+            card += self.card_bytes();
+        }
+        cards
+    }
+
+    /// Clear all the dirty cards. This should only be called during a GC.
+    pub(crate) fn clear(&self) {
+        for chunk in self.take_dirty_chunks() {
+            CARD_TABLE_SPEC.bzero_metadata(chunk, BYTES_IN_CHUNK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::heap::layout::vm_layout_constants;
+    use crate::util::metadata::side_metadata::SideMetadataContext;
+    use crate::util::test_util::{serial_test, with_cleanup};
+
+    #[test]
+    fn mark_and_take_dirty_cards() {
+        serial_test(|| {
+            let data_addr =
+                vm_layout_constants::HEAP_START + (vm_layout_constants::BYTES_IN_CHUNK << 1);
+            let metadata = SideMetadataContext {
+                global: CardTable::side_metadata_specs(),
+                local: vec![],
+            };
+            with_cleanup(
+                || {
+                    assert!(metadata
+                        .try_map_metadata_space(data_addr, BYTES_IN_CHUNK)
+                        .is_ok());
+                    let card_table = CardTable::new(512);
+
+                    card_table.mark_card(data_addr + 10usize);
+                    // This range overlaps with three cards
+                    card_table.mark_range(data_addr + 1000usize, 1000);
+                    assert!(card_table.is_card_dirty(data_addr));
+                    assert!(card_table.is_card_dirty(data_addr + 2000usize));
+                    assert!(!card_table.is_card_dirty(data_addr + 2048usize));
+
+                    let chunks = card_table.take_dirty_chunks();
+                    assert_eq!(chunks, vec![data_addr]);
+                    assert_eq!(
+                        card_table.take_dirty_cards(data_addr),
+                        vec![
+                            data_addr,
+                            data_addr + 512usize,
+                            data_addr + 1024usize,
+                            data_addr + 1536usize
+                        ]
+                    );
+                    assert!(!card_table.is_card_dirty(data_addr));
+
+                    // Once the chunk is taken, dirtying a card records the chunk again
+                    card_table.mark_card(data_addr + 4096usize);
+                    assert_eq!(card_table.dirty_chunks.lock().unwrap().len(), 1);
+                    card_table.clear();
+                    assert!(!card_table.is_card_dirty(data_addr + 4096usize));
+                    assert!(card_table.take_dirty_chunks().is_empty());
+                },
+                || {
+                    CARD_TABLE_SPEC.bzero_metadata(data_addr, BYTES_IN_CHUNK);
+                    metadata.ensure_unmap_metadata_space(data_addr, BYTES_IN_CHUNK);
+                },
+            )
+        })
+    }
+}
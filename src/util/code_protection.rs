@@ -0,0 +1,163 @@
+//! Write-xor-execute (W^X) permission management for code spaces.
+//!
+//! MMTk maps all the memory as readable and writable, but not executable. The binding makes a range
+//! of the code spaces executable after it writes code into it, and the range then becomes read-only.
+//! The binding must make the range writable again before it modifies the code. A range is never
+//! writable and executable at the same time.
+//!
+//! Code objects are never moved, as the code spaces are immortal. But GC may still write to code
+//! objects, e.g. setting the mark bit or the log bit in the object header. So the executable ranges
+//! are made writable during each GC, and made executable again before mutators resume.
+
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::memory;
+use crate::util::Address;
+use std::sync::Mutex;
+
+/// Executable ranges in the code spaces.
+#[derive(Default)]
+pub(crate) struct CodeProtection {
+    /// Sorted and disjoint page-aligned ranges as `(start, end)` that are executable when mutators run.
+    executable: Mutex<Vec<(Address, Address)>>,
+}
+
+impl CodeProtection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the pages that overlap with the range readable and executable.
+    pub fn make_executable(&self, start: Address, bytes: usize) {
+        let (start, end) = Self::page_range(start, bytes);
+        let mut executable = self.executable.lock().unwrap();
+        Self::protect(start, end, true);
+        add_range(&mut executable, start, end);
+    }
+
+    /// Make the pages that overlap with the range readable and writable.
+    pub fn make_writable(&self, start: Address, bytes: usize) {
+        let (start, end) = Self::page_range(start, bytes);
+        let mut executable = self.executable.lock().unwrap();
+        Self::protect(start, end, false);
+        remove_range(&mut executable, start, end);
+    }
+
+    /// Make all the executable ranges writable so GC can write to code objects. This should be
+    /// called when mutators are stopped.
+    pub fn prepare_for_gc(&self) {
+        for &(start, end) in self.executable.lock().unwrap().iter() {
+            Self::protect(start, end, false);
+        }
+    }
+
+    /// Make the executable ranges executable again after GC. This should be called before mutators
+    /// resume.
+    pub fn release_after_gc(&self) {
+        for &(start, end) in self.executable.lock().unwrap().iter() {
+            Self::protect(start, end, true);
+        }
+    }
+
+    fn page_range(start: Address, bytes: usize) -> (Address, Address) {
+        (
+            start.align_down(BYTES_IN_PAGE),
+            (start + bytes).align_up(BYTES_IN_PAGE),
+        )
+    }
+
+    fn protect(start: Address, end: Address, executable: bool) {
+        if start == end {
+            return;
+        }
+        let result = if executable {
+            memory::mprotect_executable(start, end - start)
+        } else {
+            memory::munprotect(start, end - start)
+        };
+        result.unwrap_or_else(|e| {
+            panic!(
+                "Failed to make {} to {} {}: {}",
+                start,
+                end,
+                if executable { "executable" } else { "writable" },
+                e
+            )
+        });
+    }
+}
+
+/// Add `[start, end)` to the sorted and disjoint ranges, and merge the ranges that overlap or are adjacent.
+fn add_range(ranges: &mut Vec<(Address, Address)>, start: Address, end: Address) {
+    if start == end {
+        return;
+    }
+    ranges.push((start, end));
+    ranges.sort_unstable();
+    let mut merged: Vec<(Address, Address)> = Vec::with_capacity(ranges.len());
+    for &(s, e) in ranges.iter() {
+        match merged.last_mut() {
+            Some(last) if s <= last.1 => last.1 = last.1.max(e),
+            _ => merged.push((s, e)),
+        }
+    }
+    *ranges = merged;
+}
+
+/// Remove `[start, end)` from the sorted and disjoint ranges.
+fn remove_range(ranges: &mut Vec<(Address, Address)>, start: Address, end: Address) {
+    let mut remaining = Vec::with_capacity(ranges.len() + 1);
+    for &(s, e) in ranges.iter() {
+        if e <= start || s >= end {
+            remaining.push((s, e));
+            continue;
+        }
+        if s < start {
+            remaining.push((s, start));
+        }
+        if e > end {
+            remaining.push((end, e));
+        }
+    }
+    *ranges = remaining;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: usize, end: usize) -> (Address, Address) {
+        unsafe { (Address::from_usize(start), Address::from_usize(end)) }
+    }
+
+    #[test]
+    fn add_and_merge_ranges() {
+        let mut ranges = vec![];
+        let (s, e) = range(0x3000, 0x4000);
+        add_range(&mut ranges, s, e);
+        let (s, e) = range(0x1000, 0x2000);
+        add_range(&mut ranges, s, e);
+        assert_eq!(ranges, vec![range(0x1000, 0x2000), range(0x3000, 0x4000)]);
+        // Adjacent to both ranges
+        let (s, e) = range(0x2000, 0x3000);
+        add_range(&mut ranges, s, e);
+        assert_eq!(ranges, vec![range(0x1000, 0x4000)]);
+    }
+
+    #[test]
+    fn remove_ranges() {
+        let mut ranges = vec![range(0x1000, 0x4000), range(0x5000, 0x6000)];
+        let (s, e) = range(0x2000, 0x3000);
+        remove_range(&mut ranges, s, e);
+        assert_eq!(
+            ranges,
+            vec![
+                range(0x1000, 0x2000),
+                range(0x3000, 0x4000),
+                range(0x5000, 0x6000)
+            ]
+        );
+        let (s, e) = range(0x3000, 0x6000);
+        remove_range(&mut ranges, s, e);
+        assert_eq!(ranges, vec![range(0x1000, 0x2000)]);
+    }
+}
@@ -8,6 +8,7 @@ use crate::policy::copyspace::CopySpaceCopyContext;
 use crate::policy::immix::ImmixSpace;
 use crate::policy::immix::{ImmixCopyContext, ImmixHybridCopyContext};
 use crate::policy::space::Space;
+use crate::util::metadata::field_log_bit;
 use crate::util::object_forwarding;
 use crate::util::opaque_pointer::VMWorkerThread;
 use crate::util::{Address, ObjectReference};
@@ -115,6 +116,10 @@ impl<VM: VMBinding> GCWorkerCopyContext<VM> {
             VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC
                 .mark_byte_as_unlogged::<VM>(object, Ordering::Relaxed);
         }
+        if semantics.is_mature() && self.config.constraints.needs_field_log_bit {
+            // If the plan uses field log bits, we set the field log bits for all the fields of the object
+            field_log_bit::mark_fields_as_unlogged::<VM>(object, bytes);
+        }
         // Policy specific post copy.
         match self.config.copy_mapping[semantics] {
             CopySelector::CopySpace(index) => {
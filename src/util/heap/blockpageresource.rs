@@ -54,7 +54,9 @@ impl<VM: VMBinding, B: Region> PageResource<VM> for BlockPageResource<VM, B> {
 
 impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
     /// Block granularity in pages
-    const LOG_PAGES: usize = B::LOG_BYTES - LOG_BYTES_IN_PAGE as usize;
+    fn log_pages() -> usize {
+        B::log_bytes() - LOG_BYTES_IN_PAGE as usize
+    }
 
     pub fn new_contiguous(
         log_pages: usize,
@@ -114,7 +116,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         // 3. Push all remaining blocks to one or more block lists
         let last_block = start + BYTES_IN_CHUNK;
         let mut array = BlockQueue::new();
-        let mut cursor = start + B::BYTES;
+        let mut cursor = start + B::bytes();
         while cursor < last_block {
             let result = unsafe { array.push_relaxed(B::from_aligned_address(cursor)) };
             if let Err(block) = result {
@@ -123,7 +125,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
                 let result2 = unsafe { array.push_relaxed(block) };
                 debug_assert!(result2.is_ok());
             }
-            cursor += B::BYTES;
+            cursor += B::bytes();
         }
         debug_assert!(!array.is_empty());
         // 4. Push the block list to the global pool
@@ -146,7 +148,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         tls: VMThread,
     ) -> Result<PRAllocResult, PRAllocFail> {
         debug_assert_eq!(reserved_pages, required_pages);
-        debug_assert_eq!(reserved_pages, 1 << Self::LOG_PAGES);
+        debug_assert_eq!(reserved_pages, 1 << Self::log_pages());
         // Fast allocate from the blocks list
         if let Some(block) = self.block_queue.pop() {
             self.commit_pages(reserved_pages, required_pages, tls);
@@ -162,7 +164,7 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
 
     pub fn release_block(&self, block: B) {
         debug_assert!(self.common().contiguous);
-        let pages = 1 << Self::LOG_PAGES;
+        let pages = 1 << Self::log_pages();
         debug_assert!(pages as usize <= self.common().accounting.get_committed_pages());
         self.common().accounting.release(pages as _);
         self.block_queue.push(block)
@@ -172,6 +174,34 @@ impl<VM: VMBinding, B: Region> BlockPageResource<VM, B> {
         self.block_queue.flush_all()
         // TODO: For 32-bit space, we may want to free some contiguous chunks.
     }
+
+    /// Remove the free blocks that satisfy the predicate from the block pool so that they cannot
+    /// be allocated, and return them. This should only be called during GC, when no other thread
+    /// is allocating or releasing blocks.
+    pub fn take_free_blocks(&self, f: impl Fn(B) -> bool) -> Vec<B> {
+        let _guard = self.sync.lock().unwrap();
+        self.block_queue.take_blocks(f)
+    }
+
+    /// Return the free blocks taken by `take_free_blocks` to the block pool.
+    pub fn return_free_blocks(&self, blocks: Vec<B>) {
+        let _guard = self.sync.lock().unwrap();
+        self.block_queue.add_blocks(blocks)
+    }
+
+    /// Release a chunk that was allocated by this page resource, so its address range can be
+    /// reused and its memory can be returned to the OS. All the blocks in the chunk must have
+    /// been released with `release_block`, and taken out of the pool with `take_free_blocks`.
+    pub fn release_free_chunk(&self, chunk: Address) {
+        debug_assert!(chunk.is_aligned_to(BYTES_IN_CHUNK));
+        let _guard = self.sync.lock().unwrap();
+        // Replace the chunk with fresh pages so the OS can reclaim the physical memory.
+        // The chunk stays mapped, and reads as zero when it is reused.
+        if let Err(e) = unsafe { crate::util::memory::dzmmap(chunk, BYTES_IN_CHUNK) } {
+            panic!("Failed to release the memory of chunk {}: {}", chunk, e);
+        }
+        self.flpr.release_one_chunk_no_commit(chunk);
+    }
 }
 
 /// A block list that supports fast lock-free push/pop operations
@@ -194,6 +224,17 @@ impl<B: Region> BlockQueue<B> {
 impl<B: Region> BlockQueue<B> {
     const CAPACITY: usize = 256;
 
+    /// Create an array with the given blocks.
+    fn from_blocks(blocks: &[B]) -> Self {
+        debug_assert!(blocks.len() <= Self::CAPACITY);
+        let array = Self::new();
+        for block in blocks {
+            let result = unsafe { array.push_relaxed(*block) };
+            debug_assert!(result.is_ok());
+        }
+        array
+    }
+
     /// Get an entry
     fn get_entry(&self, i: usize) -> B {
         unsafe { (*self.data.get())[i] }
@@ -378,6 +419,41 @@ impl<B: Region> BlockPool<B> {
         }
     }
 
+    /// Remove all the blocks that satisfy the predicate from the pool, and return them.
+    /// The pool should not be accessed by other threads at the same time.
+    pub fn take_blocks(&self, f: impl Fn(B) -> bool) -> Vec<B> {
+        self.flush_all();
+        let mut head_global_freed_blocks = self.head_global_freed_blocks.write();
+        let mut global_freed_blocks = self.global_freed_blocks.write();
+        let mut taken = vec![];
+        let mut kept = vec![];
+        for array in head_global_freed_blocks
+            .take()
+            .into_iter()
+            .chain(global_freed_blocks.drain(..))
+        {
+            array.iterate_blocks(&mut |block| {
+                if f(block) {
+                    taken.push(block)
+                } else {
+                    kept.push(block)
+                }
+            });
+        }
+        self.count.fetch_sub(taken.len(), Ordering::SeqCst);
+        for blocks in kept.chunks(BlockQueue::<B>::CAPACITY) {
+            global_freed_blocks.push(BlockQueue::from_blocks(blocks));
+        }
+        taken
+    }
+
+    /// Add blocks to the global pool.
+    pub fn add_blocks(&self, blocks: Vec<B>) {
+        for blocks in blocks.chunks(BlockQueue::<B>::CAPACITY) {
+            self.add_global_array(BlockQueue::from_blocks(blocks));
+        }
+    }
+
     /// Get total number of blocks in the whole BlockQueue
     pub fn len(&self) -> usize {
         self.count.load(Ordering::SeqCst)
@@ -9,11 +9,13 @@ use std::ops::Range;
 
 /// Data structure to reference a MMTk 4 MB chunk.
 #[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Hash)]
 pub struct Chunk(Address);
 
 impl Region for Chunk {
-    const LOG_BYTES: usize = crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK;
+    fn log_bytes() -> usize {
+        Self::LOG_BYTES
+    }
 
     fn from_aligned_address(address: Address) -> Self {
         debug_assert!(address.is_aligned_to(Self::BYTES));
@@ -30,10 +32,15 @@ impl Chunk {
     // FIXME: We use this as an empty value. What if we actually use the first chunk?
     pub const ZERO: Self = Self(Address::ZERO);
 
+    /// The log of the chunk size in bytes.
+    pub const LOG_BYTES: usize = crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK;
+    /// The chunk size in bytes.
+    pub const BYTES: usize = 1 << Self::LOG_BYTES;
+
     /// Get an iterator for regions within this chunk.
     pub fn iter_region<R: Region>(&self) -> RegionIterator<R> {
         // R should be smaller than a chunk
-        debug_assert!(R::LOG_BYTES < Self::LOG_BYTES);
+        debug_assert!(R::log_bytes() < Self::LOG_BYTES);
         // R should be aligned to chunk boundary
         debug_assert!(R::is_aligned(self.start()));
         debug_assert!(R::is_aligned(self.end()));
@@ -241,8 +241,12 @@ impl<VM: VMBinding> FreeListPageResource<VM> {
         #[allow(clippy::cast_ref_to_mut)]
         let self_mut: &mut Self = unsafe { &mut *(self as *const _ as *mut _) };
         let mut sync = self.sync.lock().unwrap();
-        let page_offset =
-            self_mut.allocate_contiguous_chunks(space_descriptor, PAGES_IN_CHUNK, &mut sync);
+        // Reuse a chunk released by `release_one_chunk_no_commit` before growing the space.
+        let mut page_offset = self_mut.free_list.alloc(PAGES_IN_CHUNK as _);
+        if page_offset == freelist::FAILURE {
+            page_offset =
+                self_mut.allocate_contiguous_chunks(space_descriptor, PAGES_IN_CHUNK, &mut sync);
+        }
 
         if page_offset == freelist::FAILURE {
             return Result::Err(PRAllocFail);
@@ -310,6 +314,27 @@ impl<VM: VMBinding> FreeListPageResource<VM> {
         self.common.release_discontiguous_chunks(chunk);
     }
 
+    /// Release a chunk allocated by `allocate_one_chunk_no_commit`. The caller must have released
+    /// all the pages in the chunk from the page accounting.
+    pub(crate) fn release_one_chunk_no_commit(&self, chunk: Address) {
+        debug_assert!(conversions::chunk_align_down(chunk) == chunk);
+        let page_offset = conversions::bytes_to_pages(chunk - self.start);
+        debug_assert_eq!(
+            self.free_list.size(page_offset as _) as usize,
+            PAGES_IN_CHUNK
+        );
+        let mut sync = self.sync.lock().unwrap();
+        // FIXME
+        #[allow(clippy::cast_ref_to_mut)]
+        let me = unsafe { &mut *(self as *const _ as *mut Self) };
+        let freed = me.free_list.free(page_offset as _, true);
+        sync.pages_currently_on_freelist += PAGES_IN_CHUNK;
+        if !self.common.contiguous {
+            // only discontiguous spaces use chunks
+            me.release_free_chunks(chunk, freed as _, &mut sync);
+        }
+    }
+
     pub fn release_pages(&self, first: Address) {
         debug_assert!(conversions::is_page_aligned(first));
         let page_offset = conversions::bytes_to_pages(first - self.start);
@@ -43,6 +43,11 @@ impl<VM: VMBinding> GCTrigger<VM> {
         self.plan.write(plan);
     }
 
+    /// Get the plan of the MMTk instance that this GC trigger belongs to.
+    pub(crate) fn plan(&self) -> &'static dyn Plan<VM = VM> {
+        unsafe { self.plan.assume_init() }
+    }
+
     /// This method is called periodically by the allocation subsystem
     /// (by default, each time a page is consumed), and provides the
     /// collector with an opportunity to collect.
@@ -51,7 +56,7 @@ impl<VM: VMBinding> GCTrigger<VM> {
     /// * `space_full`: Space request failed, must recover pages within 'space'.
     /// * `space`: The space that triggered the poll. This could `None` if the poll is not triggered by a space.
     pub fn poll(&self, space_full: bool, space: Option<&dyn Space<VM>>) -> bool {
-        let plan = unsafe { self.plan.assume_init() };
+        let plan = self.plan();
         if self.policy.is_gc_required(space_full, space, plan) {
             info!(
                 "[POLL] {}{} ({}/{} pages)",
@@ -72,7 +77,7 @@ impl<VM: VMBinding> GCTrigger<VM> {
 
     /// Check if the heap is full
     pub fn is_heap_full(&self) -> bool {
-        let plan = unsafe { self.plan.assume_init() };
+        let plan = self.plan();
         self.policy.is_heap_full(plan)
     }
 }
@@ -1,9 +1,55 @@
 use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
 use crate::util::Address;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// The address ranges reserved by the spaces of all the MMTk instances in the process, as
+    /// sorted and disjoint `(start, end)` pairs. Each instance reserves its ranges from here, so
+    /// the instances own disjoint parts of the heap range.
+    static ref RESERVED_RANGES: Mutex<Vec<(Address, Address)>> = Mutex::new(unusable_ranges());
+}
+
+/// The ranges in the heap range that no space can use. They are always reserved.
+/// * The global side metadata of mmtk-core may be in the heap range. No space should overlap with
+///   it. See https://github.com/mmtk/mmtk-core/issues/458.
+/// * On 64-bit targets, the VM map finds the space of an address with the space index bits of the
+///   address, so the spaces must be below `MAX_SPACES` space extents. The last space extent of the
+///   heap range is above that.
+fn unusable_ranges() -> Vec<(Address, Address)> {
+    #[cfg(target_pointer_width = "64")]
+    {
+        use crate::util::heap::layout::heap_parameters::LOG_MAX_SPACES;
+        use crate::util::heap::layout::vm_layout_constants::{LOG_SPACE_EXTENT, MAX_SPACE_EXTENT};
+        use crate::util::metadata::side_metadata::{
+            GLOBAL_SIDE_METADATA_BASE_ADDRESS, GLOBAL_SIDE_METADATA_VM_BASE_ADDRESS,
+        };
+        let mut ranges = vec![];
+        // Spaces are aligned to their extent on 64-bit targets.
+        let start = GLOBAL_SIDE_METADATA_BASE_ADDRESS
+            .align_down(MAX_SPACE_EXTENT)
+            .max(HEAP_START);
+        let end = GLOBAL_SIDE_METADATA_VM_BASE_ADDRESS
+            .align_up(MAX_SPACE_EXTENT)
+            .min(HEAP_END);
+        if start < end {
+            ranges.push((start, end));
+        }
+        let indexable_end =
+            unsafe { Address::from_usize(1 << (LOG_MAX_SPACES + LOG_SPACE_EXTENT)) }.max(end);
+        if indexable_end < HEAP_END {
+            ranges.push((indexable_end, HEAP_END));
+        }
+        ranges
+    }
+    #[cfg(target_pointer_width = "32")]
+    vec![]
+}
 
 pub struct HeapMeta {
     pub heap_cursor: Address,
     pub heap_limit: Address,
+    /// The address ranges reserved by this heap.
+    reserved: Vec<(Address, Address)>,
 }
 
 impl HeapMeta {
@@ -11,28 +57,47 @@ impl HeapMeta {
         HeapMeta {
             heap_cursor: HEAP_START,
             heap_limit: HEAP_END,
+            reserved: vec![],
         }
     }
 
+    /// Reserve an address range of `extent` bytes. The range is the lowest free range in the heap
+    /// range, or the highest one if `top` is true. The range is not used by any other heap in the
+    /// process until this heap is released.
     pub fn reserve(&mut self, extent: usize, top: bool) -> Address {
-        let ret = if top {
-            self.heap_limit -= extent;
-            self.heap_limit
-        } else {
-            let start = self.heap_cursor;
-            self.heap_cursor += extent;
-            start
-        };
+        let mut reserved_ranges = RESERVED_RANGES.lock().unwrap();
+        let start = find_free_range(&reserved_ranges, extent, top).unwrap_or_else(|| {
+            panic!(
+                "Out of virtual address space for {} bytes in [{}, {}). Reserved ranges: {:?}",
+                extent, HEAP_START, HEAP_END, *reserved_ranges
+            )
+        });
+        let end = start + extent;
+        let index = reserved_ranges.partition_point(|r| r.0 < start);
+        reserved_ranges.insert(index, (start, end));
+        self.reserved.push((start, end));
 
+        if top {
+            self.heap_limit = self.heap_limit.min(start);
+        } else {
+            self.heap_cursor = self.heap_cursor.max(end);
+        }
         assert!(
             self.heap_cursor <= self.heap_limit,
             "Out of virtual address space at {} ({} > {})",
-            self.heap_cursor - extent,
+            start,
             self.heap_cursor,
             self.heap_limit
         );
 
-        ret
+        start
+    }
+
+    /// Release all the ranges reserved by this heap so that other heaps can reserve them. The heap
+    /// must not be used after this.
+    pub fn release(&self) {
+        let mut reserved_ranges = RESERVED_RANGES.lock().unwrap();
+        reserved_ranges.retain(|r| !self.reserved.contains(r));
     }
 
     pub fn get_discontig_start(&self) -> Address {
@@ -44,9 +109,60 @@ impl HeapMeta {
     }
 }
 
+/// Find a free range of `extent` bytes between the sorted and disjoint `reserved` ranges in the heap
+/// range. Return the lowest one, or the highest one if `top` is true.
+fn find_free_range(reserved: &[(Address, Address)], extent: usize, top: bool) -> Option<Address> {
+    // The gaps between the reserved ranges, from low to high.
+    let gaps = reserved
+        .iter()
+        .map(|r| r.0)
+        .chain(std::iter::once(HEAP_END))
+        .zip(std::iter::once(HEAP_START).chain(reserved.iter().map(|r| r.1)))
+        .map(|(end, start)| (start, end));
+    if top {
+        gaps.filter(|&(start, end)| end - start >= extent)
+            .last()
+            .map(|(_, end)| end - extent)
+    } else {
+        gaps.filter(|&(start, end)| end - start >= extent)
+            .map(|(start, _)| start)
+            .next()
+    }
+}
+
 // make clippy happy
 impl Default for HeapMeta {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_free_ranges() {
+        const EXTENT: usize = 1 << 22;
+        let reserved = vec![
+            (HEAP_START, HEAP_START + EXTENT),
+            (HEAP_START + 2 * EXTENT, HEAP_START + 3 * EXTENT),
+        ];
+        assert_eq!(
+            find_free_range(&reserved, EXTENT, false),
+            Some(HEAP_START + EXTENT)
+        );
+        assert_eq!(
+            find_free_range(&reserved, 2 * EXTENT, false),
+            Some(HEAP_START + 3 * EXTENT)
+        );
+        assert_eq!(
+            find_free_range(&reserved, EXTENT, true),
+            Some(HEAP_END - EXTENT)
+        );
+        assert_eq!(
+            find_free_range(&reserved, HEAP_END - HEAP_START, false),
+            None
+        );
+    }
+}
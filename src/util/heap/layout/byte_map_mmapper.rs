@@ -114,6 +114,29 @@ impl Mmapper for ByteMapMmapper {
             MapState::transition_to_protected(&self.mapped[chunk], mmap_start).unwrap();
         }
     }
+
+    fn mark_as_unmapped(&self, start: Address, bytes: usize) {
+        let start_chunk = Self::address_to_mmap_chunks_down(start);
+        let end_chunk = Self::address_to_mmap_chunks_up(start + bytes) - 1;
+        for i in start_chunk..=end_chunk {
+            self.mapped[i].store(MapState::Unmapped, Ordering::Relaxed);
+        }
+    }
+
+    fn unmap(&self, start: Address, pages: usize) -> Result<()> {
+        let start_chunk = Self::address_to_mmap_chunks_down(start);
+        let end_chunk = Self::address_to_mmap_chunks_up(start + pages_to_bytes(pages));
+        let _guard = self.lock.lock().unwrap();
+        MapState::bulk_transition_to_unmapped(
+            &self.mapped[start_chunk..end_chunk],
+            Self::mmap_chunks_to_address(start_chunk),
+        )
+    }
+
+    fn unmap_all(&self) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        MapState::bulk_transition_to_unmapped(&self.mapped, Address::ZERO)
+    }
 }
 
 impl ByteMapMmapper {
@@ -203,6 +203,65 @@ impl Mmapper for FragmentedMapper {
             start = high;
         }
     }
+
+    fn mark_as_unmapped(&self, mut start: Address, bytes: usize) {
+        let end = start + bytes;
+        // Iterate over the slabs covered
+        while start < end {
+            let high = if end > Self::slab_limit(start) && !Self::slab_limit(start).is_zero() {
+                Self::slab_limit(start)
+            } else {
+                end
+            };
+            let slab = Self::slab_align_down(start);
+            let start_chunk = Self::chunk_index(slab, start);
+            let end_chunk = Self::chunk_index(slab, conversions::mmap_chunk_align_up(high));
+
+            if let Some(mapped) = self.slab_table(start) {
+                for entry in mapped.iter().take(end_chunk).skip(start_chunk) {
+                    entry.store(MapState::Unmapped, Ordering::Relaxed);
+                }
+            }
+            start = high;
+        }
+    }
+
+    fn unmap(&self, mut start: Address, pages: usize) -> Result<()> {
+        let end = start + conversions::pages_to_bytes(pages);
+        // Iterate over the slabs covered
+        while start < end {
+            let high = if end > Self::slab_limit(start) && !Self::slab_limit(start).is_zero() {
+                Self::slab_limit(start)
+            } else {
+                end
+            };
+            let slab = Self::slab_align_down(start);
+            let start_chunk = Self::chunk_index(slab, start);
+            let end_chunk = Self::chunk_index(slab, conversions::mmap_chunk_align_up(high));
+
+            // Do not allocate slabs for the memory that has never been touched.
+            if let Some(mapped) = self.slab_table(start) {
+                let _guard = self.lock.lock().unwrap();
+                MapState::bulk_transition_to_unmapped(
+                    &mapped[start_chunk..end_chunk],
+                    Self::chunk_index_to_address(slab, start_chunk),
+                )?;
+            }
+            start = high;
+        }
+        Ok(())
+    }
+
+    fn unmap_all(&self) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        for (base, slab) in self.slab_map.iter().zip(self.slab_table.iter()) {
+            if let Some(slab) = slab {
+                debug_assert_ne!(*base, SENTINEL);
+                MapState::bulk_transition_to_unmapped(&slab[..], *base)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl FragmentedMapper {
@@ -550,4 +609,75 @@ mod tests {
             )
         })
     }
+
+    #[test]
+    fn unmap() {
+        serial_test(|| {
+            with_cleanup(
+                || {
+                    // map 2 chunks, and unmap the first one
+                    let mmapper = FragmentedMapper::new();
+                    let pages_per_chunk = MMAP_CHUNK_BYTES >> LOG_BYTES_IN_PAGE as usize;
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, pages_per_chunk * 2)
+                        .unwrap();
+                    mmapper.unmap(FIXED_ADDRESS, pages_per_chunk).unwrap();
+
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS),
+                        Some(MapState::Unmapped)
+                    );
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS + MMAP_CHUNK_BYTES),
+                        Some(MapState::Mapped)
+                    );
+                },
+                || {
+                    memory::munmap(FIXED_ADDRESS, MAX_BYTES).unwrap();
+                },
+            )
+        })
+    }
+
+    #[test]
+    fn unmap_all() {
+        serial_test(|| {
+            with_cleanup(
+                || {
+                    // map 1 chunk, quarantine 1 chunk, and protect the mapped chunk
+                    let mmapper = FragmentedMapper::new();
+                    let pages_per_chunk = MMAP_CHUNK_BYTES >> LOG_BYTES_IN_PAGE as usize;
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, pages_per_chunk)
+                        .unwrap();
+                    mmapper
+                        .quarantine_address_range(FIXED_ADDRESS + MMAP_CHUNK_BYTES, pages_per_chunk)
+                        .unwrap();
+                    mmapper.protect(FIXED_ADDRESS, pages_per_chunk);
+
+                    mmapper.unmap_all().unwrap();
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS),
+                        Some(MapState::Unmapped)
+                    );
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS + MMAP_CHUNK_BYTES),
+                        Some(MapState::Unmapped)
+                    );
+
+                    // The memory can be mapped again without replacing an existing mapping.
+                    mmapper
+                        .ensure_mapped(FIXED_ADDRESS, pages_per_chunk)
+                        .unwrap();
+                    assert_eq!(
+                        get_chunk_map_state(&mmapper, FIXED_ADDRESS),
+                        Some(MapState::Mapped)
+                    );
+                },
+                || {
+                    memory::munmap(FIXED_ADDRESS, MAX_BYTES).unwrap();
+                },
+            )
+        })
+    }
 }
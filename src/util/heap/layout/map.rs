@@ -53,4 +53,11 @@ pub trait VMMap: Sync {
     fn get_descriptor_for_address(&self, address: Address) -> SpaceDescriptor;
 
     fn add_to_cumulative_committed_pages(&self, pages: usize);
+
+    /// Reset the map to its initial state so that spaces can be created again. The memory used by
+    /// the free lists created by the map is unmapped.
+    ///
+    /// # Safety
+    /// The caller must make sure that no space or page resource uses the map or its free lists any more.
+    unsafe fn reset(&self);
 }
@@ -253,6 +253,11 @@ impl VMMap for Map32 {
         self.cumulative_committed_pages
             .fetch_add(pages, Ordering::Relaxed);
     }
+
+    unsafe fn reset(&self) {
+        // The free lists are backed by ordinary vectors, and there is no memory to unmap.
+        *self.mut_self() = Self::new();
+    }
 }
 
 impl Map32 {
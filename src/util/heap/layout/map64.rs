@@ -217,6 +217,13 @@ impl VMMap for Map64 {
         self.cumulative_committed_pages
             .fetch_add(pages, Ordering::Relaxed);
     }
+
+    unsafe fn reset(&self) {
+        for fl in self.fl_map.iter().flatten() {
+            fl.unmap();
+        }
+        *self.mut_self() = Self::new();
+    }
 }
 
 impl Map64 {
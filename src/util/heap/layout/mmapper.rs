@@ -58,6 +58,28 @@ pub trait Mmapper: Sync {
     /// * `start`: Address of the first page to be protected
     /// * `pages`: Number of pages to be protected
     fn protect(&self, start: Address, pages: usize);
+
+    /// Mark a number of pages as unmapped, without making any request to the operating system.
+    /// This is the reverse of `mark_as_mapped()`, and is used to forget the pages that the VM
+    /// mapped so that they are not unmapped by `unmap_all()`.
+    ///
+    /// Arguments:
+    /// * `start`: Address of the first page to be marked
+    /// * `bytes`: Number of bytes to be marked as unmapped
+    fn mark_as_unmapped(&self, start: Address, bytes: usize);
+
+    /// Unmap the chunks in the range that are quarantined, mapped or protected by this mmapper, and
+    /// mark them as unmapped. Note that unmapping occurs at chunk granularity, not page granularity.
+    ///
+    /// Arguments:
+    /// * `start`: The start of the range to be unmapped.
+    /// * `pages`: The size of the range to be unmapped, in pages
+    fn unmap(&self, start: Address, pages: usize) -> Result<()>;
+
+    /// Unmap all the memory that is quarantined, mapped or protected by this mmapper, and mark it
+    /// as unmapped. This is used when an MMTk instance is shut down, and the memory must not be
+    /// accessed afterwards.
+    fn unmap_all(&self) -> Result<()>;
 }
 
 /// The mmap state of a mmap chunk.
@@ -193,6 +215,40 @@ impl MapState {
         Ok(())
     }
 
+    /// Unmap all the chunks of `states` that are not `MapState::Unmapped`, and transition them to
+    /// `MapState::Unmapped`. The caller should hold a lock before invoking this method.
+    ///
+    /// The memory region to transition starts from `mmap_start`, and each element of `states`
+    /// governs a chunk.
+    pub(super) fn bulk_transition_to_unmapped(
+        states: &[Atomic<MapState>],
+        mmap_start: Address,
+    ) -> Result<()> {
+        let mut start_index = 0;
+
+        for group in states
+            .iter()
+            .revisitable_group_by(|s| s.load(Ordering::Relaxed) == MapState::Unmapped)
+        {
+            let end_index = start_index + group.len;
+
+            if !group.key {
+                let start_addr = mmap_start + MMAP_CHUNK_BYTES * start_index;
+                let end_addr = mmap_start + MMAP_CHUNK_BYTES * end_index;
+                trace!("Trying to unmap {} - {}", start_addr, end_addr);
+                munmap(start_addr, end_addr - start_addr)?;
+
+                for state in group {
+                    state.store(MapState::Unmapped, Ordering::Relaxed);
+                }
+            }
+
+            start_index = end_index;
+        }
+
+        Ok(())
+    }
+
     /// Check the current MapState of the chunk, and transition the chunk to MapState::Protected.
     /// The caller should hold a lock before invoking this method.
     pub(super) fn transition_to_protected(
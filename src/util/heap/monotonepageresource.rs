@@ -2,7 +2,8 @@ use super::layout::vm_layout_constants::{BYTES_IN_CHUNK, PAGES_IN_CHUNK};
 use crate::policy::space::required_chunks;
 use crate::util::address::Address;
 use crate::util::conversions::*;
-use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::Ordering;
+use std::sync::{Condvar, Mutex, MutexGuard};
 
 use crate::util::alloc::embedded_meta_data::*;
 use crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK;
@@ -16,12 +17,64 @@ use crate::util::heap::space_descriptor::SpaceDescriptor;
 use crate::vm::VMBinding;
 use std::marker::PhantomData;
 
+/// The zeroing thread zeroes this many bytes at a time, and allocation may proceed after each block is zeroed.
+const CONCURRENT_ZEROING_BLOCK_BYTES: usize = 64 * 1024;
+
 pub struct MonotonePageResource<VM: VMBinding> {
     common: CommonPageResource,
     sync: Mutex<MonotonePageResourceSync>,
+    /// The state of concurrent zeroing. This is only used for contiguous page resources.
+    zeroing: Mutex<ConcurrentZeroingState>,
+    /// Notified when the zeroing state changes.
+    zeroing_cond: Condvar,
     _p: PhantomData<VM>,
 }
 
+struct ConcurrentZeroingState {
+    /** Current frontier of zeroing, in a separate zeroing thread */
+    cursor: Address,
+    /** Current limit of zeroing.  If cursor < sentinel, zeroing is still happening. Pages above it have never been allocated. */
+    sentinel: Address,
+    /// The retained regions are not zeroed.
+    retained: Vec<(Address, Address)>,
+    /// Is the zeroing thread zeroing a block without holding the lock?
+    busy: bool,
+    /// Is the zeroing thread running? If not, the allocator zeroes pages by itself.
+    running: bool,
+    /// Has the zeroing thread been asked to exit?
+    exit_requested: bool,
+}
+
+impl ConcurrentZeroingState {
+    fn new(start: Address) -> Self {
+        Self {
+            cursor: start,
+            sentinel: start,
+            retained: vec![],
+            busy: false,
+            running: false,
+            exit_requested: false,
+        }
+    }
+
+    /// Move the cursor past the retained regions, and get the next block below `limit` to zero.
+    fn next_block(&mut self, limit: Address) -> Option<(Address, Address)> {
+        for &(start, end) in self.retained.iter() {
+            if start <= self.cursor && self.cursor < end {
+                self.cursor = end;
+            }
+        }
+        if self.cursor >= limit {
+            return None;
+        }
+        let mut end = (self.cursor + CONCURRENT_ZEROING_BLOCK_BYTES).min(limit);
+        if let Some(&(start, _)) = self.retained.iter().find(|(start, _)| *start > self.cursor) {
+            end = end.min(start);
+        }
+        Some((self.cursor, end))
+    }
+}
+
 struct MonotonePageResourceSync {
     /** Pointer to the next block to be allocated. */
     cursor: Address,
@@ -30,16 +83,13 @@ struct MonotonePageResourceSync {
     /** Base address of the current chunk of addresses */
     current_chunk: Address,
     conditional: MonotonePageResourceConditional,
+    /// Page-aligned address ranges (sorted and disjoint) that are not released by [`MonotonePageResource::reset`],
+    /// and are skipped by allocation. This is only supported for contiguous page resources.
+    retained: Vec<(Address, Address)>,
 }
 
 pub enum MonotonePageResourceConditional {
-    Contiguous {
-        start: Address,
-        /** Current frontier of zeroing, in a separate zeroing thread */
-        zeroing_cursor: Address,
-        /** Current limit of zeroing.  If zeroingCursor < zeroingSentinel, zeroing is still happening. */
-        zeroing_sentinel: Address,
-    },
+    Contiguous { start: Address },
     Discontiguous,
 }
 impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
@@ -58,12 +108,69 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
     fn get_available_physical_pages(&self) -> usize {
         let sync = self.sync.lock().unwrap();
         let mut rtn = bytes_to_pages(sync.sentinel - sync.cursor);
+        for &(start, end) in sync.retained.iter() {
+            if end > sync.cursor {
+                rtn -= bytes_to_pages(end - start.max(sync.cursor));
+            }
+        }
         if !self.common.contiguous {
             rtn += self.common.vm_map.get_available_discontiguous_chunks() * PAGES_IN_CHUNK;
         }
         rtn
     }
 
+    fn update_zeroing_approach(&self, nontemporal: bool, concurrent: bool) {
+        assert!(
+            !concurrent || self.common.contiguous,
+            "Concurrent zeroing is only supported for contiguous MonotonePageResource"
+        );
+        self.common
+            .zero_nontemporal
+            .store(nontemporal, Ordering::SeqCst);
+        self.common
+            .zero_concurrent
+            .store(concurrent, Ordering::SeqCst);
+    }
+
+    fn skip_concurrent_zeroing(&self) {
+        let mut state = self.zeroing.lock().unwrap();
+        state.cursor = state.sentinel;
+        self.zeroing_cond.notify_all();
+    }
+
+    fn trigger_concurrent_zeroing(&self) {
+        let _state = self.zeroing.lock().unwrap();
+        self.zeroing_cond.notify_all();
+    }
+
+    fn concurrent_zeroing(&self) {
+        let mut state = self.zeroing.lock().unwrap();
+        state.running = true;
+        while !state.exit_requested {
+            let sentinel = state.sentinel;
+            match state.next_block(sentinel) {
+                Some((start, end)) => {
+                    state.busy = true;
+                    drop(state);
+                    self.zero(start, end - start);
+                    state = self.zeroing.lock().unwrap();
+                    state.busy = false;
+                    state.cursor = end;
+                    self.zeroing_cond.notify_all();
+                }
+                None => state = self.zeroing_cond.wait(state).unwrap(),
+            }
+        }
+        state.running = false;
+        self.zeroing_cond.notify_all();
+    }
+
+    fn request_zeroing_exit(&self, exit: bool) {
+        let mut state = self.zeroing.lock().unwrap();
+        state.exit_requested = exit;
+        self.zeroing_cond.notify_all();
+    }
+
     fn alloc_pages(
         &self,
         space_descriptor: SpaceDescriptor,
@@ -108,6 +215,14 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
         let mut tmp = sync.cursor + bytes;
         debug!("tmp={:?}", tmp);
 
+        // Skip the retained regions. They are sorted, so one pass is enough.
+        for &(start, end) in sync.retained.iter() {
+            if start < tmp && end > rtn {
+                rtn = end;
+                tmp = rtn + bytes;
+            }
+        }
+
         if !self.common().contiguous && tmp > sync.sentinel {
             /* we're out of virtual memory within our discontiguous region, so ask for more */
             let required_chunks = required_chunks(required_pages);
@@ -127,7 +242,7 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
             new_chunk = true;
         }
 
-        debug_assert!(rtn >= sync.cursor && rtn < sync.cursor + bytes);
+        debug_assert!(rtn >= sync.cursor && rtn < tmp);
         if tmp > sync.sentinel {
             //debug!("tmp={:?} > sync.sentinel={:?}", tmp, sync.sentinel);
             Result::Err(PRAllocFail)
@@ -141,6 +256,9 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
                 sync.current_chunk = chunk_align_down(sync.cursor);
             }
             self.commit_pages(reserved_pages, required_pages, tls);
+            if self.common().zero_concurrent.load(Ordering::Relaxed) {
+                self.wait_for_zeroing(tmp);
+            }
 
             Result::Ok(PRAllocResult {
                 start: rtn,
@@ -161,12 +279,11 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
                 cursor: start,
                 current_chunk: chunk_align_down(start),
                 sentinel,
-                conditional: MonotonePageResourceConditional::Contiguous {
-                    start,
-                    zeroing_cursor: sentinel,
-                    zeroing_sentinel: start,
-                },
+                conditional: MonotonePageResourceConditional::Contiguous { start },
+                retained: vec![],
             }),
+            zeroing: Mutex::new(ConcurrentZeroingState::new(start)),
+            zeroing_cond: Condvar::new(),
             _p: PhantomData,
         }
     }
@@ -179,16 +296,78 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
                 current_chunk: unsafe { Address::zero() },
                 sentinel: unsafe { Address::zero() },
                 conditional: MonotonePageResourceConditional::Discontiguous,
+                retained: vec![],
             }),
+            zeroing: Mutex::new(ConcurrentZeroingState::new(unsafe { Address::zero() })),
+            zeroing_cond: Condvar::new(),
             _p: PhantomData,
         }
     }
 
+    fn zero(&self, start: Address, bytes: usize) {
+        if self.common.zero_nontemporal.load(Ordering::Relaxed) {
+            crate::util::memory::zero_nontemporal(start, bytes);
+        } else {
+            crate::util::memory::zero(start, bytes);
+        }
+    }
+
+    /// Wait until the pages below `end` are zeroed by the zeroing thread. If the zeroing thread is
+    /// not running, zero the pages in the current thread.
+    fn wait_for_zeroing(&self, end: Address) {
+        let mut state = self.zeroing.lock().unwrap();
+        loop {
+            let limit = end.min(state.sentinel);
+            if state.cursor >= limit {
+                return;
+            }
+            if !state.running && !state.busy {
+                while let Some((start, end)) = state.next_block(limit) {
+                    self.zero(start, end - start);
+                    state.cursor = end;
+                }
+                return;
+            }
+            state = self.zeroing_cond.wait(state).unwrap();
+        }
+    }
+
     /// Get highwater mark of current monotone space.
     pub fn cursor(&self) -> Address {
         self.sync.lock().unwrap().cursor
     }
 
+    /// Get the end of the address range that may contain allocated memory. This is the cursor, or the
+    /// end of the last retained region if it is beyond the cursor.
+    pub fn allocated_end(&self) -> Address {
+        let sync = self.sync.lock().unwrap();
+        match sync.retained.last() {
+            Some(&(_, end)) if end > sync.cursor => end,
+            _ => sync.cursor,
+        }
+    }
+
+    /// Set the regions to retain at the next [`MonotonePageResource::reset`]. The memory in the regions
+    /// stays committed, and allocation after the reset skips them. The regions are rounded to pages.
+    /// The previously retained regions are forgotten.
+    pub fn set_retained_regions(&self, mut regions: Vec<(Address, Address)>) {
+        assert!(
+            self.common.contiguous || regions.is_empty(),
+            "Retaining regions is only supported for contiguous MonotonePageResource"
+        );
+        regions.sort_unstable();
+        let mut retained: Vec<(Address, Address)> = Vec::with_capacity(regions.len());
+        for (start, end) in regions {
+            let start = start.align_down(crate::util::constants::BYTES_IN_PAGE);
+            let end = end.align_up(crate::util::constants::BYTES_IN_PAGE);
+            match retained.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => retained.push((start, end)),
+            }
+        }
+        self.sync.lock().unwrap().retained = retained;
+    }
+
     fn log_chunk_fields(&self, space_descriptor: SpaceDescriptor, site: &str) {
         let sync = self.sync.lock().unwrap();
         debug!(
@@ -210,6 +389,12 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
     pub unsafe fn reset(&self) {
         let mut guard = self.sync.lock().unwrap();
         self.common().accounting.reset();
+        let retained_pages: usize = guard
+            .retained
+            .iter()
+            .map(|&(start, end)| bytes_to_pages(end - start))
+            .sum();
+        self.common().accounting.reserve_and_commit(retained_pages);
         self.release_pages(&mut guard);
         drop(guard);
     }
@@ -273,12 +458,28 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
     }
 
     unsafe fn release_pages(&self, guard: &mut MutexGuard<MonotonePageResourceSync>) {
-        // TODO: concurrent zeroing
         if self.common().contiguous {
-            guard.cursor = match guard.conditional {
+            let start = match guard.conditional {
                 MonotonePageResourceConditional::Contiguous { start: _start, .. } => _start,
                 _ => unreachable!(),
             };
+            if self.common().zero_concurrent.load(Ordering::Relaxed) {
+                // Zero the released pages (up to the highest page that has ever been allocated) ahead of the allocator.
+                let mut state = self.zeroing.lock().unwrap();
+                while state.busy {
+                    state = self.zeroing_cond.wait(state).unwrap();
+                }
+                let allocated_end = match guard.retained.last() {
+                    Some(&(_, end)) if end > guard.cursor => end,
+                    _ => guard.cursor,
+                };
+                state.sentinel = state.sentinel.max(allocated_end);
+                state.cursor = start;
+                state.retained = guard.retained.clone();
+                drop(state);
+                self.trigger_concurrent_zeroing();
+            }
+            guard.cursor = start;
         } else if !guard.cursor.is_zero() {
             let bytes = guard.cursor - guard.current_chunk;
             self.release_pages_extent(guard.current_chunk, bytes);
@@ -302,7 +503,11 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
         let sync = self.sync.lock().unwrap();
         match sync.conditional {
             MonotonePageResourceConditional::Contiguous { start, .. } => {
-                let end = sync.cursor.align_up(BYTES_IN_CHUNK);
+                let end = match sync.retained.last() {
+                    Some(&(_, end)) if end > sync.cursor => end,
+                    _ => sync.cursor,
+                }
+                .align_up(BYTES_IN_CHUNK);
                 f(start, end - start);
             }
             MonotonePageResourceConditional::Discontiguous => {
@@ -2,6 +2,7 @@ use crate::util::address::Address;
 use crate::util::conversions;
 use crate::util::opaque_pointer::*;
 use crate::vm::ActivePlan;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
 use super::layout::VMMap;
@@ -34,23 +35,41 @@ pub trait PageResource<VM: VMBinding>: 'static {
         self.common().accounting.clear_reserved(reserved_pages);
     }
 
-    fn update_zeroing_approach(&self, _nontemporal: bool, concurrent: bool) {
-        debug_assert!(!concurrent || self.common().contiguous);
-        unimplemented!()
+    /// Set how the pages are zeroed if the space needs zeroed pages. With `nontemporal`, pages are
+    /// zeroed with non-temporal stores. With `concurrent`, released pages are zeroed by a zeroing
+    /// thread ahead of the allocator (see [`PageResource::concurrent_zeroing`]). This should be
+    /// called before the GC threads are spawned.
+    fn update_zeroing_approach(&self, nontemporal: bool, concurrent: bool) {
+        assert!(
+            !concurrent,
+            "This PageResource does not implement concurrent zeroing"
+        );
+        self.common()
+            .zero_nontemporal
+            .store(nontemporal, Ordering::SeqCst);
     }
 
+    /// Treat the released pages as zeroed, and do not zero them concurrently.
     fn skip_concurrent_zeroing(&self) {
-        unimplemented!()
+        panic!("This PageResource does not implement concurrent zeroing")
     }
 
+    /// Start zeroing the released pages in the zeroing thread.
     fn trigger_concurrent_zeroing(&self) {
-        unimplemented!()
+        panic!("This PageResource does not implement concurrent zeroing")
     }
 
+    /// The main loop of the zeroing thread. This does not return until the exit is requested by
+    /// [`PageResource::request_zeroing_exit`].
     fn concurrent_zeroing(&self) {
         panic!("This PageResource does not implement concurrent zeroing")
     }
 
+    /// Ask the zeroing thread to exit (or clear the request if `exit` is false).
+    fn request_zeroing_exit(&self, _exit: bool) {
+        panic!("This PageResource does not implement concurrent zeroing")
+    }
+
     fn alloc_pages(
         &self,
         space_descriptor: SpaceDescriptor,
@@ -123,6 +142,11 @@ pub struct CommonPageResource {
 
     pub vm_map: &'static dyn VMMap,
     head_discontiguous_region: Mutex<Address>,
+
+    /// Zero pages with non-temporal stores.
+    pub zero_nontemporal: AtomicBool,
+    /// Zero released pages in a zeroing thread. The allocated pages are already zeroed.
+    pub zero_concurrent: AtomicBool,
 }
 
 impl CommonPageResource {
@@ -135,6 +159,9 @@ impl CommonPageResource {
             vm_map,
 
             head_discontiguous_region: Mutex::new(Address::ZERO),
+
+            zero_nontemporal: AtomicBool::new(false),
+            zero_concurrent: AtomicBool::new(false),
         }
     }
 
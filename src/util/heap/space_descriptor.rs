@@ -82,6 +82,12 @@ impl SpaceDescriptor {
         ret
     }
 
+    /// Reset the index for discontiguous spaces so that the spaces created afterwards get the
+    /// same descriptors as the first spaces. This is used when an MMTk instance is shut down.
+    pub(crate) fn reset_discontiguous_space_index() {
+        DISCONTIGUOUS_SPACE_INDEX.store(DISCONTIG_INDEX_INCREMENT, Ordering::Relaxed);
+    }
+
     pub fn is_empty(self) -> bool {
         self.0 == SpaceDescriptor::UNINITIALIZED.0
     }
@@ -0,0 +1,474 @@
+//! Heap snapshots for building boot images.
+//!
+//! A binding can run its initialization once, allocating the objects it wants to keep with the
+//! `Immortal` or the `NonMoving` semantics, and save those objects into a snapshot file with
+//! [`crate::memory_manager::save_heap_snapshot`]. In a later run, the binding loads the file with
+//! [`crate::memory_manager::load_heap_snapshot`], and the objects are mapped into the VM space
+//! and are usable immediately.
+//!
+//! A snapshot file consists of:
+//! * A header: the magic bytes, the format version, and the number of regions, objects and
+//!   relocations.
+//! * The region table: the original start address and the size of each region.
+//! * The contents of each region. The regions are laid out contiguously in the image, in the
+//!   order of the region table.
+//! * The objects: the offset of each object reference in the image. This is the VO bits of the
+//!   saved regions. Other object metadata, such as mark bits and log bits, is initialized by the
+//!   VM space when the objects are loaded.
+//! * The relocations: the offset of each reference slot in the image, and the offset of the
+//!   object it refers to. The slots are rewritten when the image is loaded at a different address.
+//!
+//! All the numbers are stored as little-endian 64-bit integers. A slot that needs relocation must
+//! hold the raw value of the object reference (i.e. [`crate::util::ObjectReference::value`]) in
+//! a word, which is the case for [`crate::vm::edge_shape::SimpleEdge`].
+
+use crate::mmtk::MMTK;
+use crate::policy::sft::SFT;
+use crate::util::constants::{BYTES_IN_PAGE, BYTES_IN_WORD};
+use crate::util::conversions::raw_align_up;
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::linear_scan::{DefaultObjectSize, ObjectIterator};
+use crate::util::{Address, ObjectReference, VMWorkerThread};
+use crate::vm::edge_shape::Edge;
+use crate::vm::{ObjectModel, Scanning, VMBinding};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+const MAGIC: [u8; 8] = *b"MMTKHEAP";
+const VERSION: u64 = 1;
+
+/// A contiguous memory region in a heap snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotRegion {
+    /// The start address of the region when the snapshot was saved.
+    pub original_start: Address,
+    /// The start address of the region after the snapshot is loaded.
+    pub start: Address,
+    /// The size of the region in bytes.
+    pub bytes: usize,
+}
+
+/// A heap snapshot that has been loaded into the VM space.
+#[derive(Debug)]
+pub struct HeapSnapshot {
+    /// The start address of the loaded image.
+    pub start: Address,
+    /// The size of the loaded image in bytes.
+    pub bytes: usize,
+    /// The regions in the image.
+    pub regions: Vec<SnapshotRegion>,
+    /// All the objects in the image.
+    pub objects: Vec<ObjectReference>,
+}
+
+impl HeapSnapshot {
+    /// Translate a reference to an object when the snapshot was saved to the reference to the
+    /// same object in the loaded image. The binding can use this to find its roots in the image.
+    /// Return `None` if the object was not saved in the snapshot.
+    pub fn relocate<VM: VMBinding>(&self, object: ObjectReference) -> Option<ObjectReference> {
+        let object_start = object.to_object_start::<VM>();
+        self.regions
+            .iter()
+            .find(|r| object_start >= r.original_start && object_start < r.original_start + r.bytes)
+            .map(|r| {
+                ObjectReference::from_raw_address(unsafe {
+                    Address::from_usize(
+                        object
+                            .value()
+                            .wrapping_sub(r.original_start.as_usize())
+                            .wrapping_add(r.start.as_usize()),
+                    )
+                })
+            })
+    }
+}
+
+/// A region in the image, and its offset from the start of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageRegion {
+    original_start: Address,
+    offset: usize,
+    bytes: usize,
+}
+
+/// The objects and the relocations of an image, in offsets from the start of the image.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ImageContents {
+    regions: Vec<ImageRegion>,
+    objects: Vec<usize>,
+    /// `(slot, target)` pairs
+    relocations: Vec<(usize, usize)>,
+}
+
+impl ImageContents {
+    fn add_region(&mut self, original_start: Address, bytes: usize) {
+        let offset = self.regions.last().map_or(0, |r| r.offset + r.bytes);
+        self.regions.push(ImageRegion {
+            original_start,
+            offset,
+            bytes,
+        });
+    }
+
+    fn find_region(&self, addr: Address) -> Option<&ImageRegion> {
+        self.regions
+            .iter()
+            .find(|r| addr >= r.original_start && addr < r.original_start + r.bytes)
+    }
+
+    /// The offset of an address in the image. The address must be in one of the regions.
+    fn offset_of(region: &ImageRegion, addr: Address) -> usize {
+        addr - region.original_start + region.offset
+    }
+
+    /// The offset of an object reference in the image. Object references may not point into the
+    /// object, so we use wrapping arithmetic here, and apply the offset with wrapping arithmetic
+    /// when loading the image.
+    fn reference_offset(region: &ImageRegion, object: ObjectReference) -> usize {
+        object
+            .value()
+            .wrapping_sub(region.original_start.as_usize())
+            .wrapping_add(region.offset)
+    }
+
+    fn image_bytes(&self) -> usize {
+        self.regions.last().map_or(0, |r| r.offset + r.bytes)
+    }
+}
+
+fn write_u64<W: Write>(w: &mut W, val: usize) -> Result<()> {
+    w.write_all(&(val as u64).to_le_bytes())
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<usize> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    usize::try_from(u64::from_le_bytes(buf)).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Write the image. The contents of each region are read from its original address.
+fn write_image<W: Write>(w: &mut W, contents: &ImageContents) -> Result<()> {
+    w.write_all(&MAGIC)?;
+    write_u64(w, VERSION as usize)?;
+    write_u64(w, contents.regions.len())?;
+    write_u64(w, contents.objects.len())?;
+    write_u64(w, contents.relocations.len())?;
+    for r in contents.regions.iter() {
+        write_u64(w, r.original_start.as_usize())?;
+        write_u64(w, r.bytes)?;
+    }
+    for r in contents.regions.iter() {
+        w.write_all(unsafe {
+            std::slice::from_raw_parts(r.original_start.to_ptr::<u8>(), r.bytes)
+        })?;
+    }
+    for o in contents.objects.iter() {
+        write_u64(w, *o)?;
+    }
+    for (slot, target) in contents.relocations.iter() {
+        write_u64(w, *slot)?;
+        write_u64(w, *target)?;
+    }
+    Ok(())
+}
+
+/// Read the image. `map` is called with the size of the image before the contents of the regions
+/// are read, and returns the address where the image should be loaded. The memory must be mapped
+/// and writable. The relocations are applied to the loaded image. The offsets of the objects and
+/// the relocations are checked against the size of the image, so a corrupted file cannot make us
+/// write outside the image, or create object references outside the image.
+fn read_image<R: Read, F: FnOnce(usize) -> Result<Address>>(
+    r: &mut R,
+    map: F,
+) -> Result<(Address, ImageContents)> {
+    let mut magic = [0u8; 8];
+    r.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Not a heap snapshot file",
+        ));
+    }
+    let version = read_u64(r)?;
+    if version != VERSION as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported heap snapshot version {}", version),
+        ));
+    }
+    let num_regions = read_u64(r)?;
+    let num_objects = read_u64(r)?;
+    let num_relocations = read_u64(r)?;
+
+    let mut contents = ImageContents::default();
+    for _ in 0..num_regions {
+        let original_start = unsafe { Address::from_usize(read_u64(r)?) };
+        let bytes = read_u64(r)?;
+        contents.add_region(original_start, bytes);
+    }
+    let image_bytes = contents.image_bytes();
+
+    let start = map(image_bytes)?;
+    r.read_exact(unsafe { std::slice::from_raw_parts_mut(start.to_mut_ptr::<u8>(), image_bytes) })?;
+
+    for _ in 0..num_objects {
+        let object = read_u64(r)?;
+        if object >= image_bytes {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Object {} is outside the image", object),
+            ));
+        }
+        contents.objects.push(object);
+    }
+    for _ in 0..num_relocations {
+        let slot = read_u64(r)?;
+        let target = read_u64(r)?;
+        if slot
+            .checked_add(BYTES_IN_WORD)
+            .map_or(true, |end| end > image_bytes)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Relocation slot {} is outside the image", slot),
+            ));
+        }
+        if target >= image_bytes {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Relocation target {} is outside the image", target),
+            ));
+        }
+        unsafe { (start + slot).store::<usize>(start.as_usize().wrapping_add(target)) };
+        contents.relocations.push((slot, target));
+    }
+    Ok((start, contents))
+}
+
+/// Find the objects in the immortal and the non-moving spaces, and the relocations for them.
+fn collect_image<VM: VMBinding>(mmtk: &MMTK<VM>, tls: VMWorkerThread) -> Result<ImageContents> {
+    let common = mmtk.plan.common();
+    let mut contents = ImageContents::default();
+    let mut objects = vec![];
+    for space in [&common.immortal, &common.nonmoving] {
+        space.for_allocated_regions(|start, bytes| {
+            if bytes == 0 || contents.find_region(start).is_some() {
+                return;
+            }
+            let region_objects: Vec<ObjectReference> =
+                ObjectIterator::<VM, DefaultObjectSize<VM>, true>::new(start, start + bytes)
+                    .collect();
+            if let Some(end) = region_objects
+                .iter()
+                .map(|o| o.to_object_start::<VM>() + VM::VMObjectModel::get_current_size(*o))
+                .max()
+            {
+                contents.add_region(start, end.align_up(BYTES_IN_PAGE) - start);
+                objects.extend(region_objects);
+            }
+        });
+    }
+
+    let mut error = None;
+    for object in objects.iter().copied() {
+        let region = *contents
+            .find_region(object.to_object_start::<VM>())
+            .unwrap();
+        contents
+            .objects
+            .push(ImageContents::reference_offset(&region, object));
+
+        if !VM::VMScanning::support_edge_enqueuing(tls, object) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Object {} does not support edge enqueuing", object),
+            ));
+        }
+        VM::VMScanning::scan_object(tls, object, &mut |edge: VM::VMEdge| {
+            if error.is_some() {
+                return;
+            }
+            let target = edge.load();
+            if target.is_null() {
+                return;
+            }
+            let target_region = match contents.find_region(target.to_object_start::<VM>()) {
+                Some(r) => *r,
+                None => {
+                    error = Some(format!(
+                        "Object {} refers to {}, which is not in the snapshot",
+                        object, target
+                    ));
+                    return;
+                }
+            };
+            match edge.slot_address() {
+                Some(slot) => contents.relocations.push((
+                    ImageContents::offset_of(&region, slot),
+                    ImageContents::reference_offset(&target_region, target),
+                )),
+                None => {
+                    error = Some(format!(
+                        "A reference slot in object {} does not have an address",
+                        object
+                    ))
+                }
+            }
+        });
+        if let Some(e) = error.take() {
+            return Err(Error::new(ErrorKind::InvalidData, e));
+        }
+    }
+    Ok(contents)
+}
+
+pub(crate) fn save_heap_snapshot<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    tls: VMWorkerThread,
+    path: &Path,
+) -> Result<()> {
+    let contents = collect_image(mmtk, tls)?;
+    info!(
+        "Save heap snapshot to {:?}: {} regions, {} bytes, {} objects, {} relocations",
+        path,
+        contents.regions.len(),
+        contents.image_bytes(),
+        contents.objects.len(),
+        contents.relocations.len()
+    );
+    let mut w = BufWriter::new(File::create(path)?);
+    write_image(&mut w, &contents)?;
+    w.flush()
+}
+
+pub(crate) fn load_heap_snapshot<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    path: &Path,
+    start: Address,
+) -> Result<HeapSnapshot> {
+    if !start.is_aligned_to(BYTES_IN_CHUNK) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("The start address {} is not aligned to chunks", start),
+        ));
+    }
+    let mut r = BufReader::new(File::open(path)?);
+    // The VM space is mapped in chunks.
+    let mut mapped_bytes = 0;
+    let (start, contents) = read_image(&mut r, |bytes| {
+        mapped_bytes = raw_align_up(bytes.max(1), BYTES_IN_CHUNK);
+        let end = start + mapped_bytes;
+        if !Address::range_intersection(
+            &(start..end),
+            &crate::util::heap::layout::available_range(),
+        )
+        .is_empty()
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The image ({}, {}) overlaps with the heap", start, end),
+            ));
+        }
+        crate::util::memory::dzmmap_noreplace(start, mapped_bytes)?;
+        Ok(start)
+    })?;
+
+    let vm_space = &mmtk.plan.base().vm_space;
+    vm_space.add_region(start, mapped_bytes);
+    let objects: Vec<ObjectReference> = contents
+        .objects
+        .iter()
+        .map(|o| {
+            let object = ObjectReference::from_raw_address(unsafe {
+                Address::from_usize(start.as_usize().wrapping_add(*o))
+            });
+            vm_space.initialize_object_metadata(object, false);
+            object
+        })
+        .collect();
+    let bytes = contents.image_bytes();
+    info!(
+        "Loaded heap snapshot from {:?} to ({}, {}): {} objects",
+        path,
+        start,
+        start + bytes,
+        objects.len()
+    );
+
+    Ok(HeapSnapshot {
+        start,
+        bytes,
+        regions: contents
+            .regions
+            .iter()
+            .map(|r| SnapshotRegion {
+                original_start: r.original_start,
+                start: start + r.offset,
+                bytes: r.bytes,
+            })
+            .collect(),
+        objects,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_image() {
+        // Two regions with a reference from the first region to the second region.
+        let mut region0 = vec![0usize; 4];
+        let region1 = vec![0x1234usize; 2];
+        let start0 = Address::from_ref(&region0[0]);
+        let start1 = Address::from_ref(&region1[0]);
+        region0[1] = start1.as_usize() + BYTES_IN_WORD;
+
+        let mut contents = ImageContents::default();
+        contents.add_region(start0, 4 * BYTES_IN_WORD);
+        contents.add_region(start1, 2 * BYTES_IN_WORD);
+        contents.objects = vec![0, 4 * BYTES_IN_WORD];
+        contents.relocations = vec![(BYTES_IN_WORD, 5 * BYTES_IN_WORD)];
+
+        let mut buf = vec![];
+        write_image(&mut buf, &contents).unwrap();
+
+        let mut loaded = vec![0usize; 6];
+        let loaded_start = Address::from_mut_ptr(loaded.as_mut_ptr());
+        let (start, read) = read_image(&mut buf.as_slice(), |bytes| {
+            assert_eq!(bytes, 6 * BYTES_IN_WORD);
+            Ok(loaded_start)
+        })
+        .unwrap();
+        assert_eq!(start, loaded_start);
+        assert_eq!(read, contents);
+        // The reference is relocated to the second region in the loaded image.
+        assert_eq!(loaded[1], loaded_start.as_usize() + 5 * BYTES_IN_WORD);
+        assert_eq!(loaded[4], 0x1234);
+        assert_eq!(loaded[5], 0x1234);
+    }
+
+    #[test]
+    fn read_object_outside_image() {
+        let region = vec![0usize; 2];
+        let mut contents = ImageContents::default();
+        contents.add_region(Address::from_ref(&region[0]), 2 * BYTES_IN_WORD);
+        contents.objects = vec![2 * BYTES_IN_WORD];
+
+        let mut buf = vec![];
+        write_image(&mut buf, &contents).unwrap();
+
+        let mut loaded = vec![0usize; 2];
+        let loaded_start = Address::from_mut_ptr(loaded.as_mut_ptr());
+        let res = read_image(&mut buf.as_slice(), |_| Ok(loaded_start));
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_bad_magic() {
+        let buf = vec![0u8; 64];
+        let res = read_image(&mut buf.as_slice(), |_| unreachable!());
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -24,9 +24,10 @@ impl<VM: VMBinding, S: LinearScanObjectSize, const ATOMIC_LOAD_VO_BIT: bool>
     ObjectIterator<VM, S, ATOMIC_LOAD_VO_BIT>
 {
     /// Create an iterator for the address range. The caller must ensure
-    /// that the VO bit metadata is mapped for the address range.
+    /// that the VO bit metadata is mapped for the address range. The range may be empty, e.g. for
+    /// a space that has not allocated any object.
     pub fn new(start: Address, end: Address) -> Self {
-        debug_assert!(start < end);
+        debug_assert!(start <= end);
         ObjectIterator {
             start,
             end,
@@ -77,9 +78,17 @@ impl<VM: VMBinding> LinearScanObjectSize for DefaultObjectSize<VM> {
 
 /// Region represents a memory region with a properly aligned address as its start and a fixed size for the region.
 /// Region provides a set of utility methods, along with a RegionIterator that linearly scans at the step of a region.
+///
+/// The size of a region may be decided at run time (e.g. an Immix block), so the trait does not have a constant for
+/// the size. A region whose size is known at compile time should also provide the size as the associated constants
+/// `LOG_BYTES` and `BYTES`.
 pub trait Region: Copy + PartialEq + PartialOrd {
-    const LOG_BYTES: usize;
-    const BYTES: usize = 1 << Self::LOG_BYTES;
+    /// Return the log of the size of the region in bytes. All the other methods use this for the size of the region.
+    fn log_bytes() -> usize;
+    /// Return the size of the region in bytes.
+    fn bytes() -> usize {
+        1 << Self::log_bytes()
+    }
 
     /// Create a region from an address that is aligned to the region boundary. The method should panic if the address
     /// is not properly aligned to the region. For performance, this method should always be inlined.
@@ -94,16 +103,16 @@ pub trait Region: Copy + PartialEq + PartialOrd {
 
     /// Align the address to the region.
     fn align(address: Address) -> Address {
-        address.align_down(Self::BYTES)
+        address.align_down(Self::bytes())
     }
     /// Check if an address is aligned to the region.
     fn is_aligned(address: Address) -> bool {
-        address.is_aligned_to(Self::BYTES)
+        address.is_aligned_to(Self::bytes())
     }
 
     /// Return the end address of the region. Note that the end address is not in the region.
     fn end(&self) -> Address {
-        self.start() + Self::BYTES
+        self.start() + Self::bytes()
     }
     /// Return the next region after this one.
     fn next(&self) -> Self {
@@ -111,8 +120,8 @@ pub trait Region: Copy + PartialEq + PartialOrd {
     }
     /// Return the next nth region after this one.
     fn next_nth(&self, n: usize) -> Self {
-        debug_assert!(self.start().as_usize() < usize::MAX - (n << Self::LOG_BYTES));
-        Self::from_aligned_address(self.start() + (n << Self::LOG_BYTES))
+        debug_assert!(self.start().as_usize() < usize::MAX - (n << Self::log_bytes()));
+        Self::from_aligned_address(self.start() + (n << Self::log_bytes()))
     }
     /// Return the region that contains the object (by its cell address).
     fn containing<VM: VMBinding>(object: ObjectReference) -> Self {
@@ -163,10 +172,12 @@ mod tests {
     struct Page(Address);
 
     impl Region for Page {
-        const LOG_BYTES: usize = LOG_BYTES_IN_PAGE as usize;
+        fn log_bytes() -> usize {
+            LOG_BYTES_IN_PAGE as usize
+        }
 
         fn from_aligned_address(address: Address) -> Self {
-            debug_assert!(address.is_aligned_to(Self::BYTES));
+            debug_assert!(address.is_aligned_to(Self::bytes()));
             Self(address)
         }
 
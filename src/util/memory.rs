@@ -17,6 +17,34 @@ pub fn zero(start: Address, len: usize) {
     set(start, 0, len);
 }
 
+/// Zero the memory with non-temporal (streaming) stores that bypass the cache. This is useful for
+/// zeroing a large amount of memory that will not be accessed soon. On architectures without
+/// non-temporal stores, this is the same as `zero`.
+pub fn zero_nontemporal(start: Address, len: usize) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use std::arch::x86_64::{__m128i, _mm_setzero_si128, _mm_sfence, _mm_stream_si128};
+        const BYTES: usize = std::mem::size_of::<__m128i>();
+        let end = start + len;
+        let aligned_start = start.align_up(BYTES).min(end);
+        let aligned_end = end.align_down(BYTES).max(aligned_start);
+        zero(start, aligned_start - start);
+        unsafe {
+            let value = _mm_setzero_si128();
+            let mut cursor = aligned_start;
+            while cursor < aligned_end {
+                _mm_stream_si128(cursor.to_mut_ptr::<__m128i>(), value);
+                cursor += BYTES;
+            }
+            // Make the streaming stores visible to other threads.
+            _mm_sfence();
+        }
+        zero(aligned_end, end - aligned_end);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    zero(start, len)
+}
+
 pub fn set(start: Address, val: u8, len: usize) {
     unsafe {
         std::ptr::write_bytes::<u8>(start.to_mut_ptr(), val, len);
@@ -34,7 +62,7 @@ pub fn set(start: Address, val: u8, len: usize) {
 /// may corrupt others' data.
 #[allow(clippy::let_and_return)] // Zeroing is not neceesary for some OS/s
 pub unsafe fn dzmmap(start: Address, size: usize) -> Result<()> {
-    let prot = PROT_READ | PROT_WRITE | PROT_EXEC;
+    let prot = PROT_READ | PROT_WRITE;
     let flags = libc::MAP_ANON | libc::MAP_PRIVATE | libc::MAP_FIXED;
     let ret = mmap_fixed(start, size, prot, flags);
     // We do not need to explicitly zero for Linux (memory is guaranteed to be zeroed)
@@ -57,7 +85,7 @@ const MMAP_FLAGS: libc::c_int = libc::MAP_ANON | libc::MAP_PRIVATE | libc::MAP_F
 /// This function will not overwrite existing memory mapping, and it will result Err if there is an existing mapping.
 #[allow(clippy::let_and_return)] // Zeroing is not neceesary for some OS/s
 pub fn dzmmap_noreplace(start: Address, size: usize) -> Result<()> {
-    let prot = PROT_READ | PROT_WRITE | PROT_EXEC;
+    let prot = PROT_READ | PROT_WRITE;
     let flags = MMAP_FLAGS;
     let ret = mmap_fixed(start, size, prot, flags);
     // We do not need to explicitly zero for Linux (memory is guaranteed to be zeroed)
@@ -154,9 +182,11 @@ pub fn panic_if_unmapped(_start: Address, _size: usize) {
     // Possibly we can use posix_mem_offset for both OS/s.
 }
 
+/// Make the memory readable and writable. MMTk does not map memory as executable unless it is
+/// made executable with `mprotect_executable`.
 pub fn munprotect(start: Address, size: usize) -> Result<()> {
     wrap_libc_call(
-        &|| unsafe { libc::mprotect(start.to_mut_ptr(), size, PROT_READ | PROT_WRITE | PROT_EXEC) },
+        &|| unsafe { libc::mprotect(start.to_mut_ptr(), size, PROT_READ | PROT_WRITE) },
         0,
     )
 }
@@ -168,6 +198,31 @@ pub fn mprotect(start: Address, size: usize) -> Result<()> {
     )
 }
 
+/// Protect the memory as read-only. Use `munprotect` to make it writable again.
+pub fn mprotect_read_only(start: Address, size: usize) -> Result<()> {
+    wrap_libc_call(
+        &|| unsafe { libc::mprotect(start.to_mut_ptr(), size, PROT_READ) },
+        0,
+    )
+}
+
+/// Make the memory readable and executable, but not writable. Use `munprotect` to make it writable again.
+pub fn mprotect_executable(start: Address, size: usize) -> Result<()> {
+    wrap_libc_call(
+        &|| unsafe { libc::mprotect(start.to_mut_ptr(), size, PROT_READ | PROT_EXEC) },
+        0,
+    )
+}
+
+/// Discard the contents of the memory. The memory stays mapped with the same protection, and it
+/// reads as zero and does not take physical memory until it is written again.
+pub fn discard(start: Address, size: usize) -> Result<()> {
+    wrap_libc_call(
+        &|| unsafe { libc::madvise(start.to_mut_ptr(), size, libc::MADV_DONTNEED) },
+        0,
+    )
+}
+
 fn wrap_libc_call<T: PartialEq>(f: &dyn Fn() -> T, expect: T) -> Result<()> {
     let ret = f();
     if ret == expect {
@@ -254,6 +309,32 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_zero_nontemporal() {
+        serial_test(|| {
+            with_cleanup(
+                || {
+                    let res = unsafe { dzmmap(START, BYTES_IN_PAGE) };
+                    assert!(res.is_ok());
+                    set(START, 0xff, BYTES_IN_PAGE);
+                    // Neither the start nor the end is aligned to the vector size.
+                    zero_nontemporal(START + 3usize, 100);
+                    unsafe {
+                        assert_eq!(START.load::<u8>(), 0xff);
+                        assert_eq!((START + 2usize).load::<u8>(), 0xff);
+                        for i in 3..103usize {
+                            assert_eq!((START + i).load::<u8>(), 0);
+                        }
+                        assert_eq!((START + 103usize).load::<u8>(), 0xff);
+                    }
+                },
+                || {
+                    assert!(munmap(START, BYTES_IN_PAGE).is_ok());
+                },
+            )
+        });
+    }
+
     #[cfg(target_os = "linux")]
     #[test]
     fn test_mmap_noreplace() {
@@ -0,0 +1,62 @@
+//! Per-field log bits
+//!
+//! The field log bit is a global per-word side metadata used by the field barrier. It is analogous
+//! to the log bit of an object (see [`crate::vm::ObjectModel::GLOBAL_LOG_BIT_SPEC`]), but it is set
+//! for each reference slot instead of each object. 1 means the field is unlogged, and the field
+//! barrier will log the field (set the bit to 0) and remember the slot when the field is written.
+//!
+//! A plan that uses the field log bits sets all the field log bits of an object to 1 when the
+//! object becomes mature, and sets the field log bit of a remembered slot to 1 again after a GC.
+
+use crate::util::constants::BYTES_IN_ADDRESS;
+use crate::util::metadata::side_metadata::SideMetadataSpec;
+use crate::util::Address;
+use crate::util::ObjectReference;
+use crate::vm::ObjectModel;
+use crate::vm::VMBinding;
+use std::sync::atomic::Ordering;
+
+/// One bit per word.
+pub(crate) const FIELD_LOG_BIT_SIDE_METADATA_SPEC: SideMetadataSpec =
+    crate::util::metadata::side_metadata::spec_defs::FIELD_LOG_BIT;
+
+pub const FIELD_LOG_BIT_SIDE_METADATA_ADDR: Address =
+    FIELD_LOG_BIT_SIDE_METADATA_SPEC.get_absolute_offset();
+
+/// Is the field at the slot address unlogged?
+pub fn is_field_unlogged(slot: Address) -> bool {
+    FIELD_LOG_BIT_SIDE_METADATA_SPEC.load_atomic::<u8>(slot, Ordering::Relaxed) == 1
+}
+
+/// Attempt to atomically log the field at the slot address.
+/// Returns true if the field was not logged previously.
+pub fn log_field(slot: Address) -> bool {
+    loop {
+        if FIELD_LOG_BIT_SIDE_METADATA_SPEC.load_atomic::<u8>(slot, Ordering::SeqCst) == 0 {
+            return false;
+        }
+        if FIELD_LOG_BIT_SIDE_METADATA_SPEC
+            .compare_exchange_atomic::<u8>(slot, 1, 0, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return true;
+        }
+    }
+}
+
+/// Mark the field at the slot address as unlogged.
+pub fn mark_field_as_unlogged(slot: Address) {
+    FIELD_LOG_BIT_SIDE_METADATA_SPEC.store_atomic::<u8>(slot, 1, Ordering::SeqCst);
+}
+
+/// Mark all the fields of an object as unlogged. `bytes` is the size of the object.
+pub fn mark_fields_as_unlogged<VM: VMBinding>(object: ObjectReference, bytes: usize) {
+    let start = VM::VMObjectModel::ref_to_object_start(object).align_down(BYTES_IN_ADDRESS);
+    let end = (VM::VMObjectModel::ref_to_object_start(object) + bytes).align_up(BYTES_IN_ADDRESS);
+    FIELD_LOG_BIT_SIDE_METADATA_SPEC.bset_metadata(start, end - start);
+}
+
+/// Bulk zero the field log bits.
+pub fn bzero_field_log_bits(start: Address, size: usize) {
+    FIELD_LOG_BIT_SIDE_METADATA_SPEC.bzero_metadata(start, size);
+}
@@ -1,6 +1,6 @@
 use super::header_metadata::HeaderMetadataSpec;
 use crate::util::metadata::metadata_val_traits::*;
-use crate::util::metadata::side_metadata::SideMetadataSpec;
+use crate::util::metadata::side_metadata::{address_to_meta_address, SideMetadataSpec};
 use crate::util::ObjectReference;
 use crate::vm::ObjectModel;
 use crate::vm::VMBinding;
@@ -35,6 +35,21 @@ impl MetadataSpec {
         }
     }
 
+    /// Prefetch the specified metadata of an object so that a subsequent access will be faster.
+    /// For in-header metadata, this prefetches the header of the object.
+    ///
+    /// # Arguments:
+    ///
+    /// * `object`: is a reference to the target object.
+    pub fn prefetch<VM: VMBinding>(&self, object: ObjectReference) {
+        match self {
+            MetadataSpec::OnSide(metadata_spec) => {
+                address_to_meta_address(metadata_spec, object.to_address::<VM>()).prefetch_load()
+            }
+            MetadataSpec::InHeader(_) => VM::VMObjectModel::ref_to_header(object).prefetch_load(),
+        }
+    }
+
     /// A function to non-atomically load the specified metadata's content.
     /// Returns the metadata value.
     ///
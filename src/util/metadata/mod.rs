@@ -224,6 +224,7 @@ mod metadata_val_traits;
 pub mod side_metadata;
 pub use metadata_val_traits::*;
 
+pub(crate) mod field_log_bit;
 pub(crate) mod log_bit;
 pub(crate) mod mark_bit;
 pub(crate) mod pin_bit;
@@ -1,4 +1,5 @@
-use crate::util::ObjectReference;
+use crate::util::metadata::MetadataSpec;
+use crate::util::{Address, ObjectReference};
 use crate::vm::VMBinding;
 use crate::vm::VMLocalPinningBitSpec;
 use std::sync::atomic::Ordering;
@@ -31,6 +32,15 @@ impl VMLocalPinningBitSpec {
         res.is_ok()
     }
 
+    /// Clear the pinning bits for a memory region if the pinning bits are in side metadata. A space should
+    /// call this when it releases the memory of dead objects, so the objects allocated later in the memory are
+    /// not pinned. Pinning bits in the header are initialized with the header of new objects.
+    pub fn bzero_side_metadata(&self, start: Address, size: usize) {
+        if let MetadataSpec::OnSide(spec) = self.as_spec() {
+            spec.bzero_metadata(start, size);
+        }
+    }
+
     pub fn is_object_pinned<VM: VMBinding>(&self, object: ObjectReference) -> bool {
         if unsafe { self.load::<VM, u8>(object, None) == 1 } {
             return true;
@@ -20,7 +20,8 @@ pub const GLOBAL_SIDE_METADATA_BASE_ADDRESS: Address = unsafe { Address::from_us
 
 // FIXME: The 64-bit base address is changed from 0x0600_0000_0000 to 0x0c00_0000_0000 so that it
 // is less likely to overlap with any space.  But it does not solve the problem completely.
-// If there are more spaces, it will still overlap with some spaces.
+// `HeapMeta` does not let spaces reserve the range of the global side metadata of mmtk-core, but
+// the side metadata defined by VM bindings may still overlap with some spaces.
 // See: https://github.com/mmtk/mmtk-core/issues/458
 #[cfg(target_pointer_width = "64")]
 pub const GLOBAL_SIDE_METADATA_BASE_ADDRESS: Address =
@@ -33,6 +34,10 @@ pub(crate) const GLOBAL_SIDE_METADATA_BASE_OFFSET: SideMetadataOffset =
 pub const VO_BIT_SIDE_METADATA_ADDR: Address =
     crate::util::metadata::vo_bit::VO_BIT_SIDE_METADATA_ADDR;
 
+// Base address of the field log bits, public to VM bindings which may implement the fast path of the field barrier.
+pub const FIELD_LOG_BIT_SIDE_METADATA_ADDR: Address =
+    crate::util::metadata::field_log_bit::FIELD_LOG_BIT_SIDE_METADATA_ADDR;
+
 /// This constant represents the worst-case ratio of source data size to global side metadata.
 /// A value of 2 means the space required for global side metadata must be less than 1/4th of the source data.
 /// So, a value of `n` means this ratio must be less than $2^-n$.
@@ -1130,6 +1130,23 @@ impl SideMetadataContext {
         Ok(())
     }
 
+    /// Discard the metadata for the data address range so that it reads as zero, e.g. when the
+    /// space that owns the range is released. The metadata stays mapped, as the mmap chunks of the
+    /// metadata may be shared with the metadata of other data ranges.
+    ///
+    /// Only the contiguous metadata is discarded. The local metadata on 32-bit targets is not
+    /// discarded, as only one MMTk instance can live at a time on 32-bit targets.
+    pub fn discard_metadata_space(&self, start: Address, size: usize) {
+        trace!("discard_metadata_space({}, 0x{:x})", start, size);
+        for spec in self.global.iter() {
+            discard_contiguous_metadata_space(start, size, spec);
+        }
+        #[cfg(target_pointer_width = "64")]
+        for spec in self.local.iter() {
+            discard_contiguous_metadata_space(start, size, spec);
+        }
+    }
+
     /// Unmap the corresponding metadata space or panic.
     ///
     /// Note-1: This function is only used for test and debug right now.
@@ -1,5 +1,7 @@
 use super::SideMetadataSpec;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::heap::layout::vm_layout_constants::MMAP_CHUNK_BYTES;
+use crate::util::memory;
 #[cfg(target_pointer_width = "32")]
 use crate::util::metadata::side_metadata::address_to_chunked_meta_address;
 use crate::util::Address;
@@ -86,6 +88,40 @@ pub(crate) fn try_mmap_contiguous_metadata_space(
     }
 }
 
+/// Discard the contiguous metadata (`spec`) for the data address range so that it reads as zero.
+/// The metadata stays mapped. The whole metadata pages are discarded, and the partial pages at
+/// both ends are zeroed, as they may hold the metadata of the neighbouring data. The metadata that
+/// is not mapped, or is only quarantined, has never been written, and is left untouched.
+pub(crate) fn discard_contiguous_metadata_space(
+    start: Address,
+    size: usize,
+    spec: &SideMetadataSpec,
+) {
+    let metadata_start = address_to_meta_address(spec, start);
+    let metadata_size = (size + ((1 << addr_rshift(spec)) - 1)) >> addr_rshift(spec);
+    let metadata_end = metadata_start + metadata_size;
+
+    // The mmapper tracks the mapping state in mmap chunks.
+    let mut cursor = metadata_start;
+    while cursor < metadata_end {
+        let limit = (cursor + 1usize)
+            .align_up(MMAP_CHUNK_BYTES)
+            .min(metadata_end);
+        if MMAPPER.is_mapped_address(cursor) {
+            let whole_start = cursor.align_up(BYTES_IN_PAGE);
+            let whole_end = limit.align_down(BYTES_IN_PAGE);
+            if whole_start < whole_end {
+                memory::discard(whole_start, whole_end - whole_start).unwrap();
+                memory::zero(cursor, whole_start - cursor);
+                memory::zero(whole_end, limit - whole_end);
+            } else {
+                memory::zero(cursor, limit - cursor);
+            }
+        }
+        cursor = limit;
+    }
+}
+
 /// Performs the translation of data address (`data_addr`) to metadata address for the specified metadata (`metadata_spec`).
 pub(crate) fn address_to_meta_address(
     metadata_spec: &SideMetadataSpec,
@@ -13,4 +13,6 @@ pub use global::*;
 pub use helpers::*;
 #[cfg(target_pointer_width = "32")]
 pub use helpers_32::*;
+#[cfg(feature = "extreme_assertions")]
+pub(crate) use sanity::reset as reset_content_sanity_map;
 pub use sanity::SideMetadataSanity;
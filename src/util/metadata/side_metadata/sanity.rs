@@ -44,8 +44,9 @@ lazy_static! {
     pub(crate) static ref SANITY_LOCK: Mutex<()> = Mutex::new(());
 }
 
-/// A test helper function which resets contents map to prevent propagation of test failure
-#[cfg(test)]
+/// Reset the contents map. This is used to prevent propagation of test failure, and to forget the
+/// contents when an MMTk instance is shut down.
+#[cfg(any(test, feature = "extreme_assertions"))]
 pub(crate) fn reset() {
     CONTENT_SANITY_MAP.write().unwrap().clear()
 }
@@ -1,6 +1,5 @@
 use crate::util::constants::*;
 use crate::util::heap::layout::vm_layout_constants::*;
-use crate::util::linear_scan::Region;
 use crate::util::metadata::side_metadata::constants::{
     GLOBAL_SIDE_METADATA_BASE_OFFSET, LOCAL_SIDE_METADATA_BASE_OFFSET,
 };
@@ -60,6 +59,12 @@ define_side_metadata_specs!(
     MS_ACTIVE_CHUNK = (global: true, log_num_of_bits: 3, log_bytes_in_region: LOG_BYTES_IN_CHUNK),
     // Track the index in SFT map for a chunk (only used for SFT sparse chunk map)
     SFT_DENSE_CHUNK_MAP_INDEX   = (global: true, log_num_of_bits: 3, log_bytes_in_region: LOG_BYTES_IN_CHUNK),
+    // Dirty cards for the card-marking barrier (one byte per card of the minimum card size)
+    CARD_TABLE   = (global: true, log_num_of_bits: 3, log_bytes_in_region: crate::util::card_table::LOG_MIN_CARD_BYTES),
+    // Track chunks that contain dirty cards for the card-marking barrier
+    CARD_DIRTY_CHUNK = (global: true, log_num_of_bits: 3, log_bytes_in_region: LOG_BYTES_IN_CHUNK),
+    // Mark each reference field as logged or unlogged for the field barrier
+    FIELD_LOG_BIT = (global: true, log_num_of_bits: 0, log_bytes_in_region: LOG_BYTES_IN_ADDRESS as usize),
 );
 
 // This defines all LOCAL side metadata used by mmtk-core.
@@ -69,12 +74,13 @@ define_side_metadata_specs!(
     MALLOC_MS_ACTIVE_PAGE  = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::util::malloc::library::LOG_BYTES_IN_MALLOC_PAGE as usize),
     // Record objects allocated with some offset
     MS_OFFSET_MALLOC = (global: false, log_num_of_bits: 0, log_bytes_in_region: LOG_MIN_OBJECT_SIZE as usize),
-    // Mark lines by immix
-    IX_LINE_MARK    = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::policy::immix::line::Line::LOG_BYTES),
+    // Mark lines by immix. The immix line and block sizes are set at run time, so the immix specs have room for the
+    // smallest sizes. The lines and blocks of a chunk use the first bytes of the metadata for the chunk.
+    IX_LINE_MARK    = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::policy::immix::line::Line::MIN_LOG_BYTES),
     // Record defrag state for immix blocks
-    IX_BLOCK_DEFRAG = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::policy::immix::block::Block::LOG_BYTES),
+    IX_BLOCK_DEFRAG = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::policy::immix::block::Block::MIN_LOG_BYTES),
     // Mark blocks by immix
-    IX_BLOCK_MARK   = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::policy::immix::block::Block::LOG_BYTES),
+    IX_BLOCK_MARK   = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::policy::immix::block::Block::MIN_LOG_BYTES),
     // Mark chunks (any plan that uses the chunk map should include this spec in their local sidemetadata specs)
     CHUNK_MARK   = (global: false, log_num_of_bits: 3, log_bytes_in_region: crate::util::heap::chunk_map::Chunk::LOG_BYTES),
     // Mark blocks by (native mimalloc) marksweep
@@ -96,6 +102,14 @@ define_side_metadata_specs!(
     MS_LOCAL_FREE   = (global: false, log_num_of_bits: LOG_BITS_IN_ADDRESS, log_bytes_in_region: crate::policy::marksweepspace::native_ms::Block::LOG_BYTES),
     // First cell of thread free list in block for native mimalloc
     MS_THREAD_FREE  = (global: false, log_num_of_bits: LOG_BITS_IN_ADDRESS, log_bytes_in_region: crate::policy::marksweepspace::native_ms::Block::LOG_BYTES),
+    // Record allocation sites for nursery objects in generational plans (only used for pretenuring)
+    GEN_ALLOC_SITE  = (global: false, log_num_of_bits: 4, log_bytes_in_region: 6),
+    // Mark the live memory of mark compact (only used for side forwarding)
+    MC_LIVE_BITMAP  = (global: false, log_num_of_bits: 0, log_bytes_in_region: LOG_MIN_OBJECT_SIZE as usize),
+    // The forwarding address of each region for mark compact (only used for side forwarding)
+    MC_REGION_FORWARDING = (global: false, log_num_of_bits: LOG_BITS_IN_ADDRESS, log_bytes_in_region: crate::policy::markcompactspace::LOG_BYTES_IN_FORWARDING_REGION),
+    // Mark objects in the read-only space
+    RO_MARK_BIT     = (global: false, log_num_of_bits: 0, log_bytes_in_region: LOG_MIN_OBJECT_SIZE as usize),
 );
 
 #[cfg(test)]
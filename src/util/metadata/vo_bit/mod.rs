@@ -48,6 +48,8 @@ pub(crate) mod helper;
 
 use atomic::Ordering;
 
+use crate::util::constants::LOG_BITS_IN_BYTE;
+use crate::util::heap::layout::vm_layout_constants::MMAP_CHUNK_BYTES;
 use crate::util::metadata::side_metadata::SideMetadataSpec;
 use crate::util::Address;
 use crate::util::ObjectReference;
@@ -138,6 +140,61 @@ pub unsafe fn is_vo_bit_set_unsafe<VM: VMBinding>(address: Address) -> Option<Ob
     }
 }
 
+/// Search backwards from `end` for the last address in `[start, end)` that has the VO bit set, and
+/// return the object at the address. Return `None` if no VO bit is set in the range. The memory whose
+/// VO bits are not mapped is skipped, as no object has been allocated there.
+pub(crate) fn find_last_object_in_range<VM: VMBinding>(
+    start: Address,
+    end: Address,
+) -> Option<ObjectReference> {
+    // The data bytes whose VO bits are mapped together. One bit covers a granule.
+    let bytes_per_meta_mmap_chunk = MMAP_CHUNK_BYTES
+        << (LOG_BITS_IN_BYTE as usize + VO_BIT_SIDE_METADATA_SPEC.log_bytes_in_region);
+    let mut range_end = end;
+    while range_end > start {
+        let range_start = (range_end - 1usize)
+            .align_down(bytes_per_meta_mmap_chunk)
+            .max(start);
+        if VO_BIT_SIDE_METADATA_SPEC.is_mapped(range_start) {
+            if let Some(object) = find_last_object_in_mapped_range::<VM>(range_start, range_end) {
+                return Some(object);
+            }
+        }
+        range_end = range_start;
+    }
+    None
+}
+
+/// The same as [`find_last_object_in_range`], but the VO bits for the range must be mapped.
+fn find_last_object_in_mapped_range<VM: VMBinding>(
+    start: Address,
+    end: Address,
+) -> Option<ObjectReference> {
+    let granule = 1usize << VO_BIT_SIDE_METADATA_SPEC.log_bytes_in_region;
+    // The data bytes covered by one byte of VO bit metadata.
+    let bytes_per_meta_byte = granule << LOG_BITS_IN_BYTE;
+    let mut cursor = end.align_down(granule);
+    while cursor > start {
+        // Skip a whole metadata byte if no VO bit is set in it.
+        if cursor.is_aligned_to(bytes_per_meta_byte) && cursor - start >= bytes_per_meta_byte {
+            let meta_addr = crate::util::metadata::side_metadata::address_to_meta_address(
+                &VO_BIT_SIDE_METADATA_SPEC,
+                cursor - bytes_per_meta_byte,
+            );
+            if unsafe { meta_addr.load::<u8>() } == 0 {
+                cursor -= bytes_per_meta_byte;
+                continue;
+            }
+        }
+        cursor -= granule;
+        if VO_BIT_SIDE_METADATA_SPEC.load_atomic::<u8>(cursor, Ordering::SeqCst) == 1 {
+            // The VO bit is set for the address of the object (see `ObjectReference::to_address`).
+            return Some(VM::VMObjectModel::address_to_ref(cursor));
+        }
+    }
+    None
+}
+
 /// Bulk zero the VO bit.
 pub fn bzero_vo_bit(start: Address, size: usize) {
     VO_BIT_SIDE_METADATA_SPEC.bzero_metadata(start, size);
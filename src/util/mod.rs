@@ -11,6 +11,8 @@ pub mod address;
 /// Allocators
 // This module is made public so the binding could implement allocator slowpaths if they would like to.
 pub mod alloc;
+/// Card table for the card-marking barrier.
+pub mod card_table;
 /// Constants used in MMTk
 pub mod constants;
 /// Calculation, conversion and rounding for memory related numbers.
@@ -32,6 +34,9 @@ pub mod reference_processor;
 /// An analysis framework for collecting data and profiling in GC.
 #[cfg(feature = "analysis")]
 pub(crate) mod analysis;
+/// Write-xor-execute permission management for code spaces.
+#[cfg(feature = "code_space")]
+pub(crate) mod code_protection;
 /// Logging edges to check duplicated edges in GC.
 #[cfg(feature = "extreme_assertions")]
 pub(crate) mod edge_logger;
@@ -41,6 +46,9 @@ pub(crate) mod erase_vm;
 pub(crate) mod finalizable_processor;
 /// Heap implementation, including page resource, mmapper, etc.
 pub(crate) mod heap;
+/// Saving and loading heap snapshots.
+#[cfg(feature = "heap_snapshot")]
+pub mod heap_snapshot;
 #[cfg(feature = "is_mmtk_object")]
 pub mod is_mmtk_object;
 /// Logger initialization
@@ -54,7 +62,7 @@ pub(crate) mod object_forwarding;
 /// Utilities funcitons for Rust
 pub(crate) mod rust_util;
 /// Sanity checker for GC.
-#[cfg(feature = "sanity")]
+#[cfg(any(feature = "sanity", feature = "verify_remset"))]
 pub(crate) mod sanity;
 /// Utils for collecting statistics.
 pub(crate) mod statistics;
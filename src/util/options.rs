@@ -1,4 +1,6 @@
+use crate::plan::BarrierSelector;
 use crate::scheduler::affinity::{get_total_num_cpus, CoreId};
+use crate::util::constants::BYTES_IN_KBYTE;
 use crate::util::constants::DEFAULT_STRESS_FACTOR;
 use crate::util::constants::LOG_BYTES_IN_MBYTE;
 use crate::util::Address;
@@ -15,6 +17,56 @@ pub enum NurseryZeroingOptions {
     Adaptive,
 }
 
+impl NurseryZeroingOptions {
+    /// Should we zero memory with non-temporal (streaming) stores?
+    pub fn is_nontemporal(&self) -> bool {
+        !matches!(self, NurseryZeroingOptions::Temporal)
+    }
+
+    /// Should we zero memory in a separate thread? `Adaptive` only does so if there is more than one CPU.
+    pub fn is_concurrent(&self) -> bool {
+        match self {
+            NurseryZeroingOptions::Concurrent => true,
+            NurseryZeroingOptions::Adaptive => get_total_num_cpus() > 1,
+            _ => false,
+        }
+    }
+}
+
+/// The order in which a GC worker executes the work packets in its local work queue.
+#[derive(Copy, Clone, EnumString, Debug, PartialEq, Eq)]
+pub enum WorkQueueOrder {
+    /// Execute the oldest packet first. This processes the object graph breadth-first.
+    Fifo,
+    /// Execute the newest packet first. This is closer to depth-first processing, and has better locality.
+    Lifo,
+}
+
+/// How a GC worker steals work packets from the local queues of other workers when it runs out of work.
+#[derive(Copy, Clone, EnumString, Debug, PartialEq, Eq)]
+pub enum StealPolicy {
+    /// Try the other workers in the order of their ordinals.
+    Sequential,
+    /// Try the next worker first, and then the others in a round-robin fashion, so that different workers
+    /// start from different victims.
+    Neighbor,
+    /// Start from a randomly chosen worker, and then try the others in a round-robin fashion.
+    Random,
+    /// Never steal. A worker only gets packets from its own local queue and the work buckets.
+    Disabled,
+}
+
+/// The order in which a `ProcessEdgesWork` packet traces the object graph.
+#[derive(Copy, Clone, EnumString, Debug, PartialEq, Eq)]
+pub enum TraceOrder {
+    /// Process all the edges in the packet before scanning the objects they point to.
+    BreadthFirst,
+    /// Scan an object as soon as it is traced for the first time, and process its edges before the remaining
+    /// edges in the packet. Copying plans then copy the children of an object right after the object itself,
+    /// which improves the locality of the mutator.
+    DepthFirst,
+}
+
 #[derive(Copy, Clone, EnumString, Debug)]
 pub enum PlanSelector {
     NoGC,
@@ -416,6 +468,59 @@ impl FromStr for NurserySize {
     }
 }
 
+/// The sizes of Immix blocks and lines. The two sizes are set together, as the valid line sizes
+/// depend on the block size.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ImmixBlockSize {
+    /// The log of the block size in bytes
+    pub block_log_bytes: usize,
+    /// The log of the line size in bytes
+    pub line_log_bytes: usize,
+}
+
+impl ImmixBlockSize {
+    /// Returns an ImmixBlockSize or String containing error. Expects the sizes to be formatted as
+    /// "<log of block size>,<log of line size>". For example, "13,9" sets 8 KB blocks and 512 B lines.
+    pub fn parse(s: &str) -> Result<ImmixBlockSize, String> {
+        let sizes: Vec<&str> = s.split(',').collect();
+        if sizes.len() != 2 {
+            return Err(String::from(
+                "Please specify the log of the block size and the log of the line size, e.g. \"15,8\"",
+            ));
+        }
+        let parse = |s: &str| {
+            s.parse()
+                .map_err(|_| format!("Failed to parse size: {}", s))
+        };
+        Ok(ImmixBlockSize {
+            block_log_bytes: parse(sizes[0])?,
+            line_log_bytes: parse(sizes[1])?,
+        })
+    }
+
+    /// Return true if a block is at least the smallest block size and smaller than a chunk, a line
+    /// is at least the smallest line size and smaller than a block, and a block has at most 128
+    /// lines.
+    pub fn validate(&self) -> bool {
+        use crate::policy::immix::block::Block;
+        use crate::policy::immix::line::Line;
+        self.block_log_bytes >= Block::MIN_LOG_BYTES
+            && self.block_log_bytes
+                < crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK
+            && self.line_log_bytes >= Line::MIN_LOG_BYTES
+            && self.line_log_bytes < self.block_log_bytes
+            && self.block_log_bytes - self.line_log_bytes <= Block::MAX_LOG_LINES
+    }
+}
+
+impl FromStr for ImmixBlockSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ImmixBlockSize::parse(s)
+    }
+}
+
 impl Options {
     /// Return upper bound of the nursery size (in number of bytes)
     pub fn get_max_nursery_bytes(&self) -> usize {
@@ -638,11 +743,45 @@ mod gc_trigger_tests {
 options! {
     // The plan to use.
     plan:                  PlanSelector         [env_var: true, command_line: true] [always_valid] = PlanSelector::GenImmix,
-    // Number of GC worker threads. (There is always one GC controller thread.)
-    // FIXME: Currently we create GCWorkScheduler when MMTK is created, which is usually static.
-    // To allow this as a command-line option, we need to refactor the creation fo the `MMTK` instance.
-    // See: https://github.com/mmtk/mmtk-core/issues/532
+    // Number of GC worker threads at start-up. (There is always one GC controller thread.) The binding may change the number of
+    // workers later with `memory_manager::set_gc_threads`, up to the larger one of this and the number of CPUs.
     threads:               usize                [env_var: true, command_line: true] [|v: &usize| *v > 0]    = num_cpus::get(),
+    // Decide the number of workers that execute work packets in each GC based on the used memory after the last GC, and the number
+    // of roots in the last GC. Other workers stay parked in the GC. The number of active workers never exceeds the number of workers.
+    adaptive_threads:      bool                 [env_var: true, command_line: true]  [always_valid] = false,
+    // Perform GC synchronously on the mutator thread that requests it, without any GC thread. The `threads` option is ignored.
+    // The binding should call `memory_manager::collect_now` in `Collection::block_for_gc`.
+    synchronous_gc:        bool                 [env_var: true, command_line: true]  [always_valid] = false,
+    // If a GC takes longer than this many milliseconds, report the state of the work buckets, the GC workers, the GC status and
+    // the progress of stack scanning with `Collection::gc_watchdog_timeout`, which helps debug a stuck GC. By default, the report
+    // is printed to the standard error. 0 disables the watchdog. The watchdog does not work if
+    // `synchronous_gc` is set.
+    gc_watchdog_timeout:   usize                [env_var: true, command_line: true]  [always_valid] = 0,
+    // Abort the process after the watchdog reports the state of a GC that exceeds `gc_watchdog_timeout`.
+    gc_watchdog_abort:     bool                 [env_var: true, command_line: true]  [always_valid] = false,
+    // The order in which a GC worker executes the work packets in its local queue. `Fifo` is breadth-first. `Lifo` executes
+    // the newest packet first, which is closer to depth-first and has better locality. Other workers always steal the oldest packets.
+    work_queue_order:      WorkQueueOrder       [env_var: true, command_line: true]  [always_valid] = WorkQueueOrder::Fifo,
+    // The maximum number of work packets that a GC worker keeps in its local queue. More packets are added to the work buckets.
+    local_work_queue_size: usize                [env_var: true, command_line: true]  [|v: &usize| *v > 0] = 16,
+    // How a GC worker steals work packets from other workers when it runs out of work. It can be Sequential, Neighbor, Random
+    // or Disabled.
+    work_stealing:         StealPolicy          [env_var: true, command_line: true]  [always_valid] = StealPolicy::Sequential,
+    // Add the work packets that scan objects to the prioritized queues of the work buckets, so that workers scan objects before
+    // processing more edges. This only affects `ProcessEdgesWork` implementations that do not scan objects immediately.
+    prioritize_scan_objects: bool               [env_var: true, command_line: true]  [always_valid] = false,
+    // Scan large objects (objects larger than the plan allocates outside the large object space) before other objects found in the
+    // same work packet. If the objects are not scanned immediately, the large objects are scanned in separate work packets in the
+    // prioritized queue of the closure bucket, so that the work of scanning them can be shared by more workers earlier.
+    prioritize_large_objects: bool              [env_var: true, command_line: true]  [always_valid] = false,
+    // The order in which GC workers trace the object graph within a work packet. BreadthFirst or DepthFirst.
+    trace_order:           TraceOrder           [env_var: true, command_line: true]  [always_valid] = TraceOrder::BreadthFirst,
+    // When processing edges breadth-first, prefetch the object pointed by the edge this number of edges ahead of the
+    // current edge, as well as its mark bit and forwarding bits. 0 disables prefetching. With work_packet_stats, the
+    // number of prefetched objects is reported as total-worker.prefetches. It only counts the prefetches issued, and
+    // does not tell whether they hide any cache misses. Use hardware performance counters to measure that. The
+    // distance is at most 64 edges.
+    tracing_prefetch_distance: usize            [env_var: true, command_line: true]  [|v: &usize| *v <= 64] = 0,
     // Enable an optimization that only scans the part of the stack that has changed since the last GC (not supported)
     use_short_stack_scans: bool                 [env_var: true, command_line: true]  [always_valid] = false,
     // Enable a return barrier (not supported)
@@ -660,6 +799,51 @@ options! {
         = NurserySize { kind: NurseryKind::Bounded, min: DEFAULT_MIN_NURSERY, max: DEFAULT_MAX_NURSERY },
     // Should a major GC be performed when a system GC is required?
     full_heap_system_gc:   bool                 [env_var: true, command_line: true]  [always_valid] = false,
+    // Should generational plans (GenCopy and GenImmix) pretenure objects based on their allocation sites?
+    // Only objects allocated with memory_manager::alloc_with_site() are tracked. The allocation site side metadata
+    // needs more address space than we have on 32 bits, so this is only supported on 64 bits.
+    pretenuring:           bool                 [env_var: true, command_line: true]  [|v: &bool| !*v || cfg!(target_pointer_width = "64")] = false,
+    // An allocation site is pretenured if the percentage of its nursery allocation that survives a nursery GC is at least this value.
+    pretenure_threshold:   usize                [env_var: true, command_line: true]  [|v: &usize| *v <= 100] = 80,
+    // An allocation site needs to allocate at least this many bytes into the nursery between two GCs before we consider pretenuring it.
+    pretenure_min_bytes:   usize                [env_var: true, command_line: true]  [always_valid] = 64 * BYTES_IN_KBYTE,
+    // The barrier used by generational plans (GenCopy, GenImmix and StickyImmix). It can be ObjectBarrier, CardBarrier or FieldBarrier. Other plans ignore this option.
+    // The card-marking barrier finds objects in dirty cards with VO bits, so it requires the "vo_bit" feature.
+    generational_barrier:  BarrierSelector      [env_var: true, command_line: true]  [|v: &BarrierSelector| *v == BarrierSelector::ObjectBarrier || *v == BarrierSelector::FieldBarrier || (*v == BarrierSelector::CardBarrier && cfg!(feature = "vo_bit"))]
+        = BarrierSelector::ObjectBarrier,
+    // The card size in bytes for the card-marking barrier. It needs to be a power of two, at least 128 bytes, and at most the chunk size.
+    card_size:             usize                [env_var: true, command_line: true]  [|v: &usize| crate::util::card_table::is_valid_card_size(*v)] = 512,
+    // Make Immix a non-moving policy: no defragmentation, and no copying of nursery objects in StickyImmix. This is
+    // mainly used for debugging to rule out issues from copying. The default can be changed with the "immix_non_moving" feature.
+    immix_non_moving:      bool                 [env_var: true, command_line: true]  [always_valid] = cfg!(feature = "immix_non_moving"),
+    // Make every full heap GC of Immix a defrag GC (for debugging). This has no effect if Immix is non-moving.
+    immix_stress_defrag:   bool                 [env_var: true, command_line: true]  [always_valid] = false,
+    // Select every allocated block as a defrag source in a defrag GC (for debugging).
+    // Set both this and immix_stress_defrag to true to make Immix move as many objects as possible.
+    immix_defrag_every_block: bool              [env_var: true, command_line: true]  [always_valid] = false,
+    // The percentage of the heap reserved as free space for Immix defragmentation.
+    immix_defrag_headroom_percent: usize        [env_var: true, command_line: true]  [|v: &usize| *v <= 100] = 2,
+    // Do a chunk evacuation GC in Immix if the memory of the chunks used by the Immix space exceeds this percentage
+    // of the live bytes after the last GC. A chunk evacuation GC evacuates the least occupied chunks, and releases
+    // empty chunks so that the memory can be returned to the OS. This is disabled by default (0). A value such as 400
+    // triggers chunk evacuation when the chunks hold four times the live bytes.
+    immix_chunk_evacuation_trigger_percent: usize [env_var: true, command_line: true] [|v: &usize| *v == 0 || *v > 100] = 0,
+    // Mark the lines of an object when the object is scanned. Otherwise, mark the lines when the object is marked.
+    immix_mark_line_at_scan_time: bool          [env_var: true, command_line: true]  [always_valid] = true,
+    // Zero the unmarked lines after a GC cycle in Immix. This helps debug untraced objects.
+    // The default can be changed with the "immix_zero_on_release" feature.
+    immix_zero_on_release: bool                 [env_var: true, command_line: true]  [always_valid] = cfg!(feature = "immix_zero_on_release"),
+    // The log of the Immix block size and the log of the line size in bytes, e.g. "15,8". Smaller blocks mitigate
+    // fragmentation when defrag is disabled, and limit the objects that can be allocated into Immix to half a block.
+    // A block can have at most 128 lines. All the Immix spaces that are alive at the same time must use the same sizes.
+    // The default block size can be changed to 2^13 with the "immix_smaller_block" feature.
+    immix_block_size:      ImmixBlockSize       [env_var: true, command_line: true]  [|v: &ImmixBlockSize| v.validate()]
+        = ImmixBlockSize { block_log_bytes: crate::policy::immix::block::Block::DEFAULT_LOG_BYTES, line_log_bytes: crate::policy::immix::line::Line::DEFAULT_LOG_BYTES },
+    // Store the forwarding addresses of MarkCompact in side metadata (a live bitmap and a forwarding address for each
+    // region, as in the Compressor), rather than in an extra header word for each object. Objects need no extra header
+    // space in this mode. Objects that need a larger alignment than the minimum object size are not moved, and objects
+    // must not change their sizes when copied.
+    mark_compact_side_forwarding: bool          [env_var: true, command_line: true]  [always_valid] = false,
     // Should we shrink/grow the heap to adjust to application working set? (not supported)
     variable_size_heap:    bool                 [env_var: true, command_line: true]  [always_valid] = true,
     // Should finalization be disabled?
@@ -670,7 +854,10 @@ options! {
     // We disable weak reference processing by default, as we are still working on it. This will be changed to `false`
     // once weak reference processing is implemented properly.
     no_reference_types:    bool                 [env_var: true, command_line: true]  [always_valid] = true,
-    // The zeroing approach to use for new object allocations. Affects each plan differently. (not supported)
+    // The zeroing approach to use for new object allocations. Affects each plan differently. Currently only the nursery of
+    // generational plans uses it. `Nontemporal` zeroes memory with streaming stores when the pages are allocated. `Concurrent`
+    // zeroes the released pages after each GC in a separate thread with streaming stores. `Adaptive` is `Concurrent` if there is
+    // more than one CPU, and `Nontemporal` otherwise.
     nursery_zeroing:       NurseryZeroingOptions[env_var: true, command_line: true]  [always_valid] = NurseryZeroingOptions::Temporal,
     // How frequent (every X bytes) should we do a stress GC?
     stress_factor:         usize                [env_var: true, command_line: true]  [always_valid] = DEFAULT_STRESS_FACTOR,
@@ -686,6 +873,10 @@ options! {
     vm_space_start:        Address              [env_var: true, command_line: true]  [always_valid] = Address::ZERO,
     // The size of vmspace.
     vm_space_size:         usize                [env_var: true, command_line: true] [|v: &usize| *v > 0]    = usize::MAX,
+    // Use a remembered set for the VM space in generational plans with the object barrier or the field barrier. The objects in
+    // the VM space are assumed to only refer to objects in the VM space when they are added. In full heap GCs, the VM space is
+    // not traced, and only the objects and the slots in the VM space that have been recorded by the barrier are scanned.
+    vm_space_remembered_set: bool               [env_var: true, command_line: true]  [always_valid] = false,
     // Perf events to measure
     // Semicolons are used to separate events
     // Each event is in the format of event_name,pid,cpu (see man perf_event_open for what pid and cpu mean).
@@ -1013,6 +1204,56 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_work_queue_options() {
+        serial_test(|| {
+            let mut options = Options::default();
+            assert_eq!(*options.work_queue_order, WorkQueueOrder::Fifo);
+            assert_eq!(*options.work_stealing, StealPolicy::Sequential);
+            assert!(options.set_bulk_from_command_line(
+                "work_queue_order=Lifo work_stealing=Random local_work_queue_size=64"
+            ));
+            assert_eq!(*options.work_queue_order, WorkQueueOrder::Lifo);
+            assert_eq!(*options.work_stealing, StealPolicy::Random);
+            assert_eq!(*options.local_work_queue_size, 64);
+            assert!(!options.set_from_command_line("local_work_queue_size", "0"));
+            assert!(!options.set_from_command_line("work_stealing", "Everyone"));
+        })
+    }
+
+    #[test]
+    fn test_tracing_prefetch_distance() {
+        serial_test(|| {
+            let mut options = Options::default();
+            assert!(options.set_from_command_line("tracing_prefetch_distance", "64"));
+            assert_eq!(*options.tracing_prefetch_distance, 64);
+            assert!(!options.set_from_command_line("tracing_prefetch_distance", "65"));
+            assert!(!options
+                .set_from_command_line("tracing_prefetch_distance", &usize::MAX.to_string()));
+            assert_eq!(*options.tracing_prefetch_distance, 64);
+        })
+    }
+
+    #[test]
+    fn test_immix_block_size() {
+        serial_test(|| {
+            let mut options = Options::default();
+            assert!(options.set_from_command_line("immix_block_size", "13,9"));
+            let expected = ImmixBlockSize {
+                block_log_bytes: 13,
+                line_log_bytes: 9,
+            };
+            assert_eq!(*options.immix_block_size, expected);
+            // A line must be smaller than a block.
+            assert!(!options.set_from_command_line("immix_block_size", "15,15"));
+            // A block can have at most 128 lines.
+            assert!(!options.set_from_command_line("immix_block_size", "16,8"));
+            assert!(!options.set_from_command_line("immix_block_size", "12,8"));
+            assert!(!options.set_from_command_line("immix_block_size", "15"));
+            assert_eq!(*options.immix_block_size, expected);
+        })
+    }
+
     #[test]
     fn test_process_valid() {
         serial_test(|| {
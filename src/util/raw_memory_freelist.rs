@@ -197,6 +197,13 @@ impl RawMemoryFreeList {
         let res = super::memory::dzmmap_noreplace(start, bytes);
         assert!(res.is_ok(), "Can't get more space with mmap()");
     }
+    /// Unmap the memory of the free list. The free list must not be used afterwards.
+    pub(crate) fn unmap(&self) {
+        if self.high_water > self.base {
+            let res = super::memory::munmap(self.base, self.high_water - self.base);
+            assert!(res.is_ok(), "Can't unmap the free list with munmap()");
+        }
+    }
     pub fn get_limit(&self) -> Address {
         self.limit
     }
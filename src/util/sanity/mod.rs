@@ -1 +1,4 @@
+#[cfg(feature = "verify_remset")]
+pub mod remset_verifier;
+#[cfg(feature = "sanity")]
 pub mod sanity_checker;
@@ -0,0 +1,376 @@
+//! Remembered set verification for generational plans.
+//!
+//! If a binding forgets to call the write barrier for a reference store, a nursery GC may miss a
+//! pointer from a mature object to a nursery object, and the heap is corrupted long after the
+//! missing barrier. With the `verify_remset` feature, we scan all the mature objects before each
+//! nursery GC, and check that every pointer from a mature object to a nursery object is covered by
+//! the remembered set of the barrier in use:
+//! * Object barrier: the source object is logged.
+//! * Field barrier: the field is logged.
+//! * Card-marking barrier: the card that contains the slot is dirty.
+//!
+//! A pointer is also covered if the barrier remembered the source object or the memory region of
+//! the slot as a whole (e.g. for array copying). Objects are found with VO bits, so we can only
+//! check objects that have VO bits, and objects that support edge enqueuing.
+
+use crate::mmtk::{MMAPPER, SFT_MAP};
+use crate::plan::BarrierSelector;
+use crate::policy::space::Space;
+use crate::util::card_table::CardTable;
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::linear_scan::{DefaultObjectSize, ObjectIterator};
+use crate::util::metadata::field_log_bit;
+use crate::util::{Address, ObjectReference, VMWorkerThread};
+use crate::vm::edge_shape::Edge;
+use crate::vm::*;
+use crate::MMTK;
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+/// A pointer from a mature object to a nursery object that is not in the remembered set.
+struct UnrememberedEdge {
+    source: ObjectReference,
+    slot: Option<Address>,
+    target: ObjectReference,
+}
+
+/// The remembered set verifier. It records the objects and the memory regions that the barrier
+/// remembered as a whole since the last GC.
+#[derive(Default)]
+pub struct RemsetVerifier {
+    objects: Mutex<HashSet<ObjectReference>>,
+    /// Memory regions as `(start, bytes)`
+    regions: Mutex<Vec<(Address, usize)>>,
+}
+
+impl RemsetVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an object that is remembered as a whole by the barrier.
+    pub fn record_object(&self, object: ObjectReference) {
+        self.objects.lock().unwrap().insert(object);
+    }
+
+    /// Record a memory region that is remembered as a whole by the barrier.
+    pub fn record_region(&self, start: Address, bytes: usize) {
+        self.regions.lock().unwrap().push((start, bytes));
+    }
+
+    fn clear(&self) {
+        self.objects.lock().unwrap().clear();
+        self.regions.lock().unwrap().clear();
+    }
+
+    /// Verify the remembered set if the current GC is a nursery GC, and clear the recorded objects
+    /// and regions. This should be called after all the mutators are stopped, and before the plan
+    /// is prepared for the GC.
+    pub fn verify<VM: VMBinding>(&self, tls: VMWorkerThread, mmtk: &'static MMTK<VM>) {
+        if let Some(gen) = mmtk.plan.generational() {
+            if gen.is_current_gc_nursery() {
+                self.verify_nursery_gc(tls, mmtk);
+            }
+        }
+        self.clear();
+    }
+
+    fn verify_nursery_gc<VM: VMBinding>(&self, tls: VMWorkerThread, mmtk: &'static MMTK<VM>) {
+        let gen = mmtk.plan.generational().unwrap();
+        let mut unremembered = vec![];
+        let mut num_objects = 0;
+        for space in mmtk.plan.get_spaces() {
+            for chunk in Self::get_chunks(space) {
+                for source in ObjectIterator::<VM, DefaultObjectSize<VM>, true>::new(
+                    chunk,
+                    chunk + BYTES_IN_CHUNK,
+                ) {
+                    if gen.is_object_in_nursery(source)
+                        || !VM::VMScanning::support_edge_enqueuing(tls, source)
+                    {
+                        continue;
+                    }
+                    num_objects += 1;
+                    VM::VMScanning::scan_object(tls, source, &mut |edge: VM::VMEdge| {
+                        let target = edge.load();
+                        if !target.is_null()
+                            && gen.is_object_in_nursery(target)
+                            && !Self::is_retained_in_nursery(gen, target)
+                            && !self.is_remembered(mmtk, source, edge)
+                        {
+                            unremembered.push(UnrememberedEdge {
+                                source,
+                                slot: edge.slot_address(),
+                                target,
+                            });
+                        }
+                    });
+                }
+            }
+        }
+        debug!(
+            "Remembered set verification: scanned {} mature objects",
+            num_objects
+        );
+
+        for e in unremembered.iter() {
+            error!(
+                "Unremembered edge from mature object {} (slot {:?}) to nursery object {}",
+                e.source, e.slot, e.target
+            );
+        }
+        if !unremembered.is_empty() {
+            panic!(
+                "Remembered set verification failed: {} edge(s) from mature objects to nursery objects are not remembered. First: from {} (slot {:?}) to {}",
+                unremembered.len(),
+                unremembered[0].source,
+                unremembered[0].slot,
+                unremembered[0].target
+            );
+        }
+    }
+
+    /// Is the object kept in place in the nursery? Such objects are scanned as roots in nursery GCs.
+    fn is_retained_in_nursery<VM: VMBinding>(
+        _gen: &dyn crate::plan::GenerationalPlan<VM = VM>,
+        _object: ObjectReference,
+    ) -> bool {
+        #[cfg(feature = "object_pinning")]
+        {
+            _gen.is_object_retained_in_nursery(_object)
+        }
+        #[cfg(not(feature = "object_pinning"))]
+        {
+            false
+        }
+    }
+
+    /// Is the edge from a mature object to a nursery object in the remembered set?
+    fn is_remembered<VM: VMBinding>(
+        &self,
+        mmtk: &'static MMTK<VM>,
+        source: ObjectReference,
+        edge: VM::VMEdge,
+    ) -> bool {
+        let slot = edge.slot_address();
+        let barrier = mmtk.plan.constraints().barrier;
+        let card_table = mmtk.plan.generational().unwrap().card_table();
+        Self::is_remembered_by_barrier(
+            barrier,
+            || !VM::VMObjectModel::GLOBAL_LOG_BIT_SPEC.is_unlogged::<VM>(source, Ordering::SeqCst),
+            slot,
+            card_table,
+            source.to_address::<VM>(),
+        ) || self.is_recorded(source, slot)
+    }
+
+    /// Is the slot remembered by the barrier itself? `is_source_logged` returns whether the
+    /// source object is logged, and `source_address` is an address in the source object.
+    fn is_remembered_by_barrier(
+        barrier: BarrierSelector,
+        is_source_logged: impl FnOnce() -> bool,
+        slot: Option<Address>,
+        card_table: Option<&CardTable>,
+        source_address: Address,
+    ) -> bool {
+        match barrier {
+            BarrierSelector::ObjectBarrier => is_source_logged(),
+            // A slot without an address cannot be logged. The barrier remembers the object instead.
+            BarrierSelector::FieldBarrier => {
+                slot.map_or(false, |slot| !field_log_bit::is_field_unlogged(slot))
+            }
+            BarrierSelector::CardBarrier => card_table
+                .unwrap()
+                .is_card_dirty(slot.unwrap_or(source_address)),
+            BarrierSelector::NoBarrier => false,
+        }
+    }
+
+    /// Has the barrier recorded the source object, or a memory region that contains the slot?
+    fn is_recorded(&self, source: ObjectReference, slot: Option<Address>) -> bool {
+        self.objects.lock().unwrap().contains(&source)
+            || slot.map_or(false, |slot| {
+                self.regions
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|(start, bytes)| slot >= *start && slot < *start + *bytes)
+            })
+    }
+
+    /// Get the chunks that are mapped and currently used by the space.
+    fn get_chunks<VM: VMBinding>(space: &dyn Space<VM>) -> Vec<Address> {
+        let common = space.common();
+        let mut regions = vec![];
+        if common.contiguous {
+            regions.push((common.start, common.extent));
+        } else {
+            let mut start = space
+                .get_page_resource()
+                .common()
+                .get_head_discontiguous_region();
+            while !start.is_zero() {
+                regions.push((start, common.vm_map().get_contiguous_region_size(start)));
+                start = common.vm_map().get_next_contiguous_region(start);
+            }
+        }
+
+        let mut chunks = vec![];
+        for (start, bytes) in regions {
+            let mut chunk = start.align_down(BYTES_IN_CHUNK);
+            while chunk < start + bytes {
+                if MMAPPER.is_mapped_address(chunk)
+                    && SFT_MAP.has_sft_entry(chunk)
+                    && SFT_MAP.get_checked(chunk).name() == space.get_name()
+                {
+                    chunks.push(chunk);
+                }
+                chunk += BYTES_IN_CHUNK;
+            }
+        }
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::card_table::CARD_TABLE_SPEC;
+    use crate::util::heap::layout::vm_layout_constants;
+    use crate::util::metadata::side_metadata::SideMetadataContext;
+    use crate::util::test_util::{serial_test, with_cleanup};
+
+    #[test]
+    fn object_barrier_logged_object() {
+        let source = Address::ZERO + 0x1000usize;
+        let slot = Some(source + 8usize);
+        let remembered = |logged| {
+            RemsetVerifier::is_remembered_by_barrier(
+                BarrierSelector::ObjectBarrier,
+                || logged,
+                slot,
+                None,
+                source,
+            )
+        };
+        assert!(remembered(true));
+        assert!(!remembered(false));
+        // Without a barrier, nothing is remembered.
+        assert!(!RemsetVerifier::is_remembered_by_barrier(
+            BarrierSelector::NoBarrier,
+            || true,
+            slot,
+            None,
+            source
+        ));
+    }
+
+    #[test]
+    fn field_barrier_logged_field() {
+        serial_test(|| {
+            let data_addr =
+                vm_layout_constants::HEAP_START + (vm_layout_constants::BYTES_IN_CHUNK << 1);
+            let metadata = SideMetadataContext {
+                global: vec![field_log_bit::FIELD_LOG_BIT_SIDE_METADATA_SPEC],
+                local: vec![],
+            };
+            with_cleanup(
+                || {
+                    assert!(metadata
+                        .try_map_metadata_space(data_addr, BYTES_IN_CHUNK)
+                        .is_ok());
+                    let slot = data_addr + 16usize;
+                    let remembered = |slot| {
+                        RemsetVerifier::is_remembered_by_barrier(
+                            BarrierSelector::FieldBarrier,
+                            || unreachable!(),
+                            slot,
+                            None,
+                            data_addr,
+                        )
+                    };
+                    field_log_bit::mark_field_as_unlogged(slot);
+                    assert!(!remembered(Some(slot)));
+                    assert!(field_log_bit::log_field(slot));
+                    assert!(remembered(Some(slot)));
+                    // A slot without an address cannot be remembered by the field barrier.
+                    assert!(!remembered(None));
+                },
+                || {
+                    field_log_bit::bzero_field_log_bits(data_addr, BYTES_IN_CHUNK);
+                    metadata.ensure_unmap_metadata_space(data_addr, BYTES_IN_CHUNK);
+                },
+            )
+        })
+    }
+
+    #[test]
+    fn card_barrier_dirty_card() {
+        serial_test(|| {
+            let data_addr =
+                vm_layout_constants::HEAP_START + (vm_layout_constants::BYTES_IN_CHUNK << 1);
+            let metadata = SideMetadataContext {
+                global: CardTable::side_metadata_specs(),
+                local: vec![],
+            };
+            with_cleanup(
+                || {
+                    assert!(metadata
+                        .try_map_metadata_space(data_addr, BYTES_IN_CHUNK)
+                        .is_ok());
+                    let card_table = CardTable::new(512);
+                    let source = data_addr + 1024usize;
+                    let remembered = |slot| {
+                        RemsetVerifier::is_remembered_by_barrier(
+                            BarrierSelector::CardBarrier,
+                            || unreachable!(),
+                            slot,
+                            Some(&card_table),
+                            source,
+                        )
+                    };
+                    let slot = data_addr + 2048usize;
+                    assert!(!remembered(Some(slot)));
+                    card_table.mark_card(slot);
+                    assert!(remembered(Some(slot)));
+                    // A slot without an address is checked with the card of the source object.
+                    assert!(!remembered(None));
+                    card_table.mark_card(source);
+                    assert!(remembered(None));
+                    card_table.clear();
+                },
+                || {
+                    CARD_TABLE_SPEC.bzero_metadata(data_addr, BYTES_IN_CHUNK);
+                    metadata.ensure_unmap_metadata_space(data_addr, BYTES_IN_CHUNK);
+                },
+            )
+        })
+    }
+
+    #[test]
+    fn recorded_objects_and_regions() {
+        let verifier = RemsetVerifier::new();
+        let start = Address::ZERO + 0x10000usize;
+        let source = ObjectReference::from_raw_address(start);
+        let other = ObjectReference::from_raw_address(start + 0x100usize);
+        assert!(!verifier.is_recorded(source, Some(start + 8usize)));
+
+        // An object recorded by the modbuf covers all its slots.
+        verifier.record_object(source);
+        assert!(verifier.is_recorded(source, Some(start + 8usize)));
+        assert!(verifier.is_recorded(source, None));
+        assert!(!verifier.is_recorded(other, Some(start + 0x108usize)));
+
+        // A region covers the slots in it, but not slots outside it.
+        verifier.record_region(start + 0x100usize, 16);
+        assert!(verifier.is_recorded(other, Some(start + 0x108usize)));
+        assert!(!verifier.is_recorded(other, Some(start + 0x110usize)));
+        assert!(!verifier.is_recorded(other, None));
+
+        // Recorded objects and regions are cleared after each GC.
+        verifier.clear();
+        assert!(!verifier.is_recorded(source, None));
+        assert!(!verifier.is_recorded(other, Some(start + 0x108usize)));
+    }
+}
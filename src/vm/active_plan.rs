@@ -9,9 +9,11 @@ use crate::ObjectQueue;
 /// VM-specific methods for the current plan.
 pub trait ActivePlan<VM: VMBinding> {
     /// Return a reference to the current plan.
-    // TODO: I don't know how this can be implemented when we have multiple MMTk instances.
-    // This function is used by space and phase to refer to the current plan.
-    // Possibly we should remove the use of this function, and remove this function?
+    ///
+    /// MMTk only uses this in the object size analysis of the `analysis` feature. If the binding
+    /// creates multiple MMTk instances, it may return the plan of any of them, and the object sizes
+    /// are counted in the statistics of that instance.
+    // TODO: Possibly we should remove the use of this function, and remove this function?
     fn global() -> &'static dyn Plan<VM = VM>;
 
     /// Return whether there is a mutator created and associated with the thread.
@@ -1,13 +1,14 @@
 use crate::plan::MutatorContext;
 use crate::util::alloc::AllocationError;
 use crate::util::opaque_pointer::*;
-use crate::vm::VMBinding;
+use crate::vm::{ReferenceGlue, VMBinding};
 use crate::{scheduler::*, Mutator};
 
 /// Thread context for the spawned GC thread.  It is used by spawn_gc_thread.
 pub enum GCThreadContext<VM: VMBinding> {
     Controller(Box<GCController<VM>>),
     Worker(Box<GCWorker<VM>>),
+    Zeroing(Box<ZeroingThread<VM>>),
 }
 
 /// VM-specific methods for garbage collection.
@@ -34,6 +35,9 @@ pub trait Collection<VM: VMBinding> {
     /// is going to happen. Then MMTk starts a GC. For a stop-the-world GC, MMTk will then call `stop_all_mutators()`
     /// before the GC, and call `resume_mutators()` after the GC.
     ///
+    /// If the option `synchronous_gc` is set, there is no GC thread, and the VM should call
+    /// `memory_manager::collect_now` in this method to perform the GC on the current thread.
+    ///
     /// Arguments:
     /// * `tls`: The current thread pointer that should be blocked. The VM can optionally check if the current thread matches `tls`.
     fn block_for_gc(tls: VMMutatorThread);
@@ -51,7 +55,11 @@ pub trait Collection<VM: VMBinding> {
     ///     The spawned thread shall call `memory_manager::start_control_collector`.
     ///   * If `Worker` is passed, it means spawning a thread to run as a GC worker.
     ///     The spawned thread shall call `memory_manager::start_worker`.
-    ///   In either case, the `Box` inside should be passed back to the called function.
+    ///   * If `Zeroing` is passed, it means spawning a thread to zero the released pages of a space concurrently.
+    ///     The spawned thread shall call `memory_manager::start_zeroing_thread`.
+    ///   In any case, the `Box` inside should be passed back to the called function.
+    ///   Those functions return only if GC threads are stopped by `memory_manager::prepare_to_fork`,
+    ///   and the spawned thread should exit after that.
     fn spawn_gc_thread(tls: VMThread, ctx: GCThreadContext<VM>);
 
     /// Allow VM-specific behaviors for a mutator after all the mutators are stopped and before any actual GC work starts.
@@ -83,12 +91,52 @@ pub trait Collection<VM: VMBinding> {
         panic!("Out of memory with {:?}!", err_kind);
     }
 
+    /// Inform the VM that a work packet panicked during GC. MMTk catches panics in work packets,
+    /// discards the remaining work packets of the GC, waits until all the GC workers have stopped,
+    /// and then calls this method on the GC controller thread (or on the mutator that performs a
+    /// synchronous GC). The GC cannot be completed, and the mutators are not resumed. The GC workers
+    /// have been asked to exit. The VM should print its diagnostics and terminate, or call
+    /// `memory_manager::mmtk_shutdown`. If this method returns, MMTk resumes the panic on the
+    /// current thread, which ends the GC controller thread. No more GC can be performed.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the GC controller thread, or the mutator that performs the GC.
+    /// * `message`: A message that describes the panic, including the type of the work packet.
+    fn gc_panicked(_tls: VMWorkerThread, _message: &str) {}
+
+    /// Inform the VM that a GC has not finished within the time set by the option `gc_watchdog_timeout`.
+    /// MMTk calls this method on the GC controller thread once in each GC that exceeds the time, with a
+    /// report of the state of the work buckets, the GC workers, the GC status and the progress of stack
+    /// scanning. The GC keeps running after this method returns, unless the option `gc_watchdog_abort`
+    /// is set. The default implementation prints the report to the standard error.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the GC controller thread.
+    /// * `report`: A report of the state of the GC.
+    fn gc_watchdog_timeout(_tls: VMWorkerThread, report: &str) {
+        eprintln!("{}", report);
+    }
+
     /// Inform the VM to schedule finalization threads.
     ///
     /// Arguments:
     /// * `tls`: The thread pointer for the current GC thread.
     fn schedule_finalization(_tls: VMWorkerThread) {}
 
+    /// Run the finalizers of the objects that are ready for finalization but have not been fetched
+    /// by the binding when the MMTk instance is shut down. MMTk calls this method in
+    /// [`crate::memory_manager::mmtk_shutdown`] before unmapping the heap, so this is the last
+    /// chance to access those objects.
+    ///
+    /// Arguments:
+    /// * `tls`: The thread pointer for the thread that shuts down MMTk.
+    /// * `objects`: The objects that are ready for finalization.
+    fn run_pending_finalizers(
+        _tls: VMThread,
+        _objects: Vec<<VM::VMReferenceGlue as ReferenceGlue<VM>>::FinalizableType>,
+    ) {
+    }
+
     /// A hook for the VM to do work after forwarding objects.
     ///
     /// This function is called after all of the following have finished:
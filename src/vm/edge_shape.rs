@@ -55,6 +55,16 @@ pub trait Edge: Copy + Send + Debug + PartialEq + Eq + Hash {
     fn prefetch_store(&self) {
         // no-op by default
     }
+
+    /// Return the address of the slot if the edge is a slot in memory.
+    ///
+    /// This is used by the card-marking barrier to dirty the card that contains the slot, and to
+    /// only process the slots in dirty cards when scanning cards.  If the VM returns `None`, the
+    /// card-marking barrier will dirty the card that contains the source object, and card scanning
+    /// will process every slot of an object that overlaps a dirty card.
+    fn slot_address(&self) -> Option<Address> {
+        None
+    }
 }
 
 /// A simple edge implementation that represents a word-sized slot where an ObjectReference value
@@ -94,6 +104,10 @@ impl Edge for SimpleEdge {
     fn store(&self, object: ObjectReference) {
         unsafe { (*self.slot_addr).store(object, atomic::Ordering::Relaxed) }
     }
+
+    fn slot_address(&self) -> Option<Address> {
+        Some(self.as_address())
+    }
 }
 
 /// For backword compatibility, we let `Address` implement `Edge` so that existing bindings that
@@ -114,6 +128,10 @@ impl Edge for Address {
     fn store(&self, object: ObjectReference) {
         unsafe { Address::store(*self, object) }
     }
+
+    fn slot_address(&self) -> Option<Address> {
+        Some(*self)
+    }
 }
 
 #[test]
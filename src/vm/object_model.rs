@@ -85,6 +85,10 @@ pub trait ObjectModel<VM: VMBinding> {
     /// [forwarding bits](crate::vm::ObjectModel::LOCAL_FORWARDING_BITS_SPEC), you can often steal the last bit in
     /// the object header (due to alignment requirements) for the mark bit. Though some bindings such as the
     /// OpenJDK binding prefer to have the mark bits in side metadata to allow for bulk operations.
+    ///
+    /// With the `object_pinning` feature, copying spaces also use the mark bit to mark the objects that are
+    /// kept in place, while the other objects in the space are forwarded. In that case, the mark bit must not
+    /// overlap the [forwarding bits](crate::vm::ObjectModel::LOCAL_FORWARDING_BITS_SPEC).
     const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec;
 
     #[cfg(feature = "object_pinning")]
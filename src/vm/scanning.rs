@@ -2,7 +2,7 @@ use crate::plan::Mutator;
 use crate::scheduler::GCWorker;
 use crate::util::ObjectReference;
 use crate::util::VMWorkerThread;
-use crate::vm::edge_shape::Edge;
+use crate::vm::edge_shape::{Edge, MemorySlice};
 use crate::vm::VMBinding;
 
 /// Callback trait of scanning functions that report edges.
@@ -24,6 +24,21 @@ impl<ES: Edge, F: FnMut(ES)> EdgeVisitor<ES> for F {
     }
 }
 
+/// Callback trait of scanning functions that report memory slices.
+pub trait SliceVisitor<MS: MemorySlice> {
+    /// Call this function for each memory slice.
+    fn visit_slice(&mut self, slice: MS);
+}
+
+/// This lets us use closures as SliceVisitor.
+impl<MS: MemorySlice, F: FnMut(MS)> SliceVisitor<MS> for F {
+    fn visit_slice(&mut self, slice: MS) {
+        #[cfg(debug_assertions)]
+        trace!("(FunctionClosure) Visit slice {:?}", slice);
+        self(slice)
+    }
+}
+
 /// Callback trait of scanning functions that directly trace through edges.
 pub trait ObjectTracer {
     /// Call this function for the content of each edge,
@@ -146,6 +161,24 @@ pub trait Scanning<VM: VMBinding> {
         true
     }
 
+    /// Return true if the given object should be scanned as memory slices.
+    ///
+    /// -   If this returns true, MMTk core will call `scan_object_as_slices` on the object, and
+    ///     the reference fields in each reported slice may be processed by different GC workers
+    ///     in parallel.
+    /// -   Otherwise, MMTk core will call `support_edge_enqueuing` to decide how to scan the object.
+    ///
+    /// This is intended for large reference arrays, which would otherwise be scanned by a single
+    /// GC worker.  Like `support_edge_enqueuing`, this method is called for every object to be
+    /// scanned, so it must be fast.  The VM binding should return false for small objects.
+    ///
+    /// Arguments:
+    /// * `tls`: The VM-specific thread-local storage for the current worker.
+    /// * `object`: The object to be scanned.
+    fn support_slice_enqueuing(_tls: VMWorkerThread, _object: ObjectReference) -> bool {
+        false
+    }
+
     /// Delegated scanning of a object, visiting each reference field encountered.
     ///
     /// The VM shall call `edge_visitor.visit_edge` on each reference field.
@@ -194,6 +227,32 @@ pub trait Scanning<VM: VMBinding> {
         unreachable!("scan_object_and_trace_edges() will not be called when support_edge_enqueue() is always true.")
     }
 
+    /// Delegated scanning of a object, reporting its reference fields as memory slices.
+    ///
+    /// The VM shall call `slice_visitor.visit_slice` on a sequence of `VM::VMMemorySlice`s that
+    /// together cover all the reference fields of the object.  Each slice will be processed by a
+    /// separate work packet, which splits the edges in the slice into edge-processing work packets
+    /// of `ProcessEdgesWork::CAPACITY` edges each.  The VM decides the granularity.  For example,
+    /// it may report a large array as a sequence of slices of a few thousand elements each, so
+    /// that different GC workers can enumerate different parts of the array.
+    ///
+    /// The slices must not overlap, and each reference field must be covered by exactly one
+    /// slice.  `MemorySlice::iter_edges` must skip non-reference values in the slices.
+    ///
+    /// This method is only called if `support_slice_enqueuing` returns true for the object.
+    ///
+    /// Arguments:
+    /// * `tls`: The VM-specific thread-local storage for the current worker.
+    /// * `object`: The object to be scanned.
+    /// * `slice_visitor`: Called back for each memory slice.
+    fn scan_object_as_slices<SV: SliceVisitor<VM::VMMemorySlice>>(
+        _tls: VMWorkerThread,
+        _object: ObjectReference,
+        _slice_visitor: &mut SV,
+    ) {
+        unreachable!("scan_object_as_slices() will not be called when support_slice_enqueuing() is always false.")
+    }
+
     /// MMTk calls this method at the first time during a collection that thread's stacks
     /// have been scanned. This can be used (for example) to clean up
     /// obsolete compiled methods that are no longer being executed.
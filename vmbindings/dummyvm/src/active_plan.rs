@@ -3,17 +3,16 @@ use mmtk::vm::ActivePlan;
 use mmtk::util::opaque_pointer::*;
 use mmtk::Mutator;
 use crate::DummyVM;
-use crate::SINGLETON;
 
 pub struct VMActivePlan<> {}
 
 impl ActivePlan<DummyVM> for VMActivePlan {
     fn global() -> &'static dyn Plan<VM=DummyVM> {
-        SINGLETON.get_plan()
+        crate::current_instance().get_plan()
     }
 
     fn number_of_mutators() -> usize {
-        unimplemented!()
+        crate::MUTATORS.lock().unwrap().len()
     }
 
     fn is_mutator(_tls: VMThread) -> bool {
@@ -26,6 +25,7 @@ impl ActivePlan<DummyVM> for VMActivePlan {
     }
 
     fn mutators<'a>() -> Box<dyn Iterator<Item = &'a mut Mutator<DummyVM>> + 'a> {
-        unimplemented!()
+        let mutators = crate::MUTATORS.lock().unwrap().clone();
+        Box::new(mutators.into_iter().map(|m| unsafe { &mut *(m as *mut Mutator<DummyVM>) }))
     }
 }
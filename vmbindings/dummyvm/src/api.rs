@@ -6,6 +6,7 @@ use std::sync::atomic::Ordering;
 use std::ffi::CStr;
 use mmtk::memory_manager;
 use mmtk::AllocationSemantics;
+use mmtk::plan::AllocationSiteId;
 use mmtk::util::{ObjectReference, Address};
 use mmtk::util::opaque_pointer::*;
 use mmtk::scheduler::{GCController, GCWorker};
@@ -31,13 +32,16 @@ pub extern "C" fn mmtk_init(heap_size: usize) {
 
 #[no_mangle]
 pub extern "C" fn mmtk_bind_mutator(tls: VMMutatorThread) -> *mut Mutator<DummyVM> {
-    Box::into_raw(memory_manager::bind_mutator(&SINGLETON, tls))
+    let mutator = Box::into_raw(memory_manager::bind_mutator(&SINGLETON, tls));
+    crate::MUTATORS.lock().unwrap().push(mutator as usize);
+    mutator
 }
 
 #[no_mangle]
 pub extern "C" fn mmtk_destroy_mutator(mutator: *mut Mutator<DummyVM>) {
     // notify mmtk-core about destroyed mutator
     memory_manager::destroy_mutator(unsafe { &mut *mutator });
+    crate::MUTATORS.lock().unwrap().retain(|m| *m != mutator as usize);
     // turn the ptr back to a box, and let Rust properly reclaim it
     let _ = unsafe { Box::from_raw(mutator) };
 }
@@ -60,11 +64,105 @@ pub extern "C" fn mmtk_post_alloc(mutator: *mut Mutator<DummyVM>, refer: ObjectR
     memory_manager::post_alloc::<DummyVM>(unsafe { &mut *mutator }, refer, bytes, semantics)
 }
 
+#[no_mangle]
+pub extern "C" fn mmtk_alloc_with_site(mutator: *mut Mutator<DummyVM>, size: usize,
+                    align: usize, offset: usize, mut semantics: AllocationSemantics, site: AllocationSiteId) -> Address {
+    if size >= SINGLETON.get_plan().constraints().max_non_los_default_alloc_bytes {
+        semantics = AllocationSemantics::Los;
+    }
+    memory_manager::alloc_with_site::<DummyVM>(unsafe { &mut *mutator }, size, align, offset, semantics, site)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_post_alloc_with_site(mutator: *mut Mutator<DummyVM>, refer: ObjectReference,
+                                        bytes: usize, mut semantics: AllocationSemantics, site: AllocationSiteId) {
+    if bytes >= SINGLETON.get_plan().constraints().max_non_los_default_alloc_bytes {
+        semantics = AllocationSemantics::Los;
+    }
+    memory_manager::post_alloc_with_site::<DummyVM>(unsafe { &mut *mutator }, refer, bytes, semantics, site)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_is_allocation_site_pretenured(site: AllocationSiteId) -> bool {
+    memory_manager::get_allocation_site_stats(&SINGLETON, site).map_or(false, |stats| stats.pretenured)
+}
+
 #[no_mangle]
 pub extern "C" fn mmtk_will_never_move(object: ObjectReference) -> bool {
     !object.is_movable()
 }
 
+#[cfg(feature = "object_pinning")]
+#[no_mangle]
+pub extern "C" fn mmtk_pin_object(object: ObjectReference) -> bool {
+    memory_manager::pin_object::<DummyVM>(object)
+}
+
+#[cfg(feature = "object_pinning")]
+#[no_mangle]
+pub extern "C" fn mmtk_unpin_object(object: ObjectReference) -> bool {
+    memory_manager::unpin_object::<DummyVM>(object)
+}
+
+#[cfg(feature = "object_pinning")]
+#[no_mangle]
+pub extern "C" fn mmtk_is_pinned(object: ObjectReference) -> bool {
+    memory_manager::is_pinned::<DummyVM>(object)
+}
+
+#[cfg(feature = "vm_space")]
+#[no_mangle]
+pub extern "C" fn mmtk_add_vm_space_region(start: Address, size: usize) {
+    memory_manager::add_vm_space_region(&SINGLETON, start, size)
+}
+
+#[cfg(feature = "vm_space")]
+#[no_mangle]
+pub extern "C" fn mmtk_remove_vm_space_region(start: Address) {
+    memory_manager::remove_vm_space_region(&SINGLETON, start)
+}
+
+#[cfg(feature = "vm_space")]
+#[no_mangle]
+pub extern "C" fn mmtk_initialize_vm_space_object(object: ObjectReference) {
+    memory_manager::initialize_vm_space_object(&SINGLETON, object)
+}
+
+#[cfg(feature = "code_space")]
+#[no_mangle]
+pub extern "C" fn mmtk_make_code_executable(start: Address, bytes: usize) {
+    memory_manager::make_code_executable(&SINGLETON, start, bytes)
+}
+
+#[cfg(feature = "code_space")]
+#[no_mangle]
+pub extern "C" fn mmtk_make_code_writable(start: Address, bytes: usize) {
+    memory_manager::make_code_writable(&SINGLETON, start, bytes)
+}
+
+#[cfg(feature = "ro_space")]
+#[no_mangle]
+pub extern "C" fn mmtk_seal_read_only_space() {
+    memory_manager::seal_read_only_space(&SINGLETON)
+}
+
+#[cfg(feature = "heap_snapshot")]
+#[no_mangle]
+pub extern "C" fn mmtk_save_heap_snapshot(tls: VMWorkerThread, path: *const c_char) -> bool {
+    let path = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    memory_manager::save_heap_snapshot(&SINGLETON, tls, std::path::Path::new(path)).is_ok()
+}
+
+#[cfg(feature = "heap_snapshot")]
+#[no_mangle]
+pub extern "C" fn mmtk_load_heap_snapshot(path: *const c_char, start: Address) -> *mut mmtk::util::heap_snapshot::HeapSnapshot {
+    let path = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    match memory_manager::load_heap_snapshot(&SINGLETON, std::path::Path::new(path), start) {
+        Ok(snapshot) => Box::into_raw(Box::new(snapshot)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn mmtk_start_control_collector(tls: VMWorkerThread, controller: &'static mut GCController<DummyVM>) {
     memory_manager::start_control_collector(&SINGLETON, tls, controller);
@@ -80,6 +178,43 @@ pub extern "C" fn mmtk_initialize_collection(tls: VMThread) {
     memory_manager::initialize_collection(&SINGLETON, tls)
 }
 
+#[no_mangle]
+pub extern "C" fn mmtk_num_of_workers() -> usize {
+    memory_manager::num_of_workers(&SINGLETON)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_num_of_active_workers() -> usize {
+    memory_manager::num_of_active_workers(&SINGLETON)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_max_num_of_workers() -> usize {
+    memory_manager::max_num_of_workers(&SINGLETON)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_set_gc_threads(num_workers: usize) {
+    memory_manager::set_gc_threads(&SINGLETON, num_workers)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_prepare_to_fork() {
+    memory_manager::prepare_to_fork(&SINGLETON);
+    crate::collection::join_gc_threads();
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_after_fork(tls: VMThread) {
+    memory_manager::after_fork(&SINGLETON, tls)
+}
+
+#[no_mangle]
+pub extern "C" fn mmtk_shutdown(tls: VMThread) {
+    memory_manager::mmtk_shutdown(&SINGLETON, tls);
+    crate::collection::join_gc_threads();
+}
+
 #[no_mangle]
 pub extern "C" fn mmtk_disable_collection() {
     memory_manager::disable_collection(&SINGLETON)
@@ -136,6 +271,11 @@ pub extern "C" fn mmtk_handle_user_collection_request(tls: VMMutatorThread) {
     memory_manager::handle_user_collection_request::<DummyVM>(&SINGLETON, tls);
 }
 
+#[no_mangle]
+pub extern "C" fn mmtk_handle_user_compaction_request(tls: VMMutatorThread) {
+    memory_manager::handle_user_compaction_request::<DummyVM>(&SINGLETON, tls);
+}
+
 #[no_mangle]
 pub extern "C" fn mmtk_add_weak_candidate(reff: ObjectReference) {
     memory_manager::add_weak_candidate(&SINGLETON, reff)
@@ -1,35 +1,171 @@
 use crate::DummyVM;
+use mmtk::memory_manager;
 use mmtk::util::opaque_pointer::*;
+use mmtk::vm::ActivePlan;
 use mmtk::vm::Collection;
 use mmtk::vm::GCThreadContext;
 use mmtk::Mutator;
 use mmtk::MutatorContext;
+use mmtk::util::Address;
+use std::cell::Cell;
+use std::sync::{Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+lazy_static! {
+    /// Join handles of the spawned GC threads.
+    static ref GC_THREADS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(vec![]);
+    /// The message reported by `gc_panicked`, if a work packet has panicked.
+    static ref GC_PANIC_MESSAGE: Mutex<Option<String>> = Mutex::new(None);
+    /// Notified when `gc_panicked` is called.
+    static ref GC_PANICKED: Condvar = Condvar::new();
+    /// The report of the last GC that exceeds the option `gc_watchdog_timeout`.
+    static ref GC_WATCHDOG_REPORT: Mutex<Option<String>> = Mutex::new(None);
+    /// Notified when `gc_watchdog_timeout` is called.
+    static ref GC_WATCHDOG_FIRED: Condvar = Condvar::new();
+    /// The number of GCs that have resumed the mutators.
+    static ref FINISHED_GCS: Mutex<usize> = Mutex::new(0);
+    /// Notified when `resume_mutators` is called.
+    static ref GC_FINISHED: Condvar = Condvar::new();
+}
+
+thread_local! {
+    /// The number of finished GCs before the current thread requests the next GC, if `block_for_gc` waits for the GC
+    /// on the current thread. See `with_blocking_gc`.
+    static FINISHED_GCS_BEFORE_REQUEST: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Return the number of GCs that have finished so far.
+pub fn finished_gcs() -> usize {
+    *FINISHED_GCS.lock().unwrap()
+}
+
+/// Wait until the number of finished GCs reaches `count`. Return false if it does not happen within the timeout.
+/// This can be used to wait for a GC run by GC threads if `block_for_gc` does not wait for it.
+pub fn wait_for_finished_gcs(count: usize, timeout: Duration) -> bool {
+    let finished = FINISHED_GCS.lock().unwrap();
+    let (_finished, result) = GC_FINISHED
+        .wait_timeout_while(finished, timeout, |finished| *finished < count)
+        .unwrap();
+    !result.timed_out()
+}
+
+/// Call `f`, in which `block_for_gc` waits until the GC run by GC threads finishes, like a real VM. Otherwise,
+/// `block_for_gc` panics without `synchronous_gc`. The GCs must be requested by the current thread, one at a time.
+pub fn with_blocking_gc<R>(f: impl FnOnce() -> R) -> R {
+    let old = FINISHED_GCS_BEFORE_REQUEST.with(|before| before.replace(Some(finished_gcs())));
+    let result = f();
+    FINISHED_GCS_BEFORE_REQUEST.with(|before| before.set(old));
+    result
+}
+
+/// Wait until MMTk reports a panic in a work packet with `gc_panicked`, and return the message.
+/// Return `None` if no panic is reported within the timeout.
+pub fn wait_for_gc_panic(timeout: Duration) -> Option<String> {
+    let message = GC_PANIC_MESSAGE.lock().unwrap();
+    let (message, _) = GC_PANICKED
+        .wait_timeout_while(message, timeout, |message| message.is_none())
+        .unwrap();
+    message.clone()
+}
+
+/// Wait until MMTk reports a GC that exceeds the option `gc_watchdog_timeout` with `gc_watchdog_timeout`,
+/// and return the report. Return `None` if no report is made within the timeout.
+pub fn wait_for_gc_watchdog_report(timeout: Duration) -> Option<String> {
+    let report = GC_WATCHDOG_REPORT.lock().unwrap();
+    let (report, _) = GC_WATCHDOG_FIRED
+        .wait_timeout_while(report, timeout, |report| report.is_none())
+        .unwrap();
+    report.clone()
+}
+
+/// Wait until all the spawned GC threads exit. GC threads exit after they are stopped by
+/// `memory_manager::prepare_to_fork` or `memory_manager::mmtk_shutdown`. The controller thread
+/// exits with a panic if a GC is aborted because a work packet panicked, which has been reported
+/// by `gc_panicked`.
+pub fn join_gc_threads() {
+    let handles = std::mem::take(&mut *GC_THREADS.lock().unwrap());
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+/// The GC thread context is created by MMTk for a new thread. It is safe to move it to that thread.
+struct SendableGCThreadContext(GCThreadContext<DummyVM>);
+unsafe impl Send for SendableGCThreadContext {}
 
 pub struct VMCollection {}
 
 impl Collection<DummyVM> for VMCollection {
-    fn stop_all_mutators<F>(_tls: VMWorkerThread, _mutator_visitor: F)
+    fn stop_all_mutators<F>(_tls: VMWorkerThread, mut mutator_visitor: F)
     where
         F: FnMut(&'static mut Mutator<DummyVM>),
     {
-        unimplemented!()
+        // DummyVM mutators do not run by themselves. They are stopped as long as the test thread is in MMTk.
+        for mutator in <crate::active_plan::VMActivePlan as ActivePlan<DummyVM>>::mutators() {
+            mutator_visitor(mutator);
+        }
     }
 
     fn resume_mutators(_tls: VMWorkerThread) {
-        unimplemented!()
+        *FINISHED_GCS.lock().unwrap() += 1;
+        GC_FINISHED.notify_all();
     }
 
-    fn block_for_gc(_tls: VMMutatorThread) {
-        panic!("block_for_gc is not implemented")
+    fn block_for_gc(tls: VMMutatorThread) {
+        let mmtk = crate::current_instance();
+        if *mmtk.get_options().synchronous_gc {
+            memory_manager::collect_now(mmtk, tls);
+        } else if let Some(before) = FINISHED_GCS_BEFORE_REQUEST.with(|before| before.get()) {
+            assert!(wait_for_finished_gcs(before + 1, Duration::from_secs(10)), "The GC did not finish");
+            FINISHED_GCS_BEFORE_REQUEST.with(|before| before.set(Some(finished_gcs())));
+        } else {
+            panic!("block_for_gc is not implemented")
+        }
     }
 
-    fn spawn_gc_thread(_tls: VMThread, _ctx: GCThreadContext<DummyVM>) {}
+    /// Spawn a real thread for each GC thread. The threads keep running until they are stopped by
+    /// `memory_manager::prepare_to_fork`. They use the same MMTk instance as the current thread.
+    fn spawn_gc_thread(_tls: VMThread, ctx: GCThreadContext<DummyVM>) {
+        let ctx = SendableGCThreadContext(ctx);
+        let mmtk = crate::current_instance();
+        let handle = std::thread::spawn(move || {
+            crate::use_instance(mmtk);
+            // GC work packets require a valid thread pointer.
+            let tls = VMWorkerThread(VMThread(OpaquePointer::from_address(unsafe {
+                Address::from_usize(0x2000)
+            })));
+            match ctx {
+                SendableGCThreadContext(GCThreadContext::Controller(mut controller)) => {
+                    memory_manager::start_control_collector(mmtk, tls, &mut controller)
+                }
+                SendableGCThreadContext(GCThreadContext::Worker(mut worker)) => {
+                    memory_manager::start_worker(mmtk, tls, &mut worker)
+                }
+                SendableGCThreadContext(GCThreadContext::Zeroing(mut zeroing_thread)) => {
+                    memory_manager::start_zeroing_thread(mmtk, tls, &mut zeroing_thread)
+                }
+            }
+        });
+        GC_THREADS.lock().unwrap().push(handle);
+    }
+
+    fn gc_panicked(_tls: VMWorkerThread, message: &str) {
+        eprintln!("GC panicked: {}", message);
+        *GC_PANIC_MESSAGE.lock().unwrap() = Some(message.to_string());
+        GC_PANICKED.notify_all();
+    }
+
+    fn gc_watchdog_timeout(_tls: VMWorkerThread, report: &str) {
+        eprintln!("{}", report);
+        *GC_WATCHDOG_REPORT.lock().unwrap() = Some(report.to_string());
+        GC_WATCHDOG_FIRED.notify_all();
+    }
 
     fn prepare_mutator<T: MutatorContext<DummyVM>>(
         _tls_w: VMWorkerThread,
         _tls_m: VMMutatorThread,
         _mutator: &T,
     ) {
-        unimplemented!()
     }
 }
@@ -37,6 +37,16 @@ impl Edge for DummyVMEdge {
             DummyVMEdge::Tagged(e) => e.store(object),
         }
     }
+
+    fn slot_address(&self) -> Option<Address> {
+        match self {
+            DummyVMEdge::Simple(e) => e.slot_address(),
+            #[cfg(target_pointer_width = "64")]
+            DummyVMEdge::Compressed(e) => Some(e.as_address()),
+            DummyVMEdge::Offset(e) => Some(e.slot_address()),
+            DummyVMEdge::Tagged(_) => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -44,6 +54,13 @@ pub struct DummyVMMemorySlice(*mut [ObjectReference]);
 
 unsafe impl Send for DummyVMMemorySlice {}
 
+impl DummyVMMemorySlice {
+    /// A slice of `len` reference slots starting at `start`.
+    pub fn from_raw_parts(start: Address, len: usize) -> Self {
+        DummyVMMemorySlice(std::ptr::slice_from_raw_parts_mut(start.to_mut_ptr::<ObjectReference>(), len))
+    }
+}
+
 impl MemorySlice for DummyVMMemorySlice {
     type Edge = DummyVMEdge;
     type EdgeIterator = DummyVMMemorySliceIterator;
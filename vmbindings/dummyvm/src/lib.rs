@@ -34,6 +34,7 @@ impl VMBinding for DummyVM {
     const MAX_ALIGNMENT: usize = 1 << 6;
 }
 
+use std::cell::Cell;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
@@ -49,4 +50,22 @@ lazy_static! {
         MMTK_INITIALIZED.store(true, std::sync::atomic::Ordering::Relaxed);
         *ret
     };
+    /// The addresses of the mutators that are bound and not yet destroyed.
+    pub static ref MUTATORS: Mutex<Vec<usize>> = Mutex::new(vec![]);
+}
+
+thread_local! {
+    /// The MMTk instance used by the current thread, if it is not `SINGLETON`.
+    static CURRENT_INSTANCE: Cell<Option<&'static MMTK<DummyVM>>> = const { Cell::new(None) };
+}
+
+/// Let the current thread use `mmtk` instead of `SINGLETON`. When MMTk calls back to DummyVM on this thread, e.g. to
+/// block for GC, DummyVM uses this instance. The GC threads spawned from this thread use this instance as well.
+pub fn use_instance(mmtk: &'static MMTK<DummyVM>) {
+    CURRENT_INSTANCE.with(|instance| instance.set(Some(mmtk)));
+}
+
+/// Return the MMTk instance used by the current thread.
+pub fn current_instance() -> &'static MMTK<DummyVM> {
+    CURRENT_INSTANCE.with(|instance| instance.get()).unwrap_or_else(|| &SINGLETON)
 }
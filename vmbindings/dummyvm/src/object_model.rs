@@ -6,32 +6,57 @@ use crate::DummyVM;
 pub struct VMObjectModel {}
 
 // This is intentionally set to a non-zero value to see if it breaks.
-// Change this if you want to test other values.
-pub const OBJECT_REF_OFFSET: usize = 4;
+// Change this if you want to test other values. It needs to be word-aligned so that the header
+// can hold a forwarding pointer when objects are copied.
+pub const OBJECT_REF_OFFSET: usize = 8;
+
+/// The size of an object is stored as a `u32` at the object start, before the header. A test needs
+/// to store the size if MMTk may query the object size in the test.
+pub const OBJECT_SIZE_OFFSET: usize = 0;
+
+/// All the words from this offset to the end of an object are reference fields, each holding an
+/// `ObjectReference` or null. A test needs to initialize them if MMTk may scan the object in the test.
+pub const OBJECT_REFS_OFFSET: usize = 16;
 
 impl ObjectModel<DummyVM> for VMObjectModel {
-    const GLOBAL_LOG_BIT_SPEC: VMGlobalLogBitSpec = VMGlobalLogBitSpec::in_header(0);
+    // The log bit cannot be in the header, as it would overlap the forwarding bits of mature objects.
+    const GLOBAL_LOG_BIT_SPEC: VMGlobalLogBitSpec = VMGlobalLogBitSpec::side_first();
     const LOCAL_FORWARDING_POINTER_SPEC: VMLocalForwardingPointerSpec = VMLocalForwardingPointerSpec::in_header(0);
     const LOCAL_FORWARDING_BITS_SPEC: VMLocalForwardingBitsSpec = VMLocalForwardingBitsSpec::in_header(0);
-    const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec = VMLocalMarkBitSpec::in_header(0);
+    // Immix and MarkSweep require a side mark bit.
+    const LOCAL_MARK_BIT_SPEC: VMLocalMarkBitSpec = VMLocalMarkBitSpec::side_first();
     const LOCAL_LOS_MARK_NURSERY_SPEC: VMLocalLOSMarkNurserySpec = VMLocalLOSMarkNurserySpec::in_header(0);
+    #[cfg(feature = "object_pinning")]
+    const LOCAL_PINNING_BIT_SPEC: VMLocalPinningBitSpec = VMLocalPinningBitSpec::side_after(Self::LOCAL_MARK_BIT_SPEC.as_spec());
 
     const OBJECT_REF_OFFSET_LOWER_BOUND: isize = OBJECT_REF_OFFSET as isize;
 
     fn copy(
-        _from: ObjectReference,
-        _semantics: CopySemantics,
-        _copy_context: &mut GCWorkerCopyContext<DummyVM>,
+        from: ObjectReference,
+        semantics: CopySemantics,
+        copy_context: &mut GCWorkerCopyContext<DummyVM>,
     ) -> ObjectReference {
-        unimplemented!()
+        let bytes = Self::get_size_when_copied(from);
+        let align = Self::get_align_when_copied(from);
+        let offset = Self::get_align_offset_when_copied(from);
+        let to = copy_context.alloc_copy(from, bytes, align, offset, semantics);
+        let to_obj = Self::get_reference_when_copied_to(from, to);
+        Self::copy_to(from, to_obj, Address::ZERO);
+        copy_context.post_copy(to_obj, bytes, semantics);
+        to_obj
     }
 
-    fn copy_to(_from: ObjectReference, _to: ObjectReference, _region: Address) -> Address {
-        unimplemented!()
+    fn copy_to(from: ObjectReference, to: ObjectReference, _region: Address) -> Address {
+        let bytes = Self::get_current_size(from);
+        let src = Self::ref_to_object_start(from);
+        let dst = Self::ref_to_object_start(to);
+        // The source and the destination may overlap when objects are slid down by compaction.
+        unsafe { std::ptr::copy::<u8>(src.to_ptr(), dst.to_mut_ptr(), bytes) };
+        dst + bytes
     }
 
-    fn get_current_size(_object: ObjectReference) -> usize {
-        unimplemented!()
+    fn get_current_size(object: ObjectReference) -> usize {
+        unsafe { (Self::ref_to_object_start(object) + OBJECT_SIZE_OFFSET).load::<u32>() as usize }
     }
 
     fn get_size_when_copied(object: ObjectReference) -> usize {
@@ -46,8 +71,8 @@ impl ObjectModel<DummyVM> for VMObjectModel {
         0
     }
 
-    fn get_reference_when_copied_to(_from: ObjectReference, _to: Address) -> ObjectReference {
-        unimplemented!()
+    fn get_reference_when_copied_to(_from: ObjectReference, to: Address) -> ObjectReference {
+        Self::address_to_ref(to)
     }
 
     fn get_type_descriptor(_reference: ObjectReference) -> &'static [i8] {
@@ -1,42 +1,140 @@
 use crate::DummyVM;
-use crate::edges::DummyVMEdge;
+use crate::edges::{DummyVMEdge, DummyVMMemorySlice};
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
 use mmtk::util::opaque_pointer::*;
-use mmtk::util::ObjectReference;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::edge_shape::SimpleEdge;
 use mmtk::vm::EdgeVisitor;
+use mmtk::vm::ObjectModel;
 use mmtk::vm::RootsWorkFactory;
 use mmtk::vm::Scanning;
+use mmtk::vm::SliceVisitor;
 use mmtk::Mutator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread::ThreadId;
+
+lazy_static! {
+    /// Root slots added by tests with `add_root`.
+    static ref ROOTS: Mutex<Vec<DummyVMEdge>> = Mutex::new(vec![]);
+    /// The objects scanned by `scan_object` in order, and the threads that scanned them, if recording is started by
+    /// `record_scanned_objects`.
+    static ref SCANNED_OBJECTS: Mutex<Option<Vec<(ObjectReference, ThreadId)>>> = Mutex::new(None);
+}
+
+/// Objects with at least this many reference fields are scanned as memory slices.
+static SLICE_SCANNING_MIN_FIELDS: AtomicUsize = AtomicUsize::new(usize::MAX);
+/// The number of reference fields in each memory slice of an object scanned as slices.
+static FIELDS_PER_SLICE: AtomicUsize = AtomicUsize::new(usize::MAX);
+/// The number of memory slices reported by `scan_object_as_slices`.
+static REPORTED_SLICES: AtomicUsize = AtomicUsize::new(0);
+
+/// Add a slot outside the MMTk heap that holds an `ObjectReference` as a VM-specific root.
+pub fn add_root(slot: Address) {
+    ROOTS.lock().unwrap().push(DummyVMEdge::Simple(SimpleEdge::from_address(slot)));
+}
+
+/// Remove all the roots added by `add_root`.
+pub fn clear_roots() {
+    ROOTS.lock().unwrap().clear();
+}
+
+/// Start recording the objects scanned in GCs.
+pub fn record_scanned_objects() {
+    *SCANNED_OBJECTS.lock().unwrap() = Some(vec![]);
+}
+
+/// Stop recording the scanned objects, and return the objects scanned since `record_scanned_objects` in order, with
+/// the threads that scanned them.
+pub fn take_scanned_objects() -> Vec<(ObjectReference, ThreadId)> {
+    SCANNED_OBJECTS.lock().unwrap().take().expect("The scanned objects are not recorded")
+}
+
+/// Scan the objects that have at least `min_fields` reference fields as memory slices of `fields_per_slice` fields
+/// each, like large arrays. Different GC workers may process the fields in different slices.
+pub fn scan_large_objects_as_slices(min_fields: usize, fields_per_slice: usize) {
+    assert!(fields_per_slice > 0);
+    SLICE_SCANNING_MIN_FIELDS.store(min_fields, Ordering::SeqCst);
+    FIELDS_PER_SLICE.store(fields_per_slice, Ordering::SeqCst);
+}
+
+/// The number of memory slices reported to MMTk so far.
+pub fn reported_slices() -> usize {
+    REPORTED_SLICES.load(Ordering::SeqCst)
+}
+
+/// The number of reference fields of an object. All the words after the header are reference fields.
+fn num_fields(object: ObjectReference) -> usize {
+    (VMObjectModel::get_current_size(object) - OBJECT_REFS_OFFSET) / BYTES_IN_ADDRESS
+}
 
 pub struct VMScanning {}
 
 impl Scanning<DummyVM> for VMScanning {
     fn scan_roots_in_all_mutator_threads(_tls: VMWorkerThread, _factory: impl RootsWorkFactory<DummyVMEdge>) {
-        unimplemented!()
+        // DummyVM mutators do not have roots.
     }
     fn scan_roots_in_mutator_thread(
         _tls: VMWorkerThread,
         _mutator: &'static mut Mutator<DummyVM>,
         _factory: impl RootsWorkFactory<DummyVMEdge>,
     ) {
-        unimplemented!()
+        // DummyVM mutators do not have roots.
     }
-    fn scan_vm_specific_roots(_tls: VMWorkerThread, _factory: impl RootsWorkFactory<DummyVMEdge>) {
-        unimplemented!()
+    fn scan_vm_specific_roots(_tls: VMWorkerThread, mut factory: impl RootsWorkFactory<DummyVMEdge>) {
+        // DummyVM only has the roots added by tests.
+        let roots = ROOTS.lock().unwrap().clone();
+        if !roots.is_empty() {
+            factory.create_process_edge_roots_work(roots);
+        }
     }
     fn scan_object<EV: EdgeVisitor<DummyVMEdge>>(
         _tls: VMWorkerThread,
-        _object: ObjectReference,
-        _edge_visitor: &mut EV,
+        object: ObjectReference,
+        edge_visitor: &mut EV,
     ) {
-        unimplemented!()
+        if let Some(objects) = SCANNED_OBJECTS.lock().unwrap().as_mut() {
+            objects.push((object, std::thread::current().id()));
+        }
+        // All the words after the header are reference fields. Null fields are not reported.
+        let start = VMObjectModel::ref_to_object_start(object);
+        let end = start + VMObjectModel::get_current_size(object);
+        let mut slot = start + OBJECT_REFS_OFFSET;
+        while slot < end {
+            if !unsafe { slot.load::<ObjectReference>() }.is_null() {
+                edge_visitor.visit_edge(DummyVMEdge::Simple(SimpleEdge::from_address(slot)));
+            }
+            slot += BYTES_IN_ADDRESS;
+        }
+    }
+    fn support_slice_enqueuing(_tls: VMWorkerThread, object: ObjectReference) -> bool {
+        num_fields(object) >= SLICE_SCANNING_MIN_FIELDS.load(Ordering::Relaxed)
     }
-    fn notify_initial_thread_scan_complete(_partial_scan: bool, _tls: VMWorkerThread) {
-        unimplemented!()
+    fn scan_object_as_slices<SV: SliceVisitor<DummyVMMemorySlice>>(
+        _tls: VMWorkerThread,
+        object: ObjectReference,
+        slice_visitor: &mut SV,
+    ) {
+        let fields = VMObjectModel::ref_to_object_start(object) + OBJECT_REFS_OFFSET;
+        let num_fields = num_fields(object);
+        let fields_per_slice = FIELDS_PER_SLICE.load(Ordering::Relaxed);
+        let mut index = 0;
+        while index < num_fields {
+            let len = fields_per_slice.min(num_fields - index);
+            slice_visitor.visit_slice(DummyVMMemorySlice::from_raw_parts(
+                fields + index * BYTES_IN_ADDRESS,
+                len,
+            ));
+            index += len;
+            REPORTED_SLICES.fetch_add(1, Ordering::SeqCst);
+        }
     }
+    fn notify_initial_thread_scan_complete(_partial_scan: bool, _tls: VMWorkerThread) {}
     fn supports_return_barrier() -> bool {
-        unimplemented!()
+        false
     }
     fn prepare_for_roots_re_scanning() {
-        unimplemented!()
+        // The roots added by tests are scanned again as they are.
     }
 }
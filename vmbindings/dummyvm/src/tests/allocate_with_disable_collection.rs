@@ -11,8 +11,8 @@ pub fn allocate_with_disable_collection() {
     mmtk_init(MB);
     mmtk_initialize_collection(VMThread::UNINITIALIZED);
     let handle = mmtk_bind_mutator(VMMutatorThread(VMThread::UNINITIALIZED));
-    // Allocate 1MB. It should be fine.
-    let addr = mmtk_alloc(handle, MB, 8, 0, AllocationSemantics::Default);
+    // Allocate half MB. It should be fine. The side metadata of the space also takes some of the heap.
+    let addr = mmtk_alloc(handle, MB >> 1, 8, 0, AllocationSemantics::Default);
     assert!(!addr.is_zero());
     // Disable GC
     mmtk_disable_collection();
@@ -0,0 +1,68 @@
+// GITHUB-CI: MMTK_PLAN=GenImmix GenCopy
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use crate::tests::fixtures::{gc_mutator_tls, init_object, run_gc};
+use mmtk::plan::AllocationSiteId;
+use mmtk::memory_manager;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+use mmtk::Mutator;
+use crate::DummyVM;
+
+const SITE: AllocationSiteId = 42;
+/// Objects without reference fields.
+const SIZE: usize = crate::object_model::OBJECT_REFS_OFFSET;
+const NUM_OBJECTS: usize = 64;
+
+fn alloc_from_site(handle: *mut Mutator<DummyVM>) -> ObjectReference {
+    let addr = mmtk_alloc_with_site(handle, SIZE, 8, 0, AllocationSemantics::Default, SITE);
+    assert!(!addr.is_zero());
+    init_object(addr, 0);
+    let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+    mmtk_post_alloc_with_site(handle, obj, SIZE, AllocationSemantics::Default, SITE);
+    assert!(mmtk_is_in_mmtk_spaces(obj));
+    obj
+}
+
+/// This test allocates objects from an allocation site and keeps all of them alive through a nursery GC.
+/// The site is then pretenured, and later objects from the site are allocated into the mature space,
+/// so they are not moved by the next nursery GC.
+#[test]
+pub fn allocate_with_site() {
+    const MB: usize = 1024 * 1024;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.pretenuring.set(true));
+        assert!(builder.options.pretenure_min_bytes.set(NUM_OBJECTS * SIZE));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB). Otherwise, all the GCs after the first GC are full heap GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    // All the objects survive the nursery GC, as they are reachable from the roots.
+    let roots: Box<[ObjectReference]> = (0..NUM_OBJECTS).map(|_| alloc_from_site(handle)).collect();
+    for root in roots.iter() {
+        crate::scanning::add_root(Address::from_ref(root));
+    }
+    assert!(!mmtk_is_allocation_site_pretenured(SITE));
+    if memory_manager::get_allocation_site_stats(&crate::SINGLETON, SITE).is_none() {
+        // Only GenCopy and GenImmix support pretenuring.
+        return;
+    }
+    let before: Vec<ObjectReference> = roots.to_vec();
+    run_gc(tls);
+    // The objects are promoted out of the nursery.
+    assert!(roots.iter().zip(before.iter()).all(|(after, before)| after != before));
+    assert!(mmtk_is_allocation_site_pretenured(SITE));
+
+    // A pretenured object is allocated into the mature space. It is not moved by a nursery GC.
+    let pretenured = Box::new(alloc_from_site(handle));
+    crate::scanning::add_root(Address::from_ref(&*pretenured));
+    let before = *pretenured;
+    run_gc(tls);
+    assert_eq!(*pretenured, before);
+}
@@ -0,0 +1,65 @@
+// GITHUB-CI: MMTK_PLAN=GenImmix GenCopy StickyImmix
+// GITHUB-CI: FEATURES=vo_bit
+
+use crate::api::*;
+use crate::edges::DummyVMEdge;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::memory_manager;
+use mmtk::plan::BarrierSelector;
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::edge_shape::{Edge, SimpleEdge};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// This test uses the card-marking barrier for a generational plan. It writes a reference to a young
+/// object into a mature object with the barrier, and checks that the card of the slot is dirty. The young
+/// object is only reachable through the card, and it survives a nursery GC, after which the card is clean.
+#[test]
+pub fn barrier_card() {
+    const MB: usize = 1024 * 1024;
+    const TARGET_FIELDS: usize = 3;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.generational_barrier.set(BarrierSelector::CardBarrier));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB) for nursery GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    assert_eq!(
+        crate::SINGLETON.get_plan().constraints().barrier,
+        BarrierSelector::CardBarrier
+    );
+    let card_table = memory_manager::get_card_table(&crate::SINGLETON).unwrap();
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    // The source object becomes mature after it survives a GC.
+    let src = Box::new(alloc_object(handle, 1, AllocationSemantics::Default));
+    crate::scanning::add_root(Address::from_ref(&*src));
+    mmtk_handle_user_collection_request(tls);
+    let slot = field(*src, 0);
+    assert!(!card_table.is_card_dirty(slot));
+
+    let target = alloc_object(handle, TARGET_FIELDS, AllocationSemantics::Default);
+    let mutator = unsafe { &mut *handle };
+    let edge = SimpleEdge::from_address(slot);
+    mutator
+        .barrier
+        .object_reference_write(*src, DummyVMEdge::Simple(edge), target);
+    assert_eq!(edge.load(), target);
+    assert!(card_table.is_card_dirty(slot));
+
+    // The target is only reachable from the dirty card.
+    mmtk_handle_user_collection_request(tls);
+    assert!(!card_table.is_card_dirty(slot));
+    let target: ObjectReference = edge.load();
+    assert!(mmtk_is_live_object(target));
+    assert_eq!(
+        VMObjectModel::get_current_size(target),
+        OBJECT_REFS_OFFSET + TARGET_FIELDS * BYTES_IN_ADDRESS
+    );
+}
@@ -0,0 +1,84 @@
+// GITHUB-CI: MMTK_PLAN=GenImmix GenCopy StickyImmix
+
+use crate::api::*;
+use crate::edges::DummyVMEdge;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::plan::BarrierSelector;
+use mmtk::util::constants::{BYTES_IN_ADDRESS, LOG_BITS_IN_BYTE, LOG_BYTES_IN_ADDRESS};
+use mmtk::util::metadata::side_metadata::FIELD_LOG_BIT_SIDE_METADATA_ADDR;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::edge_shape::{Edge, SimpleEdge};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// Read the field log bit of a slot, in the same way as a binding that implements the fast path of the field barrier.
+fn is_field_unlogged(slot: Address) -> bool {
+    let word = slot.as_usize() >> LOG_BYTES_IN_ADDRESS;
+    let byte = FIELD_LOG_BIT_SIDE_METADATA_ADDR + (word >> LOG_BITS_IN_BYTE);
+    let shift = word & ((1 << LOG_BITS_IN_BYTE) - 1);
+    (unsafe { byte.load::<u8>() } >> shift) & 1 == 1
+}
+
+/// This test uses the field barrier for a generational plan. Writing a young object into a field of a
+/// mature object logs the field, and the young object is only reachable from the remembered slot.
+/// After a nursery GC, the young object survives and the field is unlogged again.
+#[test]
+pub fn barrier_field() {
+    const MB: usize = 1024 * 1024;
+    const TARGET_FIELDS: usize = 3;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.generational_barrier.set(BarrierSelector::FieldBarrier));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB) for nursery GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let constraints = crate::SINGLETON.get_plan().constraints();
+    assert_eq!(constraints.barrier, BarrierSelector::FieldBarrier);
+    assert!(constraints.needs_field_log_bit);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+    let mutator = unsafe { &mut *handle };
+
+    // The fields of a nursery object are not unlogged. Writing them takes the fast path.
+    let young = alloc_object(handle, 1, AllocationSemantics::Default);
+    let young_slot = field(young, 0);
+    assert!(!is_field_unlogged(young_slot));
+    let young_edge = DummyVMEdge::Simple(SimpleEdge::from_address(young_slot));
+    mutator
+        .barrier
+        .object_reference_write(young, young_edge, young);
+    assert!(!is_field_unlogged(young_slot));
+
+    // The source object becomes mature after it survives a GC, and its fields are unlogged.
+    let src = Box::new(alloc_object(handle, 2, AllocationSemantics::Default));
+    crate::scanning::add_root(Address::from_ref(&*src));
+    mmtk_handle_user_collection_request(tls);
+    let slot = field(*src, 0);
+    let other_slot = field(*src, 1);
+    assert!(is_field_unlogged(slot));
+    assert!(is_field_unlogged(other_slot));
+
+    // The first write logs the field. Only the written field is logged.
+    let target = alloc_object(handle, TARGET_FIELDS, AllocationSemantics::Default);
+    let edge = SimpleEdge::from_address(slot);
+    mutator
+        .barrier
+        .object_reference_write(*src, DummyVMEdge::Simple(edge), target);
+    assert_eq!(edge.load(), target);
+    assert!(!is_field_unlogged(slot));
+    assert!(is_field_unlogged(other_slot));
+
+    // The target is only reachable from the remembered slot.
+    mmtk_handle_user_collection_request(tls);
+    let target: ObjectReference = edge.load();
+    assert!(mmtk_is_live_object(target));
+    assert_eq!(
+        VMObjectModel::get_current_size(target),
+        OBJECT_REFS_OFFSET + TARGET_FIELDS * BYTES_IN_ADDRESS
+    );
+    assert!(is_field_unlogged(slot));
+}
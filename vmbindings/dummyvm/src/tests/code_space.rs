@@ -0,0 +1,56 @@
+// GITHUB-CI: MMTK_PLAN=NoGC SemiSpace Immix GenImmix
+// GITHUB-CI: FEATURES=code_space
+
+use crate::api::*;
+use crate::object_model::{OBJECT_REF_OFFSET, OBJECT_SIZE_OFFSET};
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+
+/// Get the permissions of the mapping that contains the address from `/proc/self/maps`, e.g. `rw-p`.
+fn permissions(addr: Address) -> String {
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    for line in maps.lines() {
+        let mut fields = line.split_whitespace();
+        let (start, end) = fields.next().unwrap().split_once('-').unwrap();
+        let start = usize::from_str_radix(start, 16).unwrap();
+        let end = usize::from_str_radix(end, 16).unwrap();
+        if (start..end).contains(&addr.as_usize()) {
+            return fields.next().unwrap().to_string();
+        }
+    }
+    panic!("{} is not mapped", addr)
+}
+
+/// This test checks that only the memory made executable in the code space is executable.
+#[test]
+pub fn code_space() {
+    const MB: usize = 1024 * 1024;
+    const SIZE: usize = 24;
+    // 1MB heap
+    mmtk_init(MB);
+    let handle = mmtk_bind_mutator(VMMutatorThread(VMThread::UNINITIALIZED));
+
+    let alloc_object = |semantics: AllocationSemantics| {
+        let addr = mmtk_alloc(handle, SIZE, 8, 0, semantics);
+        assert!(!addr.is_zero());
+        unsafe { (addr + OBJECT_SIZE_OFFSET).store::<u32>(SIZE as u32) };
+        let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+        mmtk_post_alloc(handle, obj, SIZE, semantics);
+        addr
+    };
+
+    // Memory in other spaces is not executable.
+    let default = alloc_object(AllocationSemantics::Default);
+    assert!(!permissions(default).contains('x'));
+    let immortal = alloc_object(AllocationSemantics::Immortal);
+    assert!(!permissions(immortal).contains('x'));
+
+    // Code is writable until it is made executable.
+    let code = alloc_object(AllocationSemantics::Code);
+    assert_eq!(&permissions(code)[..3], "rw-");
+    mmtk_make_code_executable(code, SIZE);
+    assert_eq!(&permissions(code)[..3], "r-x");
+    mmtk_make_code_writable(code, SIZE);
+    assert_eq!(&permissions(code)[..3], "rw-");
+}
@@ -0,0 +1,73 @@
+// GITHUB-CI: MMTK_PLAN=GenCopy GenImmix
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use crate::tests::fixtures::{gc_mutator_tls, init_object};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::options::NurseryZeroingOptions;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+use std::collections::HashSet;
+
+/// This test zeroes the nursery concurrently. Objects are filled with garbage, and die in nursery GCs. The nursery
+/// memory is reused after each GC, and it is zeroed before it is allocated again. The zeroing thread is spawned with
+/// the other GC threads, and it can be stopped by `prepare_to_fork` and spawned again by `after_fork`.
+#[test]
+pub fn concurrent_zeroing() {
+    const MB: usize = 1024 * 1024;
+    const SIZE: usize = 64;
+    const OBJECTS: usize = 10000;
+    const GCS: usize = 3;
+    const GARBAGE: usize = 0xdead_beef;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder
+            .options
+            .nursery_zeroing
+            .set(NurseryZeroingOptions::Concurrent));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB) for nursery GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let mut allocated: HashSet<Address> = HashSet::new();
+    for gc in 0..=GCS {
+        let mut reused = 0;
+        for _ in 0..OBJECTS {
+            let addr = mmtk_alloc(handle, SIZE, 8, 0, AllocationSemantics::Default);
+            assert!(!addr.is_zero());
+            // The memory of new objects is zeroed, including the memory reused after GCs.
+            for offset in (0..SIZE).step_by(BYTES_IN_ADDRESS) {
+                let value = unsafe { (addr + offset).load::<usize>() };
+                assert_eq!(value, 0, "{} is not zeroed", addr + offset);
+            }
+            if !allocated.insert(addr) {
+                reused += 1;
+            }
+            init_object(addr, 0);
+            // Fill the object with garbage after its size. The object dies in the next GC.
+            for offset in (BYTES_IN_ADDRESS..SIZE).step_by(BYTES_IN_ADDRESS) {
+                unsafe { (addr + offset).store::<usize>(GARBAGE) };
+            }
+            let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+            mmtk_post_alloc(handle, obj, SIZE, AllocationSemantics::Default);
+        }
+        if gc > 0 {
+            assert!(reused > 0, "No memory was reused after GC {}", gc);
+        }
+        if gc < GCS {
+            mmtk_handle_user_collection_request(tls);
+        }
+    }
+
+    // The zeroing thread exits with the other GC threads, and is spawned again.
+    mmtk_prepare_to_fork();
+    crate::collection::join_gc_threads();
+    mmtk_after_fork(VMThread::UNINITIALIZED);
+    mmtk_prepare_to_fork();
+    crate::collection::join_gc_threads();
+}
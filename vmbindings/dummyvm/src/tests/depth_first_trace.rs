@@ -0,0 +1,116 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace GenCopy PageProtect
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::options::TraceOrder;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// The depth of the binary tree.
+const DEPTH: usize = 6;
+/// The number of objects in the binary tree.
+const NUM_OBJECTS: usize = (1 << DEPTH) - 1;
+/// Each object has two reference fields for its children.
+const SIZE: usize = OBJECT_REFS_OFFSET + 2 * BYTES_IN_ADDRESS;
+/// The number of fields of the wide object. A `ProcessEdgesWork` packet holds at most 4096 edges, so scanning the
+/// wide object locally leaves more pending edges than one packet can hold.
+const WIDE_FIELDS: usize = 2 * 4096 + 1;
+/// The number of distinct objects that the wide object points to.
+const WIDE_CHILDREN: usize = 16;
+
+/// The objects of the tree rooted at `root`. The children of objects[i] are objects[2i+1] and objects[2i+2].
+fn tree_objects(root: ObjectReference) -> Vec<ObjectReference> {
+    let mut objects = vec![root];
+    for i in 0..NUM_OBJECTS / 2 {
+        for j in 0..2 {
+            let child: ObjectReference = unsafe { field(objects[i], j).load() };
+            assert!(!child.is_null());
+            objects.push(child);
+        }
+    }
+    objects
+}
+
+/// This test traces a binary tree and a wide object with the `DepthFirst` trace order. PageProtect frees the page of
+/// each dead object, so any object that is not traced cannot be accessed after the GC. Copying plans copy the first
+/// child of each object right after the object, and update every field of the wide object, including the edges that
+/// do not fit in the packet that scans it.
+#[test]
+pub fn depth_first_trace() {
+    const MB: usize = 1024 * 1024;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.synchronous_gc.set(true));
+        assert!(builder.options.trace_order.set(TraceOrder::DepthFirst));
+    }
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+    let moves_objects = crate::SINGLETON.get_plan().constraints().moves_objects;
+
+    let objects: Vec<ObjectReference> = (0..NUM_OBJECTS)
+        .map(|_| alloc_object(handle, 2, AllocationSemantics::Default))
+        .collect();
+    for i in 0..NUM_OBJECTS / 2 {
+        for j in 0..2 {
+            unsafe { field(objects[i], j).store(objects[2 * i + 1 + j]) };
+        }
+    }
+    let root = Box::new(objects[0]);
+    crate::scanning::add_root(Address::from_ref(&*root));
+
+    let wide_children: Vec<ObjectReference> = (0..WIDE_CHILDREN)
+        .map(|_| alloc_object(handle, 0, AllocationSemantics::Default))
+        .collect();
+    // The wide object is too large for copying spaces. PageProtect allocates all its objects as large objects in
+    // its own space.
+    let wide_semantics = if moves_objects {
+        AllocationSemantics::Los
+    } else {
+        AllocationSemantics::Default
+    };
+    let wide = Box::new(alloc_object(handle, WIDE_FIELDS, wide_semantics));
+    for i in 0..WIDE_FIELDS {
+        unsafe { field(*wide, i).store(wide_children[i % WIDE_CHILDREN]) };
+    }
+    crate::scanning::add_root(Address::from_ref(&*wide));
+
+    mmtk_handle_user_collection_request(tls);
+
+    let traced = tree_objects(*root);
+    for (i, object) in traced.iter().enumerate() {
+        assert!(mmtk_is_live_object(*object));
+        assert_eq!(VMObjectModel::get_current_size(*object), SIZE);
+        if moves_objects {
+            assert_ne!(*object, objects[i], "Object {} is not copied", i);
+        } else {
+            assert_eq!(*object, objects[i]);
+        }
+    }
+    if moves_objects {
+        for i in 0..NUM_OBJECTS / 2 {
+            assert_eq!(
+                traced[2 * i + 1].to_raw_address(),
+                traced[i].to_raw_address() + SIZE,
+                "The first child of object {} is not copied right after it",
+                i
+            );
+        }
+    }
+
+    for i in 0..WIDE_FIELDS {
+        let child: ObjectReference = unsafe { field(*wide, i).load() };
+        let first: ObjectReference = unsafe { field(*wide, i % WIDE_CHILDREN).load() };
+        assert_eq!(child, first, "Field {} of the wide object is not updated", i);
+        assert!(mmtk_is_live_object(child));
+        assert_eq!(VMObjectModel::get_current_size(child), OBJECT_REFS_OFFSET);
+        if moves_objects {
+            assert_ne!(child, wide_children[i % WIDE_CHILDREN]);
+        }
+    }
+}
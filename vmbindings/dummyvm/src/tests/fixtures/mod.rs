@@ -6,13 +6,53 @@ use std::sync::Once;
 use std::sync::Mutex;
 
 use mmtk::AllocationSemantics;
+use mmtk::Mutator;
 use mmtk::MMTK;
-use mmtk::util::{ObjectReference, VMThread, VMMutatorThread};
+use mmtk::util::{Address, ObjectReference, OpaquePointer, VMThread, VMMutatorThread};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
 
 use crate::api::*;
-use crate::object_model::OBJECT_REF_OFFSET;
+use crate::object_model::{OBJECT_REFS_OFFSET, OBJECT_REF_OFFSET};
 use crate::DummyVM;
 
+/// A thread pointer for mutators that trigger GCs. GC work packets require a valid thread pointer.
+pub fn gc_mutator_tls() -> VMMutatorThread {
+    VMMutatorThread(VMThread(OpaquePointer::from_address(unsafe {
+        Address::from_usize(0x1000)
+    })))
+}
+
+/// Allocate an object with `num_fields` null reference fields. The size of the object is stored in the
+/// object, so MMTk can scan and copy the object in GCs.
+pub fn alloc_object(mutator: *mut Mutator<DummyVM>, num_fields: usize, semantics: AllocationSemantics) -> ObjectReference {
+    let size = OBJECT_REFS_OFFSET + num_fields * BYTES_IN_ADDRESS;
+    let addr = mmtk_alloc(mutator, size, 8, 0, semantics);
+    assert!(!addr.is_zero());
+    init_object(addr, num_fields);
+    let object = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+    mmtk_post_alloc(mutator, object, size, semantics);
+    object
+}
+
+/// Initialize the size and the null reference fields of an object allocated at `addr`.
+pub fn init_object(addr: Address, num_fields: usize) {
+    let size = OBJECT_REFS_OFFSET + num_fields * BYTES_IN_ADDRESS;
+    unsafe { addr.store::<u32>(size as u32) };
+    for i in 0..num_fields {
+        unsafe { (addr + OBJECT_REFS_OFFSET + i * BYTES_IN_ADDRESS).store(ObjectReference::NULL) };
+    }
+}
+
+/// Run a GC, and wait for it to finish. Without `synchronous_gc`, the GC runs on the GC threads.
+pub fn run_gc(tls: VMMutatorThread) {
+    crate::collection::with_blocking_gc(|| mmtk_handle_user_collection_request(tls));
+}
+
+/// The address of the `index`-th reference field of an object.
+pub fn field(object: ObjectReference, index: usize) -> Address {
+    object.to_raw_address().sub(OBJECT_REF_OFFSET) + OBJECT_REFS_OFFSET + index * BYTES_IN_ADDRESS
+}
+
 pub trait FixtureContent {
     fn create() -> Self;
 }
@@ -141,8 +181,6 @@ impl FixtureContent for TwoObjects {
     }
 }
 
-use mmtk::plan::Mutator;
-
 pub struct MutatorFixture {
     pub mmtk: &'static MMTK<DummyVM>,
     pub mutator: *mut Mutator<DummyVM>,
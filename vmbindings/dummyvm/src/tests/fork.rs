@@ -0,0 +1,87 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace GenCopy GenImmix Immix StickyImmix MarkSweep MarkCompact
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+use std::time::Duration;
+
+/// The child process uses the heap created by the parent, and the GC threads spawned by after_fork().
+/// `root` is a root slot that holds the object allocated by the parent, and `child` is the object
+/// referenced by its only field.
+fn run_in_child(handle: *mut mmtk::Mutator<crate::DummyVM>, root: &ObjectReference, child: ObjectReference) {
+    mmtk_after_fork(VMThread::UNINITIALIZED);
+
+    // The objects allocated by the parent are still in the heap.
+    assert!(mmtk_is_in_mmtk_spaces(*root));
+    assert_eq!(unsafe { field(*root, 0).load::<ObjectReference>() }, child);
+
+    // We can allocate in the child.
+    for _ in 0..100 {
+        let object = alloc_object(handle, 0, AllocationSemantics::Default);
+        assert!(mmtk_is_in_mmtk_spaces(object));
+    }
+
+    // The respawned GC threads can perform a GC. DummyVM does not implement `block_for_gc`, so the mutator
+    // panics after requesting the GC, and we wait for the GC threads instead.
+    mmtk_enable_collection();
+    let finished = crate::collection::finished_gcs();
+    let _ = std::panic::catch_unwind(|| {
+        mmtk::memory_manager::handle_user_collection_request(&crate::SINGLETON, gc_mutator_tls())
+    });
+    assert!(
+        crate::collection::wait_for_finished_gcs(finished + 1, Duration::from_secs(10)),
+        "The GC in the child process did not finish"
+    );
+
+    // The objects reachable from the root survive the GC.
+    assert!(mmtk_is_in_mmtk_spaces(*root));
+    let child = unsafe { field(*root, 0).load::<ObjectReference>() };
+    assert!(mmtk_is_in_mmtk_spaces(child));
+    assert_eq!(VMObjectModel::get_current_size(child), OBJECT_REFS_OFFSET);
+
+    // The respawned GC threads can be stopped again.
+    mmtk_prepare_to_fork();
+}
+
+#[test]
+pub fn fork() {
+    const MB: usize = 1024 * 1024;
+    mmtk_init(MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    // Make sure GC does not run in the parent.
+    mmtk_disable_collection();
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let root = Box::new(alloc_object(handle, 1, AllocationSemantics::Default));
+    crate::scanning::add_root(Address::from_ref(&*root));
+    let child = alloc_object(handle, 0, AllocationSemantics::Default);
+    unsafe { field(*root, 0).store(child) };
+
+    mmtk_prepare_to_fork();
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0, "fork() failed");
+    if pid == 0 {
+        // Only this thread exists in the child. Exit the process without returning to the test harness.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_in_child(handle, &root, child)
+        }));
+        unsafe { libc::_exit(if result.is_ok() { 0 } else { 1 }) };
+    }
+
+    // The parent can keep using MMTk, too.
+    mmtk_after_fork(VMThread::UNINITIALIZED);
+    let object = alloc_object(handle, 0, AllocationSemantics::Default);
+    assert!(mmtk_is_in_mmtk_spaces(object));
+
+    let mut status: libc::c_int = 0;
+    let waited = unsafe { libc::waitpid(pid, &mut status, 0) };
+    assert_eq!(waited, pid);
+    assert!(libc::WIFEXITED(status), "The child process did not exit normally");
+    assert_eq!(libc::WEXITSTATUS(status), 0, "The child process failed");
+}
@@ -0,0 +1,69 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace GenCopy GenImmix Immix StickyImmix MarkSweep MarkCompact PageProtect
+
+use crate::api::*;
+use crate::DummyVM;
+use mmtk::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::Address;
+use mmtk::MMTK;
+use std::time::Duration;
+
+/// A packet that runs in the prepare stage when the mutators are stopped, and queues `PanickingPacket` for the
+/// closure stage.
+struct SchedulePanickingPacket;
+
+impl GCWork<DummyVM> for SchedulePanickingPacket {
+    fn do_work(&mut self, _worker: &mut GCWorker<DummyVM>, mmtk: &'static MMTK<DummyVM>) {
+        mmtk::memory_manager::add_work_packet(mmtk, WorkBucketStage::Closure, PanickingPacket);
+    }
+}
+
+struct PanickingPacket;
+
+impl GCWork<DummyVM> for PanickingPacket {
+    fn do_work(&mut self, _worker: &mut GCWorker<DummyVM>, _mmtk: &'static MMTK<DummyVM>) {
+        panic!("Injected panic");
+    }
+}
+
+/// This test injects a work packet that panics on a GC worker in the middle of a GC. MMTk should stop the GC and
+/// report the panic with `Collection::gc_panicked` instead of hanging. No more GC can be performed, but MMTk can
+/// still be shut down, which stops the GC threads. NoGC is not tested because it never performs a GC.
+#[test]
+pub fn gc_panic() {
+    const MB: usize = 1024 * 1024;
+    mmtk_init(MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+
+    let tls = VMMutatorThread(VMThread(OpaquePointer::from_address(unsafe {
+        Address::from_usize(0x1000)
+    })));
+    let _handle = mmtk_bind_mutator(tls);
+
+    mmtk::memory_manager::add_work_packet(&crate::SINGLETON, WorkBucketStage::Prepare, SchedulePanickingPacket);
+
+    // DummyVM does not implement `block_for_gc`, so the mutator may panic after requesting the GC. The GC still runs on
+    // the GC threads. With synchronous GC, the panic is resumed on the mutator.
+    let _ = std::panic::catch_unwind(|| {
+        mmtk::memory_manager::handle_user_collection_request(&crate::SINGLETON, tls)
+    });
+
+    let message = crate::collection::wait_for_gc_panic(Duration::from_secs(10))
+        .expect("The panic in the work packet is not reported.");
+    assert!(message.contains(std::any::type_name::<PanickingPacket>()), "{}", message);
+    assert!(message.contains("Injected panic"));
+
+    if *crate::SINGLETON.get_options().synchronous_gc {
+        // The panic must not poison the lock of the controller that runs synchronous GCs.
+        let panic = std::panic::catch_unwind(|| mmtk::memory_manager::collect_now(&crate::SINGLETON, tls))
+            .expect_err("A GC is performed after a GC is aborted.");
+        let panic = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        assert!(panic.contains("GC is aborted"), "{}", panic);
+    }
+
+    mmtk_shutdown(VMThread::UNINITIALIZED);
+}
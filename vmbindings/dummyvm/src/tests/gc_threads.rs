@@ -0,0 +1,76 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace Immix
+
+use crate::api::*;
+use crate::tests::fixtures::{alloc_object, gc_mutator_tls};
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+use std::time::Duration;
+
+/// Run a GC on the GC threads, and wait for it to finish. DummyVM does not implement `block_for_gc`, so the mutator
+/// panics after requesting the GC.
+fn run_gc() {
+    let finished = crate::collection::finished_gcs();
+    let _ = std::panic::catch_unwind(|| {
+        mmtk::memory_manager::handle_user_collection_request(&crate::SINGLETON, gc_mutator_tls())
+    });
+    assert!(
+        crate::collection::wait_for_finished_gcs(finished + 1, Duration::from_secs(10)),
+        "The GC did not finish"
+    );
+}
+
+/// This test changes the number of GC workers between GCs. The change only takes effect at the start of the next GC.
+/// With `adaptive_threads`, the number of active workers in a GC depends on the used memory and the number of roots
+/// in the last GC.
+#[test]
+#[should_panic(expected = "The number of GC workers must be between 1 and")]
+pub fn gc_threads() {
+    const MB: usize = 1024 * 1024;
+    // Enough roots for two more active workers. See `GCWorkScheduler::ADAPTIVE_ROOTS_PER_WORKER`.
+    const ROOTS: usize = 2 * 4096;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.threads.set(4));
+        assert!(builder.options.adaptive_threads.set(true));
+        // This test needs GC threads, even if CI sets `MMTK_SYNCHRONOUS_GC`.
+        assert!(builder.options.synchronous_gc.set(false));
+    }
+    mmtk_init(4 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let handle = mmtk_bind_mutator(gc_mutator_tls());
+
+    assert_eq!(mmtk_num_of_workers(), 4);
+    let max_workers = mmtk_max_num_of_workers();
+    assert!(max_workers >= 4);
+
+    // We know nothing about the amount of work in the first GC, so all the workers are active.
+    run_gc();
+    assert_eq!(mmtk_num_of_workers(), 4);
+    assert_eq!(mmtk_num_of_active_workers(), 4);
+
+    // The heap is almost empty, so one worker is active.
+    mmtk_set_gc_threads(2);
+    // No GC has happened since the change.
+    assert_eq!(mmtk_num_of_workers(), 4);
+    run_gc();
+    assert_eq!(mmtk_num_of_workers(), 2);
+    assert_eq!(mmtk_num_of_active_workers(), 1);
+
+    // Add many roots. They are counted in the next GC, and more workers are active in the GC after that.
+    let object = alloc_object(handle, 0, AllocationSemantics::Default);
+    let roots: Vec<ObjectReference> = vec![object; ROOTS];
+    for root in roots.iter() {
+        crate::scanning::add_root(Address::from_ref(root));
+    }
+    mmtk_set_gc_threads(max_workers);
+    run_gc();
+    assert_eq!(mmtk_num_of_workers(), max_workers);
+    assert_eq!(mmtk_num_of_active_workers(), 1);
+    run_gc();
+    assert_eq!(mmtk_num_of_workers(), max_workers);
+    assert_eq!(mmtk_num_of_active_workers(), 3);
+
+    // Call the Rust API directly. A panic cannot unwind out of an `extern "C"` function.
+    mmtk::memory_manager::set_gc_threads(&crate::SINGLETON, max_workers + 1);
+}
@@ -0,0 +1,81 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace Immix MarkSweep
+
+use crate::api::*;
+use crate::tests::fixtures::gc_mutator_tls;
+use crate::DummyVM;
+use mmtk::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use mmtk::util::opaque_pointer::*;
+use mmtk::MMTK;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+lazy_static! {
+    /// Set to true when `BlockingPacket` may finish.
+    static ref RELEASED: Mutex<bool> = Mutex::new(false);
+    static ref RELEASE: Condvar = Condvar::new();
+}
+
+/// A packet that blocks the only GC worker until the test releases it.
+struct BlockingPacket;
+
+impl GCWork<DummyVM> for BlockingPacket {
+    fn do_work(&mut self, _worker: &mut GCWorker<DummyVM>, _mmtk: &'static MMTK<DummyVM>) {
+        let released = RELEASED.lock().unwrap();
+        let (_released, result) = RELEASE
+            .wait_timeout_while(released, Duration::from_secs(10), |released| !*released)
+            .unwrap();
+        assert!(!result.timed_out(), "The packet is not released");
+    }
+}
+
+/// A packet that stays in a closed bucket while `BlockingPacket` is running.
+struct QueuedPacket;
+
+impl GCWork<DummyVM> for QueuedPacket {
+    fn do_work(&mut self, _worker: &mut GCWorker<DummyVM>, _mmtk: &'static MMTK<DummyVM>) {}
+}
+
+/// This test blocks the only GC worker in a GC with the option `gc_watchdog_timeout`. MMTk should report the state of
+/// the GC with `Collection::gc_watchdog_timeout`, including the running packet and the number of packets queued in
+/// each bucket. The GC finishes after the packet is released.
+#[test]
+pub fn gc_watchdog() {
+    const MB: usize = 1024 * 1024;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.threads.set(1));
+        assert!(builder.options.gc_watchdog_timeout.set(100));
+        // The watchdog does not work with synchronous GC.
+        assert!(builder.options.synchronous_gc.set(false));
+    }
+    mmtk_init(MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let _handle = mmtk_bind_mutator(tls);
+
+    mmtk::memory_manager::add_work_packet(&crate::SINGLETON, WorkBucketStage::Unconstrained, BlockingPacket);
+    // The closure bucket is not opened before the blocking packet finishes.
+    mmtk::memory_manager::add_work_packet(&crate::SINGLETON, WorkBucketStage::Closure, QueuedPacket);
+
+    // DummyVM does not implement `block_for_gc`, so the mutator panics after requesting the GC. The GC still runs on
+    // the GC threads.
+    let finished = crate::collection::finished_gcs();
+    let _ = std::panic::catch_unwind(|| {
+        mmtk::memory_manager::handle_user_collection_request(&crate::SINGLETON, tls)
+    });
+
+    let report = crate::collection::wait_for_gc_watchdog_report(Duration::from_secs(10))
+        .expect("The stuck GC is not reported.");
+    assert!(report.contains("GC has not finished in 100 ms"), "{}", report);
+    let running = format!("Worker 0: running {}", std::any::type_name::<BlockingPacket>());
+    assert!(report.contains(&running), "{}", report);
+    assert!(
+        report.lines().any(|line| line.contains("Bucket Closure: closed, queued: 1,")),
+        "{}",
+        report
+    );
+
+    *RELEASED.lock().unwrap() = true;
+    RELEASE.notify_all();
+    assert!(crate::collection::wait_for_finished_gcs(finished + 1, Duration::from_secs(10)));
+}
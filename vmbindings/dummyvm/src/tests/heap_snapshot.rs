@@ -0,0 +1,68 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace Immix MarkCompact
+// GITHUB-CI: FEATURES=heap_snapshot
+
+use crate::api::*;
+use crate::object_model::{OBJECT_REF_OFFSET, OBJECT_SIZE_OFFSET};
+use crate::tests::fixtures::gc_mutator_tls;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+use std::ffi::CString;
+
+/// This test saves immortal objects to a heap snapshot file after a full heap GC, and loads the file into the VM
+/// space.
+#[test]
+pub fn heap_snapshot() {
+    const MB: usize = 1024 * 1024;
+    const SIZE: usize = 24;
+    // An address after our heap range.
+    let image_start = unsafe { Address::from_usize(0x2400_0000_0000) };
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let objects: Vec<ObjectReference> = (0..3usize)
+        .map(|i| {
+            let addr = mmtk_alloc(handle, SIZE, 8, 0, AllocationSemantics::Immortal);
+            assert!(!addr.is_zero());
+            let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+            mmtk_post_alloc(handle, obj, SIZE, AllocationSemantics::Immortal);
+            unsafe {
+                (addr + OBJECT_SIZE_OFFSET).store::<u32>(SIZE as u32);
+                (addr + 8usize).store::<usize>(0x1000 + i);
+            }
+            obj
+        })
+        .collect();
+
+    // The snapshot is saved after a full heap GC. The immortal objects survive the GC.
+    mmtk_handle_user_compaction_request(tls);
+    for obj in objects.iter() {
+        assert!(mmtk_is_live_object(*obj));
+    }
+
+    let path = std::env::temp_dir().join(format!("mmtk_heap_snapshot_{}.bin", std::process::id()));
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+    assert!(mmtk_save_heap_snapshot(VMWorkerThread(VMThread::UNINITIALIZED), c_path.as_ptr()));
+
+    let snapshot = mmtk_load_heap_snapshot(c_path.as_ptr(), image_start);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!snapshot.is_null());
+    let snapshot = unsafe { Box::from_raw(snapshot) };
+    assert_eq!(snapshot.start, image_start);
+    assert_eq!(snapshot.objects.len(), objects.len());
+
+    for (i, obj) in objects.iter().enumerate() {
+        let loaded = snapshot.relocate::<crate::DummyVM>(*obj).unwrap();
+        assert_eq!(loaded, snapshot.objects[i]);
+        assert!(loaded.to_raw_address() >= image_start);
+        assert!(mmtk_is_in_mmtk_spaces(loaded));
+        let addr = loaded.to_raw_address().sub(OBJECT_REF_OFFSET);
+        assert_eq!(unsafe { (addr + 8usize).load::<usize>() }, 0x1000 + i);
+    }
+}
@@ -0,0 +1,61 @@
+// GITHUB-CI: MMTK_PLAN=Immix StickyImmix GenImmix
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// The number of fields of the `i`-th object. The largest object spans several lines.
+fn num_fields(i: usize) -> usize {
+    (i * 37) % 400
+}
+
+/// This test sets a smaller Immix block size and a larger line size with the option `immix_block_size`.
+/// The plan only allocates objects up to half of a block into the Immix space, and objects of different
+/// sizes survive GCs.
+#[test]
+pub fn immix_block_size() {
+    const MB: usize = 1024 * 1024;
+    const OBJECTS: usize = 64;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.set_from_command_line("immix_block_size", "13,9"));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    assert_eq!(
+        crate::SINGLETON
+            .get_plan()
+            .constraints()
+            .max_non_los_default_alloc_bytes,
+        4096
+    );
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let holder = Box::new(alloc_object(handle, OBJECTS, AllocationSemantics::Default));
+    crate::scanning::add_root(Address::from_ref(&*holder));
+    for i in 0..OBJECTS {
+        let object = alloc_object(handle, num_fields(i), AllocationSemantics::Default);
+        unsafe { field(*holder, i).store(object) };
+        // Leave dead objects between the live ones, so the GC leaves holes in the lines.
+        alloc_object(handle, num_fields(i + 1), AllocationSemantics::Default);
+    }
+
+    for _ in 0..2 {
+        mmtk_handle_user_collection_request(tls);
+        for i in 0..OBJECTS {
+            let object = unsafe { field(*holder, i).load::<ObjectReference>() };
+            assert!(mmtk_is_live_object(object));
+            assert_eq!(
+                VMObjectModel::get_current_size(object),
+                OBJECT_REFS_OFFSET + num_fields(i) * BYTES_IN_ADDRESS
+            );
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// GITHUB-CI: MMTK_PLAN=Immix StickyImmix
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+use std::collections::HashSet;
+
+/// This test enables chunk evacuation with the option `immix_chunk_evacuation_trigger_percent`.
+/// A few objects survive in each of the chunks allocated by the Immix space. The first GC finds that the chunks
+/// are sparsely used, and the next GC evacuates the objects out of the least occupied chunks.
+#[test]
+pub fn immix_chunk_evacuation() {
+    const MB: usize = 1024 * 1024;
+    const FIELDS: usize = 120;
+    const OBJECTS: usize = 16 * 1024;
+    const KEEP_EVERY: usize = 256;
+    const KEPT: usize = OBJECTS / KEEP_EVERY;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.immix_chunk_evacuation_trigger_percent.set(200));
+        assert!(builder.options.full_heap_system_gc.set(true));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(64 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let holder = Box::new(alloc_object(handle, KEPT, AllocationSemantics::Los));
+    crate::scanning::add_root(Address::from_ref(&*holder));
+    for i in 0..OBJECTS {
+        let object = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+        if i % KEEP_EVERY == 0 {
+            unsafe { field(*holder, i / KEEP_EVERY).store(object) };
+        }
+    }
+
+    let kept = || (0..KEPT).map(|i| unsafe { field(*holder, i).load::<ObjectReference>() });
+    let chunks = || {
+        kept()
+            .map(|object| object.to_raw_address().chunk_index())
+            .collect::<HashSet<_>>()
+    };
+    let chunks_before = chunks();
+    assert!(chunks_before.len() > 1);
+
+    // The first GC finds the occupancy of the chunks. The second one evacuates chunks.
+    for _ in 0..2 {
+        mmtk_handle_user_collection_request(tls);
+        for object in kept() {
+            assert!(mmtk_is_live_object(object));
+            assert_eq!(
+                VMObjectModel::get_current_size(object),
+                OBJECT_REFS_OFFSET + FIELDS * BYTES_IN_ADDRESS
+            );
+        }
+    }
+    let chunks_after = chunks();
+    assert!(
+        chunks_after.len() < chunks_before.len(),
+        "The objects were in {} chunks before the GCs, and {} chunks after the GCs",
+        chunks_before.len(),
+        chunks_after.len()
+    );
+}
@@ -0,0 +1,29 @@
+// GITHUB-CI: MMTK_PLAN=Immix StickyImmix
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::ObjectReference;
+use mmtk::AllocationSemantics;
+
+/// This test makes Immix non-moving with the option `immix_non_moving`, and checks that objects
+/// in the Immix space will never move.
+#[test]
+pub fn immix_non_moving() {
+    const MB: usize = 1024 * 1024;
+    const SIZE: usize = 40;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.immix_non_moving.set(true));
+    }
+    // 1MB heap
+    mmtk_init(MB);
+    assert!(!crate::SINGLETON.get_plan().constraints().moves_objects);
+    let handle = mmtk_bind_mutator(VMMutatorThread(VMThread::UNINITIALIZED));
+
+    let addr = mmtk_alloc(handle, SIZE, 8, 0, AllocationSemantics::Default);
+    assert!(!addr.is_zero());
+    let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+    mmtk_post_alloc(handle, obj, SIZE, AllocationSemantics::Default);
+    assert!(mmtk_will_never_move(obj));
+}
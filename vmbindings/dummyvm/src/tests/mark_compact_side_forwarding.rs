@@ -0,0 +1,56 @@
+// GITHUB-CI: MMTK_PLAN=MarkCompact
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET, OBJECT_REF_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// This test uses side forwarding for MarkCompact with the option `mark_compact_side_forwarding`. It checks that
+/// objects are allocated without the extra header word for forwarding pointers, and that a GC slides the live
+/// objects over the dead ones and updates the references to them.
+#[test]
+pub fn mark_compact_side_forwarding() {
+    const MB: usize = 1024 * 1024;
+    const FIELDS: usize = 3;
+    const SIZE: usize = OBJECT_REFS_OFFSET + FIELDS * BYTES_IN_ADDRESS;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.mark_compact_side_forwarding.set(true));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let dead1 = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+    let parent = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+    let dead2 = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+    let child = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+
+    // Objects are allocated next to each other, with no extra header.
+    let start = |object: ObjectReference| object.to_raw_address() - OBJECT_REF_OFFSET;
+    assert_eq!(start(parent), start(dead1) + SIZE);
+    assert_eq!(start(dead2), start(parent) + SIZE);
+    assert_eq!(start(child), start(dead2) + SIZE);
+
+    unsafe { field(parent, 0).store(child) };
+    let root = Box::new(parent);
+    crate::scanning::add_root(Address::from_ref(&*root));
+
+    mmtk_handle_user_collection_request(tls);
+
+    // The parent slides over the first dead object, and the child slides next to the parent.
+    let new_parent = *root;
+    assert_eq!(start(new_parent), start(dead1));
+    let new_child = unsafe { field(new_parent, 0).load::<ObjectReference>() };
+    assert_eq!(start(new_child), start(new_parent) + SIZE);
+    for object in [new_parent, new_child] {
+        assert!(mmtk_is_in_mmtk_spaces(object));
+        assert_eq!(VMObjectModel::get_current_size(object), SIZE);
+    }
+}
@@ -13,6 +13,7 @@ mod allocate_without_initialize_collection;
 mod allocate_with_initialize_collection;
 mod allocate_with_disable_collection;
 mod allocate_with_re_enable_collection;
+mod allocate_with_site;
 #[cfg(not(feature = "malloc_counted_size"))]
 mod malloc_api;
 #[cfg(feature = "malloc_counted_size")]
@@ -24,3 +25,43 @@ mod is_in_mmtk_spaces;
 mod fixtures;
 mod edges_test;
 mod barrier_slow_path_assertion;
+#[cfg(feature = "vo_bit")]
+mod barrier_card;
+mod barrier_field;
+#[cfg(feature = "verify_remset")]
+mod verify_remset;
+mod immix_non_moving;
+mod mark_compact_side_forwarding;
+mod concurrent_zeroing;
+mod gc_threads;
+mod gc_panic;
+mod gc_watchdog;
+mod work_stealing;
+mod prioritize_large_objects;
+mod scan_array_slices;
+mod depth_first_trace;
+mod tracing_prefetch;
+mod synchronous_gc;
+mod immix_block_size;
+mod immix_chunk_evacuation;
+mod shutdown;
+// With the code spaces or the read-only space, two instances of some plans need more spaces than
+// the heap range can hold. Malloc mark sweep only allows one instance.
+#[cfg(not(any(feature = "code_space", feature = "ro_space", feature = "malloc_mark_sweep")))]
+mod multiple_instances;
+#[cfg(not(any(feature = "code_space", feature = "ro_space", feature = "malloc_mark_sweep")))]
+mod multiple_instances_gc;
+#[cfg(feature = "object_pinning")]
+mod pin_object;
+#[cfg(target_os = "linux")]
+mod fork;
+#[cfg(feature = "heap_snapshot")]
+mod heap_snapshot;
+#[cfg(all(feature = "vm_space", feature = "is_mmtk_object"))]
+mod vm_space_regions;
+#[cfg(feature = "vm_space")]
+mod vm_space_remembered_set;
+#[cfg(feature = "ro_space")]
+mod read_only_space;
+#[cfg(all(feature = "code_space", target_os = "linux"))]
+mod code_space;
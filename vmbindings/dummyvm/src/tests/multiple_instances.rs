@@ -0,0 +1,63 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use crate::DummyVM;
+use mmtk::memory_manager;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::ObjectReference;
+use mmtk::AllocationSemantics;
+use mmtk::MMTK;
+
+const OBJECT_SIZE: usize = 40;
+
+fn alloc_object(mmtk: &'static MMTK<DummyVM>) -> ObjectReference {
+    let mut mutator =
+        memory_manager::bind_mutator(mmtk, VMMutatorThread(VMThread::UNINITIALIZED));
+    let addr = memory_manager::alloc(&mut mutator, OBJECT_SIZE, 8, 0, AllocationSemantics::Default);
+    assert!(!addr.is_zero());
+    unsafe { addr.store::<usize>(OBJECT_SIZE) };
+    let object = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+    memory_manager::post_alloc(&mut mutator, object, OBJECT_SIZE, AllocationSemantics::Default);
+    memory_manager::destroy_mutator(&mut mutator);
+    object
+}
+
+fn assert_owned_by(object: ObjectReference, owner: &MMTK<DummyVM>, other: &MMTK<DummyVM>) {
+    assert!(memory_manager::is_in_mmtk_spaces::<DummyVM>(object));
+    assert!(memory_manager::is_in_mmtk_spaces_of(owner, object));
+    assert!(!memory_manager::is_in_mmtk_spaces_of(other, object));
+    #[cfg(feature = "is_mmtk_object")]
+    {
+        assert!(memory_manager::is_mmtk_object_of(owner, object.to_raw_address()));
+        assert!(!memory_manager::is_mmtk_object_of(other, object.to_raw_address()));
+    }
+}
+
+/// Two MMTk instances allocate in parallel threads. Each instance owns its objects, and shutting
+/// down one instance does not affect the other.
+#[test]
+pub fn multiple_instances() {
+    const MB: usize = 1024 * 1024;
+    mmtk_init(MB);
+    let first: &'static MMTK<DummyVM> = &crate::SINGLETON;
+    let second: &'static MMTK<DummyVM> =
+        Box::leak(memory_manager::mmtk_init(&crate::BUILDER.lock().unwrap()));
+
+    let threads: Vec<_> = [first, second]
+        .into_iter()
+        .map(|mmtk| std::thread::spawn(move || alloc_object(mmtk)))
+        .collect();
+    let objects: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    assert_owned_by(objects[0], first, second);
+    assert_owned_by(objects[1], second, first);
+
+    memory_manager::mmtk_shutdown(second, VMThread::UNINITIALIZED);
+    assert!(!memory_manager::is_in_mmtk_spaces::<DummyVM>(objects[1]));
+    // The first instance still works.
+    assert_owned_by(objects[0], first, second);
+    let object = alloc_object(first);
+    assert_owned_by(object, first, second);
+
+    mmtk_shutdown(VMThread::UNINITIALIZED);
+}
@@ -0,0 +1,70 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace GenCopy GenImmix StickyImmix Immix MarkSweep MarkCompact PageProtect
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use crate::DummyVM;
+use mmtk::memory_manager;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+use mmtk::MMTK;
+
+const OBJECT_SIZE: usize = 40;
+const OBJECTS_PER_GC: usize = 100;
+const GCS: usize = 20;
+
+/// Allocate objects in the instance, and perform a GC after each batch of objects.
+fn allocate_and_collect(mmtk: &'static MMTK<DummyVM>, other: &'static MMTK<DummyVM>, tls: VMMutatorThread) {
+    crate::use_instance(mmtk);
+    memory_manager::initialize_collection(mmtk, tls.0);
+    for _ in 0..GCS {
+        let mut mutator = memory_manager::bind_mutator(mmtk, tls);
+        for _ in 0..OBJECTS_PER_GC {
+            let addr = memory_manager::alloc(&mut mutator, OBJECT_SIZE, 8, 0, AllocationSemantics::Default);
+            assert!(!addr.is_zero());
+            unsafe { addr.store::<usize>(OBJECT_SIZE) };
+            let object = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+            memory_manager::post_alloc(&mut mutator, object, OBJECT_SIZE, AllocationSemantics::Default);
+            assert!(memory_manager::is_in_mmtk_spaces_of(mmtk, object));
+            assert!(!memory_manager::is_in_mmtk_spaces_of(other, object));
+        }
+        // DummyVM only reports the mutators bound with `mmtk_bind_mutator` to MMTk, so the mutator is destroyed
+        // before GC to flush its allocation buffers.
+        memory_manager::destroy_mutator(&mut mutator);
+        memory_manager::handle_user_collection_request(mmtk, tls);
+    }
+}
+
+/// Two MMTk instances perform GCs at the same time in parallel threads. The GCs run synchronously on the threads
+/// that request them, and each of them only collects the heap of its own instance.
+#[test]
+pub fn multiple_instances_gc() {
+    const MB: usize = 1024 * 1024;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(4 * MB);
+    let first: &'static MMTK<DummyVM> = &crate::SINGLETON;
+    let second: &'static MMTK<DummyVM> =
+        Box::leak(memory_manager::mmtk_init(&crate::BUILDER.lock().unwrap()));
+
+    let threads: Vec<_> = [(first, second), (second, first)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (mmtk, other))| {
+            // GC work packets require a valid thread pointer.
+            let tls = VMMutatorThread(VMThread(OpaquePointer::from_address(unsafe {
+                Address::from_usize(0x1000 * (i + 1))
+            })));
+            std::thread::spawn(move || allocate_and_collect(mmtk, other, tls))
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(crate::collection::finished_gcs(), 2 * GCS);
+
+    memory_manager::mmtk_shutdown(second, VMThread::UNINITIALIZED);
+    mmtk_shutdown(VMThread::UNINITIALIZED);
+}
@@ -0,0 +1,75 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace GenCopy GenImmix Immix StickyImmix MarkCompact
+// GITHUB-CI: FEATURES=object_pinning
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::Address;
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// This test pins and unpins an object in the default space of a moving plan, including the copying
+/// and mark-compact spaces. Then it checks that a pinned object keeps its address and stays pinned across
+/// GCs. The pinning bits of dead objects are cleared, so objects allocated in the released memory are not pinned.
+#[test]
+pub fn pin_object() {
+    const MB: usize = 1024 * 1024;
+    // All the objects are 64 bytes, so the new objects are allocated at the addresses of the old objects.
+    const FIELDS: usize = 6;
+    const FILLER_OBJECTS: usize = 128;
+    const NEW_OBJECTS: usize = 10000;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB) for nursery GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    // A dead object before the pinned object. A compacting GC would move the pinned object over it.
+    alloc_object(handle, FIELDS, AllocationSemantics::Default);
+
+    // A pinned object that survives.
+    let pinned = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+    assert!(!mmtk_is_pinned(pinned));
+    assert!(mmtk_pin_object(pinned));
+    assert!(mmtk_is_pinned(pinned));
+    // Pinning a pinned object does nothing.
+    assert!(!mmtk_pin_object(pinned));
+    assert!(mmtk_unpin_object(pinned));
+    assert!(!mmtk_is_pinned(pinned));
+    assert!(!mmtk_unpin_object(pinned));
+    assert!(mmtk_pin_object(pinned));
+    let root = Box::new(pinned);
+    crate::scanning::add_root(Address::from_ref(&*root));
+
+    // Leave more than a page between the objects, so the memory of the dead object is not retained with the
+    // pinned object.
+    for _ in 0..FILLER_OBJECTS {
+        alloc_object(handle, FIELDS, AllocationSemantics::Default);
+    }
+    // A pinned object that dies.
+    let dead = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+    assert!(mmtk_pin_object(dead));
+
+    for _ in 0..2 {
+        mmtk_handle_user_collection_request(tls);
+        assert_eq!(*root, pinned);
+        assert!(mmtk_is_in_mmtk_spaces(pinned));
+        assert!(mmtk_is_pinned(pinned));
+        assert_eq!(
+            VMObjectModel::get_current_size(pinned),
+            OBJECT_REFS_OFFSET + FIELDS * BYTES_IN_ADDRESS
+        );
+    }
+
+    // The new objects may be allocated in the memory of the dead object.
+    for _ in 0..NEW_OBJECTS {
+        let object = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+        assert!(!mmtk_is_pinned(object), "{} is pinned", object);
+    }
+}
@@ -0,0 +1,57 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace Immix MarkSweep
+
+use crate::api::*;
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+
+/// This test sets the option `prioritize_large_objects`. An object references small objects, and then a large object.
+/// The large object is scanned before the small objects, although it is traced after them.
+#[test]
+pub fn prioritize_large_objects() {
+    const MB: usize = 1024 * 1024;
+    const SMALL_OBJECTS: usize = 16;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.prioritize_large_objects.set(true));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    let max_small_object_bytes = crate::SINGLETON
+        .get_plan()
+        .constraints()
+        .max_non_los_default_alloc_bytes;
+    let large_object_fields = max_small_object_bytes / BYTES_IN_ADDRESS;
+
+    let holder = Box::new(alloc_object(handle, SMALL_OBJECTS + 1, AllocationSemantics::Default));
+    crate::scanning::add_root(Address::from_ref(&*holder));
+    for i in 0..SMALL_OBJECTS {
+        let small = alloc_object(handle, 0, AllocationSemantics::Default);
+        unsafe { field(*holder, i).store(small) };
+    }
+    let large = alloc_object(handle, large_object_fields, AllocationSemantics::Los);
+    unsafe { field(*holder, SMALL_OBJECTS).store(large) };
+
+    crate::scanning::record_scanned_objects();
+    mmtk_handle_user_collection_request(tls);
+    let scanned = crate::scanning::take_scanned_objects();
+
+    let position = |object: ObjectReference| {
+        scanned
+            .iter()
+            .position(|(o, _)| *o == object)
+            .unwrap_or_else(|| panic!("{} is not scanned", object))
+    };
+    let large = unsafe { field(*holder, SMALL_OBJECTS).load::<ObjectReference>() };
+    assert!(position(*holder) < position(large));
+    for i in 0..SMALL_OBJECTS {
+        let small = unsafe { field(*holder, i).load::<ObjectReference>() };
+        assert!(position(large) < position(small));
+    }
+}
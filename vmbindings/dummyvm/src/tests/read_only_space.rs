@@ -0,0 +1,53 @@
+// GITHUB-CI: MMTK_PLAN=NoGC SemiSpace Immix GenImmix
+// GITHUB-CI: FEATURES=ro_space
+
+use crate::api::*;
+use crate::object_model::{OBJECT_REF_OFFSET, OBJECT_SIZE_OFFSET};
+use mmtk::util::conversions;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::ObjectReference;
+use mmtk::AllocationSemantics;
+
+/// This test allocates read-only objects, seals the read-only space, and allocates more read-only objects.
+#[test]
+pub fn read_only_space() {
+    const MB: usize = 1024 * 1024;
+    const SIZE: usize = 24;
+    // 1MB heap
+    mmtk_init(MB);
+    let handle = mmtk_bind_mutator(VMMutatorThread(VMThread::UNINITIALIZED));
+
+    let alloc_object = |value: usize| {
+        let addr = mmtk_alloc(handle, SIZE, 8, 0, AllocationSemantics::ReadOnly);
+        assert!(!addr.is_zero());
+        unsafe {
+            (addr + OBJECT_SIZE_OFFSET).store::<u32>(SIZE as u32);
+            (addr + 8usize).store::<usize>(value);
+        }
+        let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+        mmtk_post_alloc(handle, obj, SIZE, AllocationSemantics::ReadOnly);
+        obj
+    };
+
+    let sealed = alloc_object(42);
+    let ro_space = &crate::SINGLETON.get_plan().base().ro_space;
+    assert!(!ro_space.is_sealed(sealed));
+    mmtk_seal_read_only_space();
+    assert!(ro_space.is_sealed(sealed));
+    assert!(mmtk_is_in_mmtk_spaces(sealed));
+    // Sealed objects can still be read.
+    let sealed_addr = sealed.to_raw_address().sub(OBJECT_REF_OFFSET);
+    assert_eq!(unsafe { (sealed_addr + 8usize).load::<usize>() }, 42);
+
+    // Later objects are allocated in fresh pages that are not sealed.
+    let fresh = alloc_object(43);
+    assert!(!ro_space.is_sealed(fresh));
+    assert_ne!(
+        conversions::page_align_down(fresh.to_raw_address()),
+        conversions::page_align_down(sealed.to_raw_address())
+    );
+    let fresh_addr = fresh.to_raw_address().sub(OBJECT_REF_OFFSET);
+    assert_eq!(unsafe { (fresh_addr + 8usize).load::<usize>() }, 43);
+
+    mmtk_destroy_mutator(handle);
+}
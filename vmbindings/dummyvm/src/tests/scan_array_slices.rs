@@ -0,0 +1,129 @@
+// GITHUB-CI: MMTK_PLAN=Immix GenCopy GenImmix StickyImmix
+
+use crate::api::*;
+use crate::edges::DummyVMMemorySlice;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// The number of fields of the `i`-th element of the array.
+fn num_fields(i: usize) -> usize {
+    i % 4
+}
+
+/// Run a GC on the GC threads, and wait for it to finish. DummyVM does not implement `block_for_gc`, so the mutator
+/// panics after requesting the GC.
+fn run_gc(full_heap: bool) {
+    let finished = crate::collection::finished_gcs();
+    let _ = std::panic::catch_unwind(|| {
+        if full_heap {
+            mmtk::memory_manager::handle_user_compaction_request(&crate::SINGLETON, gc_mutator_tls())
+        } else {
+            mmtk::memory_manager::handle_user_collection_request(&crate::SINGLETON, gc_mutator_tls())
+        }
+    });
+    assert!(
+        crate::collection::wait_for_finished_gcs(finished + 1, Duration::from_secs(10)),
+        "The GC did not finish"
+    );
+}
+
+/// Check that the elements of the array in `range` are live, and have the expected sizes. If an element has been
+/// moved, the array holds its new address.
+fn check_elements(array: ObjectReference, range: std::ops::Range<usize>) {
+    for i in range {
+        let element = unsafe { field(array, i).load::<ObjectReference>() };
+        assert!(mmtk_is_live_object(element), "Element {} ({}) is not live", i, element);
+        assert_eq!(
+            VMObjectModel::get_current_size(element),
+            OBJECT_REFS_OFFSET + num_fields(i) * BYTES_IN_ADDRESS
+        );
+    }
+}
+
+/// This test reports a large array as several memory slices with `Scanning::scan_object_as_slices`. The elements in
+/// different slices are traced by different GC workers. Then the test copies new objects into the array after the
+/// array becomes mature, so generational plans trace the copied elements from the region modification buffer in
+/// nursery GCs.
+#[test]
+pub fn scan_array_slices() {
+    const MB: usize = 1024 * 1024;
+    const ELEMENTS: usize = 16 * 1024;
+    const ELEMENTS_PER_SLICE: usize = 1024;
+    // Copy new elements to the middle of the array, more than a `ProcessEdgesWork` packet can hold.
+    const COPIED: std::ops::Range<usize> = 1000..6000;
+    // Retry a few times if a GC happens to trace all the elements on one worker.
+    const MAX_GCS: usize = 10;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.threads.set(4));
+        // This test needs GC threads, even if CI sets `MMTK_SYNCHRONOUS_GC`.
+        assert!(builder.options.synchronous_gc.set(false));
+    }
+    mmtk_init(16 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+    crate::scanning::scan_large_objects_as_slices(ELEMENTS, ELEMENTS_PER_SLICE);
+
+    let array = Box::new(alloc_object(handle, ELEMENTS, AllocationSemantics::Los));
+    crate::scanning::add_root(Address::from_ref(&*array));
+    for i in 0..ELEMENTS {
+        let element = alloc_object(handle, num_fields(i), AllocationSemantics::Default);
+        unsafe { field(*array, i).store(element) };
+    }
+
+    let mut scanned_by_many_threads = false;
+    for _ in 0..MAX_GCS {
+        let slices = crate::scanning::reported_slices();
+        crate::scanning::record_scanned_objects();
+        run_gc(true);
+        let scanned = crate::scanning::take_scanned_objects();
+        assert_eq!(crate::scanning::reported_slices() - slices, ELEMENTS / ELEMENTS_PER_SLICE);
+        check_elements(*array, 0..ELEMENTS);
+
+        // The elements that have reference fields are scanned by the threads that trace them.
+        let elements: HashSet<ObjectReference> = (0..ELEMENTS)
+            .map(|i| unsafe { field(*array, i).load::<ObjectReference>() })
+            .collect();
+        let threads: HashSet<_> = scanned
+            .iter()
+            .filter(|(object, _)| elements.contains(object))
+            .map(|(_, thread)| *thread)
+            .collect();
+        if threads.len() > 1 {
+            scanned_by_many_threads = true;
+            break;
+        }
+    }
+    assert!(scanned_by_many_threads, "The elements are always traced by one thread");
+
+    // Copy new objects into the mature array with the memory region copy barrier.
+    let new_elements: Vec<ObjectReference> = COPIED
+        .map(|i| alloc_object(handle, num_fields(i), AllocationSemantics::Default))
+        .collect();
+    let src = DummyVMMemorySlice::from_raw_parts(Address::from_ptr(new_elements.as_ptr()), new_elements.len());
+    let dst = DummyVMMemorySlice::from_raw_parts(field(*array, COPIED.start), COPIED.len());
+    mmtk::memory_manager::memory_region_copy(unsafe { &mut *handle }, src, dst);
+    drop(new_elements);
+
+    // Generational plans only find the new elements through the region modification buffer. The elements that have
+    // reference fields are scanned if they are traced.
+    crate::scanning::record_scanned_objects();
+    run_gc(false);
+    let scanned: HashSet<ObjectReference> = crate::scanning::take_scanned_objects()
+        .into_iter()
+        .map(|(object, _)| object)
+        .collect();
+    check_elements(*array, 0..ELEMENTS);
+    for i in COPIED.filter(|i| num_fields(*i) > 0) {
+        let element = unsafe { field(*array, i).load::<ObjectReference>() };
+        assert!(scanned.contains(&element), "Element {} ({}) is not traced", i, element);
+    }
+}
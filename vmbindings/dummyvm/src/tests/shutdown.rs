@@ -0,0 +1,65 @@
+// GITHUB-CI: MMTK_PLAN=all
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use crate::DummyVM;
+use mmtk::memory_manager;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::options::PlanSelector;
+use mmtk::util::ObjectReference;
+use mmtk::AllocationSemantics;
+use mmtk::Mutator;
+use mmtk::MMTK;
+
+const OBJECT_SIZE: usize = 40;
+
+fn alloc_object(mutator: &mut Mutator<DummyVM>) -> ObjectReference {
+    let addr = memory_manager::alloc(mutator, OBJECT_SIZE, 8, 0, AllocationSemantics::Default);
+    assert!(!addr.is_zero());
+    // The memory is mapped and writable.
+    unsafe { addr.store::<usize>(OBJECT_SIZE) };
+    let object = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+    memory_manager::post_alloc(mutator, object, OBJECT_SIZE, AllocationSemantics::Default);
+    assert!(memory_manager::is_in_mmtk_spaces::<DummyVM>(object));
+    object
+}
+
+/// After the first instance is shut down, a second instance can be created in the same process, and
+/// it uses the same memory as the first instance. The second instance may use different Immix block
+/// and line sizes, as the Immix spaces of the first instance are gone.
+#[test]
+pub fn shutdown() {
+    const MB: usize = 1024 * 1024;
+    mmtk_init(MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    mmtk_disable_collection();
+    let handle = mmtk_bind_mutator(VMMutatorThread(VMThread::UNINITIALIZED));
+    let first = alloc_object(unsafe { &mut *handle });
+    mmtk_destroy_mutator(handle);
+
+    // This stops and joins the GC threads.
+    mmtk_shutdown(VMThread::UNINITIALIZED);
+
+    let mmtk: &'static MMTK<DummyVM> = {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.set_from_command_line("immix_block_size", "13,9"));
+        Box::leak(memory_manager::mmtk_init(&builder))
+    };
+    if matches!(
+        *mmtk.get_options().plan,
+        PlanSelector::Immix | PlanSelector::StickyImmix | PlanSelector::GenImmix
+    ) {
+        assert_eq!(mmtk.get_plan().constraints().max_non_los_default_alloc_bytes, 4096);
+    }
+    let mut mutator =
+        memory_manager::bind_mutator(mmtk, VMMutatorThread(VMThread::UNINITIALIZED));
+    let second = alloc_object(&mut mutator);
+    // Malloc may return different memory.
+    if !cfg!(feature = "malloc_mark_sweep") {
+        assert_eq!(first.to_raw_address(), second.to_raw_address());
+    }
+    memory_manager::destroy_mutator(&mut mutator);
+
+    // The second instance can be shut down as well.
+    memory_manager::mmtk_shutdown(mmtk, VMThread::UNINITIALIZED);
+}
@@ -0,0 +1,39 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace GenCopy PageProtect
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::AllocationSemantics;
+
+/// This test performs GC synchronously on the test thread without any GC thread. DummyVM has no roots, so all the
+/// objects are dead in each GC, and we can allocate much more than the heap size.
+#[test]
+pub fn synchronous_gc() {
+    const MB: usize = 1024 * 1024;
+    const SIZE: usize = 40;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    assert_eq!(mmtk_num_of_workers(), 0);
+
+    // GC work packets require a valid thread pointer.
+    let tls = VMMutatorThread(VMThread(OpaquePointer::from_address(unsafe {
+        Address::from_usize(0x1000)
+    })));
+    let handle = mmtk_bind_mutator(tls);
+
+    for _ in 0..(8 * MB / SIZE) {
+        let addr = mmtk_alloc(handle, SIZE, 8, 0, AllocationSemantics::Default);
+        assert!(!addr.is_zero());
+        unsafe { addr.store::<u32>(SIZE as u32) };
+        let obj = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+        mmtk_post_alloc(handle, obj, SIZE, AllocationSemantics::Default);
+    }
+
+    // No GC thread was spawned.
+    crate::collection::join_gc_threads();
+}
@@ -0,0 +1,87 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace Immix MarkSweep
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::options::PlanSelector;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+
+/// The number of fields of the `i`-th child of the holder. Every fifth field of the holder is null.
+fn num_fields(i: usize) -> Option<usize> {
+    if i % 5 == 0 {
+        None
+    } else {
+        Some(i % 3)
+    }
+}
+
+/// This test sets the option `tracing_prefetch_distance`. The edges of an object are processed while the objects a
+/// few edges ahead, including null references and the last edges of the packet, are prefetched. Every object must
+/// still be traced, and every edge to an object must hold the same (possibly new) address of the object.
+#[test]
+pub fn tracing_prefetch() {
+    const MB: usize = 1024 * 1024;
+    const CHILDREN: usize = 1000;
+    const PREFETCH_DISTANCE: usize = 8;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.tracing_prefetch_distance.set(PREFETCH_DISTANCE));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+    // SemiSpace copies every live object in every GC.
+    let copies_all_objects = matches!(*crate::SINGLETON.get_options().plan, PlanSelector::SemiSpace);
+
+    let holder = Box::new(alloc_object(handle, CHILDREN, AllocationSemantics::Los));
+    crate::scanning::add_root(Address::from_ref(&*holder));
+    let mut children = vec![];
+    for i in 0..CHILDREN {
+        let child = num_fields(i).map_or(ObjectReference::NULL, |num_fields| {
+            alloc_object(handle, num_fields, AllocationSemantics::Default)
+        });
+        // Each child points to the previous one, so the children are also traced from the edges of other children.
+        if let Some(previous) = children.last().filter(|_| num_fields(i).unwrap_or(0) > 0) {
+            unsafe { field(child, 0).store::<ObjectReference>(*previous) };
+        }
+        unsafe { field(*holder, i).store(child) };
+        if !child.is_null() {
+            children.push(child);
+        }
+    }
+
+    mmtk_handle_user_collection_request(tls);
+
+    assert!(mmtk_is_live_object(*holder));
+    let mut old_children = children.iter();
+    let mut previous = None;
+    for i in 0..CHILDREN {
+        let child = unsafe { field(*holder, i).load::<ObjectReference>() };
+        let num_fields = match num_fields(i) {
+            Some(num_fields) => num_fields,
+            None => {
+                assert!(child.is_null());
+                continue;
+            }
+        };
+        assert!(mmtk_is_live_object(child), "Child {} ({}) is not live", i, child);
+        assert_eq!(
+            VMObjectModel::get_current_size(child),
+            OBJECT_REFS_OFFSET + num_fields * BYTES_IN_ADDRESS
+        );
+        let old_child = *old_children.next().unwrap();
+        if copies_all_objects {
+            assert_ne!(child, old_child, "Child {} is not copied", i);
+        }
+        if let Some(previous) = previous.filter(|_| num_fields > 0) {
+            assert_eq!(unsafe { field(child, 0).load::<ObjectReference>() }, previous);
+        }
+        previous = Some(child);
+    }
+}
@@ -0,0 +1,38 @@
+// GITHUB-CI: MMTK_PLAN=GenImmix GenCopy StickyImmix
+// GITHUB-CI: FEATURES=verify_remset
+
+use crate::api::*;
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use mmtk::memory_manager;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::Address;
+use mmtk::vm::edge_shape::{Edge, SimpleEdge};
+use mmtk::AllocationSemantics;
+
+/// This test stores a reference to a young object into a mature object without the write barrier.
+/// The remembered set verifier should report the missing edge in the next nursery GC.
+#[test]
+#[should_panic(expected = "Remembered set verification failed")]
+pub fn verify_remset() {
+    const MB: usize = 1024 * 1024;
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB) for nursery GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    // The source object becomes mature after it survives a GC.
+    let src = Box::new(alloc_object(handle, 1, AllocationSemantics::Default));
+    crate::scanning::add_root(Address::from_ref(&*src));
+    mmtk_handle_user_collection_request(tls);
+
+    // Skip the barrier.
+    let target = alloc_object(handle, 0, AllocationSemantics::Default);
+    SimpleEdge::from_address(field(*src, 0)).store(target);
+    // The panic cannot unwind through the `extern "C"` API function.
+    memory_manager::handle_user_collection_request(&crate::SINGLETON, tls);
+}
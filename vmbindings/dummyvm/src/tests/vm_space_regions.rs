@@ -0,0 +1,54 @@
+// GITHUB-CI: MMTK_PLAN=NoGC SemiSpace Immix MarkSweep
+// GITHUB-CI: FEATURES=vm_space,is_mmtk_object
+
+use crate::api::*;
+use crate::object_model::OBJECT_REF_OFFSET;
+use mmtk::util::memory;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+
+/// This test adds two regions to the VM space from two threads, and removes one of them.
+#[test]
+pub fn vm_space_regions() {
+    const MB: usize = 1024 * 1024;
+    // Two regions after our heap range. They are in the same entry of the SFT space map on 64 bits.
+    let region1 = unsafe { Address::from_usize(0x2400_0000_0000) };
+    let region2 = unsafe { Address::from_usize(0x2500_0000_0000) };
+
+    // 1MB heap
+    mmtk_init(MB);
+    let _handle = mmtk_bind_mutator(VMMutatorThread(VMThread::UNINITIALIZED));
+
+    // Add the regions from different threads at the same time.
+    let objects: Vec<ObjectReference> = [region1, region2]
+        .iter()
+        .map(|start| {
+            let start = *start;
+            std::thread::spawn(move || {
+                assert!(memory::dzmmap_noreplace(start, MB).is_ok());
+                mmtk_add_vm_space_region(start, MB);
+                let obj = ObjectReference::from_raw_address(start + 64usize + OBJECT_REF_OFFSET);
+                mmtk_initialize_vm_space_object(obj);
+                obj
+            })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect();
+
+    for obj in objects.iter() {
+        assert!(mmtk_is_in_mmtk_spaces(*obj));
+        assert!(mmtk_is_mmtk_object(obj.to_raw_address()));
+    }
+    // An address between the two regions
+    let between = ObjectReference::from_raw_address(region1 + 64 * MB + OBJECT_REF_OFFSET);
+    assert!(!mmtk_is_in_mmtk_spaces(between));
+
+    mmtk_remove_vm_space_region(region1);
+    assert!(!mmtk_is_in_mmtk_spaces(objects[0]));
+    assert!(!mmtk_is_mmtk_object(objects[0].to_raw_address()));
+    assert!(mmtk_is_in_mmtk_spaces(objects[1]));
+    assert!(mmtk_is_mmtk_object(objects[1].to_raw_address()));
+    assert!(memory::munmap(region1, MB).is_ok());
+}
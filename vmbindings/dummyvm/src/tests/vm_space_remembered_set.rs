@@ -0,0 +1,89 @@
+// GITHUB-CI: MMTK_PLAN=GenCopy GenImmix
+// GITHUB-CI: FEATURES=vm_space
+
+use crate::api::*;
+use crate::edges::DummyVMEdge;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET, OBJECT_REF_OFFSET, OBJECT_SIZE_OFFSET};
+use crate::tests::fixtures::{alloc_object, field, gc_mutator_tls};
+use crate::DummyVM;
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::memory;
+use mmtk::util::opaque_pointer::*;
+use mmtk::util::{Address, ObjectReference};
+use mmtk::vm::edge_shape::{Edge, SimpleEdge};
+use mmtk::vm::ObjectModel;
+use mmtk::AllocationSemantics;
+use std::sync::atomic::Ordering;
+
+/// This test uses a remembered set for the VM space, and writes a reference to a nursery object
+/// into an object in the VM space with the object barrier. The young object is only reachable from
+/// the VM space, and it is kept alive by the modified buffer in a nursery GC, and by the remembered
+/// set in a full heap GC. The object leaves the remembered set once it no longer refers to objects
+/// outside the VM space.
+#[test]
+pub fn vm_space_remembered_set() {
+    const MB: usize = 1024 * 1024;
+    const FIELDS: usize = 1;
+    const SIZE: usize = OBJECT_REFS_OFFSET + FIELDS * BYTES_IN_ADDRESS;
+    // A region after our heap range.
+    let region = unsafe { Address::from_usize(0x2400_0000_0000) };
+    {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.vm_space_remembered_set.set(true));
+        assert!(builder.options.synchronous_gc.set(true));
+    }
+    // The heap needs to be larger than the minimal nursery size (2MB) for nursery GCs.
+    mmtk_init(8 * MB);
+    mmtk_initialize_collection(VMThread::UNINITIALIZED);
+    let vm_space = &crate::SINGLETON.get_plan().base().vm_space;
+    assert!(vm_space.uses_remembered_set());
+    let tls = gc_mutator_tls();
+    let handle = mmtk_bind_mutator(tls);
+
+    assert!(memory::dzmmap_noreplace(region, MB).is_ok());
+    mmtk_add_vm_space_region(region, MB);
+    let src_addr = region + 64usize;
+    unsafe { (src_addr + OBJECT_SIZE_OFFSET).store::<u32>(SIZE as u32) };
+    let src = ObjectReference::from_raw_address(src_addr + OBJECT_REF_OFFSET);
+    mmtk_initialize_vm_space_object(src);
+    // Objects in the VM space are mature when they are registered.
+    let log_bit = VMObjectModel::GLOBAL_LOG_BIT_SPEC;
+    assert!(log_bit.is_unlogged::<DummyVM>(src, Ordering::SeqCst));
+
+    let target = alloc_object(handle, FIELDS, AllocationSemantics::Default);
+
+    let mutator = unsafe { &mut *handle };
+    let edge = SimpleEdge::from_address(field(src, 0));
+    mutator
+        .barrier
+        .object_reference_write(src, DummyVMEdge::Simple(edge), target);
+    assert_eq!(edge.load(), target);
+    // The barrier logs the object, and the object is remembered when the barrier is flushed.
+    assert!(!log_bit.is_unlogged::<DummyVM>(src, Ordering::SeqCst));
+    assert!(!vm_space.is_object_remembered(src));
+    mutator.barrier.flush();
+    assert!(vm_space.is_object_remembered(src));
+
+    let check_target = || {
+        let target = edge.load();
+        assert!(!target.is_null());
+        assert!(mmtk_is_live_object(target));
+        assert_eq!(VMObjectModel::get_current_size(target), SIZE);
+    };
+    // A nursery GC. The object is in the modified buffer.
+    mmtk_handle_user_collection_request(tls);
+    check_target();
+    assert!(vm_space.is_object_remembered(src));
+    // A full heap GC. The object is in the remembered set.
+    mmtk_handle_user_compaction_request(tls);
+    check_target();
+    assert!(vm_space.is_object_remembered(src));
+
+    // Once the object no longer refers to the heap, it leaves the remembered set in the next full heap GC.
+    mutator
+        .barrier
+        .object_reference_write(src, DummyVMEdge::Simple(edge), ObjectReference::NULL);
+    mutator.barrier.flush();
+    mmtk_handle_user_compaction_request(tls);
+    assert!(!vm_space.is_object_remembered(src));
+}
@@ -0,0 +1,176 @@
+// GITHUB-CI: MMTK_PLAN=SemiSpace Immix
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use mmtk::memory_manager;
+use mmtk::scheduler::{GCWork, GCWorker, WorkBucketStage};
+use mmtk::util::constants::BYTES_IN_ADDRESS;
+use mmtk::util::options::{GCTriggerSelector, StealPolicy, WorkQueueOrder};
+use mmtk::util::{Address, ObjectReference, VMThread};
+use mmtk::vm::ObjectModel;
+use mmtk::{AllocationSemantics, Mutator, MMTK};
+
+use crate::api::*;
+use crate::object_model::{VMObjectModel, OBJECT_REFS_OFFSET, OBJECT_REF_OFFSET};
+use crate::tests::fixtures::{field, gc_mutator_tls, init_object};
+use crate::DummyVM;
+
+/// The number of packets that `SpawningPacket` adds to the local queue of its worker.
+const LOCAL_PACKETS: usize = 8;
+
+lazy_static! {
+    /// The worker that executes `SpawningPacket`.
+    static ref SPAWNER: Mutex<Option<usize>> = Mutex::new(None);
+    /// The `RecordingPacket`s in the order of execution, and the workers that execute them.
+    static ref EXECUTED: Mutex<Vec<(usize, usize)>> = Mutex::new(vec![]);
+    /// Notified when a `RecordingPacket` is executed.
+    static ref PACKET_EXECUTED: Condvar = Condvar::new();
+}
+
+/// A packet that records which worker executes it.
+struct RecordingPacket(usize);
+
+impl GCWork<DummyVM> for RecordingPacket {
+    fn do_work(&mut self, worker: &mut GCWorker<DummyVM>, _mmtk: &'static MMTK<DummyVM>) {
+        EXECUTED.lock().unwrap().push((self.0, worker.ordinal));
+        PACKET_EXECUTED.notify_all();
+    }
+}
+
+/// A packet in a work bucket that wakes up a parked worker. A worker only steals packets when it is not parked.
+struct WakeUpPacket;
+
+impl GCWork<DummyVM> for WakeUpPacket {
+    fn do_work(&mut self, _worker: &mut GCWorker<DummyVM>, _mmtk: &'static MMTK<DummyVM>) {}
+}
+
+/// A packet that adds `RecordingPacket`s to the local queue of its worker. If `wait` is true, it waits until other
+/// workers have stolen and executed all of them.
+struct SpawningPacket {
+    wait: bool,
+}
+
+impl GCWork<DummyVM> for SpawningPacket {
+    fn do_work(&mut self, worker: &mut GCWorker<DummyVM>, mmtk: &'static MMTK<DummyVM>) {
+        *SPAWNER.lock().unwrap() = Some(worker.ordinal);
+        // The bucket is open, so the packets are added to the local queue.
+        for i in 0..LOCAL_PACKETS {
+            worker.add_work(WorkBucketStage::Prepare, RecordingPacket(i));
+        }
+        if self.wait {
+            for _ in 0..LOCAL_PACKETS {
+                memory_manager::add_work_packet(mmtk, WorkBucketStage::Prepare, WakeUpPacket);
+            }
+            let executed = EXECUTED.lock().unwrap();
+            let (_executed, result) = PACKET_EXECUTED
+                .wait_timeout_while(executed, Duration::from_secs(10), |executed| {
+                    executed.len() < LOCAL_PACKETS
+                })
+                .unwrap();
+            assert!(!result.timed_out(), "The local packets are not stolen");
+        }
+    }
+}
+
+/// Allocate an object with `num_fields` null reference fields in the given mutator.
+fn alloc_object(mutator: &mut Mutator<DummyVM>, num_fields: usize) -> ObjectReference {
+    let size = OBJECT_REFS_OFFSET + num_fields * BYTES_IN_ADDRESS;
+    let addr = memory_manager::alloc(mutator, size, 8, 0, AllocationSemantics::Default);
+    assert!(!addr.is_zero());
+    init_object(addr, num_fields);
+    let object = ObjectReference::from_raw_address(addr.add(OBJECT_REF_OFFSET));
+    memory_manager::post_alloc(mutator, object, size, AllocationSemantics::Default);
+    object
+}
+
+/// Run GCs in a new MMTk instance with four GC workers, the given work stealing policy and the order of the local
+/// work queues. In the first GC, a worker adds `LOCAL_PACKETS` packets to its local queue. If `wait` is true, the
+/// worker does not return until other workers have stolen all the packets. The GCs also trace a tree of objects,
+/// which must survive the GCs. Return the worker that added the packets, and the ids of the packets in the order of
+/// execution with the workers that executed them.
+fn run_gcs_with_work_stealing(policy: StealPolicy, order: WorkQueueOrder, wait: bool) -> (usize, Vec<(usize, usize)>) {
+    const MB: usize = 1024 * 1024;
+    const CHILDREN: usize = 64;
+    const GRANDCHILDREN: usize = 4;
+    let mmtk: &'static MMTK<DummyVM> = {
+        let mut builder = crate::BUILDER.lock().unwrap();
+        assert!(builder.options.gc_trigger.set(GCTriggerSelector::FixedHeapSize(8 * MB)));
+        assert!(builder.options.threads.set(4));
+        assert!(builder.options.work_stealing.set(policy));
+        assert!(builder.options.work_queue_order.set(order));
+        // Work stealing needs GC threads, even if CI sets `MMTK_SYNCHRONOUS_GC`.
+        assert!(builder.options.synchronous_gc.set(false));
+        Box::leak(memory_manager::mmtk_init(&builder))
+    };
+    crate::use_instance(mmtk);
+    memory_manager::initialize_collection(mmtk, VMThread::UNINITIALIZED);
+    let tls = gc_mutator_tls();
+
+    let mut mutator = memory_manager::bind_mutator(mmtk, tls);
+    let root = Box::new(alloc_object(&mut mutator, CHILDREN));
+    crate::scanning::add_root(Address::from_ref(&*root));
+    for i in 0..CHILDREN {
+        let child = alloc_object(&mut mutator, GRANDCHILDREN);
+        unsafe { field(*root, i).store(child) };
+        for j in 0..GRANDCHILDREN {
+            let grandchild = alloc_object(&mut mutator, 0);
+            unsafe { field(child, j).store(grandchild) };
+        }
+    }
+    // DummyVM only reports the mutators bound with `mmtk_bind_mutator` to MMTk, so the mutator is destroyed before GC
+    // to flush its allocation buffers.
+    memory_manager::destroy_mutator(&mut mutator);
+
+    memory_manager::add_work_packet(mmtk, WorkBucketStage::Prepare, SpawningPacket { wait });
+    for _ in 0..2 {
+        crate::collection::with_blocking_gc(|| memory_manager::handle_user_collection_request(mmtk, tls));
+
+        let size = |num_fields: usize| OBJECT_REFS_OFFSET + num_fields * BYTES_IN_ADDRESS;
+        assert!(mmtk_is_live_object(*root));
+        assert_eq!(VMObjectModel::get_current_size(*root), size(CHILDREN));
+        for i in 0..CHILDREN {
+            let child = unsafe { field(*root, i).load::<ObjectReference>() };
+            assert!(mmtk_is_live_object(child));
+            assert_eq!(VMObjectModel::get_current_size(child), size(GRANDCHILDREN));
+            for j in 0..GRANDCHILDREN {
+                let grandchild = unsafe { field(child, j).load::<ObjectReference>() };
+                assert!(mmtk_is_live_object(grandchild));
+                assert_eq!(VMObjectModel::get_current_size(grandchild), size(0));
+            }
+        }
+    }
+
+    crate::scanning::clear_roots();
+    memory_manager::mmtk_shutdown(mmtk, VMThread::UNINITIALIZED);
+    (
+        SPAWNER.lock().unwrap().take().expect("The spawning packet is not executed"),
+        std::mem::take(&mut *EXECUTED.lock().unwrap()),
+    )
+}
+
+/// This test runs GCs under each work stealing policy. A worker adds packets to its local queue. If work stealing is
+/// enabled, the worker waits until other workers steal and execute all of them. If work stealing is disabled, the
+/// queue is LIFO, and the worker executes all the packets by itself, newest first. The GCs must not wait for other
+/// workers to steal them.
+#[test]
+pub fn work_stealing() {
+    for policy in [
+        StealPolicy::Sequential,
+        StealPolicy::Neighbor,
+        StealPolicy::Random,
+        StealPolicy::Disabled,
+    ] {
+        if policy == StealPolicy::Disabled {
+            let (spawner, executed) = run_gcs_with_work_stealing(policy, WorkQueueOrder::Lifo, false);
+            let expected: Vec<_> = (0..LOCAL_PACKETS).rev().map(|i| (i, spawner)).collect();
+            assert_eq!(executed, expected, "{:?}", policy);
+        } else {
+            let (spawner, executed) = run_gcs_with_work_stealing(policy, WorkQueueOrder::Fifo, true);
+            assert_eq!(executed.len(), LOCAL_PACKETS, "{:?}", policy);
+            for (_, worker) in executed {
+                assert_ne!(worker, spawner, "{:?}", policy);
+            }
+        }
+    }
+}